@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use actix_web::HttpRequest;
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use lazy_static::lazy_static;
+use log::warn;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use crate::clock::{Clock, SystemClock};
+use crate::config::CONFIG;
+use crate::models::store::Store;
+use crate::models::user::{LockoutPolicy, LoginOutcome, User};
+
+/// Способ проверки учётных данных запроса
+///
+/// Все обработчики API и git протокола аутентифицируют пользователя через
+/// бэкенд, выбранный в GIT_HTTP_AUTH_BACKEND, поэтому новый источник
+/// пользователей (LDAP, заголовок OAuth-прокси) достаточно реализовать здесь.
+pub trait AuthBackend: Send + Sync {
+    /// Имя бэкенда, под которым он выбирается в конфигурации
+    fn name(&self) -> &'static str;
+
+    /// Определяет пользователя, от имени которого выполняется запрос
+    ///
+    /// # Параметры
+    ///
+    /// * `req` - HTTP запрос
+    /// * `store` - Хранилище пользователей
+    ///
+    /// # Возвращает
+    ///
+    /// * `Option<User>` - Пользователь, если учётные данные верны
+    fn authenticate(&self, req: &HttpRequest, store: &dyn Store) -> Option<User>;
+}
+
+/// Basic-аутентификация по таблице пользователей
+pub struct LocalAuth;
+
+impl AuthBackend for LocalAuth {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    fn authenticate(&self, req: &HttpRequest, store: &dyn Store) -> Option<User> {
+        let (username, password) = basic_credentials(req)?;
+
+        let key = credentials_key(&username, &password);
+        if let Some(user) = cached_user(&key, &password, store) {
+            return Some(user);
+        }
+
+        let ip = req.peer_addr().map(|addr| addr.ip().to_string());
+        match store.authenticate(&username, &password, ip.as_deref(), &SystemClock, LockoutPolicy::configured()) {
+            Ok(LoginOutcome::Success(user)) => {
+                cache_user(key, &user);
+                Some(user)
+            }
+            _ => {
+                forget_username(&username);
+                None
+            }
+        }
+    }
+}
+
+/// Учётные данные, недавно прошедшие проверку
+struct CachedAuth {
+    user_id: i64,
+    username: String,
+    expires_at: Instant,
+}
+
+lazy_static! {
+    /// Кэш проверенных учётных данных по HMAC от `имя:пароль`
+    ///
+    /// Клонирование по dumb протоколу и каждый шаг smart протокола - отдельные
+    /// HTTP запросы с одними и теми же учётными данными. Кэш избавляет их от
+    /// полной проверки со сбросом счётчика и записью времени входа; сам
+    /// пользователь при этом каждый раз читается из базы заново.
+    static ref AUTH_CACHE: Mutex<HashMap<[u8; 32], CachedAuth>> = Mutex::new(HashMap::new());
+
+    /// Случайный ключ HMAC, свой у каждого процесса
+    ///
+    /// Без секретного ключа по содержимому памяти кэша можно было бы
+    /// перебирать пароли так же быстро, как несолёные SHA-256.
+    static ref CACHE_KEY: [u8; 32] = {
+        let mut key = [0u8; 32];
+        key[..16].copy_from_slice(uuid::Uuid::new_v4().as_bytes());
+        key[16..].copy_from_slice(uuid::Uuid::new_v4().as_bytes());
+        key
+    };
+}
+
+/// Ключ кэша: сам пароль в памяти кэша не хранится
+fn credentials_key(username: &str, password: &str) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(&*CACHE_KEY).expect("HMAC accepts keys of any length");
+    mac.update(username.as_bytes());
+    mac.update(&[0u8]);
+    mac.update(password.as_bytes());
+    mac.finalize().into_bytes().into()
+}
+
+/// Пользователь из кэша, если учётные данные проверялись недавно
+///
+/// Пользователь читается из базы заново, и запись удаляется, если он удалён,
+/// сменил пароль или заблокирован: тогда запрос проходит полную проверку.
+///
+/// # Параметры
+///
+/// * `key` - Ключ кэша из `credentials_key`
+/// * `password` - Пароль из запроса
+/// * `store` - Хранилище пользователей
+///
+/// # Возвращает
+///
+/// * `Option<User>` - Пользователь, если запись есть и всё ещё действительна
+fn cached_user(key: &[u8; 32], password: &str, store: &dyn Store) -> Option<User> {
+    if CONFIG.auth_cache_secs == 0 {
+        return None;
+    }
+
+    let user_id = {
+        let mut cache = AUTH_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        match cache.get(key) {
+            Some(entry) if entry.expires_at > Instant::now() => entry.user_id,
+            Some(_) => {
+                cache.remove(key);
+                return None;
+            }
+            None => return None,
+        }
+    };
+
+    let now = SystemClock.now();
+    match store.find_user_by_id(user_id) {
+        Ok(Some(user)) if user.password == password && user.locked_until.is_none_or(|until| until <= now) => Some(user),
+        _ => {
+            AUTH_CACHE.lock().unwrap_or_else(|e| e.into_inner()).remove(key);
+            None
+        }
+    }
+}
+
+/// Запоминает успешно проверенные учётные данные
+///
+/// Когда кэш заполнен, сначала удаляются истёкшие записи, а если их нет -
+/// запись, которая истекает раньше всех.
+fn cache_user(key: [u8; 32], user: &User) {
+    if CONFIG.auth_cache_secs == 0 || CONFIG.auth_cache_entries == 0 {
+        return;
+    }
+
+    let now = Instant::now();
+    let mut cache = AUTH_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if cache.len() >= CONFIG.auth_cache_entries && !cache.contains_key(&key) {
+        cache.retain(|_, entry| entry.expires_at > now);
+        if cache.len() >= CONFIG.auth_cache_entries {
+            let oldest = cache.iter().min_by_key(|(_, entry)| entry.expires_at).map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                cache.remove(&oldest);
+            }
+        }
+    }
+
+    cache.insert(key, CachedAuth {
+        user_id: user.id.unwrap_or_default(),
+        username: user.username.clone(),
+        expires_at: now + Duration::from_secs(CONFIG.auth_cache_secs),
+    });
+}
+
+/// Удаляет из кэша все учётные данные пользователя
+///
+/// Вызывается после неудачной попытки входа: она могла заблокировать аккаунт,
+/// и верный пароль из кэша не должен действовать в обход блокировки.
+///
+/// # Параметры
+///
+/// * `username` - Имя пользователя
+pub fn forget_username(username: &str) {
+    let mut cache = AUTH_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    cache.retain(|_, entry| entry.username != username);
+}
+
+/// Извлекает имя пользователя и пароль из заголовка `Authorization: Basic ...`
+fn basic_credentials(req: &HttpRequest) -> Option<(String, String)> {
+    let auth_str = req.headers().get("Authorization")?.to_str().ok()?;
+    let encoded = auth_str.strip_prefix("Basic ")?;
+
+    let credentials = String::from_utf8(BASE64.decode(encoded).ok()?).ok()?;
+    let (username, password) = credentials.split_once(':')?;
+
+    Some((username.to_string(), password.to_string()))
+}
+
+/// Выбирает бэкенд по имени из конфигурации
+fn select_backend(name: &str) -> Box<dyn AuthBackend> {
+    let backends: Vec<Box<dyn AuthBackend>> = vec![Box::new(LocalAuth)];
+    let fallback = LocalAuth.name();
+
+    backends
+        .into_iter()
+        .find(|backend| backend.name() == name)
+        .unwrap_or_else(|| {
+            warn!("Unknown auth backend '{}', falling back to '{}'", name, fallback);
+            Box::new(LocalAuth)
+        })
+}
+
+lazy_static! {
+    /// Бэкенд аутентификации, выбранный в GIT_HTTP_AUTH_BACKEND
+    pub static ref AUTH_BACKEND: Box<dyn AuthBackend> = select_backend(&CONFIG.auth_backend);
+}
+
+/// Значение заголовка `WWW-Authenticate` для ответов 401
+pub fn challenge() -> String {
+    // Кавычки и обратные слеши сломали бы quoted-string в заголовке
+    let realm: String = CONFIG.auth_realm.chars().filter(|c| *c != '"' && *c != '\\').collect();
+    format!("Basic realm=\"{}\"", realm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+    use rusqlite::params;
+    use sha2::Digest;
+    use crate::models::db::Database;
+    use crate::test_support::{basic_auth, create_user, test_db, PASSWORD};
+
+    fn login(user: &User, db: &Database) -> Option<User> {
+        let req = TestRequest::default().insert_header(basic_auth(user)).to_http_request();
+        LocalAuth.authenticate(&req, db)
+    }
+
+    fn update_user(db: &Database, sql: &str, user: &User) {
+        db.get_connection().lock().unwrap().execute(sql, params![user.id]).unwrap();
+    }
+
+    #[test]
+    fn cache_key_is_not_a_plain_hash_of_the_credentials() {
+        let plain: [u8; 32] = Sha256::digest(format!("alice\0{}", PASSWORD)).into();
+        assert_ne!(credentials_key("alice", PASSWORD), plain);
+        assert_eq!(credentials_key("alice", PASSWORD), credentials_key("alice", PASSWORD));
+        assert_ne!(credentials_key("alice", PASSWORD), credentials_key("alice", "other"));
+    }
+
+    #[test]
+    fn cached_credentials_stop_working_after_password_change() {
+        let db = test_db();
+        let user = create_user(&db, "changed");
+        assert!(login(&user, &db).is_some());
+
+        update_user(&db, "UPDATE users SET password = 'new-secret' WHERE id = ?1", &user);
+        assert!(login(&user, &db).is_none());
+    }
+
+    #[test]
+    fn cached_credentials_stop_working_after_lockout() {
+        let db = test_db();
+        let user = create_user(&db, "locked");
+        assert!(login(&user, &db).is_some());
+
+        update_user(&db, "UPDATE users SET locked_until = '2999-01-01 00:00:00' WHERE id = ?1", &user);
+        assert!(login(&user, &db).is_none());
+    }
+
+    #[test]
+    fn cached_credentials_stop_working_after_deletion() {
+        let db = test_db();
+        let user = create_user(&db, "deleted");
+        assert!(login(&user, &db).is_some());
+
+        update_user(&db, "DELETE FROM users WHERE id = ?1", &user);
+        assert!(login(&user, &db).is_none());
+    }
+}
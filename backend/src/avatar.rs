@@ -0,0 +1,93 @@
+use std::fmt::Write as _;
+
+/// Адрес сервиса аватаров
+const GRAVATAR_BASE: &str = "https://www.gravatar.com/avatar";
+/// Размер аватара в пикселях
+const AVATAR_SIZE: u32 = 80;
+
+/// Строит адрес аватара пользователя
+///
+/// Хэш считается на сервере, поэтому клиентам не нужен email, чтобы показать аватар.
+/// Без email используется identicon, построенный по имени пользователя.
+///
+/// # Параметры
+///
+/// * `email` - Электронная почта пользователя
+/// * `username` - Имя пользователя
+///
+/// # Возвращает
+///
+/// * `String` - URL картинки
+pub fn avatar_url(email: Option<&str>, username: &str) -> String {
+    match email.map(str::trim).filter(|email| !email.is_empty()) {
+        Some(email) => format!(
+            "{}/{}?d=identicon&s={}",
+            GRAVATAR_BASE, md5_hex(email.to_lowercase().as_bytes()), AVATAR_SIZE
+        ),
+        // f=y - всегда отдавать identicon, даже если хэш совпал с чьим-то email
+        None => format!(
+            "{}/{}?d=identicon&f=y&s={}",
+            GRAVATAR_BASE, md5_hex(username.as_bytes()), AVATAR_SIZE
+        ),
+    }
+}
+
+/// MD5 в шестнадцатеричном виде (RFC 1321) - формат хэша, который ожидает Gravatar
+fn md5_hex(input: &[u8]) -> String {
+    const SHIFTS: [u32; 64] = [
+        7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22,
+        5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20,
+        4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23,
+        6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+    ];
+    // K[i] = floor(|sin(i + 1)| * 2^32)
+    let constants: Vec<u32> = (1..=64)
+        .map(|i| ((i as f64).sin().abs() * 4294967296.0) as u32)
+        .collect();
+
+    let mut message = input.to_vec();
+    let bit_len = (input.len() as u64).wrapping_mul(8);
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&bit_len.to_le_bytes());
+
+    let mut state: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+    for chunk in message.chunks(64) {
+        let words: Vec<u32> = chunk
+            .chunks(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect();
+
+        let [mut a, mut b, mut c, mut d] = state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let rotated = a
+                .wrapping_add(f)
+                .wrapping_add(constants[i])
+                .wrapping_add(words[g])
+                .rotate_left(SHIFTS[i]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(rotated);
+        }
+
+        state[0] = state[0].wrapping_add(a);
+        state[1] = state[1].wrapping_add(b);
+        state[2] = state[2].wrapping_add(c);
+        state[3] = state[3].wrapping_add(d);
+    }
+
+    let mut hex = String::with_capacity(32);
+    for byte in state.iter().flat_map(|word| word.to_le_bytes()) {
+        let _ = write!(hex, "{:02x}", byte);
+    }
+    hex
+}
@@ -0,0 +1,101 @@
+use std::path::Path;
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use crate::config::CONFIG;
+use crate::error::AppError;
+use crate::git::{advertise, resolve_repo, REPOS_ROOT};
+use crate::git::bundle::{bundle_path, bundle_uri_enabled, create_bundle, set_bundle_advertised};
+use crate::jobs;
+use crate::models::db::Database;
+use crate::models::store::Store;
+
+/// Тип фоновой задачи пересоздания bundle репозитория
+pub const BUNDLE_JOB: &str = "bundle_regenerate";
+
+/// Параметры задачи пересоздания bundle
+#[derive(Serialize, Deserialize)]
+struct BundleJob {
+    /// Полное имя репозитория `{owner}/{name}`
+    repository: String,
+}
+
+/// Ставит пересоздание bundle в очередь, если оно ещё не запланировано
+///
+/// Ошибка только логируется: без свежего bundle клиенты получают всю историю
+/// обычным fetch.
+///
+/// # Параметры
+///
+/// * `repo_name` - Полное имя репозитория `{owner}/{name}`
+/// * `delay_secs` - Через сколько секунд пересоздать bundle
+/// * `db` - База данных
+pub fn schedule(repo_name: &str, delay_secs: u64, db: &Database) {
+    let payload = serde_json::json!(BundleJob { repository: repo_name.to_string() });
+    if let Err(e) = jobs::schedule(BUNDLE_JOB, payload, delay_secs, db) {
+        error!("Failed to schedule bundle for {}: {}", repo_name, e);
+    }
+}
+
+/// Выполняет задачу пересоздания bundle (`BUNDLE_JOB`)
+///
+/// Bundle создаётся из основного каталога, после первого создания upload-pack
+/// начинает его объявлять. Пока `bundle-uri` включён, следующая задача ставится
+/// через GIT_HTTP_BUNDLE_INTERVAL_SECS; удалённый репозиторий или выключенная
+/// настройка прекращают цепочку.
+///
+/// # Параметры
+///
+/// * `job` - Параметры задачи
+/// * `db` - База данных
+///
+/// # Возвращает
+///
+/// * `Result<(), String>` - Описание ошибки, если задачу нужно повторить
+pub fn run_bundle_job(job: &serde_json::Value, db: &Database) -> Result<(), String> {
+    let mut job: BundleJob = serde_json::from_value(job.clone())
+        .map_err(|e| format!("Invalid bundle job: {}", e))?;
+
+    // Задачи, поставленные до раскладки `{owner}/{name}.git`, хранят только имя
+    if !job.repository.contains('/') {
+        match db.find_repositories_by_name(&job.repository) {
+            Ok(repos) if repos.len() == 1 => job.repository = repos[0].full_name(),
+            Ok(_) => {
+                debug!("Skipping bundle for {}: the name no longer identifies one repository", job.repository);
+                return Ok(());
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+
+    let repo_path = match resolve_repo(Path::new(REPOS_ROOT), &job.repository) {
+        Ok(path) => path,
+        Err(AppError::RepoNotFound(_)) => {
+            debug!("Skipping bundle for deleted repository {}", job.repository);
+            return Ok(());
+        }
+        Err(e) => return Err(e.to_string()),
+    };
+
+    if !bundle_uri_enabled(&repo_path).map_err(|e| e.to_string())? {
+        debug!("bundle-uri is disabled for {}, not regenerating", job.repository);
+        return Ok(());
+    }
+
+    let dest = bundle_path(&job.repository).map_err(|e| e.to_string())?;
+    let created = create_bundle(&repo_path, &dest).map_err(|e| e.to_string())?;
+    if created {
+        info!("Regenerated bundle for {}", job.repository);
+    } else {
+        debug!("{} has no branches or tags, bundle not created", job.repository);
+    }
+
+    // Объявление меняет ответ info/refs (v2), поэтому кэш объявлений сбрасывается
+    set_bundle_advertised(&repo_path, created).map_err(|e| e.to_string())?;
+    advertise::purge(&job.repository);
+    if let Err(e) = db.bump_ref_generation(&job.repository) {
+        error!("Failed to bump ref generation of {}: {}", job.repository, e);
+    }
+
+    schedule(&job.repository, CONFIG.bundle_interval_secs, db);
+    Ok(())
+}
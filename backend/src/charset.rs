@@ -0,0 +1,190 @@
+/// Кодировка текстового файла
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Charset {
+    /// UTF-8, возможно с BOM
+    Utf8 { bom: bool },
+    /// UTF-16 little-endian, возможно с BOM
+    Utf16Le { bom: bool },
+    /// UTF-16 big-endian, возможно с BOM
+    Utf16Be { bom: bool },
+    /// ISO-8859-1: любой не-UTF-8 текст без нулевых байт, поэтому декодирование не ошибается
+    Latin1,
+}
+
+/// Сколько первых байт просматривается, чтобы отличить двоичный файл, как в git
+const SNIFF_BYTES: usize = 8000;
+
+impl Charset {
+    /// Имя кодировки для параметра `charset` в `Content-Type`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Charset::Utf8 { .. } => "utf-8",
+            Charset::Utf16Le { .. } => "utf-16le",
+            Charset::Utf16Be { .. } => "utf-16be",
+            Charset::Latin1 => "iso-8859-1",
+        }
+    }
+
+    /// Совместима ли кодировка с ASCII: можно ли резать текст на строки по байту `\n`
+    pub fn is_ascii_compatible(&self) -> bool {
+        !matches!(self, Charset::Utf16Le { .. } | Charset::Utf16Be { .. })
+    }
+}
+
+/// Определяет кодировку текста или признаёт данные двоичными
+///
+/// Сначала проверяется BOM. Без BOM нулевые байты в начале файла означают
+/// либо UTF-16 (нули стабильно на чётных или нечётных позициях, как у латиницы),
+/// либо двоичный файл. Остальное - UTF-8, если байты им являются, иначе ISO-8859-1.
+///
+/// # Параметры
+///
+/// * `bytes` - Содержимое файла
+///
+/// # Возвращает
+///
+/// * `Option<Charset>` - Кодировка или None для двоичных данных
+pub fn detect(bytes: &[u8]) -> Option<Charset> {
+    if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return Some(Charset::Utf8 { bom: true });
+    }
+    if bytes.starts_with(&[0xFF, 0xFE]) {
+        return Some(Charset::Utf16Le { bom: true });
+    }
+    if bytes.starts_with(&[0xFE, 0xFF]) {
+        return Some(Charset::Utf16Be { bom: true });
+    }
+
+    let sample = &bytes[..bytes.len().min(SNIFF_BYTES)];
+    if sample.contains(&0) {
+        return utf16_without_bom(sample);
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(_) => Some(Charset::Utf8 { bom: false }),
+        Err(_) => Some(Charset::Latin1),
+    }
+}
+
+/// Распознаёт UTF-16 без BOM: в тексте из латиницы каждый второй байт нулевой
+fn utf16_without_bom(sample: &[u8]) -> Option<Charset> {
+    if sample.len() < 2 || !sample.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let pairs = sample.len() / 2;
+    let zero_high = sample.chunks_exact(2).filter(|pair| pair[0] != 0 && pair[1] == 0).count();
+    let zero_low = sample.chunks_exact(2).filter(|pair| pair[0] == 0 && pair[1] != 0).count();
+    let both_zero = sample.chunks_exact(2).any(|pair| pair == [0, 0]);
+
+    // Пара нулей - это U+0000, в тексте его не бывает
+    if both_zero {
+        None
+    } else if zero_high * 2 >= pairs && zero_low == 0 {
+        Some(Charset::Utf16Le { bom: false })
+    } else if zero_low * 2 >= pairs && zero_high == 0 {
+        Some(Charset::Utf16Be { bom: false })
+    } else {
+        None
+    }
+}
+
+/// Перекодирует текст в UTF-8, отбрасывая BOM
+///
+/// # Параметры
+///
+/// * `bytes` - Содержимое файла
+/// * `charset` - Кодировка, определённая `detect`
+///
+/// # Возвращает
+///
+/// * `Option<String>` - Текст или None, если байты не соответствуют кодировке
+pub fn decode(bytes: &[u8], charset: Charset) -> Option<String> {
+    match charset {
+        Charset::Utf8 { bom } => {
+            let bytes = if bom { &bytes[3..] } else { bytes };
+            std::str::from_utf8(bytes).ok().map(|text| text.to_string())
+        }
+        Charset::Utf16Le { bom } | Charset::Utf16Be { bom } => {
+            let bytes = if bom { &bytes[2..] } else { bytes };
+            if !bytes.len().is_multiple_of(2) {
+                return None;
+            }
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|pair| match charset {
+                    Charset::Utf16Le { .. } => u16::from_le_bytes([pair[0], pair[1]]),
+                    _ => u16::from_be_bytes([pair[0], pair[1]]),
+                })
+                .collect();
+            String::from_utf16(&units).ok()
+        }
+        Charset::Latin1 => Some(bytes.iter().map(|&byte| char::from(byte)).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Текст в UTF-16 с BOM нужного порядка байт
+    fn utf16(text: &str, little_endian: bool) -> Vec<u8> {
+        let mut bytes = if little_endian { vec![0xFF, 0xFE] } else { vec![0xFE, 0xFF] };
+        for unit in text.encode_utf16() {
+            bytes.extend(if little_endian { unit.to_le_bytes() } else { unit.to_be_bytes() });
+        }
+        bytes
+    }
+
+    #[test]
+    fn utf16_with_bom_decodes_without_the_bom() {
+        for little_endian in [true, false] {
+            let bytes = utf16("Привет, мир\n", little_endian);
+            let charset = detect(&bytes).unwrap();
+            let expected = if little_endian { Charset::Utf16Le { bom: true } } else { Charset::Utf16Be { bom: true } };
+            assert_eq!(charset, expected);
+            assert!(!charset.is_ascii_compatible());
+            assert_eq!(decode(&bytes, charset).as_deref(), Some("Привет, мир\n"));
+        }
+    }
+
+    #[test]
+    fn utf16_without_bom_is_detected_from_latin_text() {
+        let le: Vec<u8> = "hello\n".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        assert_eq!(detect(&le), Some(Charset::Utf16Le { bom: false }));
+        assert_eq!(decode(&le, Charset::Utf16Le { bom: false }).as_deref(), Some("hello\n"));
+
+        let be: Vec<u8> = "hello\n".encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(detect(&be), Some(Charset::Utf16Be { bom: false }));
+    }
+
+    #[test]
+    fn odd_length_utf16_does_not_decode() {
+        let mut bytes = utf16("abc", true);
+        bytes.push(b'd');
+        assert_eq!(decode(&bytes, Charset::Utf16Le { bom: true }), None);
+    }
+
+    #[test]
+    fn latin1_is_detected_when_bytes_are_not_utf8() {
+        // "Grüße, café\n" в ISO-8859-1
+        let bytes = b"Gr\xFC\xDFe, caf\xE9\n";
+        assert_eq!(detect(bytes), Some(Charset::Latin1));
+        assert_eq!(Charset::Latin1.name(), "iso-8859-1");
+        assert_eq!(decode(bytes, Charset::Latin1).as_deref(), Some("Grüße, café\n"));
+    }
+
+    #[test]
+    fn utf8_with_and_without_bom() {
+        let bytes = "\u{FEFF}# Заголовок\n".as_bytes();
+        assert_eq!(detect(bytes), Some(Charset::Utf8 { bom: true }));
+        assert_eq!(decode(bytes, Charset::Utf8 { bom: true }).as_deref(), Some("# Заголовок\n"));
+        assert_eq!(detect("plain\n".as_bytes()), Some(Charset::Utf8 { bom: false }));
+    }
+
+    #[test]
+    fn binary_data_has_no_charset() {
+        assert_eq!(detect(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), None);
+        assert_eq!(detect(b"\0\0\0\0"), None);
+    }
+}
@@ -0,0 +1,46 @@
+use chrono::{DateTime, Utc};
+
+/// Источник текущего времени
+///
+/// Логика, зависящая от времени (окна лимитов, сроки блокировок, возраст веток),
+/// получает часы параметром, а не вызывает `Utc::now()` сама, поэтому её можно
+/// проверить с подставными часами, не дожидаясь, пока время пройдёт на самом деле.
+pub trait Clock: Send + Sync {
+    /// Текущий момент в UTC
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Системные часы, используются сервером
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Подставные часы для тестов: время идёт только по `advance`
+#[cfg(test)]
+pub struct MockClock {
+    now: std::sync::Mutex<DateTime<Utc>>,
+}
+
+#[cfg(test)]
+impl MockClock {
+    /// Часы, остановленные на `now`
+    pub fn new(now: DateTime<Utc>) -> Self {
+        MockClock { now: std::sync::Mutex::new(now) }
+    }
+
+    /// Переводит часы вперёд
+    pub fn advance(&self, by: chrono::Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+}
+
+#[cfg(test)]
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
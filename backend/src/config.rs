@@ -0,0 +1,411 @@
+use std::collections::HashSet;
+use std::env;
+use std::path::PathBuf;
+use lazy_static::lazy_static;
+
+/// Кто может регистрироваться через `POST /api/auth/register` (GIT_HTTP_REGISTRATION_MODE)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RegistrationMode {
+    /// Любой желающий
+    Open,
+    /// Никто; аккаунты создают только администраторы
+    Closed,
+    /// Только по одноразовому приглашению от администратора
+    Invite,
+}
+
+impl RegistrationMode {
+    /// Разбирает значение из окружения; неизвестное значение закрывает регистрацию
+    fn parse(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "open" | "" => RegistrationMode::Open,
+            "closed" => RegistrationMode::Closed,
+            "invite" => RegistrationMode::Invite,
+            other => {
+                log::warn!("Unknown registration mode '{}', registration is closed", other);
+                RegistrationMode::Closed
+            }
+        }
+    }
+}
+
+/// `Content-Security-Policy` по умолчанию: всё только с этого же origin, без плагинов
+const DEFAULT_CONTENT_SECURITY_POLICY: &str = "default-src 'self'; object-src 'none'; base-uri 'self'";
+
+/// Настройки сервера, считываемые из переменных окружения при старте
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Записывать тела запросов/ответов upload-pack и receive-pack (GIT_HTTP_TRACE_PACKETS)
+    pub trace_packets: bool,
+    /// Каталог для файлов трассировки; если не задан, трассировка пишется в лог (GIT_HTTP_TRACE_DIR)
+    pub trace_dir: Option<PathBuf>,
+    /// Сколько секунд ждать блокировку репозитория перед отказом (GIT_HTTP_REPO_LOCK_TIMEOUT)
+    pub repo_lock_timeout_secs: u64,
+    /// Максимальный суммарный размер текста диффа в ответе API (GIT_HTTP_MAX_DIFF_BYTES)
+    pub max_diff_bytes: usize,
+    /// Максимальное число файлов диффа в ответе API (GIT_HTTP_MAX_DIFF_FILES)
+    pub max_diff_files: usize,
+    /// Максимальное число коммитов в списках коммитов API (GIT_HTTP_MAX_LOG_COMMITS)
+    pub max_log_commits: usize,
+    /// Квота на хранение для новых пользователей в байтах; None - без ограничений
+    /// (GIT_HTTP_DEFAULT_STORAGE_QUOTA)
+    pub default_storage_quota_bytes: Option<i64>,
+    /// Удалять комментарии к пул-реквестам мягко, оставляя пустую запись на месте
+    /// (GIT_HTTP_SOFT_DELETE_COMMENTS)
+    pub soft_delete_comments: bool,
+    /// Realm в заголовке WWW-Authenticate (GIT_HTTP_AUTH_REALM)
+    pub auth_realm: String,
+    /// Бэкенд аутентификации (GIT_HTTP_AUTH_BACKEND), пока поддерживается только `local`
+    pub auth_backend: String,
+    /// Максимальный размер тела запроса в байтах, в том числе для push (GIT_HTTP_MAX_BODY_BYTES)
+    pub max_body_bytes: usize,
+    /// Максимальный размер тела запроса git-upload-pack (GIT_HTTP_UPLOAD_PACK_MAX_BODY_BYTES);
+    /// в нём только согласование `want`/`have`, поэтому лимит отдельный от push. При
+    /// stateless RPC клиент в каждом раунде повторяет все свои `have`, и у давно
+    /// разошедшегося клона тело занимает мегабайты. Тело целиком держится в памяти,
+    /// так что лимит ограничивает и память на каждый одновременный fetch
+    pub upload_pack_max_body_bytes: usize,
+    /// Разрешённые git сервисы через запятую (GIT_HTTP_SERVICES); например, только
+    /// `git-upload-pack` для зеркала, доступного только на чтение
+    pub enabled_services: HashSet<String>,
+    /// Число потоков пула для git процессов (GIT_HTTP_GIT_THREADS)
+    pub git_pool_threads: usize,
+    /// Сколько git операций может ждать свободного потока, прежде чем сервер ответит 503
+    /// (GIT_HTTP_GIT_QUEUE)
+    pub git_pool_queue: usize,
+    /// Поиск секретов в новых коммитах (GIT_HTTP_SECRET_SCAN): `off`, `async` - после push
+    /// с уведомлением владельца, `strict` - отклонять push в pre-receive
+    pub secret_scan: String,
+    /// Файл с правилами поиска секретов, по строке `имя регулярное_выражение`
+    /// (GIT_HTTP_SECRET_RULES); если не задан, используются встроенные правила
+    pub secret_rules_file: Option<PathBuf>,
+    /// Файл `allowed_signers` для проверки SSH подписей коммитов (GIT_HTTP_ALLOWED_SIGNERS)
+    pub allowed_signers_file: Option<PathBuf>,
+    /// Каталог GnuPG с ключами для проверки GPG подписей коммитов (GIT_HTTP_GPG_HOME);
+    /// если не задан, используется `~/.gnupg` пользователя сервера
+    pub gpg_home: Option<PathBuf>,
+    /// Отдавать `Server-Timing` и `X-Git-Duration` с временем работы git для
+    /// upload-pack и receive-pack (GIT_HTTP_SERVER_TIMING)
+    pub server_timing: bool,
+    /// Публичный адрес сервера для ссылок клонирования, например `https://git.example.com`
+    /// (GIT_HTTP_EXTERNAL_URL); если не задан, адрес берётся из запроса
+    pub external_url: Option<String>,
+    /// Адрес документации, который показывается на `GET /` (GIT_HTTP_DOCS_URL)
+    pub docs_url: Option<String>,
+    /// Режим регистрации (GIT_HTTP_REGISTRATION_MODE): `open`, `closed` или `invite`
+    pub registration_mode: RegistrationMode,
+    /// Имена администраторов через запятую (GIT_HTTP_ADMINS)
+    pub admins: HashSet<String>,
+    /// Сколько секунд может работать `git fsck` перед остановкой (GIT_HTTP_FSCK_TIMEOUT)
+    pub fsck_timeout_secs: u64,
+    /// Размер репозитория в байтах, начиная с которого полный клон отклоняется, а клиенту
+    /// предлагается shallow или partial clone (GIT_HTTP_FULL_CLONE_MAX_BYTES); None - без ограничений
+    pub full_clone_max_bytes: Option<u64>,
+    /// Интервал keepalive-пакетов upload-pack в секундах, пока он готовит pack
+    /// (GIT_HTTP_UPLOAD_PACK_KEEPALIVE, `uploadpack.keepAlive`)
+    pub upload_pack_keepalive_secs: u64,
+    /// Значение `core.sharedRepository` для новых репозиториев (GIT_HTTP_SHARED_REPOSITORY):
+    /// `group` - каталоги и файлы доступны на запись группе, `umask` - права по umask процесса
+    pub shared_repository: String,
+    /// Сколько upload-pack (clone/fetch) одного репозитория может выполняться одновременно
+    /// (GIT_HTTP_MAX_CLONES_PER_REPO); None - без ограничений
+    pub max_clones_per_repo: Option<usize>,
+    /// После скольких неудачных попыток входа подряд аккаунт блокируется
+    /// (GIT_HTTP_LOGIN_LOCKOUT_THRESHOLD); None - без блокировки
+    pub login_lockout_threshold: Option<u32>,
+    /// На сколько секунд блокируется вход (GIT_HTTP_LOGIN_LOCKOUT_SECS)
+    pub login_lockout_secs: u64,
+    /// Сколько секунд проверенные Basic учётные данные принимаются без повторной
+    /// проверки (GIT_HTTP_AUTH_CACHE_SECS); 0 - без кэша
+    pub auth_cache_secs: u64,
+    /// Максимальное число учётных данных в кэше аутентификации (GIT_HTTP_AUTH_CACHE_ENTRIES)
+    pub auth_cache_entries: usize,
+    /// Сколько раз запускать фоновую задачу, прежде чем отметить её мёртвой
+    /// (GIT_HTTP_JOB_MAX_ATTEMPTS)
+    pub job_max_attempts: u32,
+    /// Задержка перед первым повтором фоновой задачи в секундах; дальше она удваивается
+    /// (GIT_HTTP_JOB_RETRY_SECS)
+    pub job_retry_secs: u64,
+    /// Как часто пересоздавать bundle для `bundle-uri`, в секундах (GIT_HTTP_BUNDLE_INTERVAL_SECS)
+    pub bundle_interval_secs: u64,
+    /// Через сколько секунд без изменений временный каталог считается брошенным
+    /// (GIT_HTTP_TEMP_MAX_AGE_SECS)
+    pub temp_max_age_secs: u64,
+    /// Как часто искать брошенные временные каталоги, в секундах
+    /// (GIT_HTTP_TEMP_CLEANUP_INTERVAL_SECS)
+    pub temp_cleanup_interval_secs: u64,
+    /// Путь к файлу базы данных SQLite (GIT_HTTP_DATABASE_PATH)
+    pub database_path: PathBuf,
+    /// За сколько секунд клиент должен прислать заголовки запроса, иначе 408
+    /// (GIT_HTTP_CLIENT_REQUEST_TIMEOUT); 0 - без ограничения
+    pub client_request_timeout_secs: u64,
+    /// Сколько секунд ждать закрытия соединения клиентом после ответа
+    /// (GIT_HTTP_CLIENT_DISCONNECT_TIMEOUT); 0 - без ограничения
+    pub client_disconnect_timeout_secs: u64,
+    /// Сколько секунд держать простаивающее keep-alive соединение (GIT_HTTP_KEEP_ALIVE);
+    /// 0 - закрывать соединение после каждого ответа
+    pub keep_alive_secs: u64,
+    /// Сколько соединений один рабочий поток держит одновременно (GIT_HTTP_MAX_CONNECTIONS);
+    /// дальше новые соединения ждут в очереди ОС, а не открываются
+    pub max_connections: usize,
+    /// Сколько TLS рукопожатий один рабочий поток выполняет одновременно
+    /// (GIT_HTTP_MAX_CONNECTION_RATE); без TLS ни на что не влияет
+    pub max_connection_rate: usize,
+    /// Включены ли пул-реквесты на сервере (GIT_HTTP_PULL_REQUESTS, по умолчанию включены);
+    /// выключенные глобально, они недоступны во всех репозиториях независимо от их настроек
+    pub pull_requests_enabled: bool,
+    /// Сколько репозиториев пользователь может создать за час, включая форки и импорт
+    /// (GIT_HTTP_MAX_REPOS_PER_HOUR); None - без ограничений, администраторы не ограничены
+    pub max_repos_per_hour: Option<u32>,
+    /// Каталог для временных клонов при слиянии пул-реквестов (GIT_HTTP_TEMP_DIR);
+    /// по умолчанию системный временный каталог
+    pub temp_dir: PathBuf,
+    /// Каталог с репликой репозиториев только для чтения (GIT_HTTP_REPLICA_ROOT); из неё
+    /// отдаются clone, fetch, объекты и архивы, а все записи идут в основной каталог
+    pub replica_root: Option<PathBuf>,
+    /// Читать из основного каталога репозитории, которых нет на реплике или которые на ней
+    /// устарели (GIT_HTTP_REPLICA_FALLBACK, по умолчанию включено)
+    pub replica_fallback: bool,
+    /// На сколько секунд ссылки на реплике могут отставать от основного каталога
+    /// (GIT_HTTP_REPLICA_MAX_LAG_SECS); None - не проверять
+    pub replica_max_lag_secs: Option<u64>,
+    /// Значение `X-Frame-Options` (GIT_HTTP_FRAME_OPTIONS): `DENY` или `SAMEORIGIN`;
+    /// None (`off`) - не отправлять
+    pub frame_options: Option<String>,
+    /// `Content-Security-Policy` для HTML ответов (GIT_HTTP_CONTENT_SECURITY_POLICY); зависит
+    /// от фронтенда, поэтому настраивается целиком; None (`off`) - не отправлять
+    pub content_security_policy: Option<String>,
+}
+
+impl Config {
+    /// Загружает конфигурацию из переменных окружения
+    pub fn from_env() -> Self {
+        Config {
+            trace_packets: env_flag("GIT_HTTP_TRACE_PACKETS"),
+            trace_dir: env::var("GIT_HTTP_TRACE_DIR").ok().map(PathBuf::from),
+            repo_lock_timeout_secs: env_parse("GIT_HTTP_REPO_LOCK_TIMEOUT", 300),
+            max_diff_bytes: env_parse("GIT_HTTP_MAX_DIFF_BYTES", 1024 * 1024),
+            max_diff_files: env_parse("GIT_HTTP_MAX_DIFF_FILES", 1000),
+            max_log_commits: env_parse("GIT_HTTP_MAX_LOG_COMMITS", 1000),
+            default_storage_quota_bytes: env::var("GIT_HTTP_DEFAULT_STORAGE_QUOTA")
+                .ok()
+                .and_then(|value| value.trim().parse().ok()),
+            soft_delete_comments: env_flag("GIT_HTTP_SOFT_DELETE_COMMENTS"),
+            auth_realm: env::var("GIT_HTTP_AUTH_REALM").unwrap_or_else(|_| "Git".to_string()),
+            auth_backend: env::var("GIT_HTTP_AUTH_BACKEND").unwrap_or_else(|_| "local".to_string()),
+            // По умолчанию - стандартный лимит actix-web
+            max_body_bytes: env_parse("GIT_HTTP_MAX_BODY_BYTES", 256 * 1024),
+            upload_pack_max_body_bytes: env_parse("GIT_HTTP_UPLOAD_PACK_MAX_BODY_BYTES", 8 * 1024 * 1024),
+            enabled_services: env::var("GIT_HTTP_SERVICES")
+                .unwrap_or_else(|_| "git-upload-pack,git-receive-pack".to_string())
+                .split(',')
+                .map(|service| service.trim().to_string())
+                .filter(|service| !service.is_empty())
+                .collect(),
+            git_pool_threads: env_parse("GIT_HTTP_GIT_THREADS", 8),
+            git_pool_queue: env_parse("GIT_HTTP_GIT_QUEUE", 32),
+            secret_scan: env::var("GIT_HTTP_SECRET_SCAN").unwrap_or_else(|_| "off".to_string()),
+            secret_rules_file: env::var("GIT_HTTP_SECRET_RULES").ok().map(PathBuf::from),
+            allowed_signers_file: env::var("GIT_HTTP_ALLOWED_SIGNERS").ok().map(PathBuf::from),
+            gpg_home: env::var("GIT_HTTP_GPG_HOME").ok().map(PathBuf::from),
+            server_timing: env_flag("GIT_HTTP_SERVER_TIMING"),
+            external_url: env::var("GIT_HTTP_EXTERNAL_URL")
+                .ok()
+                .map(|url| url.trim().trim_end_matches('/').to_string())
+                .filter(|url| !url.is_empty()),
+            docs_url: env::var("GIT_HTTP_DOCS_URL")
+                .ok()
+                .map(|url| url.trim().to_string())
+                .filter(|url| !url.is_empty()),
+            registration_mode: RegistrationMode::parse(
+                &env::var("GIT_HTTP_REGISTRATION_MODE").unwrap_or_default()
+            ),
+            admins: env::var("GIT_HTTP_ADMINS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect(),
+            fsck_timeout_secs: env_parse("GIT_HTTP_FSCK_TIMEOUT", 600),
+            full_clone_max_bytes: env::var("GIT_HTTP_FULL_CLONE_MAX_BYTES")
+                .ok()
+                .and_then(|value| value.trim().parse().ok()),
+            upload_pack_keepalive_secs: env_parse("GIT_HTTP_UPLOAD_PACK_KEEPALIVE", 5),
+            shared_repository: env::var("GIT_HTTP_SHARED_REPOSITORY")
+                .map(|value| value.trim().to_lowercase())
+                .unwrap_or_else(|_| "group".to_string()),
+            max_clones_per_repo: env::var("GIT_HTTP_MAX_CLONES_PER_REPO")
+                .ok()
+                .and_then(|value| value.trim().parse().ok())
+                .filter(|limit| *limit > 0),
+            login_lockout_threshold: env::var("GIT_HTTP_LOGIN_LOCKOUT_THRESHOLD")
+                .ok()
+                .and_then(|value| value.trim().parse().ok())
+                .filter(|threshold| *threshold > 0),
+            login_lockout_secs: env_parse("GIT_HTTP_LOGIN_LOCKOUT_SECS", 900),
+            auth_cache_secs: env_parse("GIT_HTTP_AUTH_CACHE_SECS", 5),
+            auth_cache_entries: env_parse("GIT_HTTP_AUTH_CACHE_ENTRIES", 1024),
+            job_max_attempts: env_parse("GIT_HTTP_JOB_MAX_ATTEMPTS", 5),
+            job_retry_secs: env_parse("GIT_HTTP_JOB_RETRY_SECS", 30),
+            bundle_interval_secs: env_parse("GIT_HTTP_BUNDLE_INTERVAL_SECS", 24 * 60 * 60),
+            temp_max_age_secs: env_parse("GIT_HTTP_TEMP_MAX_AGE_SECS", 24 * 60 * 60),
+            temp_cleanup_interval_secs: env_parse("GIT_HTTP_TEMP_CLEANUP_INTERVAL_SECS", 60 * 60),
+            database_path: env::var("GIT_HTTP_DATABASE_PATH")
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from("gitea.db")),
+            client_request_timeout_secs: env_parse("GIT_HTTP_CLIENT_REQUEST_TIMEOUT", 5),
+            client_disconnect_timeout_secs: env_parse("GIT_HTTP_CLIENT_DISCONNECT_TIMEOUT", 5),
+            keep_alive_secs: env_parse("GIT_HTTP_KEEP_ALIVE", 5),
+            max_connections: env_parse("GIT_HTTP_MAX_CONNECTIONS", 1024),
+            max_connection_rate: env_parse("GIT_HTTP_MAX_CONNECTION_RATE", 256),
+            pull_requests_enabled: env::var("GIT_HTTP_PULL_REQUESTS")
+                .map(|value| !matches!(value.trim().to_lowercase().as_str(), "0" | "false" | "no" | "off"))
+                .unwrap_or(true),
+            max_repos_per_hour: env::var("GIT_HTTP_MAX_REPOS_PER_HOUR")
+                .ok()
+                .and_then(|value| value.trim().parse().ok())
+                .filter(|limit| *limit > 0),
+            temp_dir: env::var("GIT_HTTP_TEMP_DIR")
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+                .map(PathBuf::from)
+                .unwrap_or_else(env::temp_dir),
+            replica_root: env::var("GIT_HTTP_REPLICA_ROOT")
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+                .map(PathBuf::from),
+            replica_fallback: env::var("GIT_HTTP_REPLICA_FALLBACK")
+                .map(|value| !matches!(value.trim().to_lowercase().as_str(), "0" | "false" | "no" | "off"))
+                .unwrap_or(true),
+            replica_max_lag_secs: env::var("GIT_HTTP_REPLICA_MAX_LAG_SECS")
+                .ok()
+                .and_then(|value| value.trim().parse().ok()),
+            frame_options: env_header("GIT_HTTP_FRAME_OPTIONS", "DENY").map(|value| value.to_uppercase()),
+            content_security_policy: env_header("GIT_HTTP_CONTENT_SECURITY_POLICY", DEFAULT_CONTENT_SECURITY_POLICY),
+        }
+    }
+
+    /// Проверяет настройки, с которыми сервер не сможет работать
+    ///
+    /// Неизвестные значения перечислений здесь не проверяются: для них
+    /// выбирается безопасное значение по умолчанию с предупреждением в логе.
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<(), String>` - Описание первой найденной ошибки
+    pub fn validate(&self) -> Result<(), String> {
+        if self.git_pool_threads == 0 {
+            return Err("GIT_HTTP_GIT_THREADS must be greater than 0".to_string());
+        }
+        if self.job_max_attempts == 0 {
+            return Err("GIT_HTTP_JOB_MAX_ATTEMPTS must be greater than 0".to_string());
+        }
+        if self.max_connections == 0 {
+            return Err("GIT_HTTP_MAX_CONNECTIONS must be greater than 0".to_string());
+        }
+        if self.max_connection_rate == 0 {
+            return Err("GIT_HTTP_MAX_CONNECTION_RATE must be greater than 0".to_string());
+        }
+        if self.bundle_interval_secs == 0 {
+            return Err("GIT_HTTP_BUNDLE_INTERVAL_SECS must be greater than 0".to_string());
+        }
+        if self.max_diff_files == 0 {
+            return Err("GIT_HTTP_MAX_DIFF_FILES must be greater than 0".to_string());
+        }
+        if self.max_log_commits == 0 {
+            return Err("GIT_HTTP_MAX_LOG_COMMITS must be greater than 0".to_string());
+        }
+        if self.temp_cleanup_interval_secs == 0 {
+            return Err("GIT_HTTP_TEMP_CLEANUP_INTERVAL_SECS must be greater than 0".to_string());
+        }
+        if let Some(dir) = &self.trace_dir {
+            // Каталог создаётся при первой записи трассировки, но не поверх файла
+            if dir.exists() && !dir.is_dir() {
+                return Err(format!("GIT_HTTP_TRACE_DIR '{}' is not a directory", dir.display()));
+            }
+        }
+        if self.temp_dir.exists() && !self.temp_dir.is_dir() {
+            return Err(format!("GIT_HTTP_TEMP_DIR '{}' is not a directory", self.temp_dir.display()));
+        }
+        if let Some(root) = &self.replica_root {
+            if !root.is_dir() {
+                return Err(format!("GIT_HTTP_REPLICA_ROOT '{}' is not a directory", root.display()));
+            }
+        }
+        if !is_shared_repository_value(&self.shared_repository) {
+            return Err(format!(
+                "GIT_HTTP_SHARED_REPOSITORY '{}' must be umask, group, all or an octal mode like 0660",
+                self.shared_repository
+            ));
+        }
+        if let Some(file) = &self.secret_rules_file {
+            if !file.is_file() {
+                return Err(format!("GIT_HTTP_SECRET_RULES '{}' is not a file", file.display()));
+            }
+        }
+        if let Some(file) = &self.allowed_signers_file {
+            if !file.is_file() {
+                return Err(format!("GIT_HTTP_ALLOWED_SIGNERS '{}' is not a file", file.display()));
+            }
+        }
+        if let Some(dir) = &self.gpg_home {
+            if !dir.is_dir() {
+                return Err(format!("GIT_HTTP_GPG_HOME '{}' is not a directory", dir.display()));
+            }
+        }
+        if let Some(value) = &self.frame_options {
+            if value != "DENY" && value != "SAMEORIGIN" {
+                return Err(format!("GIT_HTTP_FRAME_OPTIONS '{}' must be DENY, SAMEORIGIN or off", value));
+            }
+        }
+        if let Some(policy) = &self.content_security_policy {
+            if !policy.chars().all(|c| c == ' ' || c.is_ascii_graphic()) {
+                return Err("GIT_HTTP_CONTENT_SECURITY_POLICY must contain only printable ASCII".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Проверяет, что значение допустимо для `core.sharedRepository` (см. `git init --shared`)
+fn is_shared_repository_value(value: &str) -> bool {
+    match value {
+        "umask" | "false" | "group" | "true" | "all" | "world" | "everybody" => true,
+        mode => mode.len() == 4 && mode.starts_with('0') && mode.chars().all(|c| ('0'..='7').contains(&c)),
+    }
+}
+
+/// Читает булев флаг из окружения; включён только при явном "1", "true", "yes" или "on"
+fn env_flag(name: &str) -> bool {
+    match env::var(name) {
+        Ok(value) => matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes" | "on"),
+        Err(_) => false,
+    }
+}
+
+/// Читает значение заголовка из окружения; `off` отключает заголовок, пустое значение - по умолчанию
+fn env_header(name: &str, default: &str) -> Option<String> {
+    let value = env::var(name).map(|value| value.trim().to_string()).unwrap_or_default();
+    if value.eq_ignore_ascii_case("off") {
+        None
+    } else if value.is_empty() {
+        Some(default.to_string())
+    } else {
+        Some(value)
+    }
+}
+
+/// Читает числовое значение из окружения, возвращая значение по умолчанию при ошибке
+fn env_parse<T: std::str::FromStr>(name: &str, default: T) -> T {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+lazy_static! {
+    /// Глобальная конфигурация сервера
+    pub static ref CONFIG: Config = Config::from_env();
+}
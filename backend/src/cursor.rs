@@ -0,0 +1,107 @@
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use crate::timestamps::DB_FORMAT;
+
+/// Позиция в списке, отсортированном от новых записей к старым по `(created_at, id)`
+///
+/// Клиенту отдаётся непрозрачной строкой (`next_cursor`). Следующая страница
+/// начинается строго после записи, на которой закончилась предыдущая, поэтому
+/// записи, добавленные между запросами, не сдвигают страницы: ничего не
+/// пропускается и не повторяется, в отличие от OFFSET.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cursor {
+    /// Дата последней полученной записи в формате базы данных
+    pub created_at: String,
+    /// ID последней полученной записи
+    pub id: i64,
+}
+
+impl Cursor {
+    /// Курсор, указывающий на запись
+    ///
+    /// # Параметры
+    ///
+    /// * `created_at` - Дата создания записи
+    /// * `id` - ID записи
+    pub fn after(created_at: Option<&DateTime<Utc>>, id: i64) -> Self {
+        Cursor {
+            created_at: created_at.map(|dt| dt.format(DB_FORMAT).to_string()).unwrap_or_default(),
+            id,
+        }
+    }
+
+    /// Кодирует курсор в строку для клиента
+    pub fn encode(&self) -> String {
+        BASE64_URL.encode(format!("{}|{}", self.created_at, self.id))
+    }
+
+    /// Разбирает строку, полученную из `encode`
+    ///
+    /// # Параметры
+    ///
+    /// * `token` - Значение параметра `cursor`
+    ///
+    /// # Возвращает
+    ///
+    /// * `Option<Cursor>` - Курсор или None, если строка повреждена
+    pub fn decode(token: &str) -> Option<Self> {
+        let bytes = BASE64_URL.decode(token.trim()).ok()?;
+        let text = String::from_utf8(bytes).ok()?;
+        let (created_at, id) = text.rsplit_once('|')?;
+
+        if !created_at.is_empty() && NaiveDateTime::parse_from_str(created_at, DB_FORMAT).is_err() {
+            return None;
+        }
+
+        Some(Cursor {
+            created_at: created_at.to_string(),
+            id: id.parse().ok()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn round_trips() {
+        let created_at = Utc.with_ymd_and_hms(2024, 5, 1, 12, 30, 0).unwrap();
+        let cursor = Cursor::after(Some(&created_at), 42);
+        assert_eq!(cursor.created_at, "2024-05-01 12:30:00");
+        assert_eq!(Cursor::decode(&cursor.encode()), Some(cursor));
+
+        let undated = Cursor::after(None, 7);
+        assert_eq!(Cursor::decode(&undated.encode()), Some(undated));
+    }
+
+    #[test]
+    fn encoded_cursor_is_url_safe() {
+        let token = Cursor { created_at: "2024-05-01 12:30:00".to_string(), id: i64::MAX }.encode();
+        assert!(token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'), "{}", token);
+    }
+
+    #[test]
+    fn rejects_invalid_and_tampered_cursors() {
+        let valid = Cursor { created_at: "2024-05-01 12:30:00".to_string(), id: 42 }.encode();
+        let mut tampered = valid.clone();
+        tampered.replace_range(0..1, if tampered.starts_with('A') { "B" } else { "A" });
+
+        let invalid = [
+            String::new(),
+            "not base64!".to_string(),
+            BASE64_URL.encode("2024-05-01 12:30:00"),
+            BASE64_URL.encode("2024-05-01 12:30:00|"),
+            BASE64_URL.encode("2024-05-01 12:30:00|abc"),
+            BASE64_URL.encode("2024-05-01 12:30:00|42; DROP TABLE users"),
+            BASE64_URL.encode("yesterday|42"),
+            BASE64_URL.encode("2024-05-01T12:30:00Z|42"),
+            BASE64_URL.encode([0xFF, 0xFE, b'|', b'1']),
+            tampered,
+        ];
+        for token in invalid {
+            assert_eq!(Cursor::decode(&token), None, "{}", token);
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use actix_web::error::{InternalError, JsonPayloadError};
+use actix_web::{http::StatusCode, HttpRequest, HttpResponse, ResponseError};
+use std::fmt;
+use crate::handlers::api::ApiResponse;
+
+/// Через сколько секунд клиенту предлагается повторить запрос при перегрузке
+const RETRY_AFTER_SECS: u32 = 5;
+
+/// Ошибки приложения, которые можно вернуть клиенту как HTTP ответ
+#[derive(Debug)]
+pub enum AppError {
+    /// Недопустимое имя репозитория
+    InvalidRepoName(String),
+    /// Репозиторий не найден на диске
+    RepoNotFound(String),
+    /// Репозиторий есть в базе данных, но его каталог пропал с диска
+    StorageMissing(String),
+    /// Недопустимое имя ссылки или ревизии
+    InvalidRef(String),
+    /// Ссылка или ревизия не найдена в репозитории
+    RefNotFound(String),
+    /// Ошибка при выполнении git
+    Git(String),
+    /// Состояние репозитория изменилось, операцию нужно повторить
+    Conflict(String),
+    /// Ошибка базы данных
+    Database(String),
+    /// Запрос корректен, но не может быть выполнен для этого объекта
+    Unsupported(String),
+    /// Сервер перегружен, запрос стоит повторить позже
+    Busy(String),
+    /// Операция не уложилась в отведённое время
+    Timeout(String),
+    /// Объект не найден (сообщение безопасно показывать клиенту)
+    NotFound(String),
+    /// Запрос отклонён как некорректный
+    BadRequest(String),
+    /// Нет прав на выполнение операции
+    Forbidden(String),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::InvalidRepoName(name) => write!(f, "Invalid repository name: {}", name),
+            AppError::RepoNotFound(name) => write!(f, "Repository not found: {}", name),
+            AppError::StorageMissing(name) => write!(f, "Repository storage missing: {}", name),
+            AppError::InvalidRef(rev) => write!(f, "Invalid ref: {}", rev),
+            AppError::RefNotFound(rev) => write!(f, "Ref not found: {}", rev),
+            AppError::Git(msg) => write!(f, "Git error: {}", msg),
+            AppError::Conflict(msg) => write!(f, "{}", msg),
+            AppError::Database(msg) => write!(f, "Database error: {}", msg),
+            AppError::Unsupported(msg) => write!(f, "{}", msg),
+            AppError::Busy(msg) => write!(f, "{}", msg),
+            AppError::Timeout(msg) => write!(f, "{}", msg),
+            AppError::NotFound(msg) => write!(f, "{}", msg),
+            AppError::BadRequest(msg) => write!(f, "{}", msg),
+            AppError::Forbidden(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl AppError {
+    /// Сообщение об ошибке, которое можно показать клиенту
+    ///
+    /// Вывод git и ошибки SQLite могут содержать пути на сервере, поэтому клиенту их не отдаём.
+    pub fn client_message(&self) -> String {
+        match self {
+            AppError::Git(_) => "Git operation failed".to_string(),
+            AppError::Database(_) => "Database error".to_string(),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        AppError::Database(e.to_string())
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::InvalidRepoName(_) => StatusCode::BAD_REQUEST,
+            AppError::RepoNotFound(_) => StatusCode::NOT_FOUND,
+            AppError::StorageMissing(_) => StatusCode::NOT_FOUND,
+            AppError::InvalidRef(_) => StatusCode::BAD_REQUEST,
+            AppError::RefNotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Git(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::Unsupported(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::Busy(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let message = self.client_message();
+
+        let mut response = HttpResponse::build(self.status_code());
+        if let AppError::Busy(_) = self {
+            response.append_header(("Retry-After", RETRY_AFTER_SECS.to_string()));
+        }
+
+        response.json(ApiResponse::<()> {
+            success: false,
+            message: Some(message),
+            data: None,
+        })
+    }
+}
+
+/// Обработчик ошибок разбора JSON тела запроса (`web::JsonConfig::error_handler`)
+///
+/// Вместо текстовой ошибки actix клиент получает `ApiResponse` с описанием
+/// проблемы: 400 для некорректного JSON и неподходящих полей, 413 для слишком
+/// большого тела и 415 для неверного `Content-Type`.
+pub fn json_error_handler(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    let status = match &err {
+        JsonPayloadError::ContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        JsonPayloadError::Overflow { .. } | JsonPayloadError::OverflowKnownLength { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+        _ => StatusCode::BAD_REQUEST,
+    };
+
+    let response = HttpResponse::build(status).json(ApiResponse::<()> {
+        success: false,
+        message: Some(json_error_message(&err)),
+        data: None,
+    });
+    InternalError::from_response(err, response).into()
+}
+
+/// Описание ошибки разбора JSON для клиента, с именем поля, если serde его сообщил
+fn json_error_message(err: &JsonPayloadError) -> String {
+    let e = match err {
+        JsonPayloadError::Deserialize(e) => e,
+        JsonPayloadError::ContentType => return "Content-Type must be application/json".to_string(),
+        JsonPayloadError::Overflow { limit } | JsonPayloadError::OverflowKnownLength { limit, .. } => {
+            return format!("Request body exceeds {} bytes", limit);
+        }
+        other => return format!("Invalid request body: {}", other),
+    };
+
+    if !e.is_data() {
+        return format!("Malformed JSON at line {} column {}", e.line(), e.column());
+    }
+
+    // Сообщение serde: "missing field `name` at line 1 column 2"
+    let text = e.to_string();
+    let detail = text.split(" at line ").next().unwrap_or_default();
+    let field = detail.split('`').nth(1).unwrap_or_default();
+    if detail.starts_with("missing field") {
+        format!("{} is required", field)
+    } else if detail.starts_with("unknown field") {
+        format!("{} is not a known field", field)
+    } else {
+        format!("Invalid value at line {} column {}: {}", e.line(), e.column(), detail)
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use lazy_static::lazy_static;
+use log::debug;
+use tokio::sync::oneshot;
+use crate::error::AppError;
+use crate::git::pool::run_blocking;
+
+/// Объявление ссылок: вывод `git-*-pack --advertise-refs`
+pub type Advertisement = Arc<Vec<u8>>;
+
+/// Ключ объявления: репозиторий, сервис и версия протокола
+pub type AdvertisementKey = (String, &'static str, String);
+
+/// Состояние объявления для одного ключа
+enum Slot {
+    /// Объявление для поколения ссылок уже посчитано
+    Ready(i64, Advertisement),
+    /// Объявление для поколения считается; ждущие запросы получат результат
+    Pending(i64, Vec<oneshot::Sender<Advertisement>>),
+}
+
+lazy_static! {
+    static ref SLOTS: Mutex<HashMap<AdvertisementKey, Slot>> = Mutex::new(HashMap::new());
+}
+
+/// Снимает незавершённое вычисление, если запрос-вычислитель был отменён
+///
+/// Отправители ждущих запросов удаляются вместе со слотом, и те считают
+/// объявление сами, а не ждут вечно.
+struct PendingGuard {
+    key: Option<AdvertisementKey>,
+    generation: i64,
+}
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            let mut slots = SLOTS.lock().unwrap_or_else(|e| e.into_inner());
+            if matches!(slots.get(&key), Some(Slot::Pending(generation, _)) if *generation == self.generation) {
+                slots.remove(&key);
+            }
+        }
+    }
+}
+
+/// Что делать запросу после проверки слота
+enum Action {
+    Cached(Advertisement),
+    Wait(oneshot::Receiver<Advertisement>),
+    Compute,
+}
+
+/// Удаляет из кэша все объявления репозитория
+///
+/// Незавершённые вычисления тоже снимаются: их результат не попадёт в кэш,
+/// а ждущие запросы посчитают объявление сами.
+///
+/// # Параметры
+///
+/// * `repo_name` - Полное имя репозитория `{owner}/{name}`
+pub fn purge(repo_name: &str) {
+    let mut slots = SLOTS.lock().unwrap_or_else(|e| e.into_inner());
+    slots.retain(|(name, _, _), _| name != repo_name);
+}
+
+/// Возвращает объявление ссылок, запуская git не больше одного раза на поколение ссылок
+///
+/// Одновременные запросы с одинаковым ключом и поколением ждут одного git процесса,
+/// а его результат остаётся в кэше до следующего изменения ссылок. Поколение
+/// (`repositories.ref_generation`) читается до запуска git, поэтому объявление,
+/// снятое во время push, сохранится под устаревшим поколением и не будет отдано
+/// после него.
+///
+/// # Параметры
+///
+/// * `key` - Репозиторий, сервис и версия протокола
+/// * `generation` - Поколение ссылок; None - без кэша и объединения запросов
+/// * `compute` - Запуск git, выполняется в пуле git процессов
+///
+/// # Возвращает
+///
+/// * `Result<Advertisement, AppError>` - Объявление или ошибка git/пула
+pub async fn advertisement<F>(key: AdvertisementKey, generation: Option<i64>, compute: F) -> Result<Advertisement, AppError>
+where
+    F: FnOnce() -> Result<Vec<u8>, AppError> + Send + 'static,
+{
+    let generation = match generation {
+        Some(generation) => generation,
+        None => return run_blocking(compute).await?.map(Arc::new),
+    };
+
+    let action = {
+        let mut slots = SLOTS.lock().unwrap_or_else(|e| e.into_inner());
+        match slots.get_mut(&key) {
+            Some(Slot::Ready(ready, advertisement)) if *ready == generation => Action::Cached(advertisement.clone()),
+            Some(Slot::Pending(pending, waiters)) if *pending == generation => {
+                let (sender, receiver) = oneshot::channel();
+                waiters.push(sender);
+                Action::Wait(receiver)
+            }
+            _ => {
+                slots.insert(key.clone(), Slot::Pending(generation, Vec::new()));
+                Action::Compute
+            }
+        }
+    };
+
+    match action {
+        Action::Cached(advertisement) => return Ok(advertisement),
+        Action::Wait(receiver) => {
+            // Вычислитель отменён или git завершился ошибкой - считаем сами
+            if let Ok(advertisement) = receiver.await {
+                return Ok(advertisement);
+            }
+            return run_blocking(compute).await?.map(Arc::new);
+        }
+        Action::Compute => {}
+    }
+
+    let mut guard = PendingGuard { key: Some(key), generation };
+    debug!("Computing advertisement for {:?} at generation {}", guard.key, generation);
+    let result = run_blocking(compute).await.and_then(|result| result).map(Arc::new);
+
+    let key = guard.key.take().unwrap();
+    let mut slots = SLOTS.lock().unwrap_or_else(|e| e.into_inner());
+    match slots.remove(&key) {
+        Some(Slot::Pending(pending, waiters)) if pending == generation => {
+            // При ошибке отправители удаляются, и ждущие запросы запускают git сами
+            if let Ok(advertisement) = &result {
+                slots.insert(key, Slot::Ready(generation, advertisement.clone()));
+                for waiter in waiters {
+                    let _ = waiter.send(advertisement.clone());
+                }
+            }
+        }
+        // Слот уже занят другим поколением - его не трогаем
+        Some(slot) => {
+            slots.insert(key, slot);
+        }
+        None => {}
+    }
+
+    result
+}
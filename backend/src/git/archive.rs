@@ -0,0 +1,276 @@
+use std::error::Error as StdError;
+use std::future::Future;
+use std::io::{self, Read};
+use std::path::Path;
+use std::pin::Pin;
+use std::process::{Child, ChildStdout, Output, Stdio};
+use std::task::{Context, Poll};
+use actix_web::body::{BodySize, MessageBody};
+use actix_web::web::Bytes;
+use log::{error, info, warn};
+use tokio::task::JoinHandle;
+use crate::error::AppError;
+use crate::git::errors::git_failure;
+use crate::git::git_command;
+
+/// Сколько байт вывода git читается за один шаг отправки архива
+const ARCHIVE_CHUNK: u64 = 64 * 1024;
+
+/// Формат архива, поддерживаемый `git archive`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveFormat {
+    Tar,
+    TarGz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// Отделяет расширение архива от ревизии: `v1.0.tar.gz` -> (`v1.0`, TarGz)
+    pub fn split_spec(spec: &str) -> Option<(&str, ArchiveFormat)> {
+        let formats = [
+            (".tar.gz", ArchiveFormat::TarGz),
+            (".tgz", ArchiveFormat::TarGz),
+            (".tar", ArchiveFormat::Tar),
+            (".zip", ArchiveFormat::Zip),
+        ];
+
+        formats.iter().find_map(|(ext, format)| {
+            spec.strip_suffix(ext)
+                .filter(|rev| !rev.is_empty())
+                .map(|rev| (rev, *format))
+        })
+    }
+
+    /// Имя формата для `git archive --format`
+    fn git_name(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Tar => "tar",
+            ArchiveFormat::TarGz => "tar.gz",
+            ArchiveFormat::Zip => "zip",
+        }
+    }
+
+    /// Расширение файла архива
+    pub fn extension(&self) -> &'static str {
+        self.git_name()
+    }
+
+    /// MIME-тип архива
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Tar => "application/x-tar",
+            ArchiveFormat::TarGz => "application/gzip",
+            ArchiveFormat::Zip => "application/zip",
+        }
+    }
+}
+
+/// Архив, который отправляется клиенту по мере того, как его собирает `git archive`
+///
+/// Вывод git читается кусками не больше `ARCHIVE_CHUNK` и только когда actix
+/// просит следующий кусок, то есть когда клиент забрал предыдущие. Пока клиент
+/// не читает, git упирается в заполненный pipe и ждёт, так что ни сервер, ни git
+/// не накапливают архив в памяти. Если ответ бросили (клиент отключился), процесс
+/// git завершается при удалении тела. Объём отправленного пишется в лог.
+pub struct ArchiveStream {
+    child: Child,
+    /// Вывод git; None, пока кусок читается в фоне или когда вывод закончился
+    stdout: Option<ChildStdout>,
+    /// Первый кусок, прочитанный до отправки заголовков
+    first: Option<Vec<u8>>,
+    /// Чтение следующего куска в пуле блокирующих задач
+    reading: Option<JoinHandle<(ChildStdout, io::Result<Vec<u8>>)>>,
+    /// Что отправляется, для лога
+    label: String,
+    /// Отправлено байт
+    sent: u64,
+    /// git закончил работу (успешно или нет), дальше отправлять нечего
+    finished: bool,
+    /// Архив отправлен целиком
+    completed: bool,
+}
+
+impl ArchiveStream {
+    /// Запускает `git archive` и читает первый кусок архива
+    ///
+    /// Ошибки git (например, неизвестная ревизия) появляются до первого байта
+    /// архива, поэтому первый кусок читается здесь: так ошибка возвращается
+    /// обычным ответом, а не оборванным архивом.
+    ///
+    /// Вывод воспроизводим для одного и того же коммита и префикса: время файлов
+    /// берётся из коммита, а `tar.gz` сжимается без метки времени. Байты могут
+    /// измениться при обновлении git на сервере (другая реализация сжатия).
+    ///
+    /// Файлы с атрибутом `export-ignore` в архив не попадают: git читает `.gitattributes`
+    /// из самого архивируемого дерева, рабочая копия для этого не нужна, поэтому
+    /// `--worktree-attributes` в bare репозитории не требуется.
+    ///
+    /// Функция блокирующая, её нужно вызывать через `run_blocking`.
+    ///
+    /// # Параметры
+    ///
+    /// * `repo_path` - Путь к bare репозиторию
+    /// * `sha` - SHA коммита
+    /// * `prefix` - Каталог, в который помещаются файлы внутри архива
+    /// * `format` - Формат архива
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<ArchiveStream, AppError>` - Тело ответа с архивом
+    pub fn start(repo_path: &Path, sha: &str, prefix: &str, format: ArchiveFormat) -> Result<ArchiveStream, AppError> {
+        let format_arg = format!("--format={}", format.git_name());
+        let prefix_arg = format!("--prefix={}/", prefix);
+
+        let mut child = git_command()
+            .arg("--git-dir")
+            .arg(repo_path)
+            .args(["archive", &format_arg, &prefix_arg, sha])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                error!("Failed to run git archive: {}", e);
+                AppError::Git(e.to_string())
+            })?;
+
+        let mut stdout = child.stdout.take().ok_or_else(|| AppError::Git("git archive has no output".to_string()))?;
+        let mut stream = ArchiveStream {
+            child,
+            stdout: None,
+            first: None,
+            reading: None,
+            label: prefix.to_string(),
+            sent: 0,
+            finished: false,
+            completed: false,
+        };
+
+        let first = read_chunk(&mut stdout).map_err(|e| {
+            error!("Failed to read git archive output: {}", e);
+            AppError::Git(e.to_string())
+        })?;
+        if first.is_empty() {
+            stream.finish()?;
+        } else {
+            stream.first = Some(first);
+            stream.stdout = Some(stdout);
+        }
+
+        Ok(stream)
+    }
+
+    /// Дожидается завершения git после конца вывода
+    fn finish(&mut self) -> Result<(), AppError> {
+        self.finished = true;
+
+        let mut stderr = Vec::new();
+        if let Some(mut pipe) = self.child.stderr.take() {
+            let _ = pipe.read_to_end(&mut stderr);
+        }
+        let status = self.child.wait().map_err(|e| AppError::Git(e.to_string()))?;
+        if !status.success() {
+            return Err(git_failure("archive", &Output { status, stdout: Vec::new(), stderr }));
+        }
+
+        self.completed = true;
+        Ok(())
+    }
+}
+
+/// Читает из вывода git не больше `ARCHIVE_CHUNK` байт; пустой кусок - конец вывода
+fn read_chunk(stdout: &mut ChildStdout) -> io::Result<Vec<u8>> {
+    let mut chunk = Vec::with_capacity(ARCHIVE_CHUNK as usize);
+    stdout.by_ref().take(ARCHIVE_CHUNK).read_to_end(&mut chunk)?;
+    Ok(chunk)
+}
+
+impl MessageBody for ArchiveStream {
+    type Error = Box<dyn StdError>;
+
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let this = self.get_mut();
+
+        if let Some(chunk) = this.first.take() {
+            this.sent += chunk.len() as u64;
+            return Poll::Ready(Some(Ok(Bytes::from(chunk))));
+        }
+        if this.finished {
+            return Poll::Ready(None);
+        }
+
+        // Следующий кусок читается, только когда actix его попросил
+        let reading = match this.reading.as_mut() {
+            Some(reading) => reading,
+            None => {
+                let mut stdout = match this.stdout.take() {
+                    Some(stdout) => stdout,
+                    None => return Poll::Ready(None),
+                };
+                this.reading.insert(tokio::task::spawn_blocking(move || {
+                    let chunk = read_chunk(&mut stdout);
+                    (stdout, chunk)
+                }))
+            }
+        };
+
+        let (stdout, chunk) = match Pin::new(reading).poll(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Ok(read)) => read,
+            Poll::Ready(Err(e)) => {
+                this.reading = None;
+                this.finished = true;
+                error!("Archive {} read task failed: {}", this.label, e);
+                return Poll::Ready(Some(Err(Box::new(e))));
+            }
+        };
+        this.reading = None;
+
+        // Ошибка посреди архива обрывает соединение: клиент получит неполный
+        // архив, а не файл, который выглядит целым
+        match chunk {
+            Ok(chunk) if chunk.is_empty() => match this.finish() {
+                Ok(()) => Poll::Ready(None),
+                Err(e) => {
+                    error!("Archive {} failed after {} bytes: {}", this.label, this.sent, e);
+                    Poll::Ready(Some(Err(Box::new(e))))
+                }
+            },
+            Ok(chunk) => {
+                this.stdout = Some(stdout);
+                this.sent += chunk.len() as u64;
+                Poll::Ready(Some(Ok(Bytes::from(chunk))))
+            }
+            Err(e) => {
+                this.finished = true;
+                error!("Failed to read git archive output: {}", e);
+                Poll::Ready(Some(Err(Box::new(e))))
+            }
+        }
+    }
+}
+
+impl Drop for ArchiveStream {
+    fn drop(&mut self) {
+        if self.completed {
+            info!("Archive {} streamed: {} bytes", self.label, self.sent);
+            return;
+        }
+
+        // Клиент отключился или отправка оборвалась: вывод git больше никто не прочитает
+        if let Err(e) = self.child.kill() {
+            if e.kind() != io::ErrorKind::InvalidInput {
+                error!("Failed to stop git archive: {}", e);
+            }
+        }
+        let _ = self.child.wait();
+        // Ошибки git уже записаны в лог там, где они случились
+        if !self.finished {
+            warn!("Archive {} aborted by the client after {} bytes", self.label, self.sent);
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, ChildStdout, Stdio};
+use log::error;
+use crate::error::AppError;
+use crate::git::git_command;
+
+/// Заголовок объекта из ответа `git cat-file --batch-check`
+#[derive(Debug, Clone)]
+pub struct ObjectInfo {
+    /// Полный SHA объекта
+    pub sha: String,
+    /// `commit`, `tree`, `blob` или `tag`
+    pub object_type: String,
+    /// Размер в байтах
+    pub size: u64,
+}
+
+/// Один процесс `git cat-file --batch` или `--batch-check` на много объектов
+///
+/// Вместо процесса git на каждый объект запросы пишутся построчно в stdin
+/// долгоживущего cat-file, а ответы читаются по одному. Процесс завершается,
+/// когда значение выходит из области видимости.
+pub struct CatFile {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+    /// Запущен с `--batch`: после заголовка идёт содержимое объекта
+    contents: bool,
+}
+
+impl CatFile {
+    /// Запускает `git cat-file --batch-check`: только тип и размер объектов
+    ///
+    /// # Параметры
+    ///
+    /// * `repo_path` - Путь к bare репозиторию
+    pub fn check(repo_path: &Path) -> Result<Self, AppError> {
+        Self::spawn(repo_path, "--batch-check", false)
+    }
+
+    /// Запускает `git cat-file --batch`: заголовок и содержимое объектов
+    ///
+    /// # Параметры
+    ///
+    /// * `repo_path` - Путь к bare репозиторию
+    pub fn contents(repo_path: &Path) -> Result<Self, AppError> {
+        Self::spawn(repo_path, "--batch", true)
+    }
+
+    fn spawn(repo_path: &Path, mode: &str, contents: bool) -> Result<Self, AppError> {
+        let mut child = git_command()
+            .arg("--git-dir")
+            .arg(repo_path)
+            .args(["cat-file", mode])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| {
+                error!("Failed to run git cat-file: {}", e);
+                AppError::Git(e.to_string())
+            })?;
+
+        let stdin = child.stdin.take();
+        let stdout = match child.stdout.take() {
+            Some(stdout) => BufReader::new(stdout),
+            None => return Err(AppError::Git("git cat-file has no stdout".to_string())),
+        };
+        Ok(CatFile { child, stdin, stdout, contents })
+    }
+
+    /// Тип и размер объекта
+    ///
+    /// # Параметры
+    ///
+    /// * `spec` - SHA или выражение вроде `main:README.md`, `v1.0^{commit}`
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<Option<ObjectInfo>, AppError>` - Заголовок или None, если объекта нет
+    ///   или `spec` некорректен; ошибка - только если сломался сам процесс
+    pub fn info(&mut self, spec: &str) -> Result<Option<ObjectInfo>, AppError> {
+        let info = self.request(spec)?;
+        if let (Some(info), true) = (&info, self.contents) {
+            self.read_contents(info.size)?;
+        }
+        Ok(info)
+    }
+
+    /// Заголовок и содержимое объекта; только для `CatFile::contents`
+    ///
+    /// # Параметры
+    ///
+    /// * `spec` - SHA или выражение вроде `main:README.md`
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<Option<(ObjectInfo, Vec<u8>)>, AppError>` - Объект или None, если его нет
+    pub fn read(&mut self, spec: &str) -> Result<Option<(ObjectInfo, Vec<u8>)>, AppError> {
+        if !self.contents {
+            return Err(AppError::Git("git cat-file was started without --batch".to_string()));
+        }
+
+        match self.request(spec)? {
+            Some(info) => {
+                let content = self.read_contents(info.size)?;
+                Ok(Some((info, content)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Отправляет запрос и читает строку заголовка ответа
+    fn request(&mut self, spec: &str) -> Result<Option<ObjectInfo>, AppError> {
+        // Перевод строки разделяет запросы, а пустая строка - не объект; такие
+        // значения отклоняются сразу, не сбивая протокол для остальных
+        if spec.trim().is_empty() || spec.contains(['\n', '\r', '\0']) {
+            return Ok(None);
+        }
+
+        let stdin = self.stdin.as_mut().ok_or_else(|| AppError::Git("git cat-file stdin is closed".to_string()))?;
+        stdin
+            .write_all(format!("{}\n", spec).as_bytes())
+            .and_then(|_| stdin.flush())
+            .map_err(|e| AppError::Git(format!("Failed to write to git cat-file: {}", e)))?;
+
+        let mut header = String::new();
+        match self.stdout.read_line(&mut header) {
+            Ok(0) => return Err(AppError::Git("git cat-file exited unexpectedly".to_string())),
+            Ok(_) => {}
+            Err(e) => return Err(AppError::Git(format!("Failed to read from git cat-file: {}", e))),
+        }
+
+        // "<spec> missing" и "<spec> ambiguous"; в самом spec могут быть пробелы
+        let header = header.trim_end_matches('\n');
+        if header.ends_with(" missing") || header.ends_with(" ambiguous") {
+            return Ok(None);
+        }
+
+        let mut fields = header.split(' ');
+        match (fields.next(), fields.next(), fields.next().and_then(|size| size.parse().ok()), fields.next()) {
+            (Some(sha), Some(object_type), Some(size), None) => Ok(Some(ObjectInfo {
+                sha: sha.to_string(),
+                object_type: object_type.to_string(),
+                size,
+            })),
+            _ => Err(AppError::Git(format!("Unexpected git cat-file output: {}", header))),
+        }
+    }
+
+    /// Читает содержимое объекта и завершающий перевод строки
+    fn read_contents(&mut self, size: u64) -> Result<Vec<u8>, AppError> {
+        let mut content = vec![0; size as usize + 1];
+        self.stdout
+            .read_exact(&mut content)
+            .map_err(|e| AppError::Git(format!("Failed to read from git cat-file: {}", e)))?;
+        content.pop();
+        Ok(content)
+    }
+}
+
+impl Drop for CatFile {
+    fn drop(&mut self) {
+        // Закрытый stdin завершает cat-file; kill - на случай, если он завис
+        self.stdin.take();
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,136 @@
+use std::path::Path;
+use serde::Serialize;
+use log::error;
+use crate::error::AppError;
+use crate::git::batch::CatFile;
+use crate::git::errors::git_failure;
+use crate::git::run_git;
+
+/// Коммит, последним изменивший строку
+#[derive(Debug, Serialize)]
+pub struct BlameCommit {
+    /// SHA коммита
+    pub sha: String,
+    /// Первая строка сообщения
+    pub summary: String,
+    /// Имя автора
+    pub author_name: String,
+    /// Email автора
+    pub author_email: String,
+    /// Дата авторства в формате RFC3339
+    pub date: String,
+    /// Номер строки в файле этого коммита
+    pub original_line: usize,
+}
+
+/// Результат `git blame` для одной строки
+#[derive(Debug, Serialize)]
+pub struct BlameLine {
+    /// Номер строки в запрошенной ревизии
+    pub line: usize,
+    pub commit: BlameCommit,
+    /// Путь файла в найденном коммите, если строка пришла из переименованного
+    /// или скопированного файла
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_filename: Option<String>,
+}
+
+/// Число строк в файле ревизии
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `commit` - SHA коммита (должен быть уже проверен)
+/// * `path` - Путь к файлу в дереве
+///
+/// # Возвращает
+///
+/// * `Result<Option<usize>, AppError>` - Число строк или None, если такого файла нет
+pub fn line_count(repo_path: &Path, commit: &str, path: &str) -> Result<Option<usize>, AppError> {
+    let mut batch = CatFile::contents(repo_path)?;
+    match batch.read(&format!("{}:{}", commit, path))? {
+        Some((info, content)) if info.object_type == "blob" => {
+            let newlines = content.iter().filter(|&&byte| byte == b'\n').count();
+            // Последняя строка без перевода строки тоже считается
+            let unterminated = content.last().is_some_and(|&byte| byte != b'\n');
+            Ok(Some(newlines + usize::from(unterminated)))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Находит коммит, последним изменивший одну строку файла (`git blame -C -L N,N`)
+///
+/// Благодаря `-C` строки, перенесённые или скопированные из других файлов того же
+/// коммита, приписываются исходному коммиту, а не коммиту переноса.
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `commit` - SHA коммита (должен быть уже проверен)
+/// * `path` - Путь к файлу в дереве
+/// * `line` - Номер строки, начиная с 1 (должен быть уже проверен по `line_count`)
+///
+/// # Возвращает
+///
+/// * `Result<BlameLine, AppError>` - Коммит строки
+pub fn blame_line(repo_path: &Path, commit: &str, path: &str, line: usize) -> Result<BlameLine, AppError> {
+    let range = format!("{},{}", line, line);
+
+    match run_git(repo_path, &["blame", "--porcelain", "-C", "-L", &range, commit, "--", path]) {
+        Ok(output) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            parse_porcelain(&stdout, path, line)
+                .ok_or_else(|| AppError::Git("Unexpected git blame output".to_string()))
+        }
+        Ok(output) => Err(git_failure("blame", &output)),
+        Err(e) => {
+            error!("Failed to run git blame: {}", e);
+            Err(AppError::Git(e.to_string()))
+        }
+    }
+}
+
+/// Разбирает вывод `git blame --porcelain` для одной строки
+fn parse_porcelain(stdout: &str, path: &str, line: usize) -> Option<BlameLine> {
+    let mut lines = stdout.lines();
+    let mut header = lines.next()?.split(' ');
+    let sha = header.next()?.to_string();
+    let original_line = header.next()?.parse().ok()?;
+
+    let mut summary = String::new();
+    let mut author_name = String::new();
+    let mut author_email = String::new();
+    let mut author_time = 0;
+    let mut filename = None;
+
+    // Заголовки коммита идут до строки с содержимым, которая начинается с табуляции
+    for header in lines.take_while(|header| !header.starts_with('\t')) {
+        let (key, value) = header.split_once(' ').unwrap_or((header, ""));
+        match key {
+            "author" => author_name = value.to_string(),
+            "author-mail" => author_email = value.trim_start_matches('<').trim_end_matches('>').to_string(),
+            "author-time" => author_time = value.parse().unwrap_or_default(),
+            "summary" => summary = value.to_string(),
+            "filename" => filename = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    let date = chrono::DateTime::from_timestamp(author_time, 0)
+        .map(|date| crate::timestamps::to_rfc3339(&date))
+        .unwrap_or_default();
+
+    Some(BlameLine {
+        line,
+        commit: BlameCommit {
+            sha,
+            summary,
+            author_name,
+            author_email,
+            date,
+            original_line,
+        },
+        previous_filename: filename.filter(|filename| filename != path),
+    })
+}
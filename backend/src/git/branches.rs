@@ -0,0 +1,157 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use lazy_static::lazy_static;
+use log::error;
+use serde::Serialize;
+use crate::error::AppError;
+use crate::git::run_git;
+
+/// Разделитель полей в формате `git for-each-ref`
+const FIELD_SEP: char = '\u{1f}';
+
+/// Сколько веток отдаётся в списках API; остальные клиенту стоит искать по имени
+pub const MAX_LISTED_BRANCHES: usize = 1000;
+
+lazy_static! {
+    /// Списки веток по репозиториям вместе с поколением ссылок, для которого они сняты
+    static ref BRANCH_LISTS: Mutex<HashMap<String, (i64, Arc<BranchList>)>> = Mutex::new(HashMap::new());
+}
+
+/// Имена веток, не больше `MAX_LISTED_BRANCHES`
+#[derive(Debug)]
+pub struct BranchList {
+    /// Имена веток без `refs/heads/` в порядке имён
+    pub names: Vec<String>,
+    /// В репозитории есть ещё ветки
+    pub truncated: bool,
+}
+
+/// Ветка, которую, скорее всего, можно удалить
+#[derive(Debug, Serialize)]
+pub struct StaleBranch {
+    /// Имя ветки без `refs/heads/`
+    pub name: String,
+    /// Дата последнего коммита в формате RFC3339
+    pub last_commit_date: String,
+    /// Ветка полностью влита в ветку по умолчанию
+    pub merged: bool,
+}
+
+/// Возвращает ветки, влитые в ветку по умолчанию или без коммитов с указанного момента
+///
+/// Ветка по умолчанию (HEAD) в список не попадает. Если HEAD указывает на ещё не
+/// созданную ветку, влитых веток нет, и ветки отбираются только по дате.
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `older_than` - Unix время: ветки с более старым последним коммитом считаются заброшенными
+///
+/// # Возвращает
+///
+/// * `Result<Vec<StaleBranch>, AppError>` - Ветки в порядке имён
+pub fn stale_branches(repo_path: &Path, older_than: i64) -> Result<Vec<StaleBranch>, AppError> {
+    let default_branch = match run_git(repo_path, &["symbolic-ref", "--quiet", "--short", "HEAD"]) {
+        Ok(output) if output.status.success() => Some(String::from_utf8_lossy(&output.stdout).trim().to_string()),
+        _ => None,
+    };
+
+    let format = format!("--format=%(refname:short){0}%(committerdate:unix){0}%(committerdate:iso-strict)", FIELD_SEP);
+    let branches = for_each_ref(repo_path, &[&format, "refs/heads"])?;
+
+    // Нерождённая ветка по умолчанию не разрешается в коммит - влитых веток нет
+    let merged: HashSet<String> = match &default_branch {
+        Some(default) if branches.lines().any(|line| line.split(FIELD_SEP).next() == Some(default.as_str())) => {
+            let merged_into = format!("--merged=refs/heads/{}", default);
+            for_each_ref(repo_path, &["--format=%(refname:short)", &merged_into, "refs/heads"])?
+                .lines()
+                .map(str::to_string)
+                .collect()
+        }
+        _ => HashSet::new(),
+    };
+
+    Ok(branches
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(FIELD_SEP);
+            let name = fields.next()?;
+            let timestamp: i64 = fields.next()?.parse().ok()?;
+            let date = fields.next()?;
+            if default_branch.as_deref() == Some(name) {
+                return None;
+            }
+
+            let merged = merged.contains(name);
+            (merged || timestamp < older_than).then(|| StaleBranch {
+                name: name.to_string(),
+                last_commit_date: date.to_string(),
+                merged,
+            })
+        })
+        .collect())
+}
+
+/// Возвращает первые `MAX_LISTED_BRANCHES` веток в порядке имён
+///
+/// Для списков в API: `git for-each-ref` читает только `refs/heads` и останавливается
+/// на лимите, а не собирает объявление всех ссылок, как `upload-pack --advertise-refs`.
+/// Список кэшируется до следующего изменения ссылок: поколение
+/// (`repositories.ref_generation`) нужно прочитать до вызова, как для объявления ссылок.
+///
+/// # Параметры
+///
+/// * `repo_name` - Полное имя репозитория `{owner}/{name}`, ключ кэша
+/// * `repo_path` - Путь к bare репозиторию
+/// * `generation` - Поколение ссылок; None - без кэша
+///
+/// # Возвращает
+///
+/// * `Result<Arc<BranchList>, AppError>` - Ветки
+pub fn list_branches(repo_name: &str, repo_path: &Path, generation: Option<i64>) -> Result<Arc<BranchList>, AppError> {
+    if let Some(generation) = generation {
+        let lists = BRANCH_LISTS.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((cached, list)) = lists.get(repo_name) {
+            if *cached == generation {
+                return Ok(list.clone());
+            }
+        }
+    }
+
+    // На одну ветку больше лимита - чтобы знать, что список обрезан
+    let count = format!("--count={}", MAX_LISTED_BRANCHES + 1);
+    let output = for_each_ref(repo_path, &[&count, "--format=%(refname:lstrip=2)", "refs/heads/"])?;
+    let mut names: Vec<String> = output.lines().map(str::to_string).collect();
+    let truncated = names.len() > MAX_LISTED_BRANCHES;
+    names.truncate(MAX_LISTED_BRANCHES);
+    let list = Arc::new(BranchList { names, truncated });
+
+    if let Some(generation) = generation {
+        let mut lists = BRANCH_LISTS.lock().unwrap_or_else(|e| e.into_inner());
+        lists.insert(repo_name.to_string(), (generation, list.clone()));
+    }
+    Ok(list)
+}
+
+/// Есть ли в репозитории хоть одна ссылка; читает не больше одной
+pub fn has_refs(repo_path: &Path) -> Result<bool, AppError> {
+    Ok(!for_each_ref(repo_path, &["--count=1", "--format=%(refname)"])?.is_empty())
+}
+
+/// Запускает `git for-each-ref` и возвращает его вывод
+fn for_each_ref(repo_path: &Path, args: &[&str]) -> Result<String, AppError> {
+    let args: Vec<&str> = std::iter::once("for-each-ref").chain(args.iter().copied()).collect();
+    match run_git(repo_path, &args) {
+        Ok(output) if output.status.success() => Ok(String::from_utf8_lossy(&output.stdout).to_string()),
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            error!("git for-each-ref failed: {}", stderr);
+            Err(AppError::Git(stderr))
+        }
+        Err(e) => {
+            error!("Failed to run git for-each-ref: {}", e);
+            Err(AppError::Git(e.to_string()))
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use log::{debug, error};
+use crate::error::AppError;
+use crate::git::errors::git_failure;
+use crate::git::refs::{config_bool, git_stdout, hidden_refs, list_refs};
+use crate::git::{git_command, repo_path, run_git};
+
+/// Каталог с bundle, которые раздаются клиентам через `bundle-uri`
+pub const BUNDLES_ROOT: &str = "bundles";
+
+/// Ключ конфигурации репозитория, включающий раздачу bundle через `bundle-uri`
+const BUNDLE_URI_KEY: &str = "gitserver.bundleUri";
+
+/// Проверяет, что файл - полный git bundle, из которого можно создать репозиторий
+///
+/// `git bundle verify` работает только внутри репозитория, поэтому для проверки
+/// создаётся пустой bare репозиторий `scratch`. В пустом репозитории проверка
+/// заодно отклоняет инкрементальные bundle, которым нужны отсутствующие коммиты.
+///
+/// # Параметры
+///
+/// * `bundle` - Путь к файлу bundle
+/// * `scratch` - Несуществующий путь для временного репозитория
+///
+/// # Возвращает
+///
+/// * `Result<(), AppError>` - `BadRequest` с описанием, если bundle не подходит
+pub fn verify_bundle(bundle: &Path, scratch: &Path) -> Result<(), AppError> {
+    match git_command().args(["init", "--bare", "--quiet"]).arg(scratch).output() {
+        Ok(output) if output.status.success() => {}
+        Ok(output) => return Err(git_failure("init", &output)),
+        Err(e) => {
+            error!("Failed to run git init: {}", e);
+            return Err(AppError::Git(e.to_string()));
+        }
+    }
+
+    let bundle_arg = bundle.to_string_lossy();
+    // Без --quiet: с ним git 2.39 не сообщает, каких коммитов не хватает
+    match run_git(scratch, &["bundle", "verify", &bundle_arg]) {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            debug!("git bundle verify failed: {}", stderr);
+            if stderr.contains("prerequisite") {
+                Err(AppError::BadRequest(
+                    "Bundle depends on commits it does not contain; create it with `git bundle create <file> --all`".to_string()
+                ))
+            } else {
+                Err(AppError::BadRequest("Uploaded file is not a valid git bundle".to_string()))
+            }
+        }
+        Err(e) => {
+            error!("Failed to run git bundle verify: {}", e);
+            Err(AppError::Git(e.to_string()))
+        }
+    }
+}
+
+/// Ветка, на которую указывает HEAD bundle
+///
+/// В bundle записан только SHA HEAD, поэтому выбирается ветка с тем же SHA;
+/// если HEAD нет, берётся первая ветка.
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к любому репозиторию (git требует его для чтения bundle)
+/// * `bundle` - Путь к проверенному файлу bundle
+///
+/// # Возвращает
+///
+/// * `Result<Option<String>, AppError>` - Полное имя ветки или None, если веток нет
+pub fn head_branch(repo_path: &Path, bundle: &Path) -> Result<Option<String>, AppError> {
+    let bundle_arg = bundle.to_string_lossy();
+    let output = match run_git(repo_path, &["bundle", "list-heads", &bundle_arg]) {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => return Err(git_failure("bundle list-heads", &output)),
+        Err(e) => {
+            error!("Failed to run git bundle list-heads: {}", e);
+            return Err(AppError::Git(e.to_string()));
+        }
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let heads: Vec<(&str, &str)> = stdout.lines().filter_map(|line| line.split_once(' ')).collect();
+    let head_sha = heads.iter().find(|(_, name)| *name == "HEAD").map(|(sha, _)| *sha);
+    let mut branches = heads.iter().filter(|(_, name)| name.starts_with("refs/heads/"));
+
+    let branch = match head_sha {
+        Some(head_sha) => branches.clone().find(|(sha, _)| *sha == head_sha).or_else(|| branches.next()),
+        None => branches.next(),
+    };
+    Ok(branch.map(|(_, name)| name.to_string()))
+}
+
+/// Путь к bundle репозитория для `bundle-uri`: `{BUNDLES_ROOT}/{owner}/{name}.bundle`
+///
+/// # Параметры
+///
+/// * `full_name` - Полное имя репозитория `{owner}/{name}`
+///
+/// # Возвращает
+///
+/// * `Result<PathBuf, AppError>` - Путь к файлу (файла может не быть)
+pub fn bundle_path(full_name: &str) -> Result<PathBuf, AppError> {
+    Ok(repo_path(Path::new(BUNDLES_ROOT), full_name)?.with_extension("bundle"))
+}
+
+/// Включена ли для репозитория раздача bundle через `bundle-uri`
+pub fn bundle_uri_enabled(repo_path: &Path) -> Result<bool, AppError> {
+    config_bool(repo_path, BUNDLE_URI_KEY)
+}
+
+/// Включает `bundle-uri` и записывает список bundle из одного полного bundle
+///
+/// Сам upload-pack начинает объявлять список только после `set_bundle_advertised`,
+/// когда bundle уже создан: иначе клиенты получали бы ссылку на отсутствующий файл.
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `uri` - Абсолютный URL, по которому клиенты скачивают bundle
+///
+/// # Возвращает
+///
+/// * `Result<(), AppError>` - Ошибка, если git завершился с ошибкой
+pub fn enable_bundle_uri(repo_path: &Path, uri: &str) -> Result<(), AppError> {
+    for (key, value) in [
+        (BUNDLE_URI_KEY, "true"),
+        ("bundle.version", "1"),
+        ("bundle.mode", "all"),
+        ("bundle.full.uri", uri),
+    ] {
+        git_stdout(repo_path, &["config", "--local", key, value])?;
+    }
+    Ok(())
+}
+
+/// Выключает `bundle-uri`: убирает объявление и список bundle и удаляет файл bundle
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `full_name` - Полное имя репозитория `{owner}/{name}`
+///
+/// # Возвращает
+///
+/// * `Result<(), AppError>` - Ошибка git или удаления файла
+pub fn disable_bundle_uri(repo_path: &Path, full_name: &str) -> Result<(), AppError> {
+    git_stdout(repo_path, &["config", "--local", BUNDLE_URI_KEY, "false"])?;
+    set_bundle_advertised(repo_path, false)?;
+
+    // Код 128 означает, что секции уже нет
+    for section in ["bundle", "bundle.full"] {
+        match run_git(repo_path, &["config", "--local", "--remove-section", section]) {
+            Ok(output) if output.status.success() || output.status.code() == Some(128) => {}
+            Ok(output) => return Err(git_failure("config", &output)),
+            Err(e) => {
+                error!("Failed to run git config: {}", e);
+                return Err(AppError::Git(e.to_string()));
+            }
+        }
+    }
+
+    let path = bundle_path(full_name)?;
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => {
+            error!("Failed to remove {}: {}", path.display(), e);
+            Err(AppError::Git(e.to_string()))
+        }
+    }
+}
+
+/// Включает или выключает объявление bundle в upload-pack (`uploadpack.advertiseBundleURIs`)
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `advertised` - Объявлять ли bundle
+///
+/// # Возвращает
+///
+/// * `Result<(), AppError>` - Ошибка, если git завершился с ошибкой
+pub fn set_bundle_advertised(repo_path: &Path, advertised: bool) -> Result<(), AppError> {
+    if advertised {
+        git_stdout(repo_path, &["config", "--local", "uploadpack.advertiseBundleURIs", "true"])?;
+        return Ok(());
+    }
+
+    // Код 5 означает, что ключ не задан
+    match run_git(repo_path, &["config", "--local", "--unset", "uploadpack.advertiseBundleURIs"]) {
+        Ok(output) if output.status.success() || output.status.code() == Some(5) => Ok(()),
+        Ok(output) => Err(git_failure("config", &output)),
+        Err(e) => {
+            error!("Failed to run git config: {}", e);
+            Err(AppError::Git(e.to_string()))
+        }
+    }
+}
+
+/// Создаёт bundle со всеми ветками и тегами репозитория
+///
+/// Скрытые ссылки в bundle не попадают. Bundle пишется во временный файл и
+/// переименовывается, поэтому клиенты никогда не скачивают недописанный файл.
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `dest` - Путь к файлу bundle
+///
+/// # Возвращает
+///
+/// * `Result<bool, AppError>` - false, если в репозитории нет веток и тегов и bundle не создан
+pub fn create_bundle(repo_path: &Path, dest: &Path) -> Result<bool, AppError> {
+    let hidden = hidden_refs(repo_path)?;
+    let has_refs = list_refs(repo_path)?
+        .keys()
+        .any(|name| {
+            (name.starts_with("refs/heads/") || name.starts_with("refs/tags/"))
+                && !hidden.iter().any(|prefix| name.starts_with(prefix.as_str()))
+        });
+    if !has_refs {
+        return Ok(false);
+    }
+
+    if let Some(dir) = dest.parent() {
+        fs::create_dir_all(dir).map_err(|e| AppError::Git(format!("Failed to create {}: {}", dir.display(), e)))?;
+    }
+    let partial = dest.with_extension("bundle.tmp");
+    let partial_arg = partial.to_string_lossy().to_string();
+
+    let excludes: Vec<String> = hidden.iter().map(|prefix| format!("--exclude={}*", prefix)).collect();
+    let mut args = vec!["bundle", "create", "--quiet", partial_arg.as_str()];
+    args.extend(excludes.iter().map(String::as_str));
+    args.extend(["--branches", "--tags"]);
+
+    match run_git(repo_path, &args) {
+        Ok(output) if output.status.success() => {}
+        Ok(output) => {
+            let _ = fs::remove_file(&partial);
+            return Err(git_failure("bundle create", &output));
+        }
+        Err(e) => {
+            error!("Failed to run git bundle create: {}", e);
+            return Err(AppError::Git(e.to_string()));
+        }
+    }
+
+    fs::rename(&partial, dest).map_err(|e| {
+        let _ = fs::remove_file(&partial);
+        AppError::Git(format!("Failed to move bundle to {}: {}", dest.display(), e))
+    })?;
+    Ok(true)
+}
@@ -0,0 +1,361 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use log::error;
+use crate::error::AppError;
+use crate::git::errors::git_failure;
+use crate::git::refs::RefUpdate;
+use crate::git::secrets::{scan_update, ScanMode, SCAN_MODE};
+use crate::git::{git_command, resolve_commit, run_git, validate_ref, TempDir};
+
+/// SHA, которым `update-ref` обозначает, что ссылки ещё не должно быть
+const ZERO_SHA: &str = "0000000000000000000000000000000000000000";
+
+/// Максимальная длина пути к файлу
+const MAX_PATH_LEN: usize = 4096;
+
+/// Автор коммита, созданного через API
+pub struct Author<'a> {
+    pub name: &'a str,
+    pub email: &'a str,
+}
+
+/// Коммит, созданный через API
+#[derive(Debug, Clone)]
+pub struct FileCommit {
+    /// Изменение ветки
+    pub update: RefUpdate,
+    /// SHA блоба файла после коммита; None, если файл удалён
+    pub blob: Option<String>,
+    /// Файла до коммита не было
+    pub created: bool,
+}
+
+/// Файл в дереве коммита
+struct TreeEntry {
+    mode: String,
+    kind: String,
+    sha: String,
+}
+
+/// Проверяет путь к файлу в репозитории
+///
+/// Путь относительный, без пустых компонентов, `.`, `..` и `.git`, без управляющих символов.
+///
+/// # Параметры
+///
+/// * `path` - Путь к файлу
+///
+/// # Возвращает
+///
+/// * `Result<(), AppError>` - `BadRequest`, если путь недопустим
+pub fn validate_path(path: &str) -> Result<(), AppError> {
+    let valid = !path.is_empty()
+        && path.len() <= MAX_PATH_LEN
+        && !path.chars().any(|c| c.is_control())
+        && path
+            .split('/')
+            .all(|part| !part.is_empty() && part != "." && part != ".." && !part.eq_ignore_ascii_case(".git"));
+
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::BadRequest(format!("Invalid file path: {}", path)))
+    }
+}
+
+/// Создаёт коммит, добавляющий или изменяющий файл на ветке, без рабочей копии
+///
+/// Блоб записывается `hash-object`, дерево собирается во временном индексе
+/// (`read-tree`, `update-index`, `write-tree`), коммит создаётся `commit-tree`,
+/// а ветка сдвигается `update-ref` только если всё ещё указывает на родителя.
+/// Вызывающий должен держать блокировку репозитория.
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `branch` - Имя ветки; в репозитории без веток она будет создана
+/// * `path` - Путь к файлу
+/// * `content` - Новое содержимое файла
+/// * `expected_blob` - SHA текущего блоба файла; обязателен, если файл уже есть
+/// * `message` - Сообщение коммита
+/// * `author` - Автор и коммитер
+///
+/// # Возвращает
+///
+/// * `Result<FileCommit, AppError>` - Коммит; `Conflict`, если `expected_blob` не совпал
+///   с файлом на ветке или ветку сдвинули
+pub fn write_file(
+    repo_path: &Path,
+    branch: &str,
+    path: &str,
+    content: &[u8],
+    expected_blob: Option<&str>,
+    message: &str,
+    author: &Author,
+) -> Result<FileCommit, AppError> {
+    validate_path(path)?;
+    let ref_name = branch_ref(branch)?;
+    let parent = branch_head(repo_path, &ref_name, branch)?;
+
+    let existing = match &parent {
+        Some(parent) => tree_entry(repo_path, parent, path)?,
+        None => None,
+    };
+    let mode = match &existing {
+        Some(entry) if entry.kind != "blob" || entry.mode == "120000" => {
+            return Err(AppError::BadRequest(format!("{} is not a regular file", path)));
+        }
+        Some(entry) => entry.mode.clone(),
+        None => "100644".to_string(),
+    };
+    match (&existing, expected_blob) {
+        (Some(entry), Some(expected)) if entry.sha.eq_ignore_ascii_case(expected) => {}
+        (Some(entry), Some(_)) => {
+            return Err(AppError::Conflict(format!(
+                "{} was changed on '{}' (now {}); reload it and try again",
+                path, branch, entry.sha
+            )));
+        }
+        (Some(_), None) => {
+            return Err(AppError::Conflict(format!("{} already exists on '{}'; pass its sha to update it", path, branch)));
+        }
+        (None, Some(_)) => {
+            return Err(AppError::Conflict(format!("{} does not exist on '{}'", path, branch)));
+        }
+        (None, None) => {}
+    }
+
+    let blob = git_output(git_in(repo_path).args(["hash-object", "-w", "--stdin"]), content, "hash-object")?;
+    let cacheinfo = format!("{},{},{}", mode, blob, path);
+    let tree = build_tree(repo_path, parent.as_deref(), &["--add", "--cacheinfo", &cacheinfo], b"", path)?;
+    let update = commit_and_update(repo_path, &tree, ref_name, parent, branch, message, author)?;
+
+    Ok(FileCommit { update, blob: Some(blob), created: existing.is_none() })
+}
+
+/// Создаёт коммит, удаляющий файл с ветки, без рабочей копии
+///
+/// Работает так же, как `write_file`. Вызывающий должен держать блокировку репозитория.
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `branch` - Имя ветки
+/// * `path` - Путь к файлу
+/// * `expected_blob` - SHA текущего блоба файла
+/// * `message` - Сообщение коммита
+/// * `author` - Автор и коммитер
+///
+/// # Возвращает
+///
+/// * `Result<FileCommit, AppError>` - Коммит; `NotFound`, если файла нет, `Conflict`,
+///   если `expected_blob` не совпал с файлом на ветке или ветку сдвинули
+pub fn delete_file(
+    repo_path: &Path,
+    branch: &str,
+    path: &str,
+    expected_blob: &str,
+    message: &str,
+    author: &Author,
+) -> Result<FileCommit, AppError> {
+    validate_path(path)?;
+    let ref_name = branch_ref(branch)?;
+    let parent = match branch_head(repo_path, &ref_name, branch)? {
+        Some(parent) => parent,
+        None => return Err(AppError::RefNotFound(branch.to_string())),
+    };
+
+    match tree_entry(repo_path, &parent, path)? {
+        Some(entry) if entry.kind != "blob" => {
+            return Err(AppError::BadRequest(format!("{} is not a file", path)));
+        }
+        Some(entry) if !entry.sha.eq_ignore_ascii_case(expected_blob) => {
+            return Err(AppError::Conflict(format!(
+                "{} was changed on '{}' (now {}); reload it and try again",
+                path, branch, entry.sha
+            )));
+        }
+        Some(_) => {}
+        None => return Err(AppError::NotFound(format!("{} does not exist on '{}'", path, branch))),
+    }
+
+    // `--force-remove` требует рабочую копию, а запись с режимом 0 убирает путь из индекса и без неё
+    let removal = format!("0 {}\t{}\0", ZERO_SHA, path);
+    let tree = build_tree(repo_path, Some(&parent), &["-z", "--index-info"], removal.as_bytes(), path)?;
+    let update = commit_and_update(repo_path, &tree, ref_name, Some(parent), branch, message, author)?;
+
+    Ok(FileCommit { update, blob: None, created: false })
+}
+
+/// Собирает дерево родителя с одним изменением во временном индексе
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `parent` - Коммит, дерево которого меняется; None - пустое дерево
+/// * `update_index` - Аргументы `git update-index`
+/// * `input` - stdin для `git update-index`
+/// * `path` - Путь к изменяемому файлу, для сообщения об ошибке
+///
+/// # Возвращает
+///
+/// * `Result<String, AppError>` - SHA нового дерева
+fn build_tree(repo_path: &Path, parent: Option<&str>, update_index: &[&str], input: &[u8], path: &str) -> Result<String, AppError> {
+    let index = TempDir::new("contents")
+        .map_err(|e| AppError::Git(format!("Failed to create temporary directory: {}", e)))?;
+    let index_file = index.path().join("index");
+    let with_index = || {
+        let mut command = git_in(repo_path);
+        command.env("GIT_INDEX_FILE", &index_file);
+        command
+    };
+
+    if let Some(parent) = parent {
+        git_output(with_index().args(["read-tree", parent]), b"", "read-tree")?;
+    }
+    git_output(with_index().arg("update-index").args(update_index), input, "update-index")
+        .map_err(|_| AppError::BadRequest(format!("{} conflicts with an existing file", path)))?;
+    git_output(with_index().arg("write-tree"), b"", "write-tree")
+}
+
+/// Создаёт коммит дерева и сдвигает на него ветку
+fn commit_and_update(
+    repo_path: &Path,
+    tree: &str,
+    ref_name: String,
+    parent: Option<String>,
+    branch: &str,
+    message: &str,
+    author: &Author,
+) -> Result<RefUpdate, AppError> {
+    let commit = commit_tree(repo_path, tree, parent.as_deref(), message, author)?;
+    let update = RefUpdate { name: ref_name, old: parent, new: Some(commit) };
+    reject_secrets(repo_path, &update)?;
+    update_ref(repo_path, &update, branch)?;
+    Ok(update)
+}
+
+/// Полное имя ветки после проверки
+fn branch_ref(branch: &str) -> Result<String, AppError> {
+    validate_ref(branch)?;
+    let ref_name = format!("refs/heads/{}", branch);
+    match git_command().args(["check-ref-format", &ref_name]).output() {
+        Ok(output) if output.status.success() => Ok(ref_name),
+        _ => Err(AppError::InvalidRef(branch.to_string())),
+    }
+}
+
+/// SHA вершины ветки; None, если в репозитории ещё нет ни одной ветки
+fn branch_head(repo_path: &Path, ref_name: &str, branch: &str) -> Result<Option<String>, AppError> {
+    match resolve_commit(repo_path, ref_name) {
+        Ok(sha) => Ok(Some(sha)),
+        Err(AppError::RefNotFound(_)) => {
+            let heads = git_output(git_in(repo_path).args(["for-each-ref", "--count=1", "refs/heads/"]), b"", "for-each-ref")?;
+            if heads.is_empty() {
+                Ok(None)
+            } else {
+                Err(AppError::RefNotFound(branch.to_string()))
+            }
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Запись дерева коммита для пути; None, если пути нет
+fn tree_entry(repo_path: &Path, commit: &str, path: &str) -> Result<Option<TreeEntry>, AppError> {
+    let listing = git_output(git_in(repo_path).args(["ls-tree", "-z", commit, "--", path]), b"", "ls-tree")?;
+    // Формат: `<mode> <type> <sha>\t<path>`; ls-tree сравнивает путь буквально
+    Ok(listing
+        .split('\0')
+        .filter_map(|line| line.split_once('\t'))
+        .find(|(_, name)| *name == path)
+        .and_then(|(meta, _)| {
+            let mut fields = meta.split(' ');
+            Some(TreeEntry {
+                mode: fields.next()?.to_string(),
+                kind: fields.next()?.to_string(),
+                sha: fields.next()?.to_string(),
+            })
+        }))
+}
+
+/// Создаёт коммит дерева от имени автора
+fn commit_tree(repo_path: &Path, tree: &str, parent: Option<&str>, message: &str, author: &Author) -> Result<String, AppError> {
+    let mut command = git_in(repo_path);
+    for (variable, value) in [
+        ("GIT_AUTHOR_NAME", author.name),
+        ("GIT_AUTHOR_EMAIL", author.email),
+        ("GIT_COMMITTER_NAME", author.name),
+        ("GIT_COMMITTER_EMAIL", author.email),
+    ] {
+        command.env(variable, value);
+    }
+    command.args(["commit-tree", tree]);
+    if let Some(parent) = parent {
+        command.args(["-p", parent]);
+    }
+    git_output(&mut command, message.as_bytes(), "commit-tree")
+}
+
+/// В строгом режиме поиска секретов отклоняет коммит так же, как pre-receive хук отклоняет push
+fn reject_secrets(repo_path: &Path, update: &RefUpdate) -> Result<(), AppError> {
+    if *SCAN_MODE != ScanMode::Strict {
+        return Ok(());
+    }
+
+    match scan_update(repo_path, update, false)?.first() {
+        Some(finding) => Err(AppError::Forbidden(format!(
+            "Commit rejected: possible {} in {} line {}",
+            finding.rule, finding.path, finding.line
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Сдвигает ветку на новый коммит, если она всё ещё указывает на родителя
+fn update_ref(repo_path: &Path, update: &RefUpdate, branch: &str) -> Result<(), AppError> {
+    let new = update.new.as_deref().unwrap_or(ZERO_SHA);
+    let old = update.old.as_deref().unwrap_or(ZERO_SHA);
+    match run_git(repo_path, &["update-ref", &update.name, new, old]) {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(_) => Err(AppError::Conflict(format!("Branch '{}' was updated meanwhile, please retry", branch))),
+        Err(e) => {
+            error!("Failed to run git update-ref: {}", e);
+            Err(AppError::Git(e.to_string()))
+        }
+    }
+}
+
+/// git с `--git-dir` репозитория
+fn git_in(repo_path: &Path) -> Command {
+    let mut command = git_command();
+    command.arg("--git-dir").arg(repo_path);
+    command
+}
+
+/// Запускает git, передаёт `input` на stdin и возвращает stdout без завершающего перевода строки
+fn git_output(command: &mut Command, input: &[u8], name: &str) -> Result<String, AppError> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            error!("Failed to run git {}: {}", name, e);
+            AppError::Git(e.to_string())
+        })?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input).map_err(|e| AppError::Git(format!("Failed to write to git stdin: {}", e)))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| AppError::Git(format!("Failed to wait for git: {}", e)))?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+    } else {
+        Err(git_failure(name, &output))
+    }
+}
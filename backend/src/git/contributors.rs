@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+use std::path::Path;
+use chrono::{DateTime, Utc};
+use log::error;
+use serde::Serialize;
+use crate::error::AppError;
+use crate::git::errors::git_failure;
+use crate::git::run_git;
+use crate::timestamps::to_rfc3339;
+
+/// Автор коммитов
+#[derive(Debug, Serialize)]
+pub struct Contributor {
+    /// Имя автора из последнего по времени коммита (с учётом `.mailmap`)
+    pub name: String,
+    /// Email автора из коммитов
+    pub email: String,
+    /// Количество коммитов за период
+    pub commits: u64,
+    /// Пользователь сервера с этим email, если он зарегистрирован
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+}
+
+/// Авторы коммитов, достижимых из `rev`, по убыванию числа коммитов
+///
+/// Авторы группируются по email без учёта регистра, как `git shortlog -se`
+/// с `.mailmap`. `username` не заполняется: это делает вызывающий.
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `rev` - Ревизия, от которой считается история
+/// * `since` - Учитывать коммиты не раньше этой даты
+/// * `until` - Учитывать коммиты не позже этой даты
+///
+/// # Возвращает
+///
+/// * `Result<Vec<Contributor>, AppError>` - Авторы или ошибка git
+pub fn contributors(
+    repo_path: &Path,
+    rev: &str,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+) -> Result<Vec<Contributor>, AppError> {
+    let since = since.map(|date| format!("--since={}", to_rfc3339(&date)));
+    let until = until.map(|date| format!("--until={}", to_rfc3339(&date)));
+
+    let mut args = vec!["log", "--use-mailmap", "--format=%aN%x1f%aE"];
+    args.extend(since.as_deref());
+    args.extend(until.as_deref());
+    args.extend([rev, "--"]);
+
+    let output = match run_git(repo_path, &args) {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => return Err(git_failure("log", &output)),
+        Err(e) => {
+            error!("Failed to run git log: {}", e);
+            return Err(AppError::Git(e.to_string()));
+        }
+    };
+
+    // git log выводит коммиты от новых к старым, поэтому первое имя - самое свежее
+    let mut by_email: HashMap<String, Contributor> = HashMap::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let (name, email) = match line.split_once('\x1f') {
+            Some(author) => author,
+            None => continue,
+        };
+        by_email
+            .entry(email.to_lowercase())
+            .or_insert_with(|| Contributor {
+                name: name.to_string(),
+                email: email.to_string(),
+                commits: 0,
+                username: None,
+            })
+            .commits += 1;
+    }
+
+    let mut contributors: Vec<Contributor> = by_email.into_values().collect();
+    contributors.sort_by(|a, b| b.commits.cmp(&a.commits).then_with(|| a.name.cmp(&b.name)));
+    Ok(contributors)
+}
@@ -0,0 +1,426 @@
+use std::io::Read;
+use std::path::Path;
+use std::process::Stdio;
+use serde::Serialize;
+use log::error;
+use crate::error::AppError;
+use crate::git::errors::git_failure;
+use crate::git::{git_command, run_git};
+
+/// Что именно сравнивается
+pub enum DiffTarget<'a> {
+    /// Изменения, внесённые одним коммитом
+    Commit(&'a str),
+    /// Изменения в `head` относительно общей базы с `base` (как в пул-реквесте)
+    Range { base: &'a str, head: &'a str },
+    /// Изменения, внесённые коммитом слияния
+    Merge { sha: &'a str, mode: MergeDiff },
+}
+
+/// Как показывать изменения коммита слияния
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MergeDiff {
+    /// Относительно первого родителя: всё, что слияние принесло в ветку
+    FirstParent,
+    /// Комбинированный дифф (`git diff-tree --cc`): только файлы, которые отличаются
+    /// от всех родителей, то есть разрешённые при слиянии конфликты и правки
+    Combined,
+}
+
+impl MergeDiff {
+    /// Разбирает значение параметра `against`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "first-parent" => Some(MergeDiff::FirstParent),
+            "combined" => Some(MergeDiff::Combined),
+            _ => None,
+        }
+    }
+
+    /// Значение параметра `against`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MergeDiff::FirstParent => "first-parent",
+            MergeDiff::Combined => "combined",
+        }
+    }
+}
+
+/// Наибольшее число строк контекста, которое можно запросить
+pub const MAX_DIFF_CONTEXT: u32 = 100;
+
+/// Какие изменения пробелов не показывать
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IgnoreWhitespace {
+    /// Показывать все изменения
+    None,
+    /// Не учитывать изменение количества пробелов (`-b`)
+    Change,
+    /// Не учитывать пробелы вообще (`-w`)
+    All,
+}
+
+impl IgnoreWhitespace {
+    /// Разбирает значение параметра `ignore_whitespace`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "none" => Some(IgnoreWhitespace::None),
+            "change" => Some(IgnoreWhitespace::Change),
+            "all" => Some(IgnoreWhitespace::All),
+            _ => None,
+        }
+    }
+
+    /// Флаг git для этого режима
+    fn flag(&self) -> Option<&'static str> {
+        match self {
+            IgnoreWhitespace::None => None,
+            IgnoreWhitespace::Change => Some("-b"),
+            IgnoreWhitespace::All => Some("-w"),
+        }
+    }
+}
+
+/// Параметры построения диффа
+#[derive(Debug, Clone, Copy)]
+pub struct DiffOptions {
+    /// Какие изменения пробелов не показывать
+    pub ignore_whitespace: IgnoreWhitespace,
+    /// Число строк контекста вокруг изменений (`-U`)
+    pub context: u32,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions {
+            ignore_whitespace: IgnoreWhitespace::None,
+            context: 3,
+        }
+    }
+}
+
+/// Изменения в одном файле
+#[derive(Debug, Serialize)]
+pub struct FileDiff {
+    /// Путь к файлу после изменения
+    pub path: String,
+    /// Прежний путь, если файл был переименован
+    pub old_path: Option<String>,
+    /// Количество добавленных строк
+    pub additions: u64,
+    /// Количество удалённых строк
+    pub deletions: u64,
+    /// Файл бинарный, построчной статистики нет
+    pub binary: bool,
+    /// Текст изменений; отсутствует, если дифф был обрезан
+    pub patch: Option<String>,
+}
+
+/// Результат сравнения
+#[derive(Debug, Serialize)]
+pub struct Diff {
+    /// Изменённые файлы; не больше `max_files` из `get_diff`
+    pub files: Vec<FileDiff>,
+    /// Число изменённых файлов, включая не попавшие в `files`
+    pub total_files: usize,
+    /// Суммарное число добавленных строк
+    pub additions: u64,
+    /// Суммарное число удалённых строк
+    pub deletions: u64,
+    /// Часть файлов отдана только статистикой или не попала в `files` из-за ограничений
+    pub truncated: bool,
+    /// Пояснение для клиента, если дифф обрезан
+    pub note: Option<String>,
+}
+
+/// Строит дифф с ограничением на суммарный размер текста изменений и число файлов
+///
+/// Статистика отдаётся для первых `max_files` файлов, суммы строк - по всем.
+/// Текст изменений добавляется по файлам, пока не превышен `max_bytes`; у остальных
+/// файлов `patch` остаётся пустым. Вывод `git diff --patch` читается только до
+/// `max_bytes`, так что память на запрос не зависит от размера диффа (кроме
+/// комбинированного диффа слияния, где порядок файлов другой).
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `target` - Коммит или диапазон для сравнения (ревизии должны быть уже проверены)
+/// * `options` - Контекст и игнорирование пробелов
+/// * `max_bytes` - Максимальный суммарный размер текста изменений
+/// * `max_files` - Максимальное число файлов в ответе
+///
+/// # Возвращает
+///
+/// * `Result<Diff, AppError>` - Дифф или ошибка git
+pub fn get_diff(
+    repo_path: &Path,
+    target: &DiffTarget,
+    options: &DiffOptions,
+    max_bytes: usize,
+    max_files: usize,
+) -> Result<Diff, AppError> {
+    let base_args = diff_args(target);
+    let mut base_args: Vec<&str> = base_args.iter().map(String::as_str).collect();
+    // Флаг пробелов влияет и на статистику: файлы только с такими изменениями пропадают из обоих выводов
+    base_args.extend(options.ignore_whitespace.flag());
+
+    let mut stat_args = base_args.clone();
+    stat_args.extend(["--numstat", "-z"]);
+    let stats = parse_numstat(&git_stdout(repo_path, &stat_args)?);
+
+    let context = format!("-U{}", options.context);
+    let mut patch_args = base_args;
+    patch_args.extend(["--patch", context.as_str()]);
+    let combined = matches!(target, DiffTarget::Merge { mode: MergeDiff::Combined, .. });
+    let (patch, complete) = if combined {
+        (git_stdout(repo_path, &patch_args)?, true)
+    } else {
+        git_stdout_prefix(repo_path, &patch_args, max_bytes)?
+    };
+    let mut patches = split_patch(&String::from_utf8_lossy(&patch));
+    if !complete {
+        // Последний файл прочитан не до конца; он и остальные отдаются без текста
+        patches.pop();
+    }
+
+    // В комбинированном диффе есть не все файлы из статистики (которая считается
+    // относительно первого родителя), поэтому текст сопоставляется по пути
+    let (stats, patches) = match target {
+        DiffTarget::Merge { mode: MergeDiff::Combined, .. } => {
+            let mut by_path: Vec<(String, String)> = patches
+                .into_iter()
+                .filter_map(|text| {
+                    let path = text.lines().next()?.strip_prefix("diff --cc ")?.to_string();
+                    Some((path, text))
+                })
+                .collect();
+            let stats: Vec<FileDiff> = stats
+                .into_iter()
+                .filter(|file| by_path.iter().any(|(path, _)| *path == file.path))
+                .collect();
+            by_path.sort_by_key(|(path, _)| stats.iter().position(|file| file.path == *path));
+            (stats, by_path.into_iter().map(|(_, text)| text).collect())
+        }
+        _ => (stats, patches),
+    };
+
+    let total_files = stats.len();
+    let additions = stats.iter().map(|f| f.additions).sum();
+    let deletions = stats.iter().map(|f| f.deletions).sum();
+
+    let mut files = Vec::with_capacity(total_files.min(max_files));
+    let mut used = 0;
+    let mut truncated = false;
+
+    for (i, mut file) in stats.into_iter().take(max_files).enumerate() {
+        match patches.get(i) {
+            Some(text) if !truncated && used + text.len() <= max_bytes => {
+                used += text.len();
+                file.patch = Some(text.clone());
+            }
+            Some(_) => truncated = true,
+            None if !complete => truncated = true,
+            None => {}
+        }
+        files.push(file);
+    }
+
+    let note = if total_files > files.len() {
+        Some(format!(
+            "Diff has {} changed files; only the first {} are listed",
+            total_files, files.len()
+        ))
+    } else {
+        truncated.then(|| {
+            "Diff is too large to display in full; fetch individual files to see the remaining changes".to_string()
+        })
+    };
+
+    Ok(Diff {
+        files,
+        total_files,
+        additions,
+        deletions,
+        truncated: truncated || total_files > max_files,
+        note,
+    })
+}
+
+/// Строит патч в формате mbox (`git format-patch --stdout`), пригодный для `git am`
+///
+/// Для диапазона патчи строятся по коммитам из `head`, которых нет в `base`;
+/// коммиты слияния format-patch пропускает. Для отдельного коммита слияния
+/// единого патча нет, поэтому возвращается `AppError::Unsupported`.
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `target` - Коммит или диапазон (ревизии должны быть уже проверены)
+///
+/// # Возвращает
+///
+/// * `Result<Vec<u8>, AppError>` - Текст патчей
+pub fn format_patch(repo_path: &Path, target: &DiffTarget) -> Result<Vec<u8>, AppError> {
+    match target {
+        DiffTarget::Commit(sha) | DiffTarget::Merge { sha, .. } => {
+            if commit_parents(repo_path, sha)?.len() > 1 {
+                return Err(AppError::Unsupported(format!(
+                    "Commit {} is a merge commit and has no single patch",
+                    sha
+                )));
+            }
+            git_stdout(repo_path, &["format-patch", "--stdout", "-1", sha])
+        }
+        DiffTarget::Range { base, head } => {
+            let range = format!("{}..{}", base, head);
+            git_stdout(repo_path, &["format-patch", "--stdout", &range])
+        }
+    }
+}
+
+/// Возвращает SHA родителей коммита
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `sha` - SHA коммита (должен быть уже проверен)
+///
+/// # Возвращает
+///
+/// * `Result<Vec<String>, AppError>` - Родители по порядку; больше одного - коммит слияния
+pub fn commit_parents(repo_path: &Path, sha: &str) -> Result<Vec<String>, AppError> {
+    let output = git_stdout(repo_path, &["rev-list", "--parents", "-n", "1", sha])?;
+    Ok(String::from_utf8_lossy(&output)
+        .split_whitespace()
+        .skip(1)
+        .map(str::to_string)
+        .collect())
+}
+
+/// Аргументы git для выбранного вида сравнения
+fn diff_args(target: &DiffTarget) -> Vec<String> {
+    match target {
+        DiffTarget::Commit(sha) => vec![
+            "show".to_string(), "--format=".to_string(), "-M".to_string(), sha.to_string(),
+        ],
+        DiffTarget::Merge { sha, mode: MergeDiff::FirstParent } => vec![
+            "diff".to_string(), "-M".to_string(), format!("{}^1", sha), sha.to_string(),
+        ],
+        DiffTarget::Merge { sha, mode: MergeDiff::Combined } => vec![
+            "diff-tree".to_string(), "--cc".to_string(), "--format=".to_string(), "-M".to_string(), sha.to_string(),
+        ],
+        DiffTarget::Range { base, head } => vec![
+            "diff".to_string(), "-M".to_string(), format!("{}...{}", base, head),
+        ],
+    }
+}
+
+/// Запускает git и возвращает stdout, превращая неуспешный код выхода в ошибку
+fn git_stdout(repo_path: &Path, args: &[&str]) -> Result<Vec<u8>, AppError> {
+    match run_git(repo_path, args) {
+        Ok(output) if output.status.success() => Ok(output.stdout),
+        Ok(output) => Err(git_failure(&args.join(" "), &output)),
+        Err(e) => {
+            error!("Failed to run git: {}", e);
+            Err(AppError::Git(e.to_string()))
+        }
+    }
+}
+
+/// Запускает git и читает не больше `limit` байт его stdout
+///
+/// Если вывод длиннее, git останавливается, не дописав его: так большой дифф не
+/// читается в память целиком ради первых `limit` байт.
+///
+/// # Возвращает
+///
+/// * `Result<(Vec<u8>, bool), AppError>` - Прочитанный вывод и признак того, что он полный
+fn git_stdout_prefix(repo_path: &Path, args: &[&str], limit: usize) -> Result<(Vec<u8>, bool), AppError> {
+    let mut child = git_command()
+        .arg("--git-dir")
+        .arg(repo_path)
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            error!("Failed to run git: {}", e);
+            AppError::Git(e.to_string())
+        })?;
+
+    let mut stdout = Vec::new();
+    if let Some(pipe) = child.stdout.take() {
+        pipe.take(limit as u64 + 1)
+            .read_to_end(&mut stdout)
+            .map_err(|e| AppError::Git(format!("Failed to read git output: {}", e)))?;
+    }
+
+    if stdout.len() > limit {
+        stdout.truncate(limit);
+        let _ = child.kill();
+        let _ = child.wait();
+        return Ok((stdout, false));
+    }
+
+    match child.wait_with_output() {
+        Ok(output) if output.status.success() => Ok((stdout, true)),
+        Ok(output) => Err(git_failure(&args.join(" "), &output)),
+        Err(e) => {
+            error!("Failed to run git: {}", e);
+            Err(AppError::Git(e.to_string()))
+        }
+    }
+}
+
+/// Разбирает вывод `--numstat -z`
+///
+/// Обычная запись: `adds\tdels\tpath\0`; переименование: `adds\tdels\t\0old\0new\0`.
+fn parse_numstat(raw: &[u8]) -> Vec<FileDiff> {
+    let text = String::from_utf8_lossy(raw);
+    let mut fields = text.split('\0').filter(|f| !f.is_empty());
+    let mut files = Vec::new();
+
+    while let Some(entry) = fields.next() {
+        let mut parts = entry.splitn(3, '\t');
+        let (adds, dels, path) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(a), Some(d), Some(p)) => (a, d, p),
+            _ => continue,
+        };
+
+        let (path, old_path) = if path.is_empty() {
+            let old = fields.next().unwrap_or_default().to_string();
+            let new = fields.next().unwrap_or_default().to_string();
+            (new, Some(old))
+        } else {
+            (path.to_string(), None)
+        };
+
+        let binary = adds == "-" && dels == "-";
+        files.push(FileDiff {
+            path,
+            old_path,
+            additions: adds.parse().unwrap_or(0),
+            deletions: dels.parse().unwrap_or(0),
+            binary,
+            patch: None,
+        });
+    }
+
+    files
+}
+
+/// Делит общий текст изменений на части по файлам (по строкам `diff --git`)
+fn split_patch(patch: &str) -> Vec<String> {
+    let mut parts: Vec<String> = Vec::new();
+
+    for line in patch.split_inclusive('\n') {
+        if line.starts_with("diff --git ") || line.starts_with("diff --cc ") || parts.is_empty() {
+            parts.push(String::new());
+        }
+        if let Some(current) = parts.last_mut() {
+            current.push_str(line);
+        }
+    }
+
+    parts
+}
@@ -0,0 +1,180 @@
+use std::process::{ExitStatus, Output};
+use log::error;
+use crate::error::AppError;
+
+/// Код выхода git при неверных аргументах командной строки
+const USAGE_EXIT_CODE: i32 = 129;
+
+/// Категория ошибки git, определённая по его stderr
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GitErrorKind {
+    /// Ревизия или объект не найдены
+    RefNotFound,
+    /// Путь отсутствует в дереве ревизии
+    PathNotFound,
+    /// Каталог не является репозиторием git
+    NotARepository,
+    /// git отклонил аргументы команды
+    InvalidArgument,
+    /// Нет прав на файлы репозитория
+    PermissionDenied,
+    /// Любая другая ошибка
+    Internal,
+}
+
+/// Определяет категорию ошибки по stderr и коду выхода git
+///
+/// Сообщения сравниваются на английском: `run_git` запускает git с `LC_ALL=C`.
+///
+/// # Параметры
+///
+/// * `stderr` - Вывод git в stderr
+/// * `status` - Код завершения процесса
+///
+/// # Возвращает
+///
+/// * `GitErrorKind` - Категория ошибки; `Internal`, если сообщение не распознано
+pub fn classify_git_error(stderr: &str, status: ExitStatus) -> GitErrorKind {
+    let stderr = stderr.to_lowercase();
+    let contains_any = |patterns: &[&str]| patterns.iter().any(|pattern| stderr.contains(pattern));
+
+    if contains_any(&["not a git repository"]) {
+        GitErrorKind::NotARepository
+    } else if contains_any(&["is corrupt", "inflate:", "corrupt packed object", "no space left on device", "disk quota exceeded"])
+        || (stderr.contains("object file") && stderr.contains("is empty"))
+    {
+        // Повреждённый объект часто заканчивается сообщением `bad object`, а
+        // переполненный диск - ошибкой записи; это ошибки сервера, а не запроса
+        GitErrorKind::Internal
+    } else if contains_any(&["permission denied", "insufficient permission", "operation not permitted", "read-only file system"]) {
+        GitErrorKind::PermissionDenied
+    } else if contains_any(&["does not exist in", "exists on disk, but not in", "no such path", "did not match any file"]) {
+        GitErrorKind::PathNotFound
+    } else if contains_any(&[
+        "unknown revision",
+        "bad revision",
+        "bad object",
+        "not a valid object name",
+        "invalid object name",
+        "needed a single revision",
+        "not a valid commit name",
+        "no such ref",
+        "not our ref",
+    ]) {
+        GitErrorKind::RefNotFound
+    } else if status.code() == Some(USAGE_EXIT_CODE)
+        || contains_any(&["unknown option", "unrecognized argument", "invalid option", "unknown switch", "unknown archive format", "usage: "])
+        || contains_any(&["is not a positive number", "invalid deepen", "error processing shallow info", "deepen and deepen-since"])
+    {
+        GitErrorKind::InvalidArgument
+    } else {
+        GitErrorKind::Internal
+    }
+}
+
+/// Превращает неуспешный вывод git в ошибку приложения с подходящим HTTP статусом
+///
+/// Клиенту уходит только категория ошибки; полный stderr записывается в лог.
+///
+/// # Параметры
+///
+/// * `command` - Имя команды git для лога
+/// * `output` - Вывод завершившегося с ошибкой git
+///
+/// # Возвращает
+///
+/// * `AppError` - 404, 400, 403 или 500 в зависимости от категории
+pub fn git_failure(command: &str, output: &Output) -> AppError {
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    error!("git {} failed: {}", command, stderr);
+
+    match classify_git_error(&stderr, output.status) {
+        GitErrorKind::RefNotFound => AppError::NotFound("Revision not found".to_string()),
+        GitErrorKind::PathNotFound => AppError::NotFound("Path not found".to_string()),
+        GitErrorKind::NotARepository => AppError::NotFound("Repository not found".to_string()),
+        GitErrorKind::InvalidArgument => AppError::BadRequest("Invalid git request".to_string()),
+        GitErrorKind::PermissionDenied => AppError::Forbidden("Permission denied".to_string()),
+        GitErrorKind::Internal => AppError::Git(stderr),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+
+    fn exit(code: i32) -> ExitStatus {
+        ExitStatus::from_raw(code << 8)
+    }
+
+    #[test]
+    fn missing_revisions_and_paths() {
+        let cases = [
+            ("fatal: ambiguous argument 'nope': unknown revision or path not in the working tree.", GitErrorKind::RefNotFound),
+            ("fatal: bad object 0123456789abcdef0123456789abcdef01234567", GitErrorKind::RefNotFound),
+            ("fatal: Not a valid object name nope", GitErrorKind::RefNotFound),
+            ("fatal: path 'missing.txt' does not exist in 'main'", GitErrorKind::PathNotFound),
+            ("fatal: not a git repository: 'repositories/a/b.git'", GitErrorKind::NotARepository),
+        ];
+        for (stderr, kind) in cases {
+            assert_eq!(classify_git_error(stderr, exit(128)), kind, "{}", stderr);
+        }
+    }
+
+    #[test]
+    fn not_our_ref_is_a_missing_revision() {
+        let stderr = "fatal: git upload-pack: not our ref 0123456789abcdef0123456789abcdef01234567";
+        assert_eq!(classify_git_error(stderr, exit(128)), GitErrorKind::RefNotFound);
+        let stderr = "error: upload-pack: not our ref 0123456789abcdef0123456789abcdef01234567";
+        assert_eq!(classify_git_error(stderr, exit(128)), GitErrorKind::RefNotFound);
+    }
+
+    #[test]
+    fn corrupt_objects_are_server_errors() {
+        let cases = [
+            "error: object file repositories/a/b.git/objects/01/23 is empty\nfatal: bad object HEAD",
+            "fatal: loose object 0123456789abcdef0123456789abcdef01234567 (stored in objects/01/23) is corrupt",
+            "error: inflate: data stream error (incorrect header check)\nerror: unable to unpack 0123 header\nfatal: bad object HEAD",
+            "error: packed object 0123 (stored in objects/pack/pack-1.pack) is corrupt",
+        ];
+        for stderr in cases {
+            assert_eq!(classify_git_error(stderr, exit(128)), GitErrorKind::Internal, "{}", stderr);
+        }
+    }
+
+    #[test]
+    fn shallow_and_depth_errors_are_bad_requests() {
+        let cases = [
+            "fatal: git upload-pack: deepen 0 is not a positive number",
+            "fatal: git upload-pack: invalid deepen: -1",
+            "fatal: error processing shallow info: 4",
+            "fatal: git upload-pack: deepen and deepen-since (or deepen-not) cannot be used together",
+        ];
+        for stderr in cases {
+            assert_eq!(classify_git_error(stderr, exit(128)), GitErrorKind::InvalidArgument, "{}", stderr);
+        }
+    }
+
+    #[test]
+    fn full_disk_is_a_server_error() {
+        let cases = [
+            "fatal: write error: No space left on device",
+            "error: unable to create temporary file: No space left on device\nfatal: failed to write object",
+            "fatal: sha1 file 'objects/pack/tmp_pack_1' write error: Disk quota exceeded",
+        ];
+        for stderr in cases {
+            assert_eq!(classify_git_error(stderr, exit(128)), GitErrorKind::Internal, "{}", stderr);
+        }
+    }
+
+    #[test]
+    fn usage_errors_and_permissions() {
+        assert_eq!(classify_git_error("error: unknown option `frobnicate'", exit(129)), GitErrorKind::InvalidArgument);
+        assert_eq!(classify_git_error("", exit(129)), GitErrorKind::InvalidArgument);
+        assert_eq!(
+            classify_git_error("error: insufficient permission for adding an object to repository database", exit(128)),
+            GitErrorKind::PermissionDenied
+        );
+        assert_eq!(classify_git_error("fatal: something unexpected", exit(128)), GitErrorKind::Internal);
+    }
+}
@@ -0,0 +1,101 @@
+use std::io::Cursor;
+use std::str::from_utf8;
+use crate::git::pktline::{read_pkt, Packet};
+
+/// Что клиент просит в запросе к upload-pack
+#[derive(Debug, Default)]
+pub struct FetchRequest {
+    /// Число строк `want`/`want-ref`
+    pub wants: usize,
+    /// Число строк `have` - объектов, которые у клиента уже есть
+    pub haves: usize,
+    /// Запрошена неполная история (`deepen`, `deepen-since`, `deepen-not`)
+    pub deepen: bool,
+    /// Запрошен partial clone (`filter`)
+    pub filter: bool,
+}
+
+impl FetchRequest {
+    /// Разбирает тело запроса upload-pack (protocol v0/v1 и команду `fetch` v2)
+    ///
+    /// Некорректный pkt-line не считается ошибкой: разбор останавливается,
+    /// а окончательно запрос проверит сам git.
+    ///
+    /// # Параметры
+    ///
+    /// * `body` - Тело запроса
+    ///
+    /// # Возвращает
+    ///
+    /// * `FetchRequest` - Сводка по запросу
+    pub fn parse(body: &[u8]) -> Self {
+        let mut request = FetchRequest::default();
+        let mut reader = Cursor::new(body);
+
+        while let Ok(Some(packet)) = read_pkt(&mut reader) {
+            let line = match packet {
+                Packet::Data(data) => data,
+                _ => continue,
+            };
+            let line = String::from_utf8_lossy(&line);
+            let keyword = line.split_whitespace().next().unwrap_or_default();
+
+            match keyword {
+                "want" | "want-ref" => request.wants += 1,
+                "have" => request.haves += 1,
+                "deepen" | "deepen-since" | "deepen-not" => request.deepen = true,
+                "filter" => request.filter = true,
+                _ => {}
+            }
+        }
+
+        request
+    }
+
+    /// Клиент просит всю историю с нуля: есть `want`, нет `have`, глубины и фильтра
+    pub fn is_full_clone(&self) -> bool {
+        self.wants > 0 && self.haves == 0 && !self.deepen && !self.filter
+    }
+}
+
+/// Проверяет, что тело запроса upload-pack - это последовательность pkt-line,
+/// начинающаяся со строки `want` (protocol v0/v1) или `command=` (v2)
+///
+/// Нужна, чтобы отклонить явный мусор до запуска git; смысл строк проверяет git.
+///
+/// # Параметры
+///
+/// * `body` - Тело запроса
+///
+/// # Возвращает
+///
+/// * `Result<(), String>` - Описание ошибки для ответа `400`
+pub fn check_upload_pack_body(body: &[u8]) -> Result<(), String> {
+    let mut reader = Cursor::new(body);
+    let mut first = true;
+
+    loop {
+        let packet = match read_pkt(&mut reader) {
+            Ok(Some(packet)) => packet,
+            Ok(None) if first => return Err("Empty upload-pack request".to_string()),
+            Ok(None) => return Ok(()),
+            Err(e) => return Err(format!("Malformed pkt-line at byte {}: {}", reader.position(), e)),
+        };
+
+        if first {
+            let starts_request = match &packet {
+                Packet::Data(line) => line.starts_with(b"want ") || line.starts_with(b"command="),
+                _ => false,
+            };
+            if !starts_request {
+                let line = match &packet {
+                    Packet::Data(line) if line.is_empty() => "an empty line".to_string(),
+                    Packet::Data(line) => from_utf8(line).unwrap_or("<binary>").trim_end().chars().take(40).collect(),
+                    _ => "a special packet".to_string(),
+                };
+                return Err(format!("Upload-pack request must start with want or command=, got {}", line));
+            }
+            first = false;
+        }
+    }
+}
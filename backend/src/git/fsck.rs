@@ -0,0 +1,246 @@
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::{Child, ExitStatus, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use lazy_static::lazy_static;
+use log::{error, warn};
+use serde::Serialize;
+use crate::config::CONFIG;
+use crate::error::AppError;
+use crate::git::batch::CatFile;
+use crate::git::git_command;
+
+/// Как часто проверять, завершился ли git fsck
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Результат проверки целостности репозитория
+#[derive(Debug, Serialize)]
+pub struct FsckReport {
+    /// Повреждений не найдено
+    pub ok: bool,
+    /// Ошибки и предупреждения fsck (отсутствующие и битые объекты, битые ссылки)
+    pub errors: Vec<String>,
+    /// Недостижимые объекты (`dangling <тип> <sha>`) - не повреждение, но мусор для gc
+    pub dangling: Vec<String>,
+}
+
+/// Сколько недостижимых объектов возвращается за один запрос
+pub const MAX_UNREACHABLE_OBJECTS: usize = 1000;
+
+/// Объект, недостижимый ни из одной ссылки
+#[derive(Debug, Serialize)]
+pub struct UnreachableObject {
+    pub sha: String,
+    /// `commit`, `tree`, `blob` или `tag`
+    #[serde(rename = "type")]
+    pub object_type: String,
+    /// Размер в байтах; None, если `cat-file` его не сообщил
+    pub size: Option<u64>,
+}
+
+/// Недостижимые объекты репозитория
+#[derive(Debug, Serialize)]
+pub struct UnreachableReport {
+    /// Первые `MAX_UNREACHABLE_OBJECTS` объектов в порядке вывода fsck
+    pub objects: Vec<UnreachableObject>,
+    /// Сколько всего недостижимых объектов
+    pub total: usize,
+    /// В `objects` попали не все объекты
+    pub truncated: bool,
+}
+
+lazy_static! {
+    /// Репозитории, для которых сейчас выполняется fsck
+    static ref RUNNING: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+/// Отметка о выполняющемся fsck; снимается при выходе из области видимости
+pub struct FsckGuard {
+    repo_name: String,
+}
+
+impl Drop for FsckGuard {
+    fn drop(&mut self) {
+        RUNNING.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.repo_name);
+    }
+}
+
+/// Отмечает начало fsck для репозитория
+///
+/// # Параметры
+///
+/// * `repo_name` - Полное имя репозитория `{owner}/{name}`
+///
+/// # Возвращает
+///
+/// * `Option<FsckGuard>` - Отметка или None, если fsck этого репозитория уже идёт
+pub fn try_start(repo_name: &str) -> Option<FsckGuard> {
+    let mut running = RUNNING.lock().unwrap_or_else(|e| e.into_inner());
+    running
+        .insert(repo_name.to_string())
+        .then(|| FsckGuard { repo_name: repo_name.to_string() })
+}
+
+/// Запускает `git fsck --full` и разбирает его вывод
+///
+/// Процесс останавливается, если не уложился в GIT_HTTP_FSCK_TIMEOUT.
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+///
+/// # Возвращает
+///
+/// * `Result<FsckReport, AppError>` - Отчёт или ошибка запуска/таймаута
+pub fn run_fsck(repo_path: &Path) -> Result<FsckReport, AppError> {
+    let mut child = spawn_fsck(repo_path, &["--full"])?;
+
+    // Вывод читается параллельно, чтобы fsck не заблокировался на заполненном канале
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+
+    let status = wait_with_timeout(&mut child)?;
+
+    let output = format!(
+        "{}\n{}",
+        stdout.join().unwrap_or_default(),
+        stderr.join().unwrap_or_default()
+    );
+    let mut report = parse_fsck(&output);
+    report.ok = status.success() && report.errors.is_empty();
+
+    Ok(report)
+}
+
+/// Запускает `git fsck` с общими параметрами и дополнительными аргументами
+fn spawn_fsck(repo_path: &Path, args: &[&str]) -> Result<Child, AppError> {
+    git_command()
+        .arg("--git-dir")
+        .arg(repo_path)
+        .args(["fsck", "--no-progress", "--no-reflogs"])
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            error!("Failed to run git fsck: {}", e);
+            AppError::Git(e.to_string())
+        })
+}
+
+/// Ждёт завершения fsck, останавливая его по истечении GIT_HTTP_FSCK_TIMEOUT
+fn wait_with_timeout(child: &mut Child) -> Result<ExitStatus, AppError> {
+    let deadline = Instant::now() + Duration::from_secs(CONFIG.fsck_timeout_secs);
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Ok(status),
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(AppError::Timeout(format!(
+                    "git fsck did not finish within {} seconds",
+                    CONFIG.fsck_timeout_secs
+                )));
+            }
+            Ok(None) => thread::sleep(POLL_INTERVAL),
+            Err(e) => return Err(AppError::Git(e.to_string())),
+        }
+    }
+}
+
+/// Находит объекты, недостижимые ни из одной ссылки (`git fsck --unreachable`)
+///
+/// Reflog не учитывается: объекты, на которые ссылается только он, тоже
+/// попадают в список, потому что gc удалит их после истечения reflog. Размеры
+/// берутся из одного процесса `git cat-file --batch-check`. В ответ попадают первые
+/// `MAX_UNREACHABLE_OBJECTS` объектов, `total` считает все.
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+///
+/// # Возвращает
+///
+/// * `Result<UnreachableReport, AppError>` - Объекты или ошибка запуска/таймаута
+pub fn unreachable_objects(repo_path: &Path) -> Result<UnreachableReport, AppError> {
+    let mut child = spawn_fsck(repo_path, &["--unreachable"])?;
+
+    let stdout = child.stdout.take();
+    let listed = thread::spawn(move || {
+        let mut objects = Vec::new();
+        let mut total = 0;
+        if let Some(stdout) = stdout {
+            // Все строки дочитываются до конца, но в памяти остаются только первые
+            for line in BufReader::new(stdout).lines().map_while(|line| line.ok()) {
+                let mut fields = line.split_whitespace();
+                if let (Some("unreachable"), Some(object_type), Some(sha)) = (fields.next(), fields.next(), fields.next()) {
+                    total += 1;
+                    if objects.len() < MAX_UNREACHABLE_OBJECTS {
+                        objects.push(UnreachableObject { sha: sha.to_string(), object_type: object_type.to_string(), size: None });
+                    }
+                }
+            }
+        }
+        (objects, total)
+    });
+    let stderr = read_in_background(child.stderr.take());
+
+    let status = wait_with_timeout(&mut child)?;
+    let (mut objects, total) = listed.join().unwrap_or_default();
+    if !status.success() {
+        // Повреждения не мешают перечислить недостижимые объекты; их показывает POST .../fsck
+        warn!("git fsck --unreachable exited with {}: {}", status, stderr.join().unwrap_or_default().trim());
+    }
+
+    if !objects.is_empty() {
+        let mut batch = CatFile::check(repo_path)?;
+        for object in &mut objects {
+            object.size = batch.info(&object.sha)?.map(|info| info.size);
+        }
+    }
+
+    Ok(UnreachableReport { truncated: total > objects.len(), total, objects })
+}
+
+/// Читает поток до конца в отдельном потоке
+fn read_in_background<R: Read + Send + 'static>(stream: Option<R>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut text = String::new();
+        if let Some(mut stream) = stream {
+            let mut bytes = Vec::new();
+            let _ = stream.read_to_end(&mut bytes);
+            text = String::from_utf8_lossy(&bytes).to_string();
+        }
+        text
+    })
+}
+
+/// Раскладывает строки вывода fsck на ошибки и недостижимые объекты
+fn parse_fsck(output: &str) -> FsckReport {
+    let mut report = FsckReport { ok: true, errors: Vec::new(), dangling: Vec::new() };
+
+    for line in output.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if line.starts_with("dangling ") {
+            report.dangling.push(line.to_string());
+        } else if line.starts_with("notice:") || line.starts_with("Checking ") {
+            // Например, "notice: HEAD points to an unborn branch" в пустом репозитории
+            continue;
+        } else if let (Some(target), Some(last)) = (line.strip_prefix("to "), report.errors.last_mut()) {
+            // "broken link from <тип> <sha>" и "to <тип> <sha>" - одна ошибка на двух строках
+            if last.starts_with("broken link from") {
+                last.push_str(" to ");
+                last.push_str(target.trim());
+            } else {
+                report.errors.push(line.to_string());
+            }
+        } else {
+            report.errors.push(line.split_whitespace().collect::<Vec<_>>().join(" "));
+        }
+    }
+
+    report
+}
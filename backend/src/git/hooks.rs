@@ -0,0 +1,132 @@
+use std::fs;
+use std::io::{self, BufRead};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use lazy_static::lazy_static;
+use log::error;
+use crate::config::CONFIG;
+use crate::git::enter_hook_mode;
+use crate::git::refs::{receive_protection, RefUpdate};
+use crate::git::secrets::{self, ScanMode, SCAN_MODE};
+use crate::git::signatures;
+
+/// Аргумент командной строки, с которым сервер запускается как pre-receive хук
+pub const PRE_RECEIVE_ARG: &str = "pre-receive-hook";
+
+/// SHA, которым git обозначает отсутствующую ссылку во входных данных хуков
+const ZERO_SHA: &str = "0000000000000000000000000000000000000000";
+
+lazy_static! {
+    /// Каталог с pre-receive хуком
+    static ref HOOKS_DIR: Option<PathBuf> = install_hook()
+        .map_err(|e| error!("Failed to install pre-receive hook: {}", e))
+        .ok();
+}
+
+/// Создаёт каталог хуков, где pre-receive запускает этот же исполняемый файл
+fn install_hook() -> io::Result<PathBuf> {
+    let exe = std::env::current_exe()?;
+    let dir = std::env::temp_dir().join(format!("git-http-hooks-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+
+    // git запускается с очищенным окружением, поэтому настройки, нужные хуку,
+    // записываются в сам скрипт; хук работает в каталоге репозитория, поэтому пути абсолютные
+    let mut settings = format!("GIT_HTTP_SECRET_SCAN={} ", shell_quote(&CONFIG.secret_scan));
+    for (name, path) in [
+        ("GIT_HTTP_SECRET_RULES", &CONFIG.secret_rules_file),
+        ("GIT_HTTP_ALLOWED_SIGNERS", &CONFIG.allowed_signers_file),
+        ("GIT_HTTP_GPG_HOME", &CONFIG.gpg_home),
+    ] {
+        if let Some(path) = path {
+            let path = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+            settings.push_str(&format!("{}={} ", name, shell_quote(&path.to_string_lossy())));
+        }
+    }
+    let hook = dir.join("pre-receive");
+    let script = format!(
+        "#!/bin/sh\n{}exec {} {}\n",
+        settings,
+        shell_quote(&exe.to_string_lossy()),
+        PRE_RECEIVE_ARG
+    );
+    fs::write(&hook, script)?;
+    fs::set_permissions(&hook, fs::Permissions::from_mode(0o755))?;
+
+    Ok(dir)
+}
+
+/// Заключает строку в одинарные кавычки для `sh`
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Каталог хуков для `core.hooksPath`, если push в репозиторий нужно проверять в pre-receive
+///
+/// Хук нужен в строгом режиме поиска секретов и для репозиториев, требующих
+/// подписанных коммитов.
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+///
+/// # Возвращает
+///
+/// * `Option<&'static Path>` - Каталог хуков или None, если проверять нечего
+pub fn pre_receive_hooks_dir(repo_path: &Path) -> Option<&'static Path> {
+    let signed = match receive_protection(repo_path) {
+        Ok(protection) => protection.require_signed_commits,
+        Err(e) => {
+            // Не зная настроек, проверяем подписи: лишний отказ лучше пропущенного коммита
+            error!("Failed to read receive protection of {}: {}", repo_path.display(), e);
+            true
+        }
+    };
+
+    if *SCAN_MODE == ScanMode::Strict || signed {
+        HOOKS_DIR.as_deref()
+    } else {
+        None
+    }
+}
+
+/// Точка входа pre-receive хука: проверяет все обновления и отклоняет push целиком,
+/// если хотя бы одно из них не прошло проверку
+///
+/// git передаёт на stdin строки `<old> <new> <ref>` и запускает хук в каталоге
+/// репозитория; новые объекты ещё в карантине, но доступны через окружение.
+///
+/// # Возвращает
+///
+/// * `i32` - Код завершения процесса: 0 - push разрешён
+pub fn run_pre_receive_hook() -> i32 {
+    enter_hook_mode();
+    let repo_path = PathBuf::from(std::env::var("GIT_DIR").unwrap_or_else(|_| ".".to_string()));
+
+    let updates: Vec<RefUpdate> = io::stdin()
+        .lock()
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            match (fields.next(), fields.next(), fields.next()) {
+                (Some(old), Some(new), Some(name)) => Some(RefUpdate {
+                    name: name.to_string(),
+                    old: (old != ZERO_SHA).then(|| old.to_string()),
+                    new: (new != ZERO_SHA).then(|| new.to_string()),
+                }),
+                _ => None,
+            }
+        })
+        .collect();
+
+    let mut rejected = false;
+    if *SCAN_MODE == ScanMode::Strict {
+        rejected |= secrets::reject_secrets(&repo_path, &updates);
+    }
+    let signed = receive_protection(&repo_path).map(|protection| protection.require_signed_commits);
+    if signed.unwrap_or(true) {
+        rejected |= signatures::reject_unsigned(&repo_path, &updates);
+    }
+
+    if rejected { 1 } else { 0 }
+}
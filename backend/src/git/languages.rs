@@ -0,0 +1,166 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::Mutex;
+use lazy_static::lazy_static;
+use log::error;
+use crate::error::AppError;
+use crate::git::run_git;
+
+/// Расширение файла (в нижнем регистре) и язык
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("rs", "Rust"),
+    ("c", "C"), ("h", "C"),
+    ("cc", "C++"), ("cpp", "C++"), ("cxx", "C++"), ("hpp", "C++"), ("hh", "C++"),
+    ("cs", "C#"),
+    ("go", "Go"),
+    ("java", "Java"),
+    ("kt", "Kotlin"), ("kts", "Kotlin"),
+    ("scala", "Scala"),
+    ("swift", "Swift"),
+    ("m", "Objective-C"), ("mm", "Objective-C++"),
+    ("py", "Python"), ("pyi", "Python"),
+    ("rb", "Ruby"),
+    ("php", "PHP"),
+    ("pl", "Perl"), ("pm", "Perl"),
+    ("lua", "Lua"),
+    ("r", "R"),
+    ("dart", "Dart"),
+    ("ex", "Elixir"), ("exs", "Elixir"),
+    ("erl", "Erlang"),
+    ("hs", "Haskell"),
+    ("ml", "OCaml"), ("mli", "OCaml"),
+    ("clj", "Clojure"),
+    ("zig", "Zig"),
+    ("js", "JavaScript"), ("mjs", "JavaScript"), ("cjs", "JavaScript"), ("jsx", "JavaScript"),
+    ("ts", "TypeScript"), ("tsx", "TypeScript"),
+    ("vue", "Vue"),
+    ("svelte", "Svelte"),
+    ("html", "HTML"), ("htm", "HTML"),
+    ("css", "CSS"),
+    ("scss", "SCSS"), ("sass", "Sass"),
+    ("sh", "Shell"), ("bash", "Shell"), ("zsh", "Shell"),
+    ("ps1", "PowerShell"),
+    ("sql", "SQL"),
+    ("md", "Markdown"),
+    ("json", "JSON"),
+    ("yml", "YAML"), ("yaml", "YAML"),
+    ("toml", "TOML"),
+    ("xml", "XML"),
+    ("proto", "Protocol Buffers"),
+    ("tf", "HCL"),
+    ("nix", "Nix"),
+];
+
+/// Файлы без расширения, язык которых известен по имени
+const FILENAMES: &[(&str, &str)] = &[
+    ("Makefile", "Makefile"),
+    ("GNUmakefile", "Makefile"),
+    ("Dockerfile", "Dockerfile"),
+    ("CMakeLists.txt", "CMake"),
+];
+
+/// Каталоги со сторонним или сгенерированным кодом, не относящимся к проекту
+const VENDORED_DIRS: &[&str] = &[
+    "vendor", "node_modules", "third_party", "thirdparty", "external", "dist", "target", "build",
+];
+
+/// Сгенерированные файлы, которые не пишут вручную
+const GENERATED_FILES: &[&str] = &["package-lock.json", "npm-shrinkwrap.json", "composer.lock"];
+
+/// Размер файлов в байтах по языкам
+pub type Languages = BTreeMap<String, u64>;
+
+lazy_static! {
+    /// Последний подсчёт для каждого репозитория: SHA коммита и результат
+    static ref CACHE: Mutex<HashMap<String, (String, Languages)>> = Mutex::new(HashMap::new());
+}
+
+/// Определяет язык файла по пути
+///
+/// Файлы с неизвестным расширением (в том числе картинки, архивы и прочие
+/// бинарные файлы), а также файлы из каталогов `VENDORED_DIRS`, сгенерированные
+/// файлы и минифицированные скрипты не учитываются.
+fn language_of(path: &str) -> Option<&'static str> {
+    let mut components: Vec<&str> = path.split('/').collect();
+    let name = components.pop()?;
+    if components.iter().any(|dir| VENDORED_DIRS.contains(dir))
+        || GENERATED_FILES.contains(&name)
+        || name.contains(".min.")
+    {
+        return None;
+    }
+
+    if let Some((_, language)) = FILENAMES.iter().find(|(file, _)| *file == name) {
+        return Some(language);
+    }
+
+    let (stem, extension) = name.rsplit_once('.')?;
+    if stem.is_empty() {
+        return None;
+    }
+    let extension = extension.to_lowercase();
+    EXTENSIONS
+        .iter()
+        .find(|(ext, _)| *ext == extension)
+        .map(|(_, language)| *language)
+}
+
+/// Считает байты по языкам в дереве коммита
+///
+/// Результат кэшируется по SHA коммита и пересчитывается только после того,
+/// как коммит изменился.
+///
+/// # Параметры
+///
+/// * `repo_name` - Полное имя репозитория `{owner}/{name}` (ключ кэша)
+/// * `repo_path` - Путь к bare репозиторию
+/// * `sha` - SHA коммита
+///
+/// # Возвращает
+///
+/// * `Result<Languages, AppError>` - Язык и суммарный размер его файлов в байтах
+pub fn language_breakdown(repo_name: &str, repo_path: &Path, sha: &str) -> Result<Languages, AppError> {
+    if let Some((cached_sha, languages)) = CACHE.lock().unwrap_or_else(|e| e.into_inner()).get(repo_name) {
+        if cached_sha == sha {
+            return Ok(languages.clone());
+        }
+    }
+
+    let output = match run_git(repo_path, &["ls-tree", "-r", "--long", "-z", sha]) {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            error!("git ls-tree failed: {}", stderr);
+            return Err(AppError::Git(stderr));
+        }
+        Err(e) => {
+            error!("Failed to run git ls-tree: {}", e);
+            return Err(AppError::Git(e.to_string()));
+        }
+    };
+
+    let mut languages = BTreeMap::new();
+    // Запись: "<mode> <type> <sha> <size>\t<path>"; ссылки и подмодули пропускаются
+    for entry in String::from_utf8_lossy(&output.stdout).split('\0') {
+        let (meta, path) = match entry.split_once('\t') {
+            Some(parts) => parts,
+            None => continue,
+        };
+        let fields: Vec<&str> = meta.split_whitespace().collect();
+        let size = match fields.as_slice() {
+            [mode, "blob", _, size] if *mode != "120000" => size.parse::<u64>().unwrap_or(0),
+            _ => continue,
+        };
+
+        if let Some(language) = language_of(path) {
+            *languages.entry(language.to_string()).or_insert(0) += size;
+        }
+    }
+
+    CACHE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(repo_name.to_string(), (sha.to_string(), languages.clone()));
+
+    Ok(languages)
+}
@@ -0,0 +1,198 @@
+use std::path::Path;
+use serde::Serialize;
+use log::error;
+use crate::error::AppError;
+use crate::git::errors::git_failure;
+use crate::git::run_git;
+
+/// Разделитель полей в формате `git log`
+const FIELD_SEP: char = '\u{1f}';
+/// Разделитель записей в формате `git log`
+const RECORD_SEP: char = '\u{1e}';
+
+/// Краткая информация о коммите
+#[derive(Debug, Serialize, Clone)]
+pub struct CommitSummary {
+    /// SHA коммита
+    pub sha: String,
+    /// Первая строка сообщения
+    pub subject: String,
+    /// Имя автора
+    pub author_name: String,
+    /// Email автора
+    pub author_email: String,
+    /// Дата коммита в формате RFC3339
+    pub date: String,
+}
+
+/// Коммит из истории файла вместе с именем файла в этом коммите
+#[derive(Debug, Serialize)]
+pub struct FileHistoryEntry {
+    /// Коммит
+    #[serde(flatten)]
+    pub commit: CommitSummary,
+    /// Путь к файлу в этом коммите
+    pub path: String,
+    /// Прежний путь, если в этом коммите файл переименован или скопирован
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_path: Option<String>,
+}
+
+/// Возвращает коммиты, достижимые из `to`, но не из `from` (`git log from..to`)
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `from` - Начальная ревизия (не включается)
+/// * `to` - Конечная ревизия
+/// * `no_merges` - Пропускать коммиты слияния
+/// * `max_count` - Максимальное число коммитов (самых новых)
+///
+/// # Возвращает
+///
+/// * `Result<Vec<CommitSummary>, AppError>` - Коммиты от новых к старым
+pub fn commits_between(
+    repo_path: &Path,
+    from: &str,
+    to: &str,
+    no_merges: bool,
+    max_count: usize,
+) -> Result<Vec<CommitSummary>, AppError> {
+    let range = format!("{}..{}", from, to);
+    let format = log_format();
+    let max_count = format!("--max-count={}", max_count);
+    let mut args = vec!["log", format.as_str(), max_count.as_str()];
+    if no_merges {
+        args.push("--no-merges");
+    }
+    args.push(range.as_str());
+
+    match run_git(repo_path, &args) {
+        Ok(output) if output.status.success() => Ok(parse_log(&String::from_utf8_lossy(&output.stdout))),
+        Ok(output) => Err(git_failure("log", &output)),
+        Err(e) => {
+            error!("Failed to run git log: {}", e);
+            Err(AppError::Git(e.to_string()))
+        }
+    }
+}
+
+/// Время последнего коммита, изменившего файл
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `commit` - Коммит, с которого начинается поиск (должен быть уже проверен)
+/// * `path` - Путь к файлу в дереве; трактуется буквально, без магии pathspec
+///
+/// # Возвращает
+///
+/// * `Result<Option<i64>, AppError>` - Unix время коммита (committer date) или None,
+///   если в истории нет коммитов с этим путём
+pub fn last_modified(repo_path: &Path, commit: &str, path: &str) -> Result<Option<i64>, AppError> {
+    match run_git(repo_path, &["--literal-pathspecs", "log", "-1", "--format=%ct", commit, "--", path]) {
+        Ok(output) if output.status.success() => Ok(String::from_utf8_lossy(&output.stdout).trim().parse().ok()),
+        Ok(output) => Err(git_failure("log", &output)),
+        Err(e) => {
+            error!("Failed to run git log: {}", e);
+            Err(AppError::Git(e.to_string()))
+        }
+    }
+}
+
+/// Коммиты, изменявшие файл, с учётом переименований (`git log --follow`)
+///
+/// `--skip` вместе с `--follow` пропускает не те коммиты, поэтому git читает первые
+/// `offset + limit` коммитов, а лишние отбрасываются здесь.
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `commit` - Коммит, с которого начинается история (должен быть уже проверен)
+/// * `path` - Путь к файлу в `commit`; трактуется буквально, без магии pathspec
+/// * `limit` - Размер страницы
+/// * `offset` - Сколько коммитов пропустить
+///
+/// # Возвращает
+///
+/// * `Result<Vec<FileHistoryEntry>, AppError>` - Коммиты от новых к старым; пустой
+///   список, если такого пути в истории никогда не было
+pub fn file_history(
+    repo_path: &Path,
+    commit: &str,
+    path: &str,
+    limit: usize,
+    offset: usize,
+) -> Result<Vec<FileHistoryEntry>, AppError> {
+    let format = format!("--format={1}%H{0}%s{0}%an{0}%ae{0}%cI", FIELD_SEP, RECORD_SEP);
+    let max_count = format!("--max-count={}", offset + limit);
+    let args = [
+        "--literal-pathspecs", "log", "--follow", "--name-status", "-z",
+        max_count.as_str(), format.as_str(), commit, "--", path,
+    ];
+
+    let stdout = match run_git(repo_path, &args) {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout).into_owned(),
+        Ok(output) => return Err(git_failure("log", &output)),
+        Err(e) => {
+            error!("Failed to run git log: {}", e);
+            return Err(AppError::Git(e.to_string()));
+        }
+    };
+
+    // Имя файла, под которым он известен на текущем шаге истории: для коммитов без
+    // списка изменений (слияния) берётся оно
+    let mut current = path.to_string();
+    let mut entries = Vec::new();
+    for record in stdout.split(RECORD_SEP).filter(|record| !record.is_empty()) {
+        let (header, changes) = record.split_once('\0').unwrap_or((record, ""));
+        let mut fields = header.split(FIELD_SEP);
+        let commit = match (fields.next(), fields.next(), fields.next(), fields.next(), fields.next()) {
+            (Some(sha), Some(subject), Some(author_name), Some(author_email), Some(date)) => CommitSummary {
+                sha: sha.to_string(),
+                subject: subject.to_string(),
+                author_name: author_name.to_string(),
+                author_email: author_email.to_string(),
+                date: date.to_string(),
+            },
+            _ => continue,
+        };
+
+        // `M\0path\0` или `R100\0old\0new\0`
+        let changes: Vec<&str> = changes.trim_start_matches('\n').split('\0').filter(|part| !part.is_empty()).collect();
+        let (path, previous_path) = match changes.as_slice() {
+            [status, old, new, ..] if status.starts_with('R') || status.starts_with('C') => {
+                (new.to_string(), Some(old.to_string()))
+            }
+            [_, path, ..] => (path.to_string(), None),
+            _ => (current.clone(), None),
+        };
+        current = previous_path.clone().unwrap_or_else(|| path.clone());
+
+        entries.push(FileHistoryEntry { commit, path, previous_path });
+    }
+
+    Ok(entries.into_iter().skip(offset).collect())
+}
+
+/// Формат `git log`, разбираемый функцией `parse_log`
+fn log_format() -> String {
+    format!("--format=%H{0}%s{0}%an{0}%ae{0}%cI{1}", FIELD_SEP, RECORD_SEP)
+}
+
+/// Разбирает вывод `git log` в формате `log_format`
+fn parse_log(stdout: &str) -> Vec<CommitSummary> {
+    stdout
+        .split(RECORD_SEP)
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').split(FIELD_SEP);
+            Some(CommitSummary {
+                sha: fields.next().filter(|sha| !sha.is_empty())?.to_string(),
+                subject: fields.next()?.to_string(),
+                author_name: fields.next()?.to_string(),
+                author_email: fields.next()?.to_string(),
+                date: fields.next()?.to_string(),
+            })
+        })
+        .collect()
+}
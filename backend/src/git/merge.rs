@@ -0,0 +1,96 @@
+use std::path::Path;
+use log::error;
+use crate::error::AppError;
+use crate::git::errors::git_failure;
+use crate::git::run_git;
+
+/// Что произойдёт с целевой веткой при слиянии в неё исходной
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MergeKind {
+    /// Все коммиты исходной ветки уже есть в целевой
+    UpToDate,
+    /// Целевая ветка просто передвинется вперёд, история останется линейной
+    FastForward,
+    /// Ветки разошлись, понадобится коммит слияния
+    MergeCommit,
+}
+
+/// Проверяет, является ли `ancestor` предком `descendant` (или тем же коммитом)
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `ancestor` - SHA предполагаемого предка
+/// * `descendant` - SHA потомка
+///
+/// # Возвращает
+///
+/// * `Result<bool, AppError>` - true, если `ancestor` достижим из `descendant`
+pub fn is_ancestor(repo_path: &Path, ancestor: &str, descendant: &str) -> Result<bool, AppError> {
+    match run_git(repo_path, &["merge-base", "--is-ancestor", ancestor, descendant]) {
+        Ok(output) if output.status.success() => Ok(true),
+        // Код 1 означает «не предок», остальные коды - ошибка
+        Ok(output) if output.status.code() == Some(1) => Ok(false),
+        Ok(output) => Err(git_failure("merge-base", &output)),
+        Err(e) => {
+            error!("Failed to run git merge-base: {}", e);
+            Err(AppError::Git(e.to_string()))
+        }
+    }
+}
+
+/// Определяет, как исходный коммит ляжет на целевой при слиянии
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `target` - SHA коммита целевой ветки
+/// * `source` - SHA коммита исходной ветки
+///
+/// # Возвращает
+///
+/// * `Result<MergeKind, AppError>` - Вид слияния
+pub fn merge_kind(repo_path: &Path, target: &str, source: &str) -> Result<MergeKind, AppError> {
+    if is_ancestor(repo_path, source, target)? {
+        Ok(MergeKind::UpToDate)
+    } else if is_ancestor(repo_path, target, source)? {
+        Ok(MergeKind::FastForward)
+    } else {
+        Ok(MergeKind::MergeCommit)
+    }
+}
+
+/// Находит общих предков двух коммитов (`git merge-base`)
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `a` - SHA первого коммита
+/// * `b` - SHA второго коммита
+/// * `all` - Вернуть все лучшие общие предки (`--all`), а не одного
+///
+/// # Возвращает
+///
+/// * `Result<Vec<String>, AppError>` - SHA общих предков; пустой список, если истории не пересекаются
+pub fn merge_bases(repo_path: &Path, a: &str, b: &str, all: bool) -> Result<Vec<String>, AppError> {
+    let mut args = vec!["merge-base"];
+    if all {
+        args.push("--all");
+    }
+    args.extend(["--", a, b]);
+
+    match run_git(repo_path, &args) {
+        Ok(output) if output.status.success() => Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect()),
+        // Код 1 без вывода означает, что общего предка нет
+        Ok(output) if output.status.code() == Some(1) && output.stdout.is_empty() => Ok(Vec::new()),
+        Ok(output) => Err(git_failure("merge-base", &output)),
+        Err(e) => {
+            error!("Failed to run git merge-base: {}", e);
+            Err(AppError::Git(e.to_string()))
+        }
+    }
+}
@@ -0,0 +1,620 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+use lazy_static::lazy_static;
+use crate::config::CONFIG;
+use crate::error::AppError;
+
+pub mod advertise;
+pub mod archive;
+pub mod batch;
+pub mod blame;
+pub mod branches;
+pub mod bundle;
+pub mod contents;
+pub mod contributors;
+pub mod diff;
+pub mod errors;
+pub mod fetch;
+pub mod fsck;
+pub mod hooks;
+pub mod languages;
+pub mod log;
+pub mod merge;
+pub mod pktline;
+pub mod pool;
+pub mod refs;
+pub mod replica;
+pub mod secrets;
+pub mod signatures;
+pub mod throttle;
+pub mod version;
+
+/// Корневой каталог, в котором хранятся bare репозитории
+pub const REPOS_ROOT: &str = "repositories";
+
+/// Сервис smart HTTP протокола git
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GitService {
+    /// Выдача объектов (clone, fetch)
+    UploadPack,
+    /// Приём объектов и обновление ссылок (push)
+    ReceivePack,
+}
+
+impl GitService {
+    /// Разбирает имя сервиса из запроса; неизвестные сервисы не принимаются
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "git-upload-pack" => Some(GitService::UploadPack),
+            "git-receive-pack" => Some(GitService::ReceivePack),
+            _ => None,
+        }
+    }
+
+    /// Имя сервиса в протоколе, например `git-upload-pack`
+    pub fn name(&self) -> &'static str {
+        match self {
+            GitService::UploadPack => "git-upload-pack",
+            GitService::ReceivePack => "git-receive-pack",
+        }
+    }
+
+    /// Подкоманда git, реализующая сервис
+    pub fn command(&self) -> &'static str {
+        match self {
+            GitService::UploadPack => "upload-pack",
+            GitService::ReceivePack => "receive-pack",
+        }
+    }
+
+    /// Включён ли сервис в GIT_HTTP_SERVICES
+    pub fn is_enabled(&self) -> bool {
+        CONFIG.enabled_services.contains(self.name())
+    }
+
+    /// Максимальный размер тела POST запроса к сервису
+    pub fn max_body_bytes(&self) -> usize {
+        match self {
+            GitService::UploadPack => CONFIG.upload_pack_max_body_bytes,
+            GitService::ReceivePack => CONFIG.max_body_bytes,
+        }
+    }
+}
+
+/// Максимальная длина имени репозитория
+const MAX_REPO_NAME_LEN: usize = 100;
+
+/// Проверяет, что имя репозитория безопасно использовать в пути на диске
+///
+/// Допускаются только латинские буквы, цифры, `-`, `_` и `.`; имя не может
+/// начинаться с точки и не может оканчиваться на `.git`.
+///
+/// # Параметры
+///
+/// * `name` - Имя репозитория
+///
+/// # Возвращает
+///
+/// * `Result<(), AppError>` - Ошибка, если имя недопустимо
+pub fn validate_repo_name(name: &str) -> Result<(), AppError> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_REPO_NAME_LEN
+        && !name.starts_with('.')
+        && !name.ends_with(".git")
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::InvalidRepoName(name.to_string()))
+    }
+}
+
+/// Проверяет, что имя пользователя можно использовать как каталог владельца в `REPOS_ROOT`
+///
+/// Правила те же, что у имён репозиториев (`validate_repo_name`): каталог
+/// владельца не должен выглядеть как репозиторий старой раскладки `{name}.git`.
+/// Проверяется при регистрации и при построении путей.
+///
+/// # Параметры
+///
+/// * `owner` - Имя пользователя-владельца
+///
+/// # Возвращает
+///
+/// * `Result<(), AppError>` - `BadRequest`, если имя недопустимо
+pub fn validate_owner_name(owner: &str) -> Result<(), AppError> {
+    validate_repo_name(owner).map_err(|_| AppError::BadRequest(format!("Invalid username: {}", owner)))
+}
+
+/// Полное имя репозитория `{owner}/{name}`
+///
+/// Имена репозиториев уникальны только в пределах владельца, поэтому пути на
+/// диске, блокировки и кэши используют полное имя.
+pub fn full_repo_name(owner: &str, name: &str) -> String {
+    format!("{}/{}", owner, name)
+}
+
+/// Строит путь к bare репозиторию без проверки его существования
+///
+/// Используется при создании репозитория; во всех остальных случаях
+/// следует вызывать [`resolve_repo`]. Это единственное место, где строится
+/// путь для `--git-dir`: `{root}/{owner}/{name}.git` без завершающего `/`. Регистр
+/// имени сохраняется; имена, отличающиеся только регистром, отклоняются при
+/// создании (см. `Repository::find_case_conflict`).
+///
+/// # Параметры
+///
+/// * `root` - Корневой каталог репозиториев
+/// * `full_name` - Полное имя репозитория `{owner}/{name}`
+///
+/// # Возвращает
+///
+/// * `Result<PathBuf, AppError>` - Путь к каталогу `{owner}/{name}.git`
+pub fn repo_path(root: &Path, full_name: &str) -> Result<PathBuf, AppError> {
+    let (owner, name) = full_name
+        .split_once('/')
+        .ok_or_else(|| AppError::InvalidRepoName(full_name.to_string()))?;
+    validate_owner_name(owner)?;
+    validate_repo_name(name)?;
+    Ok(root.join(owner).join(format!("{}.git", name)))
+}
+
+/// Проверяет имя, строит путь и убеждается, что по нему лежит bare репозиторий
+///
+/// # Параметры
+///
+/// * `root` - Корневой каталог репозиториев
+/// * `full_name` - Полное имя репозитория `{owner}/{name}`
+///
+/// # Возвращает
+///
+/// * `Result<PathBuf, AppError>` - Путь к существующему bare репозиторию
+pub fn resolve_repo(root: &Path, full_name: &str) -> Result<PathBuf, AppError> {
+    let path = repo_path(root, full_name)?;
+
+    if is_bare_repo(&path) {
+        Ok(path)
+    } else {
+        Err(AppError::RepoNotFound(full_name.to_string()))
+    }
+}
+
+/// Проверяет, что имя ссылки или ревизии можно безопасно передать git
+///
+/// Отсекает пустые значения, управляющие символы и строки, начинающиеся с `-`,
+/// которые git интерпретировал бы как опции.
+pub fn validate_ref(rev: &str) -> Result<(), AppError> {
+    let valid = !rev.is_empty()
+        && rev.len() <= 255
+        && !rev.starts_with('-')
+        && !rev.contains("..")
+        && !rev.chars().any(|c| c.is_control() || c.is_whitespace());
+
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::InvalidRef(rev.to_string()))
+    }
+}
+
+/// Переменные окружения сервера, которые получают процессы git
+///
+/// Остальное окружение (пароли, токены, `GIT_DIR`, `GIT_CONFIG_*` и т.п.) git и его
+/// хукам не передаётся. `HOME` нужен для глобальной конфигурации git (например,
+/// `safe.directory`).
+const PASSED_ENV: &[&str] = &["PATH", "HOME", "TMPDIR"];
+
+/// Переменные, которые git-receive-pack выставляет pre-receive хуку
+///
+/// Через них хук видит объекты push'а, пока те лежат в карантине. Передаются
+/// только из процесса хука (см. `enter_hook_mode`), а не из окружения сервера.
+const HOOK_ENV: &[&str] = &["GIT_DIR", "GIT_OBJECT_DIRECTORY", "GIT_ALTERNATE_OBJECT_DIRECTORIES", "GIT_QUARANTINE_PATH"];
+
+/// Процесс запущен git как хук
+static HOOK_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Отмечает, что процесс работает как хук git: его git команды получают `HOOK_ENV`
+pub fn enter_hook_mode() {
+    HOOK_MODE.store(true, Ordering::Relaxed);
+}
+
+/// Создаёт команду git с очищенным окружением
+///
+/// Все процессы git запускаются через неё: окружение сервера заменяется
+/// на `PASSED_ENV` и `LC_ALL=C`, а всё, что нужно конкретной команде
+/// (`GIT_PROTOCOL` и т.п.), вызывающий задаёт явно.
+pub fn git_command() -> Command {
+    let mut command = Command::new("git");
+    command.env_clear();
+
+    let hook_env: &[&str] = if HOOK_MODE.load(Ordering::Relaxed) { HOOK_ENV } else { &[] };
+    for name in PASSED_ENV.iter().chain(hook_env) {
+        if let Some(value) = std::env::var_os(name) {
+            command.env(name, value);
+        }
+    }
+
+    // Сообщения об ошибках разбираются по тексту (см. `errors::classify_git_error`)
+    command.env("LC_ALL", "C");
+    command
+}
+
+/// Запускает git с указанным `--git-dir` и возвращает его вывод
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `args` - Аргументы git после `--git-dir`
+///
+/// # Возвращает
+///
+/// * `std::io::Result<Output>` - Вывод процесса git
+pub fn run_git(repo_path: &Path, args: &[&str]) -> std::io::Result<Output> {
+    git_command()
+        .arg("--git-dir")
+        .arg(repo_path)
+        .args(args)
+        .output()
+}
+
+/// Разрешает ревизию в SHA коммита
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `rev` - Имя ветки, тега или SHA
+///
+/// # Возвращает
+///
+/// * `Result<String, AppError>` - Полный SHA коммита
+pub fn resolve_commit(repo_path: &Path, rev: &str) -> Result<String, AppError> {
+    validate_ref(rev)?;
+
+    let spec = format!("{}^{{commit}}", rev);
+    match run_git(repo_path, &["rev-parse", "--verify", "--quiet", &spec]) {
+        Ok(output) if output.status.success() => {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        }
+        _ => Err(AppError::RefNotFound(rev.to_string())),
+    }
+}
+
+/// Проверяет, что имя указывает на тег (`refs/tags/<name>`)
+pub fn is_tag(repo_path: &Path, name: &str) -> bool {
+    let full_ref = format!("refs/tags/{}", name);
+    matches!(
+        run_git(repo_path, &["show-ref", "--verify", "--quiet", &full_ref]),
+        Ok(output) if output.status.success()
+    )
+}
+
+/// Считает суммарный размер файлов в каталоге (рекурсивно, без перехода по симлинкам)
+pub fn dir_size(path: &Path) -> u64 {
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| match entry.file_type() {
+            Ok(ft) if ft.is_dir() => dir_size(&entry.path()),
+            Ok(ft) if ft.is_file() => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Строит список pack-файлов в формате `objects/info/packs` для dumb протокола
+///
+/// Файл `objects/info/packs` пишет только `git update-server-info`, поэтому после
+/// push по smart HTTP или repack его может не быть или он устаревает. Список
+/// строится по каталогу `objects/pack` при каждом запросе и ничего не записывает,
+/// так что работает и на реплике только для чтения. Пакет попадает в список, только
+/// когда для него уже записан `.idx`; новые пакеты идут первыми, как у git.
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+///
+/// # Возвращает
+///
+/// * `std::io::Result<String>` - Строки `P pack-<sha>.pack` и пустая строка в конце
+pub fn info_packs(repo_path: &Path) -> std::io::Result<String> {
+    let pack_dir = repo_path.join("objects/pack");
+    let entries = match std::fs::read_dir(&pack_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok("\n".to_string()),
+        Err(e) => return Err(e),
+    };
+
+    let mut packs = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let stem = match name.strip_prefix("pack-").and_then(|rest| rest.strip_suffix(".pack")) {
+            Some(stem) => stem,
+            None => continue,
+        };
+        if !pack_dir.join(format!("pack-{}.idx", stem)).is_file() {
+            continue;
+        }
+        let modified = entry.metadata()?.modified()?;
+        packs.push((modified, name));
+    }
+    packs.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+
+    let mut list: String = packs.iter().map(|(_, name)| format!("P {}\n", name)).collect();
+    list.push('\n');
+    Ok(list)
+}
+
+/// Проверяет, что каталог выглядит как bare репозиторий Git
+pub(crate) fn is_bare_repo(path: &Path) -> bool {
+    path.is_dir()
+        && path.join("HEAD").is_file()
+        && path.join("objects").is_dir()
+        && path.join("refs").is_dir()
+}
+
+lazy_static! {
+    /// Имена репозиториев, для которых сейчас выполняется изменяющая операция
+    static ref LOCKED_REPOS: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+    /// Сигнал об освобождении блокировки какого-либо репозитория
+    static ref REPO_UNLOCKED: Condvar = Condvar::new();
+}
+
+/// Блокировка репозитория; снимается автоматически при выходе из области видимости
+pub struct RepoLock {
+    repo_name: String,
+}
+
+impl Drop for RepoLock {
+    fn drop(&mut self) {
+        let mut locked = LOCKED_REPOS.lock().unwrap_or_else(|e| e.into_inner());
+        locked.remove(&self.repo_name);
+        REPO_UNLOCKED.notify_all();
+    }
+}
+
+/// Захватывает рекомендательную блокировку репозитория
+///
+/// Сериализует серверные операции над одним репозиторием (receive-pack, слияние
+/// пул-реквестов); операции над разными репозиториями друг друга не блокируют.
+///
+/// # Параметры
+///
+/// * `repo_name` - Полное имя репозитория `{owner}/{name}`
+///
+/// # Возвращает
+///
+/// * `Option<RepoLock>` - Блокировка или None, если её не удалось получить за отведённое время
+pub fn lock_repo(repo_name: &str) -> Option<RepoLock> {
+    let deadline = Instant::now() + Duration::from_secs(CONFIG.repo_lock_timeout_secs);
+    let mut locked = LOCKED_REPOS.lock().unwrap_or_else(|e| e.into_inner());
+
+    while locked.contains(repo_name) {
+        let now = Instant::now();
+        if now >= deadline {
+            return None;
+        }
+        locked = REPO_UNLOCKED
+            .wait_timeout(locked, deadline - now)
+            .unwrap_or_else(|e| e.into_inner())
+            .0;
+    }
+
+    locked.insert(repo_name.to_string());
+    Some(RepoLock { repo_name: repo_name.to_string() })
+}
+
+lazy_static! {
+    /// Каталоги `TempDir`, которые сейчас используются; очистка их не трогает
+    static ref LIVE_TEMP_DIRS: Mutex<HashSet<PathBuf>> = Mutex::new(HashSet::new());
+}
+
+/// Временный рабочий каталог в `GIT_HTTP_TEMP_DIR`; удаляется при выходе из области видимости
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// Создаёт каталог `{GIT_HTTP_TEMP_DIR}/{prefix}-{uuid}`
+    ///
+    /// Случайный суффикс исключает совпадение имён у одновременных операций, в том
+    /// числе с каталогами, оставшимися после аварийного завершения сервера.
+    ///
+    /// # Параметры
+    ///
+    /// * `prefix` - Начало имени каталога
+    ///
+    /// # Возвращает
+    ///
+    /// * `std::io::Result<TempDir>` - Созданный пустой каталог
+    pub fn new(prefix: &str) -> std::io::Result<TempDir> {
+        std::fs::create_dir_all(&CONFIG.temp_dir)?;
+        let path = CONFIG.temp_dir.join(format!("{}-{}", prefix, uuid::Uuid::new_v4()));
+        std::fs::create_dir(&path)?;
+        LIVE_TEMP_DIRS.lock().unwrap_or_else(|e| e.into_inner()).insert(path.clone());
+        Ok(TempDir { path })
+    }
+
+    /// Путь к каталогу
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.path) {
+            ::log::error!("Failed to remove temporary directory {}: {}", self.path.display(), e);
+        }
+        LIVE_TEMP_DIRS.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.path);
+    }
+}
+
+/// Создан ли каталог `TempDir::new`: `{prefix}-{uuid}` с одним из префиксов сервера
+///
+/// `GIT_HTTP_TEMP_DIR` по умолчанию - общий системный каталог, поэтому чужие
+/// каталоги (без известного префикса и UUID) не трогаются.
+fn is_temp_dir_name(name: &str) -> bool {
+    let Some((prefix, suffix)) = name.len().checked_sub(37).and_then(|at| name.split_at_checked(at)) else {
+        return false;
+    };
+    let Some(uuid) = suffix.strip_prefix('-') else {
+        return false;
+    };
+    let known = prefix == "contents"
+        || prefix == "import_bundle"
+        || prefix.strip_prefix("temp_merge_").is_some_and(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()));
+    known && uuid::Uuid::parse_str(uuid).is_ok()
+}
+
+/// `temp_merge_{id}` в рабочем каталоге сервера - так слияния создавали клоны до `TempDir`
+fn is_legacy_merge_dir_name(name: &str) -> bool {
+    name.strip_prefix("temp_merge_").is_some_and(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Удаляет временные каталоги, оставшиеся после аварийного завершения операций
+///
+/// `TempDir` удаляет каталог сам, но если сервер упал или был убит посреди слияния
+/// или импорта, каталог остаётся навсегда. Удаляются только каталоги сервера
+/// (см. `is_temp_dir_name`), которые не используются сейчас этим процессом и не
+/// менялись дольше `max_age`: возраст защищает каталоги других процессов сервера
+/// с тем же `GIT_HTTP_TEMP_DIR`.
+///
+/// # Параметры
+///
+/// * `max_age` - Минимальный возраст удаляемого каталога
+///
+/// # Возвращает
+///
+/// * `usize` - Сколько каталогов удалено
+pub fn remove_stale_temp_dirs(max_age: Duration) -> usize {
+    let mut removed = 0;
+
+    for (dir, is_ours) in [
+        (CONFIG.temp_dir.as_path(), is_temp_dir_name as fn(&str) -> bool),
+        (Path::new("."), is_legacy_merge_dir_name),
+    ] {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                ::log::warn!("Failed to list temporary directory {}: {}", dir.display(), e);
+                continue;
+            }
+        };
+
+        for entry in entries.flatten() {
+            if !entry.file_name().to_str().is_some_and(is_ours) {
+                continue;
+            }
+
+            // Символические ссылки не раскрываются: удаляются только настоящие каталоги
+            let path = entry.path();
+            let stale = entry
+                .metadata()
+                .ok()
+                .filter(|metadata| metadata.is_dir())
+                .and_then(|metadata| metadata.modified().ok())
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age >= max_age);
+            if !stale || LIVE_TEMP_DIRS.lock().unwrap_or_else(|e| e.into_inner()).contains(&path) {
+                continue;
+            }
+
+            match std::fs::remove_dir_all(&path) {
+                Ok(()) => removed += 1,
+                Err(e) => ::log::warn!("Failed to remove stale temporary directory {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::repository::Repository;
+    use crate::test_support::{client_git, commit_files, create_repo, create_user, test_db, unique, work_dir};
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn repo_names_with_slashes_or_a_git_suffix_are_rejected() {
+        for name in ["repo/", "/repo", "owner/repo", "repo.git", "repo.git/", ".repo", "", "re po", "repo\\"] {
+            assert!(matches!(validate_repo_name(name), Err(AppError::InvalidRepoName(_))), "{:?}", name);
+        }
+        for name in ["repo", "Repo", "my-repo_1.0"] {
+            assert!(validate_repo_name(name).is_ok(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn repo_path_has_no_trailing_slash_and_keeps_the_case() {
+        let root = Path::new("repositories");
+        assert_eq!(repo_path(root, "alice/Foo").unwrap(), Path::new("repositories/alice/Foo.git"));
+        assert!(!repo_path(root, "alice/foo").unwrap().to_string_lossy().ends_with('/'));
+
+        // Завершающий `/` в имени не даёт другого пути к тому же каталогу
+        for full_name in ["alice/foo/", "alice//foo", "alice/foo/.", "alice", "alice/"] {
+            assert!(repo_path(root, full_name).is_err(), "{:?}", full_name);
+        }
+    }
+
+    #[test]
+    fn names_differing_only_in_case_are_one_repository_per_owner() {
+        let db = test_db();
+        let owner = create_user(&db, "case");
+        let other = create_user(&db, "case");
+        create_repo(&db, &owner, "Foo", true);
+
+        for name in ["foo", "FOO"] {
+            let conflict = Repository::find_case_conflict(owner.id.unwrap(), name, db.get_connection()).unwrap();
+            assert_eq!(conflict.as_deref(), Some("Foo"), "{}", name);
+        }
+        assert_eq!(Repository::find_case_conflict(other.id.unwrap(), "foo", db.get_connection()).unwrap(), None);
+
+        // На диске путь строится из имени как есть: `foo` не находит каталог `Foo.git`
+        let root = Path::new(REPOS_ROOT);
+        assert!(resolve_repo(root, &full_repo_name(&owner.username, "Foo")).is_ok());
+        assert!(matches!(
+            resolve_repo(root, &full_repo_name(&owner.username, "foo")),
+            Err(AppError::RepoNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn hooks_do_not_see_the_server_environment() {
+        let sentinel = unique("GIT_HTTP_TEST_SENTINEL_");
+        std::env::set_var(&sentinel, "leaked");
+
+        let db = test_db();
+        let owner = create_user(&db, "env");
+        let repo = create_repo(&db, &owner, "env", true);
+        commit_files(&repo, &[("README.md", b"env\n")]);
+        let bare = work_dir().join(repo_path(Path::new(REPOS_ROOT), &repo.full_name()).unwrap());
+        let env_file = work_dir().join(unique("hook-env"));
+        let hook = bare.join("hooks/post-receive");
+        fs::write(&hook, format!("#!/bin/sh\nenv > '{}'\n", env_file.display())).unwrap();
+        fs::set_permissions(&hook, fs::Permissions::from_mode(0o755)).unwrap();
+
+        // Клиент и receive-pack запускаются через git_command, хук наследует их окружение
+        let clone = work_dir().join(unique("env-clone"));
+        client_git(work_dir(), &["clone", "-q", bare.to_str().unwrap(), clone.to_str().unwrap()]);
+        client_git(&clone, &["commit", "-q", "--allow-empty", "-m", "env"]);
+        client_git(&clone, &["push", "-q", "origin", "main"]);
+
+        let env = fs::read_to_string(&env_file).unwrap();
+        assert!(env.lines().any(|line| line.starts_with("PATH=")), "{}", env);
+        assert!(!env.contains(&sentinel), "{}", env);
+    }
+}
@@ -0,0 +1,157 @@
+use std::io::{self, Read, Write};
+
+/// Максимальный размер пакета вместе с 4 байтами длины
+pub const MAX_PKT_LEN: usize = 65520;
+
+/// Один пакет pkt-line
+#[derive(Debug, Clone, PartialEq)]
+pub enum Packet {
+    /// `0000` - конец сообщения или секции
+    Flush,
+    /// `0001` - разделитель секций в protocol v2
+    Delim,
+    /// `0002` - конец ответа в stateless protocol v2
+    ResponseEnd,
+    /// Пакет с данными (длина в заголовке включает сами 4 байта длины)
+    Data(Vec<u8>),
+}
+
+/// Читает следующий пакет
+///
+/// # Параметры
+///
+/// * `reader` - Источник байтов
+///
+/// # Возвращает
+///
+/// * `io::Result<Option<Packet>>` - Пакет, None в конце потока или ошибка
+///   `InvalidData` для некорректной длины (например, `0003` или не hex)
+pub fn read_pkt<R: Read>(reader: &mut R) -> io::Result<Option<Packet>> {
+    let mut header = [0u8; 4];
+    let mut filled = 0;
+    while filled < header.len() {
+        match reader.read(&mut header[filled..])? {
+            0 if filled == 0 => return Ok(None),
+            0 => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated pkt-line length")),
+            n => filled += n,
+        }
+    }
+
+    let len = std::str::from_utf8(&header)
+        .ok()
+        .filter(|hex| hex.chars().all(|c| c.is_ascii_hexdigit()))
+        .and_then(|hex| usize::from_str_radix(hex, 16).ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid pkt-line length"))?;
+
+    match len {
+        0 => Ok(Some(Packet::Flush)),
+        1 => Ok(Some(Packet::Delim)),
+        2 => Ok(Some(Packet::ResponseEnd)),
+        3 => Err(io::Error::new(io::ErrorKind::InvalidData, "invalid pkt-line length 0003")),
+        _ if len > MAX_PKT_LEN => Err(io::Error::new(io::ErrorKind::InvalidData, "pkt-line too long")),
+        _ => {
+            let mut payload = vec![0u8; len - 4];
+            reader.read_exact(&mut payload)?;
+            Ok(Some(Packet::Data(payload)))
+        }
+    }
+}
+
+/// Записывает пакет с данными
+///
+/// # Параметры
+///
+/// * `writer` - Куда писать
+/// * `payload` - Данные пакета (не более `MAX_PKT_LEN - 4` байт)
+pub fn write_pkt<W: Write>(writer: &mut W, payload: &[u8]) -> io::Result<()> {
+    if payload.len() + 4 > MAX_PKT_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "pkt-line payload too long"));
+    }
+
+    write!(writer, "{:04x}", payload.len() + 4)?;
+    writer.write_all(payload)
+}
+
+/// Записывает flush-пакет `0000`
+pub fn write_flush<W: Write>(writer: &mut W) -> io::Result<()> {
+    writer.write_all(b"0000")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(mut input: &[u8]) -> io::Result<Vec<Packet>> {
+        let mut packets = Vec::new();
+        while let Some(packet) = read_pkt(&mut input)? {
+            packets.push(packet);
+        }
+        Ok(packets)
+    }
+
+    #[test]
+    fn reads_special_packets_and_data() {
+        let packets = read_all(b"000ecommand=ls00010008abc\n00000002").unwrap();
+        assert_eq!(packets, vec![
+            Packet::Data(b"command=ls".to_vec()),
+            Packet::Delim,
+            Packet::Data(b"abc\n".to_vec()),
+            Packet::Flush,
+            Packet::ResponseEnd,
+        ]);
+    }
+
+    #[test]
+    fn empty_input_is_end_of_stream() {
+        assert_eq!(read_pkt(&mut &b""[..]).unwrap(), None);
+    }
+
+    #[test]
+    fn length_includes_the_header() {
+        assert_eq!(read_all(b"0004").unwrap(), vec![Packet::Data(Vec::new())]);
+    }
+
+    #[test]
+    fn accepts_packets_up_to_the_maximum_length() {
+        let payload = vec![b'x'; MAX_PKT_LEN - 4];
+        let mut encoded = Vec::new();
+        write_pkt(&mut encoded, &payload).unwrap();
+        assert_eq!(&encoded[..4], b"fff0");
+        assert_eq!(read_all(&encoded).unwrap(), vec![Packet::Data(payload)]);
+    }
+
+    #[test]
+    fn rejects_packets_over_the_maximum_length() {
+        let mut input = b"fff1".to_vec();
+        input.extend(vec![b'x'; MAX_PKT_LEN - 3]);
+        let err = read_pkt(&mut input.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let err = write_pkt(&mut Vec::new(), &vec![b'x'; MAX_PKT_LEN - 3]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn rejects_invalid_lengths() {
+        for input in [&b"0003"[..], b"00g5abcd", b"+005a", b" 005a"] {
+            let err = read_pkt(&mut &input[..]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{:?}", String::from_utf8_lossy(input));
+        }
+    }
+
+    #[test]
+    fn reports_truncated_input() {
+        assert_eq!(read_pkt(&mut &b"00"[..]).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(read_pkt(&mut &b"0009abc"[..]).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn write_round_trips() {
+        let mut encoded = Vec::new();
+        write_pkt(&mut encoded, b"want 0123\n").unwrap();
+        write_flush(&mut encoded).unwrap();
+        assert_eq!(encoded, b"000ewant 0123\n0000");
+        assert_eq!(read_all(&encoded).unwrap(), vec![Packet::Data(b"want 0123\n".to_vec()), Packet::Flush]);
+    }
+}
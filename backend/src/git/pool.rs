@@ -0,0 +1,92 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use lazy_static::lazy_static;
+use log::{error, info};
+use tokio::sync::oneshot;
+use crate::config::CONFIG;
+use crate::error::AppError;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Пул потоков для запуска git процессов
+///
+/// Отделён от рабочих потоков actix и от общего blocking-пула: всплеск
+/// клонов занимает только его потоки. Очередь ограничена, и когда она
+/// заполнена, новые задачи сразу отклоняются, а не копятся без предела.
+struct GitPool {
+    sender: SyncSender<Job>,
+}
+
+impl GitPool {
+    fn new(threads: usize, queue: usize) -> Self {
+        let (sender, receiver) = sync_channel::<Job>(queue);
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for i in 0..threads.max(1) {
+            let receiver = Arc::clone(&receiver);
+            thread::Builder::new()
+                .name(format!("git-pool-{}", i))
+                .spawn(move || worker(receiver))
+                .expect("Failed to spawn git pool thread");
+        }
+
+        info!("Started git pool with {} threads and queue of {}", threads.max(1), queue);
+        GitPool { sender }
+    }
+}
+
+/// Выполняет задачи из очереди, пока пул существует
+fn worker(receiver: Arc<Mutex<Receiver<Job>>>) {
+    loop {
+        let job = match receiver.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => return,
+        };
+
+        match job {
+            // Паника в задаче не должна уменьшать число потоков пула
+            Ok(job) => {
+                if catch_unwind(AssertUnwindSafe(job)).is_err() {
+                    error!("Git pool job panicked");
+                }
+            }
+            Err(_) => return,
+        }
+    }
+}
+
+lazy_static! {
+    static ref GIT_POOL: GitPool = GitPool::new(CONFIG.git_pool_threads, CONFIG.git_pool_queue);
+}
+
+/// Выполняет блокирующую работу с git в пуле git процессов
+///
+/// # Параметры
+///
+/// * `job` - Работа, запускающая git (процесс, ожидание блокировки и т.п.)
+///
+/// # Возвращает
+///
+/// * `Result<T, AppError>` - Результат работы или `AppError::Busy`, если очередь пула заполнена
+pub async fn run_blocking<F, T>(job: F) -> Result<T, AppError>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let (sender, receiver) = oneshot::channel();
+    let job: Job = Box::new(move || {
+        let _ = sender.send(job());
+    });
+
+    match GIT_POOL.sender.try_send(job) {
+        Ok(()) => {}
+        Err(TrySendError::Full(_)) => return Err(AppError::Busy("Too many git operations in progress".to_string())),
+        Err(TrySendError::Disconnected(_)) => return Err(AppError::Git("Git pool is not running".to_string())),
+    }
+
+    receiver
+        .await
+        .map_err(|_| AppError::Git("Git pool job failed".to_string()))
+}
@@ -0,0 +1,411 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use log::error;
+use crate::error::AppError;
+use crate::git::batch::CatFile;
+use crate::git::{run_git, validate_ref};
+
+/// Изменение одной ссылки в результате push
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefUpdate {
+    /// Полное имя ссылки, например `refs/heads/master`
+    pub name: String,
+    /// SHA до push; None - ссылка создана
+    pub old: Option<String>,
+    /// SHA после push; None - ссылка удалена
+    pub new: Option<String>,
+}
+
+/// Вид изменения ссылки
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RefChange {
+    Create,
+    Update,
+    Delete,
+}
+
+impl RefUpdate {
+    /// Имя ветки, если ссылка - ветка (`refs/heads/<branch>`)
+    pub fn branch(&self) -> Option<&str> {
+        self.name.strip_prefix("refs/heads/")
+    }
+
+    /// Имя тега, если ссылка - тег (`refs/tags/<tag>`)
+    pub fn tag(&self) -> Option<&str> {
+        self.name.strip_prefix("refs/tags/")
+    }
+
+    /// Ссылка создана, передвинута или удалена
+    pub fn change(&self) -> RefChange {
+        match (&self.old, &self.new) {
+            (None, _) => RefChange::Create,
+            (_, None) => RefChange::Delete,
+            _ => RefChange::Update,
+        }
+    }
+}
+
+/// SHA пустого дерева - база для диффа ветки, у коммитов которой нет предков
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+/// Статистика изменения одной ссылки
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PushStats {
+    /// Количество новых коммитов
+    pub commits: u64,
+    /// Количество изменённых файлов
+    pub files_changed: u64,
+    /// Количество добавленных строк
+    pub additions: u64,
+    /// Количество удалённых строк
+    pub deletions: u64,
+}
+
+/// Читает все ссылки репозитория
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+///
+/// # Возвращает
+///
+/// * `Result<BTreeMap<String, String>, AppError>` - Имя ссылки -> SHA объекта
+pub fn list_refs(repo_path: &Path) -> Result<BTreeMap<String, String>, AppError> {
+    match run_git(repo_path, &["for-each-ref", "--format=%(objectname) %(refname)"]) {
+        Ok(output) if output.status.success() => Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(sha, name)| (name.to_string(), sha.to_string()))
+            .collect()),
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            error!("git for-each-ref failed: {}", stderr);
+            Err(AppError::Git(stderr))
+        }
+        Err(e) => {
+            error!("Failed to run git for-each-ref: {}", e);
+            Err(AppError::Git(e.to_string()))
+        }
+    }
+}
+
+/// Разрешает несколько ссылок или ревизий в SHA коммитов
+///
+/// Ссылки ищутся в одном снимке `git for-each-ref` по тем же правилам, что и в
+/// `git rev-parse` (`<name>`, `refs/<name>`, `refs/tags/<name>`, `refs/heads/<name>`,
+/// `refs/remotes/<name>`, `refs/remotes/<name>/HEAD`); аннотированные теги
+/// раскрываются до коммита. То, что не нашлось среди ссылок (SHA, `HEAD`, выражения
+/// вроде `main~2`), разрешается одним общим процессом `git cat-file --batch-check`;
+/// некорректное имя даёт None только для себя.
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `names` - Имена ссылок или ревизии
+///
+/// # Возвращает
+///
+/// * `Result<BTreeMap<String, Option<String>>, AppError>` - Имя -> SHA коммита или None
+pub fn resolve_refs(repo_path: &Path, names: &[String]) -> Result<BTreeMap<String, Option<String>>, AppError> {
+    let listing = git_stdout(repo_path, &["for-each-ref", "--format=%(refname) %(objectname) %(*objectname)"])?;
+    let refs: BTreeMap<&str, &str> = listing
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(' ');
+            let name = fields.next()?;
+            let sha = fields.next()?;
+            let peeled = fields.next().filter(|peeled| !peeled.is_empty());
+            Some((name, peeled.unwrap_or(sha)))
+        })
+        .collect();
+
+    let mut batch: Option<CatFile> = None;
+    let mut resolved = BTreeMap::new();
+    for name in names {
+        let candidates = [
+                name.clone(),
+                format!("refs/{}", name),
+                format!("refs/tags/{}", name),
+                format!("refs/heads/{}", name),
+                format!("refs/remotes/{}", name),
+                format!("refs/remotes/{}/HEAD", name),
+        ];
+        let mut sha = candidates
+            .iter()
+            .find_map(|candidate| refs.get(candidate.as_str()))
+            .map(|sha| sha.to_string());
+
+        if sha.is_none() && validate_ref(name).is_ok() {
+            let batch = match &mut batch {
+                Some(batch) => batch,
+                None => batch.insert(CatFile::check(repo_path)?),
+            };
+            sha = batch.info(&format!("{}^{{commit}}", name))?.map(|info| info.sha);
+        }
+        resolved.insert(name.clone(), sha);
+    }
+    Ok(resolved)
+}
+
+/// Сравнивает два снимка ссылок и возвращает изменившиеся ссылки
+pub fn diff_refs(before: &BTreeMap<String, String>, after: &BTreeMap<String, String>) -> Vec<RefUpdate> {
+    let mut names: Vec<&String> = before.keys().chain(after.keys()).collect();
+    names.sort();
+    names.dedup();
+
+    names
+        .into_iter()
+        .filter(|name| before.get(*name) != after.get(*name))
+        .map(|name| RefUpdate {
+            name: name.clone(),
+            old: before.get(name).cloned(),
+            new: after.get(name).cloned(),
+        })
+        .collect()
+}
+
+/// Возвращает префиксы ссылок, скрытых от клиентов (`transfer.hideRefs` в конфигурации репозитория)
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+///
+/// # Возвращает
+///
+/// * `Result<Vec<String>, AppError>` - Префиксы скрытых ссылок
+pub fn hidden_refs(repo_path: &Path) -> Result<Vec<String>, AppError> {
+    match run_git(repo_path, &["config", "--local", "--get-all", "transfer.hideRefs"]) {
+        Ok(output) if output.status.success() => Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.to_string())
+            .collect()),
+        // Код 1 означает, что ключ не задан
+        Ok(output) if output.status.code() == Some(1) => Ok(Vec::new()),
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            error!("git config failed: {}", stderr);
+            Err(AppError::Git(stderr))
+        }
+        Err(e) => {
+            error!("Failed to run git config: {}", e);
+            Err(AppError::Git(e.to_string()))
+        }
+    }
+}
+
+/// Заменяет список скрытых ссылок репозитория
+///
+/// Скрытые ссылки не попадают в объявление ссылок upload-pack и receive-pack,
+/// поэтому не клонируются (в том числе `--mirror`) и не могут быть изменены push'ем.
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `prefixes` - Префиксы ссылок, например `refs/keep/`
+///
+/// # Возвращает
+///
+/// * `Result<(), AppError>` - Ошибка, если префикс недопустим или git завершился с ошибкой
+pub fn set_hidden_refs(repo_path: &Path, prefixes: &[String]) -> Result<(), AppError> {
+    for prefix in prefixes {
+        let valid = prefix.starts_with("refs/")
+            && !prefix.contains("..")
+            && prefix.len() <= 255
+            && !prefix.chars().any(|c| c.is_whitespace() || c.is_control());
+        if !valid {
+            return Err(AppError::InvalidRef(prefix.clone()));
+        }
+    }
+
+    let mut commands = vec![vec!["config", "--local", "--unset-all", "transfer.hideRefs"]];
+    for prefix in prefixes {
+        commands.push(vec!["config", "--local", "--add", "transfer.hideRefs", prefix]);
+    }
+
+    for args in commands {
+        match run_git(repo_path, &args) {
+            // --unset-all завершается с кодом 5, если ключа не было
+            Ok(output) if output.status.success() || (args[2] == "--unset-all" && output.status.code() == Some(5)) => {}
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+                error!("git config failed: {}", stderr);
+                return Err(AppError::Git(stderr));
+            }
+            Err(e) => {
+                error!("Failed to run git config: {}", e);
+                return Err(AppError::Git(e.to_string()));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Ключ конфигурации репозитория, требующий линейной истории при слиянии пул-реквестов
+const LINEAR_HISTORY_KEY: &str = "gitserver.requireLinearHistory";
+/// Ключ конфигурации репозитория, требующий подписанных коммитов в push
+const SIGNED_COMMITS_KEY: &str = "gitserver.requireSignedCommits";
+
+/// Защита ссылок репозитория
+///
+/// Первые два флага - встроенная в git защита от push; линейную историю git
+/// не проверяет, её соблюдает сервер при слиянии пул-реквестов, а подписи
+/// проверяет pre-receive хук сервера (см. `hooks::pre_receive_hooks_dir`).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReceiveProtection {
+    /// Запретить удаление ссылок (`receive.denyDeletes`)
+    pub deny_deletes: bool,
+    /// Запретить force-push (`receive.denyNonFastForwards`)
+    pub deny_non_fast_forwards: bool,
+    /// Сливать пул-реквесты только перемоткой, без коммитов слияния
+    pub require_linear_history: bool,
+    /// Принимать push, только если все новые коммиты подписаны известными ключами
+    pub require_signed_commits: bool,
+}
+
+/// Читает настройки защиты ссылок из конфигурации репозитория
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+///
+/// # Возвращает
+///
+/// * `Result<ReceiveProtection, AppError>` - Настройки; незаданный ключ считается `false`
+pub fn receive_protection(repo_path: &Path) -> Result<ReceiveProtection, AppError> {
+    Ok(ReceiveProtection {
+        deny_deletes: config_bool(repo_path, "receive.denyDeletes")?,
+        deny_non_fast_forwards: config_bool(repo_path, "receive.denyNonFastForwards")?,
+        require_linear_history: config_bool(repo_path, LINEAR_HISTORY_KEY)?,
+        require_signed_commits: config_bool(repo_path, SIGNED_COMMITS_KEY)?,
+    })
+}
+
+/// Записывает настройки защиты ссылок в конфигурацию репозитория
+///
+/// Проверку push выполняет сам git-receive-pack: отклонённые обновления приходят
+/// клиенту в отчёте о push как обычный отказ.
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `protection` - Новые настройки
+///
+/// # Возвращает
+///
+/// * `Result<(), AppError>` - Ошибка, если git завершился с ошибкой
+pub fn set_receive_protection(repo_path: &Path, protection: ReceiveProtection) -> Result<(), AppError> {
+    for (key, value) in [
+        ("receive.denyDeletes", protection.deny_deletes),
+        ("receive.denyNonFastForwards", protection.deny_non_fast_forwards),
+        (LINEAR_HISTORY_KEY, protection.require_linear_history),
+        (SIGNED_COMMITS_KEY, protection.require_signed_commits),
+    ] {
+        git_stdout(repo_path, &["config", "--local", key, if value { "true" } else { "false" }])?;
+    }
+    Ok(())
+}
+
+/// Читает булев ключ конфигурации репозитория; незаданный ключ - `false`
+pub fn config_bool(repo_path: &Path, key: &str) -> Result<bool, AppError> {
+    match run_git(repo_path, &["config", "--local", "--type=bool", "--get", key]) {
+        Ok(output) if output.status.success() => Ok(String::from_utf8_lossy(&output.stdout).trim() == "true"),
+        // Код 1 означает, что ключ не задан
+        Ok(output) if output.status.code() == Some(1) => Ok(false),
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            error!("git config failed: {}", stderr);
+            Err(AppError::Git(stderr))
+        }
+        Err(e) => {
+            error!("Failed to run git config: {}", e);
+            Err(AppError::Git(e.to_string()))
+        }
+    }
+}
+
+/// Считает статистику изменения ссылки (`git diff --shortstat before..after`)
+///
+/// Вызывается после push, когда ссылки уже обновлены. Для новой ссылки считаются
+/// коммиты, которых нет ни в одной другой ссылке, а дифф строится от родителя самого
+/// старого из них. Для удалённой ссылки статистика нулевая.
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `update` - Изменение ссылки
+///
+/// # Возвращает
+///
+/// * `Result<PushStats, AppError>` - Статистика или ошибка git
+pub fn push_stats(repo_path: &Path, update: &RefUpdate) -> Result<PushStats, AppError> {
+    let new = match update.new.as_deref() {
+        Some(new) => new,
+        None => return Ok(PushStats::default()),
+    };
+
+    let (commits, base) = match update.old.as_deref() {
+        Some(old) => {
+            let range = format!("{}..{}", old, new);
+            let count = git_stdout(repo_path, &["rev-list", "--count", range.as_str()])?;
+            (count.trim().parse().unwrap_or(0), old.to_string())
+        }
+        None => {
+            let exclude = format!("--exclude={}", update.name);
+            // Не `--all`: он включает HEAD, который может указывать на эту же ветку
+            let listed = git_stdout(repo_path, &["rev-list", "--topo-order", new, "--not", exclude.as_str(), "--glob=refs/*"])?;
+            let commits: Vec<&str> = listed.lines().collect();
+            let oldest = match commits.last() {
+                Some(oldest) => oldest,
+                // Ссылка создана на уже существующем коммите
+                None => return Ok(PushStats::default()),
+            };
+            let parent = format!("{}^", oldest);
+            let base = match run_git(repo_path, &["rev-parse", "--verify", "--quiet", parent.as_str()]) {
+                Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout).trim().to_string(),
+                _ => EMPTY_TREE.to_string(),
+            };
+            (commits.len() as u64, base)
+        }
+    };
+
+    let shortstat = git_stdout(repo_path, &["diff", "--shortstat", base.as_str(), new])?;
+    let mut stats = parse_shortstat(&shortstat);
+    stats.commits = commits;
+    Ok(stats)
+}
+
+/// Разбирает строку вида ` 3 files changed, 10 insertions(+), 2 deletions(-)`
+fn parse_shortstat(text: &str) -> PushStats {
+    let mut stats = PushStats::default();
+    for part in text.trim().split(", ") {
+        let (count, label) = match part.split_once(' ') {
+            Some((count, label)) => (count.parse().unwrap_or(0), label),
+            None => continue,
+        };
+        if label.starts_with("file") {
+            stats.files_changed = count;
+        } else if label.starts_with("insertion") {
+            stats.additions = count;
+        } else if label.starts_with("deletion") {
+            stats.deletions = count;
+        }
+    }
+    stats
+}
+
+/// Запускает git и возвращает его stdout
+pub fn git_stdout(repo_path: &Path, args: &[&str]) -> Result<String, AppError> {
+    match run_git(repo_path, args) {
+        Ok(output) if output.status.success() => Ok(String::from_utf8_lossy(&output.stdout).to_string()),
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            error!("git {} failed: {}", args[0], stderr);
+            Err(AppError::Git(stderr))
+        }
+        Err(e) => {
+            error!("Failed to run git {}: {}", args[0], e);
+            Err(AppError::Git(e.to_string()))
+        }
+    }
+}
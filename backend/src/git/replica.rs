@@ -0,0 +1,93 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use log::debug;
+use crate::config::CONFIG;
+use crate::error::AppError;
+use crate::git::{resolve_repo, REPOS_ROOT};
+
+/// Корневой каталог, из которого отдан репозиторий
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RepoRoot {
+    /// Основной каталог `REPOS_ROOT`: в него идут все записи
+    Primary,
+    /// Реплика только для чтения (GIT_HTTP_REPLICA_ROOT)
+    Replica,
+}
+
+/// Находит репозиторий для операции чтения (clone, fetch, объекты, архивы)
+///
+/// Если задана реплика и репозиторий на ней есть, читается реплика. Отсутствующий
+/// на реплике репозиторий или реплика, отставшая больше чем на
+/// GIT_HTTP_REPLICA_MAX_LAG_SECS, читаются из основного каталога, если это
+/// не запрещено GIT_HTTP_REPLICA_FALLBACK. Выбранный каталог пишется в лог.
+///
+/// # Параметры
+///
+/// * `repo_name` - Полное имя репозитория `{owner}/{name}`; реплика повторяет
+///   раскладку основного каталога
+///
+/// # Возвращает
+///
+/// * `Result<(PathBuf, RepoRoot), AppError>` - Путь к bare репозиторию и его каталог
+pub fn resolve_read_repo(repo_name: &str) -> Result<(PathBuf, RepoRoot), AppError> {
+    let primary_root = Path::new(REPOS_ROOT);
+    let replica_root = match &CONFIG.replica_root {
+        Some(root) => root,
+        None => return resolve_repo(primary_root, repo_name).map(|path| (path, RepoRoot::Primary)),
+    };
+
+    match resolve_repo(replica_root, repo_name) {
+        Ok(replica_path) => {
+            if let (true, Some(max_lag)) = (CONFIG.replica_fallback, CONFIG.replica_max_lag_secs) {
+                if let Ok(primary_path) = resolve_repo(primary_root, repo_name) {
+                    let lag = replica_lag(&primary_path, &replica_path);
+                    if lag > Duration::from_secs(max_lag) {
+                        debug!("Reading {} from primary: replica is {}s behind", repo_name, lag.as_secs());
+                        return Ok((primary_path, RepoRoot::Primary));
+                    }
+                }
+            }
+            debug!("Reading {} from replica {}", repo_name, replica_root.display());
+            Ok((replica_path, RepoRoot::Replica))
+        }
+        Err(AppError::RepoNotFound(_)) if CONFIG.replica_fallback => {
+            debug!("Reading {} from primary: not on replica", repo_name);
+            resolve_repo(primary_root, repo_name).map(|path| (path, RepoRoot::Primary))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Насколько ссылки реплики старше ссылок основного репозитория
+///
+/// Сравниваются времена изменения `packed-refs` и файлов в `refs/`, поэтому
+/// реплика должна копироваться с сохранением времён (например, `rsync -a`).
+fn replica_lag(primary: &Path, replica: &Path) -> Duration {
+    match (refs_modified(primary), refs_modified(replica)) {
+        (Some(primary), Some(replica)) => primary.duration_since(replica).unwrap_or_default(),
+        _ => Duration::ZERO,
+    }
+}
+
+/// Время последнего изменения ссылок репозитория
+fn refs_modified(repo_path: &Path) -> Option<SystemTime> {
+    let packed = fs::metadata(repo_path.join("packed-refs")).and_then(|meta| meta.modified()).ok();
+    let loose = newest_file(&repo_path.join("refs"));
+    packed.max(loose)
+}
+
+/// Самое позднее время изменения файлов в каталоге (рекурсивно, без перехода по симлинкам)
+fn newest_file(dir: &Path) -> Option<SystemTime> {
+    let entries = fs::read_dir(dir).ok()?;
+    let mut newest = None;
+    for entry in entries.flatten() {
+        let modified = match entry.file_type() {
+            Ok(kind) if kind.is_dir() => newest_file(&entry.path()),
+            Ok(kind) if kind.is_file() => entry.metadata().and_then(|meta| meta.modified()).ok(),
+            _ => None,
+        };
+        newest = newest.max(modified);
+    }
+    newest
+}
@@ -0,0 +1,247 @@
+use std::fs;
+use std::path::Path;
+use lazy_static::lazy_static;
+use log::{error, warn};
+use regex::Regex;
+use crate::config::CONFIG;
+use crate::error::AppError;
+use crate::git::refs::RefUpdate;
+use crate::git::run_git;
+
+/// Встроенные правила: имя и регулярное выражение
+const DEFAULT_RULES: &[(&str, &str)] = &[
+    ("aws-access-key-id", r"\b(AKIA|ASIA)[0-9A-Z]{16}\b"),
+    ("private-key", r"-----BEGIN ([A-Z]+ )?PRIVATE KEY( BLOCK)?-----"),
+    ("github-token", r"\bgh[pousr]_[A-Za-z0-9]{36,}\b"),
+    ("slack-token", r"\bxox[abprs]-[A-Za-z0-9-]{10,}"),
+    ("generic-api-key", r#"(?i)\b(api[_-]?key|secret|token|passw(or)?d)\b\s*[:=]\s*["'][A-Za-z0-9/+_\-]{16,}["']"#),
+];
+
+/// Когда и как искать секреты (GIT_HTTP_SECRET_SCAN)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScanMode {
+    /// Не искать
+    Off,
+    /// Искать после push, не задерживая его; находки записываются и отправляются владельцу
+    Async,
+    /// Искать в pre-receive и отклонять push с находками
+    Strict,
+}
+
+/// Правило поиска секретов
+pub struct SecretRule {
+    /// Имя правила, попадает в предупреждения
+    pub name: String,
+    /// Выражение, применяемое к каждой добавленной строке
+    pub pattern: Regex,
+}
+
+/// Найденный в коммите секрет; сам секрет не сохраняется
+#[derive(Debug, Clone)]
+pub struct SecretFinding {
+    /// SHA коммита, добавившего строку
+    pub commit: String,
+    /// Путь к файлу
+    pub path: String,
+    /// Номер строки в файле после коммита
+    pub line: u64,
+    /// Имя сработавшего правила
+    pub rule: String,
+}
+
+lazy_static! {
+    /// Режим поиска секретов
+    pub static ref SCAN_MODE: ScanMode = match CONFIG.secret_scan.trim().to_lowercase().as_str() {
+        "off" | "" => ScanMode::Off,
+        "async" => ScanMode::Async,
+        "strict" => ScanMode::Strict,
+        other => {
+            warn!("Unknown secret scan mode '{}', secret scanning is disabled", other);
+            ScanMode::Off
+        }
+    };
+
+    /// Правила поиска секретов
+    static ref RULES: Vec<SecretRule> = load_rules();
+}
+
+/// Загружает правила из GIT_HTTP_SECRET_RULES или возвращает встроенные
+///
+/// Формат файла: по правилу на строку, `имя регулярное_выражение`; пустые строки
+/// и строки, начинающиеся с `#`, пропускаются. Некорректные выражения пропускаются
+/// с предупреждением.
+fn load_rules() -> Vec<SecretRule> {
+    let lines: Vec<(String, String)> = match &CONFIG.secret_rules_file {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(text) => text
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .filter_map(|line| line.split_once(char::is_whitespace))
+                .map(|(name, pattern)| (name.to_string(), pattern.trim().to_string()))
+                .collect(),
+            Err(e) => {
+                error!("Failed to read secret rules from {}: {}", path.display(), e);
+                Vec::new()
+            }
+        },
+        None => DEFAULT_RULES
+            .iter()
+            .map(|(name, pattern)| (name.to_string(), pattern.to_string()))
+            .collect(),
+    };
+
+    lines
+        .into_iter()
+        .filter_map(|(name, pattern)| match Regex::new(&pattern) {
+            Ok(pattern) => Some(SecretRule { name, pattern }),
+            Err(e) => {
+                warn!("Skipping secret rule {}: {}", name, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Ищет секреты в строках, добавленных коммитами, которые принесло изменение ссылки
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `update` - Изменение ссылки
+/// * `applied` - Ссылка уже обновлена (после push); иначе проверка идёт до обновления
+///
+/// # Возвращает
+///
+/// * `Result<Vec<SecretFinding>, AppError>` - Находки или ошибка git
+pub fn scan_update(repo_path: &Path, update: &RefUpdate, applied: bool) -> Result<Vec<SecretFinding>, AppError> {
+    let new = match update.new.as_deref() {
+        Some(new) if !RULES.is_empty() => new,
+        _ => return Ok(Vec::new()),
+    };
+
+    let range;
+    let exclude;
+    let mut args = vec![
+        "log", "-p", "--no-color", "--no-ext-diff", "--unified=0",
+        "--src-prefix=a/", "--dst-prefix=b/", "--format=%x1e%H",
+    ];
+    match update.old.as_deref() {
+        Some(old) => {
+            range = format!("{}..{}", old, new);
+            args.push(range.as_str());
+        }
+        None => {
+            // Новая ссылка: только коммиты, которых нет в остальных ссылках
+            args.extend([new, "--not"]);
+            if applied {
+                exclude = format!("--exclude={}", update.name);
+                args.push(exclude.as_str());
+            }
+            args.push("--glob=refs/*");
+        }
+    }
+
+    match run_git(repo_path, &args) {
+        Ok(output) if output.status.success() => Ok(scan_log(&String::from_utf8_lossy(&output.stdout))),
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            error!("git log failed: {}", stderr);
+            Err(AppError::Git(stderr))
+        }
+        Err(e) => {
+            error!("Failed to run git log: {}", e);
+            Err(AppError::Git(e.to_string()))
+        }
+    }
+}
+
+/// Проверяет добавленные строки в выводе `git log -p --unified=0`
+fn scan_log(log: &str) -> Vec<SecretFinding> {
+    let mut findings = Vec::new();
+    let mut commit = "";
+    let mut path: Option<&str> = None;
+    let mut in_header = false;
+    let mut line_no = 0u64;
+
+    for line in log.lines() {
+        if let Some(sha) = line.strip_prefix('\u{1e}') {
+            commit = sha;
+            path = None;
+        } else if line.starts_with("diff --git ") {
+            in_header = true;
+            path = None;
+        } else if in_header {
+            if let Some(new_path) = line.strip_prefix("+++ ") {
+                path = new_path.strip_prefix("b/");
+            } else if let Some(hunk) = line.strip_prefix("@@ ") {
+                in_header = false;
+                line_no = hunk_start(hunk);
+            }
+        } else if let Some(hunk) = line.strip_prefix("@@ ") {
+            line_no = hunk_start(hunk);
+        } else if let Some(added) = line.strip_prefix('+') {
+            if let Some(path) = path {
+                for rule in RULES.iter().filter(|rule| rule.pattern.is_match(added)) {
+                    findings.push(SecretFinding {
+                        commit: commit.to_string(),
+                        path: path.to_string(),
+                        line: line_no,
+                        rule: rule.name.clone(),
+                    });
+                }
+            }
+            line_no += 1;
+        }
+    }
+
+    findings
+}
+
+/// Номер первой строки новой версии из заголовка `-a,b +c,d @@`
+fn hunk_start(hunk: &str) -> u64 {
+    hunk.split_whitespace()
+        .find_map(|part| part.strip_prefix('+'))
+        .and_then(|range| range.split(',').next())
+        .and_then(|start| start.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Проверка pre-receive хука в режиме strict: ищет секреты во всех обновлениях
+///
+/// Находки и ошибки поиска выводятся в stderr, который git передаёт клиенту.
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `updates` - Изменения ссылок из push
+///
+/// # Возвращает
+///
+/// * `bool` - true, если push нужно отклонить
+pub fn reject_secrets(repo_path: &Path, updates: &[RefUpdate]) -> bool {
+    let mut rejected = false;
+
+    for update in updates {
+        match scan_update(repo_path, update, false) {
+            Ok(findings) => {
+                for finding in &findings {
+                    eprintln!(
+                        "Possible secret ({}) in {}:{} of commit {}",
+                        finding.rule, finding.path, finding.line, finding.commit
+                    );
+                }
+                rejected |= !findings.is_empty();
+            }
+            Err(e) => {
+                eprintln!("Secret scan failed for {}: {}", update.name, e);
+                rejected = true;
+            }
+        }
+    }
+
+    if rejected {
+        eprintln!("Push rejected: remove the secrets from history and push again");
+    }
+    rejected
+}
@@ -0,0 +1,123 @@
+use std::path::Path;
+use log::error;
+use crate::config::CONFIG;
+use crate::error::AppError;
+use crate::git::errors::git_failure;
+use crate::git::git_command;
+use crate::git::refs::RefUpdate;
+
+/// Коммит из push, подпись которого не прошла проверку
+#[derive(Debug, Clone)]
+pub struct UnsignedCommit {
+    /// SHA коммита
+    pub commit: String,
+    /// Почему подпись не принята
+    pub reason: &'static str,
+}
+
+/// Проверяет подписи коммитов, которые принесло изменение ссылки
+///
+/// Вызывается в pre-receive, до обновления ссылок: проверяются коммиты, которых
+/// нет ни в одной существующей ссылке. Подпись проверяется так же, как
+/// `git verify-commit`: SSH подписи - по GIT_HTTP_ALLOWED_SIGNERS, GPG - по
+/// ключам из GIT_HTTP_GPG_HOME. Принимаются только действительные подписи
+/// известных ключей: `%G?` равно `G`, или `U` с известным подписантом (GPG ключ из
+/// связки без отметки доверия). SSH подпись ключа не из списка git тоже
+/// считает `U`, но без подписанта, и она отклоняется.
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `update` - Изменение ссылки
+///
+/// # Возвращает
+///
+/// * `Result<Vec<UnsignedCommit>, AppError>` - Непрошедшие проверку коммиты или ошибка git
+pub fn verify_update(repo_path: &Path, update: &RefUpdate) -> Result<Vec<UnsignedCommit>, AppError> {
+    let new = match update.new.as_deref() {
+        Some(new) => new,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut command = git_command();
+    if let Some(file) = &CONFIG.allowed_signers_file {
+        command.arg("-c").arg(format!("gpg.ssh.allowedSignersFile={}", file.display()));
+    }
+    if let Some(dir) = &CONFIG.gpg_home {
+        command.env("GNUPGHOME", dir);
+    }
+    let output = command
+        .arg("--git-dir")
+        .arg(repo_path)
+        .args(["log", "--format=%H%x1f%G?%x1f%GS", new, "--not", "--glob=refs/*", "--"])
+        .output();
+
+    let output = match output {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => return Err(git_failure("log", &output)),
+        Err(e) => {
+            error!("Failed to run git log: {}", e);
+            return Err(AppError::Git(e.to_string()));
+        }
+    };
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\x1f');
+            let (commit, status, signer) = (fields.next()?, fields.next()?, fields.next().unwrap_or(""));
+            signature_problem(status, signer).map(|reason| UnsignedCommit { commit: commit.to_string(), reason })
+        })
+        .collect())
+}
+
+/// Описание проблемы с подписью по коду `%G?` и подписанту `%GS`; None, если подпись принята
+fn signature_problem(status: &str, signer: &str) -> Option<&'static str> {
+    match status {
+        "G" => None,
+        "U" if !signer.is_empty() => None,
+        "N" => Some("commit is not signed"),
+        "B" => Some("bad signature"),
+        "X" => Some("signature has expired"),
+        "Y" => Some("signed with an expired key"),
+        "R" => Some("signed with a revoked key"),
+        _ => Some("signature cannot be checked, the key is not allowed"),
+    }
+}
+
+/// Проверка pre-receive хука для репозиториев, требующих подписанных коммитов
+///
+/// Непрошедшие проверку коммиты и ошибки выводятся в stderr, который git передаёт
+/// клиенту.
+///
+/// # Параметры
+///
+/// * `repo_path` - Путь к bare репозиторию
+/// * `updates` - Изменения ссылок из push
+///
+/// # Возвращает
+///
+/// * `bool` - true, если push нужно отклонить
+pub fn reject_unsigned(repo_path: &Path, updates: &[RefUpdate]) -> bool {
+    let mut rejected = false;
+
+    for update in updates {
+        match verify_update(repo_path, update) {
+            Ok(unsigned) => {
+                for commit in &unsigned {
+                    eprintln!("Commit {} on {}: {}", commit.commit, update.name, commit.reason);
+                }
+                rejected |= !unsigned.is_empty();
+            }
+            Err(e) => {
+                eprintln!("Signature check failed for {}: {}", update.name, e);
+                rejected = true;
+            }
+        }
+    }
+
+    if rejected {
+        eprintln!("Push rejected: this repository requires signed commits");
+    }
+    rejected
+}
@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// Число выполняющихся upload-pack для каждого репозитория
+    static ref UPLOAD_PACKS: Mutex<HashMap<String, usize>> = Mutex::new(HashMap::new());
+}
+
+/// Разрешение на один upload-pack; освобождается при выходе из области видимости
+///
+/// Разрешение нужно переносить в задачу, которая запускает git: тогда оно
+/// освобождается, когда git действительно завершился, даже если клиент
+/// отключился раньше или задача не попала в очередь пула.
+pub struct UploadPackPermit {
+    repo_name: String,
+}
+
+impl Drop for UploadPackPermit {
+    fn drop(&mut self) {
+        let mut running = UPLOAD_PACKS.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = running.get_mut(&self.repo_name) {
+            *count -= 1;
+            if *count == 0 {
+                running.remove(&self.repo_name);
+            }
+        }
+    }
+}
+
+/// Занимает место для upload-pack в репозитории, если лимит ещё не исчерпан
+///
+/// # Параметры
+///
+/// * `repo_name` - Полное имя репозитория `{owner}/{name}`
+/// * `limit` - Максимальное число одновременных upload-pack для одного репозитория
+///
+/// # Возвращает
+///
+/// * `Option<UploadPackPermit>` - Разрешение или None, если лимит исчерпан
+pub fn try_acquire_upload_pack(repo_name: &str, limit: usize) -> Option<UploadPackPermit> {
+    let mut running = UPLOAD_PACKS.lock().unwrap_or_else(|e| e.into_inner());
+    if running.get(repo_name).copied().unwrap_or(0) >= limit {
+        return None;
+    }
+
+    *running.entry(repo_name.to_string()).or_insert(0) += 1;
+    Some(UploadPackPermit { repo_name: repo_name.to_string() })
+}
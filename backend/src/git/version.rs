@@ -0,0 +1,85 @@
+use crate::git::git_command;
+use log::{info, warn};
+
+/// Минимальная поддерживаемая версия git: сервер использует `git config --type`
+/// и передаёт `Git-Protocol`, а это git 2.18
+pub const MIN_GIT_VERSION: (u32, u32) = (2, 18);
+
+/// Версия установленного git и зависящие от неё возможности
+///
+/// Определяется один раз при запуске и хранится в `app_data`, чтобы обработчики
+/// не запускали `git --version` на каждый запрос.
+#[derive(Debug, Clone, Default)]
+pub struct GitCapabilities {
+    /// Версия git, например `2.39.5`; None, если git не запускается
+    pub version: Option<String>,
+    /// Поддерживается протокол v2 (git 2.18)
+    pub protocol_v2: bool,
+    /// Разрешена частичная загрузка (`uploadpack.allowFilter`) в системной/глобальной конфигурации git
+    pub filter: bool,
+    /// upload-pack умеет объявлять bundle URI (`uploadpack.advertiseBundleURIs`, git 2.40)
+    pub bundle_uri: bool,
+}
+
+impl GitCapabilities {
+    /// Определяет версию и возможности git и пишет их в лог
+    ///
+    /// Если git старше `MIN_GIT_VERSION` или не запускается, выводится предупреждение:
+    /// сервер продолжит работу, но часть операций может завершаться ошибкой.
+    pub fn detect() -> Self {
+        let version = detect_version();
+        let mut capabilities = GitCapabilities {
+            version,
+            filter: detect_allow_filter(),
+            ..GitCapabilities::default()
+        };
+        capabilities.protocol_v2 = capabilities.version_at_least(2, 18);
+        capabilities.bundle_uri = capabilities.version_at_least(2, 40);
+
+        match &capabilities.version {
+            Some(version) if !capabilities.version_at_least(MIN_GIT_VERSION.0, MIN_GIT_VERSION.1) => warn!(
+                "git {} is older than the minimum supported {}.{}",
+                version, MIN_GIT_VERSION.0, MIN_GIT_VERSION.1
+            ),
+            Some(version) => info!(
+                "Using git {} (protocol v2: {}, partial clone: {}, bundle URIs: {})",
+                version, capabilities.protocol_v2, capabilities.filter, capabilities.bundle_uri
+            ),
+            None => warn!("Could not determine git version"),
+        }
+
+        capabilities
+    }
+
+    /// Проверяет, что версия git не ниже `major.minor`
+    pub fn version_at_least(&self, major: u32, minor: u32) -> bool {
+        let version = match &self.version {
+            Some(version) => version,
+            None => return false,
+        };
+
+        let mut parts = version.split('.').map(|part| part.parse::<u32>().unwrap_or(0));
+        let found = (parts.next().unwrap_or(0), parts.next().unwrap_or(0));
+        found >= (major, minor)
+    }
+}
+
+/// Разбирает вывод `git --version` (`git version 2.39.5` или `git version 2.39.3 (Apple Git-145)`)
+fn detect_version() -> Option<String> {
+    let output = match git_command().arg("--version").output() {
+        Ok(output) if output.status.success() => output,
+        _ => return None,
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .nth(2)
+        .map(|version| version.to_string())
+}
+
+fn detect_allow_filter() -> bool {
+    match git_command().args(["config", "--get", "--bool", "uploadpack.allowFilter"]).output() {
+        Ok(output) => output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "true",
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,528 @@
+use actix_web::{web, HttpResponse, HttpRequest, Result, ResponseError};
+use chrono::{DateTime, Utc};
+use crate::clock::{Clock, SystemClock};
+use log::error;
+use serde::{Serialize, Deserialize};
+use crate::config::CONFIG;
+use crate::handlers::api::{check_auth, create_account, page_bounds, ApiResponse, RegisterRequest};
+use crate::handlers::hooks::{validate_hook, CreateHookRequest};
+use crate::models::audit::AuditEntry;
+use crate::models::db::Database;
+use crate::models::invite::Invite;
+use crate::models::job::{self, Job};
+use crate::models::repository::Repository;
+use crate::git::pool::run_blocking;
+use crate::git::{is_bare_repo, resolve_repo, REPOS_ROOT};
+use crate::error::AppError;
+use std::path::Path;
+use std::fs;
+use crate::models::user::{User, UserView};
+use crate::models::webhook::ServerWebhook;
+use crate::webhooks::SERVER_EVENTS;
+use crate::motd;
+
+/// Является ли пользователь администратором сервера (GIT_HTTP_ADMINS)
+pub fn is_admin(user: &User) -> bool {
+    CONFIG.admins.contains(&user.username)
+}
+
+/// Проверяет, что текущий пользователь - администратор
+///
+/// # Возвращает
+///
+/// * `Result<User, HttpResponse>` - Администратор или готовый ответ 401/403
+fn require_admin(req: &HttpRequest, db: &web::Data<Database>) -> Result<User, HttpResponse> {
+    match check_auth(req, db) {
+        Some(user) if is_admin(&user) => Ok(user),
+        Some(_) => Err(HttpResponse::Forbidden().json(ApiResponse::<()> {
+            success: false,
+            message: Some("Administrator access required".to_string()),
+            data: None,
+        })),
+        None => Err(HttpResponse::Unauthorized().json(ApiResponse::<()> {
+            success: false,
+            message: Some("Unauthorized".to_string()),
+            data: None,
+        })),
+    }
+}
+
+/// Параметры постраничного вывода пользователей
+#[derive(Deserialize)]
+pub struct UsersQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+/// Пользователь в списке для администратора: с email и активностью входа
+#[derive(Serialize)]
+struct AdminUserView {
+    #[serde(flatten)]
+    user: UserView,
+    is_admin: bool,
+    #[serde(serialize_with = "crate::timestamps::serialize_rfc3339")]
+    last_login_at: Option<DateTime<Utc>>,
+    last_login_ip: Option<String>,
+    failed_login_count: i64,
+    /// До какого момента вход заблокирован; None - не заблокирован
+    #[serde(serialize_with = "crate::timestamps::serialize_rfc3339")]
+    locked_until: Option<DateTime<Utc>>,
+}
+
+/// Список пользователей с активностью входа (только администратор)
+pub async fn list_users(
+    req: HttpRequest,
+    query: web::Query<UsersQuery>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    if let Err(response) = require_admin(&req, &db) {
+        return Ok(response);
+    }
+
+    let (limit, offset) = page_bounds(query.page, query.per_page);
+    let now = SystemClock.now();
+    match User::find_all(limit, offset, db.get_connection()) {
+        Ok(users) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(
+                users
+                    .into_iter()
+                    .map(|user| AdminUserView {
+                        is_admin: is_admin(&user),
+                        last_login_at: user.last_login_at,
+                        last_login_ip: user.last_login_ip.clone(),
+                        failed_login_count: user.failed_login_count,
+                        locked_until: user.locked_until.filter(|until| *until > now),
+                        user: user.to_view(true),
+                    })
+                    .collect::<Vec<_>>()
+            ),
+        })),
+        Err(e) => {
+            error!("Failed to fetch users: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to fetch users".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
+
+/// Параметры списка фоновых задач
+#[derive(Deserialize)]
+pub struct JobsQuery {
+    /// `pending`, `running` или `dead` (по умолчанию)
+    pub status: Option<String>,
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+/// Список фоновых задач в указанном состоянии, по умолчанию мёртвых (только администратор)
+pub async fn list_jobs(
+    req: HttpRequest,
+    query: web::Query<JobsQuery>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    if let Err(response) = require_admin(&req, &db) {
+        return Ok(response);
+    }
+
+    let status = query.status.as_deref().unwrap_or(job::STATUS_DEAD);
+    if ![job::STATUS_PENDING, job::STATUS_RUNNING, job::STATUS_DEAD].contains(&status) {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: Some("status must be pending, running or dead".to_string()),
+            data: None,
+        }));
+    }
+
+    let (limit, offset) = page_bounds(query.page, query.per_page);
+    match Job::find_by_status(status, limit, offset, db.get_connection()) {
+        Ok(jobs) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(jobs),
+        })),
+        Err(e) => {
+            error!("Failed to fetch jobs: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to fetch jobs".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
+
+/// Параметры журнала аудита
+#[derive(Deserialize)]
+pub struct AuditQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+/// Журнал аудита, от новых записей к старым (только администратор)
+pub async fn list_audit_log(
+    req: HttpRequest,
+    query: web::Query<AuditQuery>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    if let Err(response) = require_admin(&req, &db) {
+        return Ok(response);
+    }
+
+    let (limit, offset) = page_bounds(query.page, query.per_page);
+    match AuditEntry::list(limit, offset, db.get_connection()) {
+        Ok(entries) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(entries),
+        })),
+        Err(e) => {
+            error!("Failed to fetch audit log: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to fetch audit log".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
+
+/// Расхождения между базой данных и каталогом репозиториев
+#[derive(Serialize)]
+struct IntegrityReport {
+    /// Репозитории из базы данных (`{owner}/{name}`), у которых нет bare репозитория на диске
+    missing_storage: Vec<String>,
+    /// Записи в каталоге репозиториев (`{owner}/{entry}` или `{entry}` в корне), которым
+    /// не соответствует ни один репозиторий в базе данных
+    orphaned_directories: Vec<String>,
+}
+
+/// Сверяет репозитории в базе данных с каталогами на диске (только администратор)
+///
+/// Ничего не исправляет: отчёт нужен оператору, чтобы решить, восстановить
+/// каталог из резервной копии или удалить лишнее.
+pub async fn check_integrity(req: HttpRequest, db: web::Data<Database>) -> Result<HttpResponse> {
+    if let Err(response) = require_admin(&req, &db) {
+        return Ok(response);
+    }
+
+    let names = match Repository::all_names(db.get_connection()) {
+        Ok(names) => names,
+        Err(e) => {
+            error!("Failed to fetch repositories: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    let report = run_blocking(move || {
+        let root = Path::new(REPOS_ROOT);
+        let missing_storage = names
+            .iter()
+            .filter(|name| resolve_repo(root, name).is_err())
+            .cloned()
+            .collect();
+
+        let entries = fs::read_dir(root).map_err(|e| {
+            error!("Failed to read {}: {}", REPOS_ROOT, e);
+            AppError::Git(e.to_string())
+        })?;
+        // Репозитории лежат в `{owner}/{name}.git`; всё остальное в корне (в том числе
+        // не перенесённые `{name}.git` старой раскладки) считается лишним
+        let mut orphaned_directories = Vec::new();
+        for owner in entries.filter_map(|entry| entry.ok()) {
+            let owner_name = owner.file_name().to_string_lossy().to_string();
+            let children = match owner.file_type() {
+                Ok(file_type) if file_type.is_dir() && !is_bare_repo(&owner.path()) => fs::read_dir(owner.path()).ok(),
+                _ => None,
+            };
+            let children = match children {
+                Some(children) => children,
+                None => {
+                    orphaned_directories.push(owner_name);
+                    continue;
+                }
+            };
+            for child in children.filter_map(|entry| entry.ok()) {
+                let entry = format!("{}/{}", owner_name, child.file_name().to_string_lossy());
+                let known = entry
+                    .strip_suffix(".git")
+                    .is_some_and(|full_name| names.iter().any(|known| known == full_name));
+                if !known {
+                    orphaned_directories.push(entry);
+                }
+            }
+        }
+        orphaned_directories.sort();
+
+        Ok(IntegrityReport { missing_storage, orphaned_directories })
+    })
+    .await;
+
+    match report {
+        Ok(Ok(report)) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(report),
+        })),
+        Ok(Err(e)) | Err(e) => Ok(e.error_response()),
+    }
+}
+
+/// Создаёт пользователя независимо от режима регистрации (только администратор)
+pub async fn create_user(
+    req: HttpRequest,
+    body: web::Json<RegisterRequest>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    if let Err(response) = require_admin(&req, &db) {
+        return Ok(response);
+    }
+
+    match create_account(&body, &db) {
+        Ok(user) => Ok(HttpResponse::Created().json(ApiResponse {
+            success: true,
+            message: Some("User created successfully".to_string()),
+            data: Some(user.to_view(true)),
+        })),
+        Err(response) => Ok(response),
+    }
+}
+
+/// Создаёт одноразовое приглашение на регистрацию (только администратор)
+pub async fn create_invite(req: HttpRequest, db: web::Data<Database>) -> Result<HttpResponse> {
+    let admin = match require_admin(&req, &db) {
+        Ok(admin) => admin,
+        Err(response) => return Ok(response),
+    };
+
+    let mut invite = Invite::new(admin.id.unwrap_or_default());
+    match invite.create(db.get_connection()) {
+        Ok(id) => {
+            invite.id = Some(id);
+            Ok(HttpResponse::Created().json(ApiResponse {
+                success: true,
+                message: Some("Invite created successfully".to_string()),
+                data: Some(invite),
+            }))
+        }
+        Err(e) => {
+            error!("Failed to create invite: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to create invite".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
+
+/// Список приглашений, использованных и свободных (только администратор)
+pub async fn list_invites(req: HttpRequest, db: web::Data<Database>) -> Result<HttpResponse> {
+    if let Err(response) = require_admin(&req, &db) {
+        return Ok(response);
+    }
+
+    match Invite::find_all(db.get_connection()) {
+        Ok(invites) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(invites),
+        })),
+        Err(e) => {
+            error!("Failed to fetch invites: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to fetch invites".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
+
+/// Создаёт webhook сервера (только администратор)
+///
+/// По умолчанию webhook подписан на все события из `SERVER_EVENTS`.
+pub async fn create_server_hook(
+    req: HttpRequest,
+    body: web::Json<CreateHookRequest>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    if let Err(response) = require_admin(&req, &db) {
+        return Ok(response);
+    }
+
+    let body = body.into_inner();
+    let events = body
+        .events
+        .unwrap_or_else(|| SERVER_EVENTS.iter().map(|event| event.to_string()).collect());
+    if let Err(message) = validate_hook(&body.url, &events, SERVER_EVENTS) {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: Some(message),
+            data: None,
+        }));
+    }
+
+    let mut hook = ServerWebhook {
+        id: None,
+        url: body.url,
+        events,
+        active: body.active.unwrap_or(true),
+        created_at: None,
+        secret: body.secret.filter(|secret| !secret.is_empty()),
+    };
+
+    match hook.create(db.get_connection()) {
+        Ok(id) => {
+            hook.id = Some(id);
+            Ok(HttpResponse::Created().json(ApiResponse {
+                success: true,
+                message: Some("Webhook created successfully".to_string()),
+                data: Some(hook),
+            }))
+        }
+        Err(e) => {
+            error!("Failed to create server webhook: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to create webhook".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
+
+/// Список webhook'ов сервера (только администратор)
+pub async fn list_server_hooks(req: HttpRequest, db: web::Data<Database>) -> Result<HttpResponse> {
+    if let Err(response) = require_admin(&req, &db) {
+        return Ok(response);
+    }
+
+    match ServerWebhook::find_all(db.get_connection()) {
+        Ok(hooks) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(hooks),
+        })),
+        Err(e) => {
+            error!("Failed to fetch server webhooks: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to fetch webhooks".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
+
+/// Удаляет webhook сервера (только администратор)
+pub async fn delete_server_hook(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    if let Err(response) = require_admin(&req, &db) {
+        return Ok(response);
+    }
+
+    match ServerWebhook::delete(path.into_inner(), db.get_connection()) {
+        Ok(true) => Ok(HttpResponse::Ok().json(ApiResponse::<()> {
+            success: true,
+            message: Some("Webhook deleted successfully".to_string()),
+            data: None,
+        })),
+        Ok(false) => Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            message: Some("Webhook not found".to_string()),
+            data: None,
+        })),
+        Err(e) => {
+            error!("Failed to delete server webhook: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to delete webhook".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
+
+/// Сообщение дня для git клиентов
+#[derive(Serialize)]
+struct MotdView {
+    message: Option<String>,
+}
+
+/// Запрос на изменение сообщения дня
+#[derive(Deserialize)]
+pub struct SetMotdRequest {
+    /// Новое сообщение; null или пустая строка убирают его
+    pub message: Option<String>,
+}
+
+/// Текущее сообщение дня (только администратор)
+pub async fn get_motd(req: HttpRequest, db: web::Data<Database>) -> Result<HttpResponse> {
+    if let Err(response) = require_admin(&req, &db) {
+        return Ok(response);
+    }
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: None,
+        data: Some(MotdView { message: motd::current() }),
+    }))
+}
+
+/// Задаёт или убирает сообщение дня без перезапуска (только администратор)
+///
+/// Сообщение показывается git клиентам как `remote: ...` при fetch, clone и push.
+pub async fn set_motd(
+    req: HttpRequest,
+    body: web::Json<SetMotdRequest>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let admin = match require_admin(&req, &db) {
+        Ok(admin) => admin,
+        Err(response) => return Ok(response),
+    };
+
+    let message = match motd::normalize(body.message.as_deref().unwrap_or_default()) {
+        Ok(message) => message,
+        Err(message) => {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: Some(message),
+                data: None,
+            }));
+        }
+    };
+
+    if let Err(e) = motd::set(message.clone(), &db) {
+        error!("Failed to save message of the day: {}", e);
+        return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            message: Some("Database error".to_string()),
+            data: None,
+        }));
+    }
+
+    let details = if message.is_some() { "set" } else { "cleared" };
+    let entry = AuditEntry::new(admin.id.unwrap_or_default(), "server.motd", "server", details.to_string());
+    if let Err(e) = entry.create(db.get_connection()) {
+        error!("Failed to record audit entry: {}", e);
+    }
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: Some("Message of the day updated".to_string()),
+        data: Some(MotdView { message }),
+    }))
+}
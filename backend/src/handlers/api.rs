@@ -1,13 +1,33 @@
-use actix_web::{web, HttpResponse, HttpRequest, Result};
+use actix_web::{http::{header, StatusCode}, web, HttpResponse, HttpRequest, Result, ResponseError};
 use crate::models::db::Database;
-use crate::models::user::User;
+use crate::models::store::Store;
+use crate::models::user::{LockoutPolicy, LoginOutcome, User, UserView};
 use crate::models::repository::Repository;
 use crate::models::notification::Notification;
-use crate::models::pull_request::{PullRequest, PullRequestComment, PullRequestStatus};
-use log::{debug, error};
+use crate::models::audit::AuditEntry;
+use crate::models::invite::Invite;
+use crate::models::pull_request::{MergeMethod, Mergeability, PullRequest, PullRequestComment, PullRequestStatus};
+use crate::auth::{challenge, forget_username, AUTH_BACKEND};
+use crate::clock::{Clock, SystemClock};
+use crate::config::{RegistrationMode, CONFIG};
+use crate::cursor::Cursor;
+use crate::error::AppError;
+use crate::git::{full_repo_name, lock_repo, repo_path, resolve_repo, validate_owner_name, validate_repo_name, REPOS_ROOT};
+use crate::git::advertise;
+use crate::git::branches::{has_refs, list_branches};
+use crate::git::pool::run_blocking;
+use crate::handlers::admin::is_admin;
+use crate::handlers::pins::visible_pinned_repos;
+use crate::webhooks;
+use log::error;
 use serde::{Serialize, Deserialize};
-use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
-use std::process::Command;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use rusqlite::Connection;
+
+/// Окно, за которое считается лимит создания репозиториев
+const REPO_CREATION_WINDOW_SECS: u64 = 3600;
 
 // Структуры запросов и ответов
 #[derive(Serialize, Deserialize)]
@@ -21,6 +41,8 @@ pub struct RegisterRequest {
     pub username: String,
     pub password: String,
     pub email: Option<String>,
+    /// Токен приглашения, нужен в режиме регистрации `invite`
+    pub invite: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -37,52 +59,406 @@ pub struct ApiResponse<T> {
     pub data: Option<T>,
 }
 
-/// Проверяет аутентификацию пользователя по HTTP заголовку
+/// Размер страницы по умолчанию для списков
+const DEFAULT_PER_PAGE: u32 = 30;
+/// Максимальный размер страницы
+const MAX_PER_PAGE: u32 = 100;
+
+/// Переводит номер страницы (с 1) и её размер в LIMIT/OFFSET
+pub fn page_bounds(page: Option<u32>, per_page: Option<u32>) -> (i64, i64) {
+    let per_page = per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE) as i64;
+    let page = page.unwrap_or(1).max(1) as i64;
+    (per_page, (page - 1) * per_page)
+}
+
+/// Страница списка с курсором следующей страницы
+///
+/// Тот же конверт, что `ApiResponse`, только `next_cursor` лежит рядом с `data`,
+/// поэтому клиенты, читающие только `data`, не замечают разницы.
+#[derive(Serialize)]
+pub struct CursorPage<T> {
+    pub success: bool,
+    pub message: Option<String>,
+    pub data: Vec<T>,
+    /// Значение `?cursor=` для следующей страницы; None на последней странице
+    pub next_cursor: Option<String>,
+}
+
+/// Разбирает параметр `cursor` списка
+///
+/// # Параметры
+///
+/// * `cursor` - Значение параметра, если он передан
+///
+/// # Возвращает
+///
+/// * `Result<Option<Cursor>, HttpResponse>` - Курсор или ответ 400 для повреждённого значения
+pub fn parse_cursor(cursor: Option<&str>) -> Result<Option<Cursor>, HttpResponse> {
+    match cursor {
+        None => Ok(None),
+        Some(token) => match Cursor::decode(token) {
+            Some(cursor) => Ok(Some(cursor)),
+            None => Err(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Invalid cursor".to_string()),
+                data: None,
+            })),
+        },
+    }
+}
+
+/// Собирает ответ со страницей списка
+///
+/// Модель должна вернуть до `limit + 1` записей: лишняя запись означает, что
+/// есть следующая страница, и в ответ не попадает.
+///
+/// # Параметры
+///
+/// * `items` - Записи страницы, от новых к старым
+/// * `limit` - Размер страницы
+/// * `key` - Курсор, указывающий на запись
+///
+/// # Возвращает
+///
+/// * `HttpResponse` - Ответ 200 с `data` и `next_cursor`
+pub fn cursor_page_response<T, F>(mut items: Vec<T>, limit: i64, key: F) -> HttpResponse
+where
+    T: Serialize,
+    F: Fn(&T) -> Cursor,
+{
+    let next_cursor = if items.len() as i64 > limit {
+        items.truncate(limit as usize);
+        items.last().map(|item| key(item).encode())
+    } else {
+        None
+    };
+
+    HttpResponse::Ok().json(CursorPage {
+        success: true,
+        message: None,
+        data: items,
+        next_cursor,
+    })
+}
+
+/// Проверяет, совпадает ли `If-None-Match` запроса с ETag ответа
+pub fn etag_matches(req: &HttpRequest, etag: &str) -> bool {
+    req.headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(',').any(|tag| {
+            let tag = tag.trim();
+            tag == "*" || tag.trim_start_matches("W/") == etag
+        }))
+}
+
+/// Увеличивает счётчик изменений ссылок репозитория по полному имени; ошибка только логируется
+pub fn bump_ref_generation(full_name: &str, db: &web::Data<Database>) {
+    if let Err(e) = db.bump_ref_generation(full_name) {
+        error!("Failed to bump ref generation of {}: {}", full_name, e);
+    }
+}
+
+/// Проверяет аутентификацию пользователя через настроенный бэкенд
 pub fn check_auth(req: &HttpRequest, db: &web::Data<Database>) -> Option<User> {
-    // Получаем заголовок Authorization
-    let auth_header = req.headers().get("Authorization")?;
-    let auth_str = auth_header.to_str().ok()?;
-    
-    // Проверяем, что это Basic Auth
-    if !auth_str.starts_with("Basic ") {
-        return None;
+    AUTH_BACKEND.authenticate(req, db.get_ref())
+}
+
+/// Проверяет, может ли пользователь читать репозиторий
+///
+/// Публичные репозитории доступны всем, приватные - только владельцу.
+pub fn can_access(repo: &Repository, user: Option<&User>) -> bool {
+    repo.is_public || user.is_some_and(|u| u.id == Some(repo.owner_id))
+}
+
+/// Проверяет, что у репозитория из базы данных есть каталог на диске
+///
+/// Без этой проверки каждая операция git над удалённым вручную каталогом
+/// заканчивалась бы непонятной ошибкой.
+///
+/// # Возвращает
+///
+/// * `Result<(), HttpResponse>` - Ошибка 404 `Repository storage missing`, если каталога нет
+pub fn check_storage(repo: &Repository) -> Result<(), HttpResponse> {
+    match resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()) {
+        Err(AppError::RepoNotFound(_)) => {
+            error!("Repository {} exists in the database but not on disk", repo.full_name());
+            Err(AppError::StorageMissing(repo.name.clone()).error_response())
+        }
+        _ => Ok(()),
     }
+}
 
-    // Декодируем Base64
-    let credentials = BASE64.decode(auth_str.trim_start_matches("Basic "))
-        .ok()?;
-    let credentials_str = String::from_utf8(credentials).ok()?;
-    
-    // Разделяем на username:password
-    let mut parts = credentials_str.splitn(2, ':');
-    let username = parts.next()?;
-    let password = parts.next()?;
+/// Считает место, занятое репозиториями пользователя (см. `User::storage_usage`)
+///
+/// Подсчёт обходит каталоги репозиториев на диске, поэтому выполняется в пуле git,
+/// а не в потоке actix.
+///
+/// # Возвращает
+///
+/// * `Result<u64, AppError>` - Размер в байтах; `Busy`, если очередь пула заполнена
+pub async fn storage_usage(user: &User, db: &web::Data<Database>) -> std::result::Result<u64, AppError> {
+    let user = user.clone();
+    let conn = db.get_connection();
+    Ok(run_blocking(move || user.storage_usage(conn)).await??)
+}
 
-    // Проверяем в базе данных
+/// Проверяет квоту пользователя на хранение (см. `User::is_over_quota`) в пуле git
+///
+/// Без квоты каталоги не обходятся вовсе.
+///
+/// # Возвращает
+///
+/// * `Result<bool, AppError>` - true, если использование достигло квоты; `Busy`,
+///   если очередь пула заполнена
+pub async fn over_quota(user: &User, db: &web::Data<Database>) -> std::result::Result<bool, AppError> {
+    if user.storage_quota_bytes.is_none() {
+        return Ok(false);
+    }
+    let user = user.clone();
     let conn = db.get_connection();
-    match User::authenticate(username, password, conn) {
-        Ok(Some(user)) => Some(user),
-        _ => None
+    Ok(run_blocking(move || user.is_over_quota(conn)).await??)
+}
+
+/// Владелец репозитория из запроса: сегмент `{owner}` маршрута или параметр `?owner=`
+pub fn requested_owner(req: &HttpRequest) -> Option<String> {
+    if let Some(owner) = req.match_info().get("owner") {
+        return Some(owner.to_string());
+    }
+    web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .ok()
+        .and_then(|query| query.get("owner").cloned())
+        .filter(|owner| !owner.is_empty())
+}
+
+/// Находит репозиторий по имени из URL
+///
+/// Имя репозитория уникально только в пределах владельца. Если владелец указан
+/// в запросе (`/git/{owner}/{repo_name}` или `?owner=`), ищется именно его
+/// репозиторий. Иначе среди репозиториев с этим именем выбирается репозиторий
+/// текущего пользователя, затем единственный доступный ему на чтение.
+///
+/// # Параметры
+///
+/// * `req` - HTTP запрос
+/// * `db` - База данных
+/// * `repo_name` - Имя репозитория из URL
+/// * `user` - Текущий пользователь
+///
+/// # Возвращает
+///
+/// * `Result<Option<Repository>, AppError>` - Репозиторий или None; `Conflict`,
+///   если имя подходит нескольким доступным репозиториям
+pub fn lookup_repo(
+    req: &HttpRequest,
+    db: &web::Data<Database>,
+    repo_name: &str,
+    user: Option<&User>,
+) -> std::result::Result<Option<Repository>, AppError> {
+    if let Some(owner) = requested_owner(req) {
+        return db.find_repository(&full_repo_name(&owner, repo_name));
+    }
+
+    let mut candidates = db.find_repositories_by_name(repo_name)?;
+    if candidates.len() <= 1 {
+        return Ok(candidates.pop());
+    }
+
+    if let Some(own) = candidates.iter().position(|repo| user.is_some_and(|u| u.id == Some(repo.owner_id))) {
+        return Ok(Some(candidates.swap_remove(own)));
+    }
+
+    candidates.retain(|repo| can_access(repo, user));
+    match candidates.len() {
+        0 | 1 => Ok(candidates.pop()),
+        _ => Err(AppError::Conflict(format!(
+            "Several users have a repository named {}; add ?owner= or use /git/{{owner}}/{}",
+            repo_name, repo_name
+        ))),
     }
 }
 
+/// Находит репозиторий по имени (см. `lookup_repo`) и проверяет право текущего пользователя на чтение
+///
+/// Приватный репозиторий без доступа выглядит для клиента как несуществующий.
+///
+/// # Возвращает
+///
+/// * `Result<(Repository, Option<User>), HttpResponse>` - Репозиторий и пользователь
+///   (если авторизован), либо готовый ответ с ошибкой
+pub fn find_readable_repo(
+    req: &HttpRequest,
+    db: &web::Data<Database>,
+    repo_name: &str,
+) -> std::result::Result<(Repository, Option<User>), HttpResponse> {
+    let user = check_auth(req, db);
+
+    match lookup_repo(req, db, repo_name, user.as_ref()) {
+        Ok(Some(repo)) if can_access(&repo, user.as_ref()) => {
+            check_storage(&repo)?;
+            Ok((repo, user))
+        },
+        Ok(_) => Err(HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            message: Some("Repository not found".to_string()),
+            data: None,
+        })),
+        Err(AppError::Database(e)) => {
+            error!("Database error: {}", e);
+            Err(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }))
+        }
+        Err(e) => Err(e.error_response()),
+    }
+}
+
+/// Находит репозиторий по имени (см. `lookup_repo`) и проверяет, что текущий пользователь - его владелец
+///
+/// Приватный репозиторий чужого пользователя выглядит как несуществующий,
+/// для публичного возвращается 403.
+///
+/// # Возвращает
+///
+/// * `Result<(Repository, User), HttpResponse>` - Репозиторий и владелец, либо готовый ответ с ошибкой
+pub fn find_owned_repo(
+    req: &HttpRequest,
+    db: &web::Data<Database>,
+    repo_name: &str,
+) -> Result<(Repository, User), HttpResponse> {
+    let user = match check_auth(req, db) {
+        Some(user) => user,
+        None => {
+            return Err(HttpResponse::Unauthorized().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Unauthorized".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    match lookup_repo(req, db, repo_name, Some(&user)) {
+        Ok(Some(repo)) if repo.owner_id == user.id.unwrap_or_default() => {
+            check_storage(&repo)?;
+            Ok((repo, user))
+        },
+        Ok(Some(repo)) if repo.is_public => Err(HttpResponse::Forbidden().json(ApiResponse::<()> {
+            success: false,
+            message: Some("Only repository owner can change repository settings".to_string()),
+            data: None,
+        })),
+        Ok(_) => Err(HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            message: Some("Repository not found".to_string()),
+            data: None,
+        })),
+        Err(AppError::Database(e)) => {
+            error!("Database error: {}", e);
+            Err(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }))
+        }
+        Err(e) => Err(e.error_response()),
+    }
+}
+
+/// Проверяет, не превысил ли пользователь лимит новых репозиториев в час
+///
+/// Учитываются все репозитории пользователя, созданные за последний час: обычные,
+/// форки и импортированные из bundle. Администраторы не ограничены.
+///
+/// # Параметры
+///
+/// * `user` - Пользователь, создающий репозиторий
+/// * `db` - База данных
+/// * `clock` - Часы, от которых отсчитывается окно
+///
+/// # Возвращает
+///
+/// * `Result<(), HttpResponse>` - Ошибка 429 с `Retry-After`, если лимит исчерпан
+pub fn check_creation_rate(user: &User, db: &web::Data<Database>, clock: &dyn Clock) -> Result<(), HttpResponse> {
+    let limit = match CONFIG.max_repos_per_hour {
+        Some(limit) if !is_admin(user) => limit as usize,
+        _ => return Ok(()),
+    };
+
+    let now = clock.now();
+    let window = chrono::Duration::seconds(REPO_CREATION_WINDOW_SECS as i64);
+    let created = match Repository::created_since(user.id.unwrap_or_default(), now - window, db.get_connection()) {
+        Ok(created) => created,
+        Err(e) => {
+            error!("Failed to count created repositories: {}", e);
+            return Err(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    if created.len() < limit {
+        return Ok(());
+    }
+
+    // Место освободится, когда из окна выйдет самый старый репозиторий сверх лимита
+    let frees_at = created[created.len() - limit] + window;
+    let retry_after = (frees_at - now).num_seconds().max(1);
+    Err(HttpResponse::TooManyRequests()
+        .insert_header((header::RETRY_AFTER, retry_after.to_string()))
+        .json(ApiResponse::<()> {
+            success: false,
+            message: Some(format!(
+                "Repository creation limit of {} per hour reached, retry in {} seconds",
+                limit, retry_after
+            )),
+            data: None,
+        }))
+}
+
 //pub fn check_notification(req: &HttpResponse, db: &web::Data<Database>) -> Option<Notification> {
 
 //}
 
 /// Обработчик для авторизации пользователя
-pub async fn login(login_req: web::Json<LoginRequest>, db: web::Data<Database>) -> Result<HttpResponse> {
+pub async fn login(req: HttpRequest, login_req: web::Json<LoginRequest>, db: web::Data<Database>) -> Result<HttpResponse> {
     let conn = db.get_connection();
+    let ip = req.peer_addr().map(|addr| addr.ip().to_string());
+    let clock = SystemClock;
     
-    match User::authenticate(&login_req.username, &login_req.password, conn) {
-        Ok(Some(user)) => {
+    match User::authenticate(&login_req.username, &login_req.password, ip.as_deref(), &clock, LockoutPolicy::configured(), conn) {
+        Ok(LoginOutcome::Success(user)) => {
             Ok(HttpResponse::Ok().json(ApiResponse {
                 success: true,
                 message: Some("Login successful".to_string()),
-                data: Some(user),
+                data: Some(user.to_view(true)),
             }))
         },
-        _ => {
+        Ok(LoginOutcome::Locked(until)) => {
+            forget_username(&login_req.username);
+            let retry_after = (until - clock.now()).num_seconds().max(1);
+            Ok(HttpResponse::build(StatusCode::LOCKED)
+                .insert_header((header::RETRY_AFTER, retry_after.to_string()))
+                .json(ApiResponse::<()> {
+                    success: false,
+                    message: Some(format!("Account is locked after too many failed login attempts, retry in {} seconds", retry_after)),
+                    data: None,
+                }))
+        },
+        Err(e) => {
+            error!("Failed to authenticate user: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }))
+        },
+        Ok(LoginOutcome::InvalidCredentials) => {
+            // Попытка могла заблокировать аккаунт, см. `auth::forget_username`
+            forget_username(&login_req.username);
             Ok(HttpResponse::Unauthorized().json(ApiResponse::<()> {
                 success: false,
                 message: Some("Invalid username or password".to_string()),
@@ -92,14 +468,148 @@ pub async fn login(login_req: web::Json<LoginRequest>, db: web::Data<Database>)
     }
 }
 
+/// Имя пользователя, чьи учётные данные прошли проверку
+#[derive(Serialize)]
+struct CredentialsCheck {
+    username: String,
+}
+
+/// Проверка учётных данных без выполнения операции (для git credential helper'ов и CI)
+///
+/// Аутентифицирует запрос через `check_auth`, поэтому неудачные попытки так же
+/// засчитываются в блокировку аккаунта, как при входе и в git протоколе.
+/// Возвращает только имя пользователя; при неверных данных - 401 с
+/// `WWW-Authenticate`.
+pub async fn check_credentials(req: HttpRequest, db: web::Data<Database>) -> Result<HttpResponse> {
+    match check_auth(&req, &db) {
+        Some(user) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(CredentialsCheck { username: user.username }),
+        })),
+        None => Ok(HttpResponse::Unauthorized()
+            .append_header(("WWW-Authenticate", challenge()))
+            .json(ApiResponse::<()> {
+                success: false,
+                message: Some("Unauthorized".to_string()),
+                data: None,
+            })),
+    }
+}
+
 /// Обработчик для регистрации нового пользователя
 pub async fn register(register_req: web::Json<RegisterRequest>, db: web::Data<Database>) -> Result<HttpResponse> {
+    let register_req = register_req.into_inner();
+
+    let invite = match CONFIG.registration_mode {
+        RegistrationMode::Open => None,
+        RegistrationMode::Closed => {
+            return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Registration is closed on this server".to_string()),
+                data: None,
+            }));
+        }
+        RegistrationMode::Invite => {
+            let token = match register_req.invite.as_deref().map(str::trim).filter(|token| !token.is_empty()) {
+                Some(token) => token.to_string(),
+                None => {
+                    return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+                        success: false,
+                        message: Some("Registration requires an invite token".to_string()),
+                        data: None,
+                    }));
+                }
+            };
+
+            match Invite::claim(&token, &register_req.username, db.get_connection()) {
+                Ok(true) => Some(token),
+                Ok(false) => {
+                    return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+                        success: false,
+                        message: Some("Invite token is invalid or has already been used".to_string()),
+                        data: None,
+                    }));
+                }
+                Err(e) => {
+                    error!("Failed to claim invite: {}", e);
+                    return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                        success: false,
+                        message: Some("Database error".to_string()),
+                        data: None,
+                    }));
+                }
+            }
+        }
+    };
+
+    match create_account(&register_req, &db) {
+        Ok(user) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: Some("User registered successfully".to_string()),
+            data: Some(user.to_view(true)),
+        })),
+        Err(response) => {
+            // Приглашение остаётся действительным, если аккаунт не создан
+            if let Some(token) = invite {
+                if let Err(e) = Invite::release(&token, db.get_connection()) {
+                    error!("Failed to release invite: {}", e);
+                }
+            }
+            Ok(response)
+        }
+    }
+}
+
+/// Создаёт аккаунт пользователя без проверки режима регистрации
+///
+/// # Параметры
+///
+/// * `register_req` - Имя, пароль и email нового пользователя
+/// * `db` - База данных
+///
+/// # Возвращает
+///
+/// * `Result<User, HttpResponse>` - Созданный пользователь или готовый ответ с ошибкой
+pub fn create_account(register_req: &RegisterRequest, db: &web::Data<Database>) -> Result<User, HttpResponse> {
+    // Имя пользователя - каталог его репозиториев на диске
+    if let Err(e) = validate_owner_name(&register_req.username) {
+        return Err(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: Some(e.to_string()),
+            data: None,
+        }));
+    }
+
     let conn = db.get_connection();
-    
+    let email = normalize_email(register_req.email.as_deref());
+
+    // Столбец email уникален, но без проверки повтор дал бы безликую ошибку базы
+    if let Some(email) = &email {
+        match User::find_by_email(email, conn.clone()) {
+            Ok(None) => {}
+            Ok(Some(_)) => {
+                return Err(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                    success: false,
+                    message: Some("Email already in use".to_string()),
+                    data: None,
+                }));
+            }
+            Err(e) => {
+                error!("Database error: {}", e);
+                return Err(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                    success: false,
+                    message: Some("Database error".to_string()),
+                    data: None,
+                }));
+            }
+        }
+    }
+
     // Проверяем, что пользователь с таким именем не существует
     match User::find_by_username(&register_req.username, conn.clone()) {
         Ok(Some(_)) => {
-            Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            Err(HttpResponse::BadRequest().json(ApiResponse::<()> {
                 success: false,
                 message: Some("User with this username already exists".to_string()),
                 data: None,
@@ -107,25 +617,35 @@ pub async fn register(register_req: web::Json<RegisterRequest>, db: web::Data<Da
         },
         Ok(None) => {
             // Создаем нового пользователя
-            let user = User {
+            let mut user = User {
                 id: None,
                 username: register_req.username.clone(),
                 password: register_req.password.clone(), // В реальном приложении пароль нужно хэшировать!
-                email: register_req.email.clone(),
+                email,
                 created_at: None,
+                storage_quota_bytes: CONFIG.default_storage_quota_bytes,
+                last_login_at: None,
+                last_login_ip: None,
+                failed_login_count: 0,
+                locked_until: None,
             };
             
             match user.create(conn) {
-                Ok(_) => {
-                    Ok(HttpResponse::Ok().json(ApiResponse {
-                        success: true,
-                        message: Some("User registered successfully".to_string()),
-                        data: Some(user),
+                Ok(id) => {
+                    user.id = Some(id);
+                    Ok(user)
+                },
+                // Такое же имя или email успели занять параллельным запросом
+                Err(rusqlite::Error::SqliteFailure(e, _)) if e.code == rusqlite::ErrorCode::ConstraintViolation => {
+                    Err(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                        success: false,
+                        message: Some("User with this username or email already exists".to_string()),
+                        data: None,
                     }))
                 },
                 Err(e) => {
                     error!("Failed to create user: {}", e);
-                    Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                    Err(HttpResponse::InternalServerError().json(ApiResponse::<()> {
                         success: false,
                         message: Some("Failed to create user".to_string()),
                         data: None,
@@ -135,7 +655,7 @@ pub async fn register(register_req: web::Json<RegisterRequest>, db: web::Data<Da
         },
         Err(e) => {
             error!("Database error: {}", e);
-            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            Err(HttpResponse::InternalServerError().json(ApiResponse::<()> {
                 success: false,
                 message: Some("Database error".to_string()),
                 data: None,
@@ -144,13 +664,39 @@ pub async fn register(register_req: web::Json<RegisterRequest>, db: web::Data<Da
     }
 }
 
+/// Приводит email к виду, в котором он хранится: без пробелов по краям и в нижнем регистре
+///
+/// Пустая строка считается отсутствием email.
+fn normalize_email(email: Option<&str>) -> Option<String> {
+    email
+        .map(|email| email.trim().to_lowercase())
+        .filter(|email| !email.is_empty())
+}
+
 /// Получение профиля пользователя
 pub async fn user_profile(req: HttpRequest, db: web::Data<Database>) -> Result<HttpResponse> {
     if let Some(user) = check_auth(&req, &db) {
+        let storage_used_bytes = match storage_usage(&user, &db).await {
+            Ok(used) => used,
+            Err(e) => {
+                error!("Failed to compute storage usage: {}", e);
+                0
+            }
+        };
+
+        #[derive(Serialize)]
+        struct UserProfile {
+            #[serde(flatten)]
+            user: UserView,
+            storage_used_bytes: u64,
+            pinned_repos: Vec<Repository>,
+        }
+
+        let pinned_repos = visible_pinned_repos(&user, Some(&user), &db);
         Ok(HttpResponse::Ok().json(ApiResponse {
             success: true,
             message: None,
-            data: Some(user),
+            data: Some(UserProfile { user: user.to_view(true), storage_used_bytes, pinned_repos }),
         }))
     } else {
         Ok(HttpResponse::Unauthorized().json(ApiResponse::<()> {
@@ -161,6 +707,52 @@ pub async fn user_profile(req: HttpRequest, db: web::Data<Database>) -> Result<H
     }
 }
 
+/// Получение публичного профиля пользователя
+///
+/// Email виден только самому пользователю; остальные получают `avatar_url`.
+/// Закреплённые репозитории отдаются в порядке, заданном пользователем, без
+/// приватных репозиториев, недоступных смотрящему.
+pub async fn get_user(
+    req: HttpRequest,
+    path: web::Path<String>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let username = path.into_inner();
+
+    #[derive(Serialize)]
+    struct PublicProfile {
+        #[serde(flatten)]
+        user: UserView,
+        pinned_repos: Vec<Repository>,
+    }
+
+    match db.find_user_by_username(&username) {
+        Ok(Some(user)) => {
+            let viewer = check_auth(&req, &db);
+            let include_email = viewer.as_ref().is_some_and(|viewer| viewer.id == user.id);
+            let pinned_repos = visible_pinned_repos(&user, viewer.as_ref(), &db);
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                message: None,
+                data: Some(PublicProfile { user: user.to_view(include_email), pinned_repos }),
+            }))
+        },
+        Ok(None) => Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            message: Some("User not found".to_string()),
+            data: None,
+        })),
+        Err(e) => {
+            error!("Database error: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
+
 /// Получение списка репозиториев
 pub async fn list_repos(req: HttpRequest, db: web::Data<Database>) -> Result<HttpResponse> {
     if let Some(user) = check_auth(&req, &db) {
@@ -198,52 +790,98 @@ pub async fn create_repo(
     db: web::Data<Database>
 ) -> Result<HttpResponse> {
     if let Some(user) = check_auth(&req, &db) {
+        if let Err(response) = check_creation_rate(&user, &db, &SystemClock) {
+            return Ok(response);
+        }
+
+        if let Err(e) = validate_repo_name(&repo_req.name) {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: Some(e.to_string()),
+                data: None,
+            }));
+        }
+
         let conn = db.get_connection();
+
+        match over_quota(&user, &db).await {
+            Ok(false) => {},
+            Ok(true) => {
+                return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+                    success: false,
+                    message: Some("Storage quota exceeded".to_string()),
+                    data: None,
+                }));
+            },
+            Err(AppError::Database(e)) => {
+                error!("Failed to check storage quota: {}", e);
+                return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                    success: false,
+                    message: Some("Database error".to_string()),
+                    data: None,
+                }));
+            }
+            Err(e) => return Ok(e.error_response()),
+        }
         
         // Создаем репозиторий в базе данных
-        let repo = Repository {
+        let mut repo = Repository {
             id: None,
             name: repo_req.name.clone(),
             description: repo_req.description.clone(),
             owner_id: user.id.unwrap(),
+            owner: user.username.clone(),
             is_public: repo_req.is_public,
             created_at: None,
+            forked_from: None,
         };
-        
-        match repo.create(conn) {
-            Ok(_) => {
-                // Инициализируем Git репозиторий
-                let repo_path = format!("repositories/{}.git", repo_req.name);
-                let init_result = Command::new("git")
-                    .args(&["init", "--bare", &repo_path])
-                    .output();
-                
-                match init_result {
-                    Ok(output) if output.status.success() => {
-                        Ok(HttpResponse::Ok().json(ApiResponse {
-                            success: true,
-                            message: Some("Repository created successfully".to_string()),
-                            data: Some(repo),
-                        }))
-                    },
-                    _ => {
-                        error!("Failed to initialize git repository");
-                        Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
-                            success: false,
-                            message: Some("Failed to initialize git repository".to_string()),
-                            data: None,
-                        }))
-                    }
-                }
+
+        // Проверка имени и создание идут под блокировкой полного имени: из одновременных
+        // запросов с одним именем второй дождётся первого и получит 409, а не общий каталог.
+        // Имена владельца уникальны без учёта регистра, поэтому и блокировка в нижнем регистре
+        let created = run_blocking(move || {
+            let _lock = match lock_repo(&repo.full_name().to_lowercase()) {
+                Some(lock) => lock,
+                None => return Err(AppError::Busy(format!("Timed out waiting for lock on repo: {}", repo.name))),
+            };
+
+            if let Some(message) = name_conflict(&repo, conn.clone())? {
+                return Err(AppError::Conflict(message));
+            }
+
+            // Repository::create сам инициализирует bare репозиторий на диске
+            Ok(repo.create(conn).map(|id| {
+                repo.id = Some(id);
+                repo
+            }))
+        }).await;
+
+        match created {
+            Ok(Ok(Ok(repo))) => {
+                webhooks::fire_repository_event("repository_created", &repo, &user, &db);
+                Ok(HttpResponse::Ok().json(ApiResponse {
+                    success: true,
+                    message: Some("Repository created successfully".to_string()),
+                    data: Some(repo),
+                }))
             },
-            Err(e) => {
+            // Одновременный запрос с тем же именем в другом регистре успел первым
+            Ok(Ok(Err(rusqlite::Error::SqliteFailure(e, _)))) if e.code == rusqlite::ErrorCode::ConstraintViolation => {
+                Ok(HttpResponse::Conflict().json(ApiResponse::<()> {
+                    success: false,
+                    message: Some("Repository with this name already exists".to_string()),
+                    data: None,
+                }))
+            },
+            Ok(Ok(Err(e))) => {
                 error!("Failed to create repository: {}", e);
                 Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
                     success: false,
                     message: Some("Failed to create repository".to_string()),
                     data: None,
                 }))
-            }
+            },
+            Ok(Err(e)) | Err(e) => Ok(e.error_response()),
         }
     } else {
         Ok(HttpResponse::Unauthorized().json(ApiResponse::<()> {
@@ -254,6 +892,148 @@ pub async fn create_repo(
     }
 }
 
+/// Почему новый репозиторий нельзя создать: у владельца уже есть репозиторий с этим
+/// именем (в том числе в другом регистре) или его каталог уже существует
+///
+/// Каталоги лежат в `{owner}/{name}.git`, поэтому имя должно быть свободно только
+/// у владельца: у других пользователей могут быть репозитории с тем же именем.
+///
+/// # Параметры
+///
+/// * `repo` - Создаваемый репозиторий (имя и владелец)
+/// * `conn` - Соединение с базой данных
+///
+/// # Возвращает
+///
+/// * `Result<Option<String>, AppError>` - Сообщение об отказе или None, если имя свободно
+pub fn name_conflict(repo: &Repository, conn: Arc<Mutex<Connection>>) -> Result<Option<String>, AppError> {
+    if Repository::find_by_full_name(&repo.full_name(), conn.clone())?.is_some() {
+        return Ok(Some("Repository with this name already exists".to_string()));
+    }
+    if let Some(existing) = Repository::find_case_conflict(repo.owner_id, &repo.name, conn)? {
+        return Ok(Some(case_conflict_message(&existing)));
+    }
+    match repo_path(Path::new(REPOS_ROOT), &repo.full_name()) {
+        Ok(path) if path.exists() => Ok(Some("Repository name is already taken".to_string())),
+        _ => Ok(None),
+    }
+}
+
+/// Сообщение об отказе создать репозиторий, имя которого отличается от существующего только регистром
+pub fn case_conflict_message(existing: &str) -> String {
+    format!("Repository name differs only in case from existing repository '{}'", existing)
+}
+
+/// Параметры проверки имени репозитория
+#[derive(Deserialize)]
+pub struct CheckNameQuery {
+    pub name: String,
+}
+
+/// Результат проверки имени репозитория
+#[derive(Serialize)]
+struct NameAvailability {
+    available: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+/// Проверка, можно ли создать репозиторий с таким именем
+///
+/// Выполняет те же проверки, что и `create_repo`, но ничего не создаёт.
+pub async fn check_repo_name(
+    req: HttpRequest,
+    query: web::Query<CheckNameQuery>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let user = match check_auth(&req, &db) {
+        Some(user) => user,
+        None => {
+            return Ok(HttpResponse::Unauthorized().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Unauthorized".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    let candidate = Repository {
+        id: None,
+        name: query.name.clone(),
+        owner_id: user.id.unwrap(),
+        owner: user.username.clone(),
+        description: None,
+        is_public: false,
+        created_at: None,
+        forked_from: None,
+    };
+
+    let reason = match validate_repo_name(&query.name) {
+        Err(e) => Some(e.to_string()),
+        Ok(()) => match name_conflict(&candidate, db.get_connection()) {
+            Ok(reason) => reason,
+            Err(e) => {
+                error!("Database error: {}", e);
+                return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                    success: false,
+                    message: Some("Database error".to_string()),
+                    data: None,
+                }));
+            }
+        },
+    };
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: None,
+        data: Some(NameAvailability {
+            available: reason.is_none(),
+            reason,
+        }),
+    }))
+}
+
+/// Адреса для клонирования репозитория
+#[derive(Serialize)]
+pub struct CloneUrls {
+    pub http: String,
+    /// Появится вместе с поддержкой SSH
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ssh: Option<String>,
+}
+
+/// Строит адреса клонирования репозитория
+///
+/// Базовый адрес берётся из GIT_HTTP_EXTERNAL_URL, потому что за прокси адрес,
+/// на котором слушает сервер, отличается от публичного; без настройки используются
+/// схема и хост запроса (с учётом `Forwarded`/`X-Forwarded-*`).
+///
+/// # Параметры
+///
+/// * `req` - HTTP запрос
+/// * `full_name` - Полное имя репозитория `{owner}/{name}`
+///
+/// # Возвращает
+///
+/// * `CloneUrls` - Адреса клонирования
+pub fn clone_urls(req: &HttpRequest, full_name: &str) -> CloneUrls {
+    CloneUrls {
+        http: format!("{}/git/{}", external_base_url(req), full_name),
+        ssh: None,
+    }
+}
+
+/// Публичный адрес сервера: `GIT_HTTP_EXTERNAL_URL` или схема и хост из запроса
+pub fn external_base_url(req: &HttpRequest) -> String {
+    match &CONFIG.external_url {
+        Some(url) => url.clone(),
+        None => {
+            let info = req.connection_info();
+            format!("{}://{}", info.scheme(), info.host())
+        }
+    }
+}
+
 /// Получение информации о репозитории
 pub async fn get_repo(
     req: HttpRequest,
@@ -262,37 +1042,93 @@ pub async fn get_repo(
 ) -> Result<HttpResponse> {
     let repo_name = path.into_inner();
     let conn = db.get_connection();
+    let viewer = check_auth(&req, &db);
     
-    match Repository::find_by_name(&repo_name, conn.clone()) {
+    match lookup_repo(&req, &db, &repo_name, viewer.as_ref()) {
         Ok(Some(repo)) => {
-            // Получаем ветки репозитория
-            let repo_path = format!("repositories/{}.git", repo_name);
-            let branches_output = Command::new("git")
-                .args(&["--git-dir", &repo_path, "branch", "--format=%(refname:short)"])
-                .output();
-            
-            let branches = match branches_output {
-                Ok(output) if output.status.success() => {
-                    let stdout = String::from_utf8_lossy(&output.stdout);
-                    stdout.lines().map(|s| s.to_string()).collect::<Vec<String>>()
+            if let Err(response) = check_storage(&repo) {
+                return Ok(response);
+            }
+            let full_name = repo.full_name();
+
+            // Получаем ветки репозитория: не больше MAX_LISTED_BRANCHES, из кэша, пока ссылки не менялись
+            let generation = Repository::ref_generation(&full_name, conn.clone()).ok().flatten();
+            let repo_path = resolve_repo(Path::new(REPOS_ROOT), &full_name).map_err(|e| error!("{}", e)).ok();
+            let (branches, branches_truncated) = match repo_path.as_ref().map(|path| list_branches(&full_name, path, generation)) {
+                Some(Ok(list)) => (list.names.clone(), list.truncated),
+                Some(Err(e)) => {
+                    error!("Failed to list branches: {}", e);
+                    (Vec::new(), false)
                 },
-                _ => Vec::new(),
+                None => (Vec::new(), false),
             };
             
-            // Получаем пул-реквесты для репозитория
-            let pull_requests = match PullRequest::find_by_repository(repo.id.unwrap(), conn) {
-                Ok(prs) => prs,
+            let pull_requests_enabled = match pull_requests_enabled(&repo, &db) {
+                Ok(enabled) => enabled,
+                Err(response) => return Ok(response),
+            };
+
+            let merge_methods = match Repository::merge_methods(repo.id.unwrap(), conn.clone()) {
+                Ok(methods) => methods,
                 Err(e) => {
-                    error!("Failed to fetch pull requests: {}", e);
-                    Vec::new()
+                    error!("Database error: {}", e);
+                    return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                        success: false,
+                        message: Some("Database error".to_string()),
+                        data: None,
+                    }));
+                }
+            };
+
+            // Получаем пул-реквесты для репозитория
+            let pull_requests = if !pull_requests_enabled {
+                Vec::new()
+            } else {
+                match PullRequest::find_by_repository(repo.id.unwrap(), conn) {
+                    Ok(prs) => prs,
+                    Err(e) => {
+                        error!("Failed to fetch pull requests: {}", e);
+                        Vec::new()
+                    }
                 }
             };
             
+            // Пустой - репозиторий без единой ссылки (ещё не было push). Если git
+            // не смог прочитать репозиторий, это не пустой репозиторий, а ошибка
+            let is_empty = branches.is_empty()
+                && repo_path.is_some_and(|repo_path| has_refs(&repo_path).is_ok_and(|has_refs| !has_refs));
+
+            let viewer_id = viewer.and_then(|user| user.id);
+            let fork_count = Repository::count_forks(repo.id.unwrap(), viewer_id, db.get_connection())
+                .unwrap_or_else(|e| {
+                    error!("Failed to count forks: {}", e);
+                    0
+                });
+            let topics = Repository::topics(repo.id.unwrap(), db.get_connection())
+                .unwrap_or_else(|e| {
+                    error!("Failed to fetch topics: {}", e);
+                    Vec::new()
+                });
+            
             #[derive(Serialize)]
             struct RepoDetails {
                 repo: Repository,
                 branches: Vec<String>,
+                /// Веток больше, чем в `branches` (см. `MAX_LISTED_BRANCHES`)
+                branches_truncated: bool,
                 pull_requests: Vec<PullRequest>,
+                /// Включены ли пул-реквесты; если нет, клиенту стоит их скрыть
+                pull_requests_enabled: bool,
+                /// Разрешённые способы слияния (`merge`, `squash`, `rebase`) - клиенту стоит
+                /// предлагать только их
+                allowed_merge_methods: Vec<&'static str>,
+                /// В репозитории ещё нет коммитов - клиенту стоит показать инструкции по первому push
+                is_empty: bool,
+                clone_urls: CloneUrls,
+                /// Число форков, видимых текущему пользователю
+                fork_count: i64,
+                /// Темы репозитория по алфавиту
+                topics: Vec<String>,
             }
             
             Ok(HttpResponse::Ok().json(ApiResponse {
@@ -301,7 +1137,14 @@ pub async fn get_repo(
                 data: Some(RepoDetails {
                     repo,
                     branches,
+                    branches_truncated,
                     pull_requests,
+                    pull_requests_enabled,
+                    allowed_merge_methods: merge_methods.allowed(),
+                    is_empty,
+                    clone_urls: clone_urls(&req, &full_name),
+                    fork_count,
+                    topics,
                 }),
             }))
         },
@@ -312,7 +1155,7 @@ pub async fn get_repo(
                 data: None,
             }))
         },
-        Err(e) => {
+        Err(AppError::Database(e)) => {
             error!("Database error: {}", e);
             Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
                 success: false,
@@ -320,6 +1163,49 @@ pub async fn get_repo(
                 data: None,
             }))
         }
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+/// Включены ли пул-реквесты в репозитории с учётом глобальной настройки
+///
+/// # Возвращает
+///
+/// * `Result<bool, HttpResponse>` - Включены ли пул-реквесты, либо готовый ответ с ошибкой
+pub fn pull_requests_enabled(repo: &Repository, db: &web::Data<Database>) -> Result<bool, HttpResponse> {
+    if !CONFIG.pull_requests_enabled {
+        return Ok(false);
+    }
+
+    match Repository::pull_requests_enabled(repo.id.unwrap_or_default(), db.get_connection()) {
+        Ok(enabled) => Ok(enabled),
+        Err(e) => {
+            error!("Database error: {}", e);
+            Err(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
+
+/// Проверяет, что в репозитории включены пул-реквесты
+///
+/// Когда они выключены, их эндпоинты выглядят несуществующими.
+///
+/// # Возвращает
+///
+/// * `Result<(), HttpResponse>` - Ошибка 404, если пул-реквесты выключены
+pub fn require_pull_requests(repo: &Repository, db: &web::Data<Database>) -> Result<(), HttpResponse> {
+    if pull_requests_enabled(repo, db)? {
+        Ok(())
+    } else {
+        Err(HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            message: Some("Pull requests are disabled for this repository".to_string()),
+            data: None,
+        }))
     }
 }
 
@@ -340,6 +1226,152 @@ pub struct CreateCommentRequest {
 #[derive(Serialize, Deserialize)]
 pub struct UpdatePullRequestStatusRequest {
     pub status: String,
+    /// Способ слияния для `status: merged`: `merge` (по умолчанию), `squash` или `rebase`
+    pub merge_method: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UpdatePullRequestRequest {
+    pub title: Option<String>,
+    pub description: Option<String>,
+}
+
+/// Запрос на изменение видимости репозитория
+#[derive(Deserialize)]
+pub struct SetVisibilityRequest {
+    pub is_public: bool,
+}
+
+/// Удаляет репозиторий вместе с его данными и каталогом на диске (только владелец)
+///
+/// Удаление идёт под блокировкой полного имени, чтобы не пересечься с push'ем или
+/// созданием репозитория с тем же именем. Кэш объявлений ссылок очищается,
+/// удаление записывается в журнал аудита, webhook'и сервера получают
+/// событие `repository_deleted`.
+pub async fn delete_repo(
+    req: HttpRequest,
+    path: web::Path<String>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let (repo, user) = match find_owned_repo(&req, &db, &repo_name) {
+        Ok(found) => found,
+        Err(response) => return Ok(response),
+    };
+
+    let conn = db.get_connection();
+    let repo_id = repo.id.unwrap();
+    let name = repo.full_name();
+    let deleted = run_blocking(move || {
+        // Создание блокирует имя в нижнем регистре, push - имя как есть
+        let _create_lock = match lock_repo(&name.to_lowercase()) {
+            Some(lock) => lock,
+            None => return Err(AppError::Busy(format!("Timed out waiting for lock on repo: {}", name))),
+        };
+        let _push_lock = if name != name.to_lowercase() {
+            match lock_repo(&name) {
+                Some(lock) => Some(lock),
+                None => return Err(AppError::Busy(format!("Timed out waiting for lock on repo: {}", name))),
+            }
+        } else {
+            None
+        };
+
+        Ok(Repository::delete(repo_id, &name, conn))
+    }).await;
+
+    match deleted {
+        Ok(Ok(Ok(()))) => {},
+        Ok(Ok(Err(e))) => {
+            error!("Failed to delete repository: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to delete repository".to_string()),
+                data: None,
+            }));
+        },
+        Ok(Err(e)) | Err(e) => return Ok(e.error_response()),
+    }
+
+    advertise::purge(&repo.full_name());
+
+    let entry = AuditEntry::new(user.id.unwrap_or_default(), "repo.delete", &repo.full_name(), String::new());
+    if let Err(e) = entry.create(db.get_connection()) {
+        error!("Failed to record audit entry: {}", e);
+    }
+
+    webhooks::fire_repository_event("repository_deleted", &repo, &user, &db);
+
+    Ok(HttpResponse::Ok().json(ApiResponse::<()> {
+        success: true,
+        message: Some("Repository deleted successfully".to_string()),
+        data: None,
+    }))
+}
+
+/// Делает репозиторий публичным или приватным (только владелец репозитория)
+///
+/// Права на чтение проверяются по базе данных на каждый запрос, поэтому после
+/// перехода в приватные репозиторий сразу перестаёт отдаваться остальным.
+/// Кэш объявлений ссылок репозитория очищается, а поколение ссылок увеличивается,
+/// чтобы прокси не продолжали отдавать сохранённые ответы по старому ETag.
+/// Изменение записывается в журнал аудита, владелец получает уведомление.
+pub async fn set_visibility(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<SetVisibilityRequest>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let (mut repo, user) = match find_owned_repo(&req, &db, &repo_name) {
+        Ok(found) => found,
+        Err(response) => return Ok(response),
+    };
+
+    let changed = match Repository::set_visibility(repo.id.unwrap(), body.is_public, db.get_connection()) {
+        Ok(changed) => changed,
+        Err(e) => {
+            error!("Failed to update repository visibility: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    if changed {
+        advertise::purge(&repo.full_name());
+        bump_ref_generation(&repo.full_name(), &db);
+
+        let (from, to) = if body.is_public { ("private", "public") } else { ("public", "private") };
+        let entry = AuditEntry::new(user.id.unwrap_or_default(), "repo.visibility", &repo.full_name(), format!("{} -> {}", from, to));
+        if let Err(e) = entry.create(db.get_connection()) {
+            error!("Failed to record audit entry: {}", e);
+        }
+
+        let notification = Notification {
+            id: None,
+            notification_type: "repository_visibility".to_string(),
+            title: format!("{} is now {}", repo.name, to),
+            content: format!("{} changed the visibility of {} from {} to {}", user.username, repo.name, from, to),
+            user_id: repo.owner_id,
+            is_read: false,
+            created_at: None,
+        };
+        if let Err(e) = notification.create(db.get_connection()) {
+            error!("Failed to create visibility notification: {}", e);
+        }
+    }
+
+    repo.is_public = body.is_public;
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: Some(if changed { "Repository visibility updated" } else { "Repository visibility unchanged" }.to_string()),
+        data: Some(repo),
+    }))
 }
 
 /// Создание нового пул-реквеста
@@ -353,9 +1385,35 @@ pub async fn create_pull_request(
         let repo_name = path.into_inner();
         let conn = db.get_connection();
         
-        // Находим репозиторий по имени
-        match Repository::find_by_name(&repo_name, conn.clone()) {
-            Ok(Some(repo)) => {
+        // Находим репозиторий по имени; приватный чужой репозиторий не найден
+        match lookup_repo(&req, &db, &repo_name, Some(&user)) {
+            Ok(Some(repo)) if can_access(&repo, Some(&user)) => {
+                match pull_requests_enabled(&repo, &db) {
+                    Ok(true) => {},
+                    Ok(false) => {
+                        return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+                            success: false,
+                            message: Some("Pull requests are disabled for this repository".to_string()),
+                            data: None,
+                        }));
+                    },
+                    Err(response) => return Ok(response),
+                }
+
+                // Между одними и теми же ветками может быть только один открытый пул-реквест
+                match PullRequest::find_open_by_branches(repo.id.unwrap(), &pr_req.source_branch, &pr_req.target_branch, conn.clone()) {
+                    Ok(None) => {},
+                    Ok(Some(existing)) => return Ok(duplicate_pull_request(&repo_name, &existing)),
+                    Err(e) => {
+                        error!("Database error: {}", e);
+                        return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                            success: false,
+                            message: Some("Database error".to_string()),
+                            data: None,
+                        }));
+                    }
+                }
+
                 // Создаем пул-реквест
                 let pull_request = PullRequest {
                     id: None,
@@ -388,13 +1446,14 @@ pub async fn create_pull_request(
                     }
                 }
             },
-            Ok(None) => {
+            Ok(_) => {
                 Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
                     success: false,
                     message: Some("Repository not found".to_string()),
                     data: None,
                 }))
             },
+            Err(e @ AppError::Conflict(_)) => Ok(e.error_response()),
             Err(e) => {
                 error!("Database error: {}", e);
                 Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
@@ -413,22 +1472,50 @@ pub async fn create_pull_request(
     }
 }
 
+/// Ссылка на уже открытый пул-реквест между теми же ветками
+#[derive(Serialize)]
+struct ExistingPullRequest {
+    id: i64,
+    url: String,
+}
+
+/// Ответ 409 на попытку открыть второй пул-реквест между теми же ветками
+fn duplicate_pull_request(repo_name: &str, existing: &PullRequest) -> HttpResponse {
+    let id = existing.id.unwrap_or_default();
+    let url = format!("/api/repos/{}/pulls/{}", repo_name, id);
+
+    HttpResponse::Conflict()
+        .insert_header((header::LOCATION, url.clone()))
+        .json(ApiResponse {
+            success: false,
+            message: Some(format!(
+                "Pull request #{} from {} into {} is already open",
+                id, existing.source_branch, existing.target_branch
+            )),
+            data: Some(ExistingPullRequest { id, url }),
+        })
+}
+
 /// Получение информации о пул-реквесте
 pub async fn get_pull_request(
     req: HttpRequest,
     path: web::Path<(String, i64)>,
     db: web::Data<Database>
 ) -> Result<HttpResponse> {
-    if let Some(_) = check_auth(&req, &db) {
+    if let Some(user) = check_auth(&req, &db) {
         let (repo_name, pr_id) = path.into_inner();
         let conn = db.get_connection();
         
-        // Находим репозиторий по имени
-        match Repository::find_by_name(&repo_name, conn.clone()) {
-            Ok(Some(_)) => {
-                // Находим пул-реквест по ID
+        // Находим репозиторий по имени; приватный чужой репозиторий не найден
+        match lookup_repo(&req, &db, &repo_name, Some(&user)) {
+            Ok(Some(repo)) if can_access(&repo, Some(&user)) => {
+                if let Err(response) = require_pull_requests(&repo, &db) {
+                    return Ok(response);
+                }
+
+                // Находим пул-реквест по ID; пул-реквест другого репозитория не найден
                 match PullRequest::find_by_id(pr_id, conn.clone()) {
-                    Ok(Some(pr)) => {
+                    Ok(Some(pr)) if Some(pr.repository_id) == repo.id => {
                         // Получаем комментарии к пул-реквесту
                         let comments = match PullRequestComment::find_by_pull_request(pr_id, conn) {
                             Ok(comments) => comments,
@@ -438,10 +1525,19 @@ pub async fn get_pull_request(
                             }
                         };
                         
+                        // Проверка слияния имеет смысл только для открытого пул-реквеста
+                        let mergeability = if pr.status == PullRequestStatus::Open {
+                            pull_request_mergeability(&repo.full_name(), &pr).await
+                        } else {
+                            None
+                        };
+                        
                         #[derive(Serialize)]
                         struct PullRequestDetails {
                             pull_request: PullRequest,
                             comments: Vec<PullRequestComment>,
+                            #[serde(skip_serializing_if = "Option::is_none")]
+                            mergeability: Option<Mergeability>,
                         }
                         
                         Ok(HttpResponse::Ok().json(ApiResponse {
@@ -450,10 +1546,11 @@ pub async fn get_pull_request(
                             data: Some(PullRequestDetails {
                                 pull_request: pr,
                                 comments,
+                                mergeability,
                             }),
                         }))
                     },
-                    Ok(None) => {
+                    Ok(_) => {
                         Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
                             success: false,
                             message: Some("Pull request not found".to_string()),
@@ -470,13 +1567,14 @@ pub async fn get_pull_request(
                     }
                 }
             },
-            Ok(None) => {
+            Ok(_) => {
                 Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
                     success: false,
                     message: Some("Repository not found".to_string()),
                     data: None,
                 }))
             },
+            Err(e @ AppError::Conflict(_)) => Ok(e.error_response()),
             Err(e) => {
                 error!("Database error: {}", e);
                 Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
@@ -486,12 +1584,144 @@ pub async fn get_pull_request(
                 }))
             }
         }
-    } else {
-        Ok(HttpResponse::Unauthorized().json(ApiResponse::<()> {
+    } else {
+        Ok(HttpResponse::Unauthorized().json(ApiResponse::<()> {
+            success: false,
+            message: Some("Unauthorized".to_string()),
+            data: None,
+        }))
+    }
+}
+
+/// Проверяет, можно ли слить открытый пул-реквест
+///
+/// Ошибка проверки не ломает ответ с пул-реквестом: поле просто не отдаётся.
+/// Удалённая ветка - обычная ситуация и в лог не пишется.
+async fn pull_request_mergeability(full_name: &str, pr: &PullRequest) -> Option<Mergeability> {
+    let repo_path = match resolve_repo(Path::new(REPOS_ROOT), full_name) {
+        Ok(path) => path,
+        Err(e) => {
+            error!("Failed to check mergeability: {}", e);
+            return None;
+        }
+    };
+
+    let pr = pr.clone();
+    match run_blocking(move || pr.mergeability(&repo_path)).await {
+        Ok(Ok(mergeability)) => Some(mergeability),
+        Ok(Err(AppError::RefNotFound(_))) => None,
+        Ok(Err(e)) | Err(e) => {
+            error!("Failed to check mergeability: {}", e);
+            None
+        }
+    }
+}
+
+/// Редактирование заголовка и описания пул-реквеста (автором или владельцем репозитория)
+pub async fn update_pull_request(
+    req: HttpRequest,
+    path: web::Path<(String, i64)>,
+    update_req: web::Json<UpdatePullRequestRequest>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let user = match check_auth(&req, &db) {
+        Some(user) => user,
+        None => {
+            return Ok(HttpResponse::Unauthorized().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Unauthorized".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    if update_req.title.as_deref().is_some_and(|title| title.trim().is_empty()) {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: Some("Title must not be empty".to_string()),
+            data: None,
+        }));
+    }
+
+    let (repo_name, pr_id) = path.into_inner();
+    let conn = db.get_connection();
+
+    let repo = match lookup_repo(&req, &db, &repo_name, Some(&user)) {
+        Ok(Some(repo)) if can_access(&repo, Some(&user)) => repo,
+        Ok(_) => {
+            return Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Repository not found".to_string()),
+                data: None,
+            }));
+        }
+        Err(e @ AppError::Conflict(_)) => return Ok(e.error_response()),
+        Err(e) => {
+            error!("Database error: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    if let Err(response) = require_pull_requests(&repo, &db) {
+        return Ok(response);
+    }
+
+    let pr = match PullRequest::find_by_id(pr_id, conn.clone()) {
+        Ok(Some(pr)) if Some(pr.repository_id) == repo.id => pr,
+        Ok(_) => {
+            return Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Pull request not found".to_string()),
+                data: None,
+            }));
+        }
+        Err(e) => {
+            error!("Database error: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    let user_id = user.id.unwrap();
+    if pr.author_id != user_id && repo.owner_id != user_id {
+        return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
             success: false,
-            message: Some("Unauthorized".to_string()),
+            message: Some("Only the pull request author or repository owner can edit it".to_string()),
             data: None,
-        }))
+        }));
+    }
+
+    let result = PullRequest::update_details(
+        pr_id,
+        update_req.title.as_deref().map(str::trim),
+        update_req.description.as_deref(),
+        conn.clone(),
+    )
+    .and_then(|_| PullRequest::find_by_id(pr_id, conn));
+
+    match result {
+        Ok(pr) => {
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                message: Some("Pull request updated successfully".to_string()),
+                data: pr,
+            }))
+        },
+        Err(e) => {
+            error!("Failed to update pull request: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to update pull request".to_string()),
+                data: None,
+            }))
+        }
     }
 }
 
@@ -506,12 +1736,16 @@ pub async fn add_comment_to_pull_request(
         let (repo_name, pr_id) = path.into_inner();
         let conn = db.get_connection();
         
-        // Находим репозиторий по имени
-        match Repository::find_by_name(&repo_name, conn.clone()) {
-            Ok(Some(_)) => {
-                // Находим пул-реквест по ID
+        // Находим репозиторий по имени; приватный чужой репозиторий не найден
+        match lookup_repo(&req, &db, &repo_name, Some(&user)) {
+            Ok(Some(repo)) if can_access(&repo, Some(&user)) => {
+                if let Err(response) = require_pull_requests(&repo, &db) {
+                    return Ok(response);
+                }
+
+                // Находим пул-реквест по ID; пул-реквест другого репозитория не найден
                 match PullRequest::find_by_id(pr_id, conn.clone()) {
-                    Ok(Some(_)) => {
+                    Ok(Some(pr)) if Some(pr.repository_id) == repo.id => {
                         // Создаем комментарий
                         let comment = PullRequestComment {
                             id: None,
@@ -519,6 +1753,7 @@ pub async fn add_comment_to_pull_request(
                             author_id: user.id.unwrap(),
                             content: comment_req.content.clone(),
                             created_at: None,
+                            deleted: false,
                         };
                         
                         match comment.create(conn) {
@@ -539,7 +1774,7 @@ pub async fn add_comment_to_pull_request(
                             }
                         }
                     },
-                    Ok(None) => {
+                    Ok(_) => {
                         Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
                             success: false,
                             message: Some("Pull request not found".to_string()),
@@ -556,13 +1791,14 @@ pub async fn add_comment_to_pull_request(
                     }
                 }
             },
-            Ok(None) => {
+            Ok(_) => {
                 Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
                     success: false,
                     message: Some("Repository not found".to_string()),
                     data: None,
                 }))
             },
+            Err(e @ AppError::Conflict(_)) => Ok(e.error_response()),
             Err(e) => {
                 error!("Database error: {}", e);
                 Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
@@ -581,6 +1817,108 @@ pub async fn add_comment_to_pull_request(
     }
 }
 
+/// Удаление комментария к пул-реквесту (автором комментария или владельцем репозитория)
+pub async fn delete_pull_request_comment(
+    req: HttpRequest,
+    path: web::Path<(String, i64, i64)>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let user = match check_auth(&req, &db) {
+        Some(user) => user,
+        None => {
+            return Ok(HttpResponse::Unauthorized().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Unauthorized".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    let (repo_name, pr_id, comment_id) = path.into_inner();
+    let conn = db.get_connection();
+
+    let repo = match lookup_repo(&req, &db, &repo_name, Some(&user)) {
+        Ok(Some(repo)) if can_access(&repo, Some(&user)) => repo,
+        Ok(_) => {
+            return Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Repository not found".to_string()),
+                data: None,
+            }));
+        }
+        Err(e @ AppError::Conflict(_)) => return Ok(e.error_response()),
+        Err(e) => {
+            error!("Database error: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    if let Err(response) = require_pull_requests(&repo, &db) {
+        return Ok(response);
+    }
+
+    // Комментарий должен относиться к пул-реквесту этого репозитория
+    let comment = match (PullRequest::find_by_id(pr_id, conn.clone()), PullRequestComment::find_by_id(comment_id, conn.clone())) {
+        (Ok(Some(pr)), Ok(Some(comment)))
+            if Some(pr.repository_id) == repo.id
+                && comment.pull_request_id == pr_id
+                && !comment.deleted => comment,
+        (Ok(_), Ok(_)) => {
+            return Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Comment not found".to_string()),
+                data: None,
+            }));
+        }
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Database error: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    let user_id = user.id.unwrap();
+    if comment.author_id != user_id && repo.owner_id != user_id {
+        return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+            success: false,
+            message: Some("Only the comment author or repository owner can delete this comment".to_string()),
+            data: None,
+        }));
+    }
+
+    match PullRequestComment::delete(comment_id, conn) {
+        Ok(true) => {
+            Ok(HttpResponse::Ok().json(ApiResponse::<()> {
+                success: true,
+                message: Some("Comment deleted successfully".to_string()),
+                data: None,
+            }))
+        },
+        Ok(false) => {
+            Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Comment not found".to_string()),
+                data: None,
+            }))
+        },
+        Err(e) => {
+            error!("Failed to delete comment: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to delete comment".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
+
 /// Обновление статуса пул-реквеста
 pub async fn update_pull_request_status(
     req: HttpRequest,
@@ -593,8 +1931,12 @@ pub async fn update_pull_request_status(
         let conn = db.get_connection();
         
         // Находим репозиторий по имени
-        match Repository::find_by_name(&repo_name, conn.clone()) {
+        match lookup_repo(&req, &db, &repo_name, Some(&user)) {
             Ok(Some(repo)) => {
+                if let Err(response) = require_pull_requests(&repo, &db) {
+                    return Ok(response);
+                }
+
                 // Проверяем, что пользователь является владельцем репозитория
                 if repo.owner_id != user.id.unwrap() {
                     return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
@@ -604,14 +1946,74 @@ pub async fn update_pull_request_status(
                     }));
                 }
                 
-                // Находим пул-реквест по ID
+                // Находим пул-реквест по ID; пул-реквест другого репозитория не найден
                 match PullRequest::find_by_id(pr_id, conn.clone()) {
-                    Ok(Some(_)) => {
+                    Ok(Some(pr)) if Some(pr.repository_id) == repo.id => {
                         let status = PullRequestStatus::from_str(&status_req.status);
                         
+                        // Повторно открыть можно, только если между ветками нет другого открытого пул-реквеста
+                        if status == PullRequestStatus::Open && pr.status != PullRequestStatus::Open {
+                            match PullRequest::find_open_by_branches(pr.repository_id, &pr.source_branch, &pr.target_branch, conn.clone()) {
+                                Ok(Some(existing)) => return Ok(duplicate_pull_request(&repo_name, &existing)),
+                                Ok(None) => {},
+                                Err(e) => {
+                                    error!("Database error: {}", e);
+                                    return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                                        success: false,
+                                        message: Some("Database error".to_string()),
+                                        data: None,
+                                    }));
+                                }
+                            }
+                        }
+                        
                         // Если статус "merged", выполняем слияние веток
                         if status == PullRequestStatus::Merged {
-                            match PullRequest::merge(pr_id, conn.clone()) {
+                            let method = match status_req.merge_method.as_deref() {
+                                None => MergeMethod::Merge,
+                                Some(method) => match MergeMethod::parse(method) {
+                                    Some(method) => method,
+                                    None => {
+                                        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                                            success: false,
+                                            message: Some("merge_method must be one of merge, squash, rebase".to_string()),
+                                            data: None,
+                                        }));
+                                    }
+                                },
+                            };
+                            match Repository::merge_methods(repo.id.unwrap(), conn.clone()) {
+                                Ok(methods) if methods.allows(method) => {}
+                                Ok(methods) => {
+                                    return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                                        success: false,
+                                        message: Some(format!(
+                                            "Merge method '{}' is not allowed in this repository; allowed: {}",
+                                            method.as_str(), methods.allowed().join(", ")
+                                        )),
+                                        data: None,
+                                    }));
+                                }
+                                Err(e) => {
+                                    error!("Database error: {}", e);
+                                    return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                                        success: false,
+                                        message: Some("Database error".to_string()),
+                                        data: None,
+                                    }));
+                                }
+                            }
+
+                            // Слияние двигает целевую ветку в обход receive-pack; клон, слияние
+                            // и push идут в пуле git, как и сам receive-pack
+                            bump_ref_generation(&repo.full_name(), &db);
+                            let merge_conn = conn.clone();
+                            let merged = run_blocking(move || PullRequest::merge(pr_id, method, merge_conn))
+                                .await
+                                .and_then(|merged| merged);
+                            bump_ref_generation(&repo.full_name(), &db);
+
+                            match merged {
                                 Ok(_) => {
                                     Ok(HttpResponse::Ok().json(ApiResponse::<()> {
                                         success: true,
@@ -621,11 +2023,7 @@ pub async fn update_pull_request_status(
                                 },
                                 Err(e) => {
                                     error!("Failed to merge pull request: {}", e);
-                                    Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
-                                        success: false,
-                                        message: Some("Failed to merge pull request".to_string()),
-                                        data: None,
-                                    }))
+                                    Ok(e.error_response())
                                 }
                             }
                         } else {
@@ -649,7 +2047,7 @@ pub async fn update_pull_request_status(
                             }
                         }
                     },
-                    Ok(None) => {
+                    Ok(_) => {
                         Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
                             success: false,
                             message: Some("Pull request not found".to_string()),
@@ -673,6 +2071,7 @@ pub async fn update_pull_request_status(
                     data: None,
                 }))
             },
+            Err(e @ AppError::Conflict(_)) => Ok(e.error_response()),
             Err(e) => {
                 error!("Database error: {}", e);
                 Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
@@ -691,20 +2090,119 @@ pub async fn update_pull_request_status(
     }
 }
 
-/// Получение уведомлений пользователя
-pub async fn get_notifications(
+#[derive(Serialize, Deserialize)]
+pub struct UserPullsQuery {
+    /// `author` - созданные пользователем, `reviewer` - в его репозиториях от других
+    pub role: Option<String>,
+    /// `open`, `closed`, `merged` или `all`
+    pub state: Option<String>,
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+/// Получение пул-реквестов пользователя во всех репозиториях
+pub async fn list_user_pull_requests(
     req: HttpRequest,
+    query: web::Query<UserPullsQuery>,
     db: web::Data<Database>
 ) -> Result<HttpResponse> {
+    if !CONFIG.pull_requests_enabled {
+        return Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            message: Some("Pull requests are disabled on this server".to_string()),
+            data: None,
+        }));
+    }
+
     if let Some(user) = check_auth(&req, &db) {
+        let status = match query.state.as_deref().unwrap_or("open") {
+            "all" => None,
+            "open" => Some(PullRequestStatus::Open),
+            "closed" => Some(PullRequestStatus::Closed),
+            "merged" => Some(PullRequestStatus::Merged),
+            _ => {
+                return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                    success: false,
+                    message: Some("state must be one of open, closed, merged, all".to_string()),
+                    data: None,
+                }));
+            }
+        };
+
+        let (limit, offset) = page_bounds(query.page, query.per_page);
         let conn = db.get_connection();
-        
-        match Notification::find_by_user_id(user.id.unwrap(), conn) {
-            Ok(notifications) => {
+        let user_id = user.id.unwrap();
+
+        let result = match query.role.as_deref().unwrap_or("author") {
+            "author" => PullRequest::find_by_author(user_id, status, limit, offset, conn),
+            "reviewer" => PullRequest::find_for_reviewer(user_id, status, limit, offset, conn),
+            _ => {
+                return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                    success: false,
+                    message: Some("role must be author or reviewer".to_string()),
+                    data: None,
+                }));
+            }
+        };
+
+        match result {
+            Ok(pull_requests) => {
                 Ok(HttpResponse::Ok().json(ApiResponse {
                     success: true,
                     message: None,
-                    data: Some(notifications),
+                    data: Some(pull_requests),
+                }))
+            },
+            Err(e) => {
+                error!("Failed to fetch pull requests: {}", e);
+                Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                    success: false,
+                    message: Some("Failed to fetch pull requests".to_string()),
+                    data: None,
+                }))
+            }
+        }
+    } else {
+        Ok(HttpResponse::Unauthorized().json(ApiResponse::<()> {
+            success: false,
+            message: Some("Unauthorized".to_string()),
+            data: None,
+        }))
+    }
+}
+
+/// Параметры постраничного вывода уведомлений
+#[derive(Deserialize)]
+pub struct NotificationsQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+    /// `next_cursor` предыдущей страницы; вместе с ним `page` не учитывается
+    pub cursor: Option<String>,
+}
+
+/// Получение уведомлений пользователя, от новых к старым
+///
+/// Для длинных списков предпочтителен `cursor`: страницы по `page` сдвигаются,
+/// когда между запросами приходят новые уведомления.
+pub async fn get_notifications(
+    req: HttpRequest,
+    query: web::Query<NotificationsQuery>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    if let Some(user) = check_auth(&req, &db) {
+        let conn = db.get_connection();
+
+        let before = match parse_cursor(query.cursor.as_deref()) {
+            Ok(before) => before,
+            Err(response) => return Ok(response),
+        };
+        let (limit, offset) = page_bounds(query.page, query.per_page);
+        let offset = if before.is_some() { 0 } else { offset };
+
+        match Notification::find_by_user_id(user.id.unwrap(), limit + 1, offset, before.as_ref(), conn) {
+            Ok(notifications) => {
+                Ok(cursor_page_response(notifications, limit, |notification| {
+                    Cursor::after(notification.created_at.as_ref(), notification.id.unwrap_or_default())
                 }))
             },
             Err(e) => {
@@ -731,7 +2229,7 @@ pub async fn mark_notification_as_read(
     path: web::Path<i64>,
     db: web::Data<Database>
 ) -> Result<HttpResponse> {
-    if let Some(_) = check_auth(&req, &db) {
+    if check_auth(&req, &db).is_some() {
         let notification_id = path.into_inner();
         let conn = db.get_connection();
         
@@ -760,3 +2258,364 @@ pub async fn mark_notification_as_read(
         }))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App};
+    use rusqlite::params;
+    use serde_json::Value;
+    use crate::test_support::{self, basic_auth, create_user, test_db, unique};
+
+    /// Добавляет уведомление с заданной датой создания
+    fn notify(db: &Database, user: &User, title: &str, created_at: &str) {
+        db.get_connection().lock().unwrap().execute(
+            "INSERT INTO notifications (notification_type, title, content, user_id, is_read, created_at)
+             VALUES ('test', ?1, '', ?2, 0, ?3)",
+            params![title, user.id, created_at],
+        ).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn notification_cursor_pages_stay_stable_while_rows_are_added() {
+        let db = test_db();
+        let user = create_user(&db, "reader");
+        // Две записи в одну секунду: порядок между ними задаёт id
+        notify(&db, &user, "n1", "2024-05-01 10:00:00");
+        notify(&db, &user, "n2", "2024-05-01 11:00:00");
+        notify(&db, &user, "n3", "2024-05-01 11:00:00");
+        notify(&db, &user, "n4", "2024-05-01 12:00:00");
+        notify(&db, &user, "n5", "2024-05-01 13:00:00");
+
+        let app = test::init_service(
+            App::new()
+                .app_data(db.clone())
+                .route("/api/notifications", web::get().to(get_notifications))
+        ).await;
+
+        let mut titles = Vec::new();
+        let mut cursor: Option<String> = None;
+        for page in 0.. {
+            let uri = match &cursor {
+                Some(cursor) => format!("/api/notifications?per_page=2&cursor={}", cursor),
+                None => "/api/notifications?per_page=2".to_string(),
+            };
+            let req = test::TestRequest::get().uri(&uri).insert_header(basic_auth(&user)).to_request();
+            let body: Value = test::call_and_read_body_json(&app, req).await;
+            titles.extend(body["data"].as_array().unwrap().iter().map(|n| n["title"].as_str().unwrap().to_string()));
+
+            // Новые записи между страницами попадают в начало списка и не сдвигают следующие страницы
+            notify(&db, &user, &format!("new{}", page), "2024-05-02 00:00:00");
+
+            cursor = body["next_cursor"].as_str().map(str::to_string);
+            if cursor.is_none() {
+                break;
+            }
+        }
+
+        assert_eq!(titles, ["n5", "n4", "n3", "n2", "n1"]);
+    }
+
+    #[actix_web::test]
+    async fn invalid_cursor_is_a_bad_request() {
+        let db = test_db();
+        let user = create_user(&db, "reader");
+        let app = test::init_service(
+            App::new()
+                .app_data(db.clone())
+                .route("/api/notifications", web::get().to(get_notifications))
+        ).await;
+
+        for cursor in ["garbage!", "MjAyNC0wNS0wMSAxMjozMDowMA", "eWVzdGVyZGF5fDQy"] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/api/notifications?cursor={}", cursor))
+                .insert_header(basic_auth(&user))
+                .to_request();
+            let response = test::call_service(&app, req).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", cursor);
+            let body: Value = test::read_body_json(response).await;
+            assert_eq!(body["message"], "Invalid cursor");
+        }
+    }
+
+    #[actix_web::test]
+    async fn concurrent_creates_of_one_name_give_one_repository() {
+        let db = test_db();
+        let user = create_user(&db, "racer");
+        let app = std::rc::Rc::new(test::init_service(
+            App::new()
+                .app_data(db.clone())
+                .route("/api/repos", web::post().to(create_repo))
+        ).await);
+
+        for (first, second) in [("same", "same"), ("case", "CASE"), ("Mixed", "mixed")] {
+            let handles: Vec<_> = [first, second].into_iter().map(|name| {
+                let app = app.clone();
+                let req = test::TestRequest::post()
+                    .uri("/api/repos")
+                    .insert_header(basic_auth(&user))
+                    .set_json(serde_json::json!({ "name": name, "is_public": true }))
+                    .to_request();
+                actix_web::rt::spawn(async move { test::call_service(&*app, req).await.status() })
+            }).collect();
+
+            let mut statuses = Vec::new();
+            for handle in handles {
+                statuses.push(handle.await.unwrap());
+            }
+            statuses.sort();
+            assert_eq!(statuses, [StatusCode::OK, StatusCode::CONFLICT], "{} / {}", first, second);
+
+            let owner_dir = Path::new(REPOS_ROOT).join(&user.username);
+            let dirs = std::fs::read_dir(&owner_dir).unwrap()
+                .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+                .filter(|name| name.eq_ignore_ascii_case(&format!("{}.git", first)))
+                .count();
+            assert_eq!(dirs, 1, "{}", first);
+
+            let rows: i64 = db.get_connection().lock().unwrap().query_row(
+                "SELECT COUNT(*) FROM repositories WHERE owner_id = ?1 AND name = ?2 COLLATE NOCASE",
+                params![user.id, first],
+                |row| row.get(0),
+            ).unwrap();
+            assert_eq!(rows, 1, "{}", first);
+        }
+    }
+
+    fn register(username: &str, email: Option<&str>) -> RegisterRequest {
+        RegisterRequest {
+            username: username.to_string(),
+            password: "secret".to_string(),
+            email: email.map(str::to_string),
+            invite: None,
+        }
+    }
+
+    /// Статус и сообщение ответа с ошибкой
+    async fn rejection(response: HttpResponse) -> (StatusCode, String) {
+        let status = response.status();
+        let body = actix_web::body::to_bytes(response.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        (status, body["message"].as_str().unwrap_or_default().to_string())
+    }
+
+    #[actix_web::test]
+    async fn duplicate_emails_are_rejected_regardless_of_case_and_spaces() {
+        let db = test_db();
+        let first = unique("mail");
+        let user = create_account(&register(&first, Some("Foo@X.com")), &db).unwrap();
+        assert_eq!(user.email.as_deref(), Some("foo@x.com"));
+
+        for email in ["foo@x.com ", "Foo@x.com", "  FOO@X.COM\t"] {
+            let response = create_account(&register(&unique("mail"), Some(email)), &db).unwrap_err();
+            assert_eq!(rejection(response).await, (StatusCode::BAD_REQUEST, "Email already in use".to_string()), "{:?}", email);
+        }
+
+        // Адреса, сохранённые до нормализации, тоже сравниваются без учёта регистра
+        db.get_connection().lock().unwrap().execute(
+            "INSERT INTO users (username, password, email) VALUES (?1, 'secret', 'Legacy@Example.com')",
+            params![unique("legacy")],
+        ).unwrap();
+        let response = create_account(&register(&unique("mail"), Some("legacy@example.COM")), &db).unwrap_err();
+        assert_eq!(rejection(response).await.0, StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn accounts_without_email_do_not_conflict() {
+        let db = test_db();
+        for email in [None, Some(""), Some("   "), None] {
+            let user = create_account(&register(&unique("nomail"), email), &db).unwrap();
+            assert_eq!(user.email, None);
+        }
+    }
+
+    #[actix_web::test]
+    async fn duplicate_and_invalid_usernames_are_rejected() {
+        let db = test_db();
+        let name = unique("taken");
+        create_account(&register(&name, None), &db).unwrap();
+
+        let response = create_account(&register(&name, Some("other@x.com")), &db).unwrap_err();
+        assert_eq!(rejection(response).await, (StatusCode::BAD_REQUEST, "User with this username already exists".to_string()));
+
+        let response = create_account(&register("../escape", None), &db).unwrap_err();
+        assert_eq!(rejection(response).await.0, StatusCode::BAD_REQUEST);
+    }
+
+    /// Открывает пул-реквест `feature` -> `main` в репозитории
+    fn open_pull_request(db: &Database, repo: &Repository, author: &User) -> i64 {
+        PullRequest {
+            id: None,
+            title: "Feature".to_string(),
+            description: None,
+            repository_id: repo.id.unwrap(),
+            source_branch: "feature".to_string(),
+            target_branch: "main".to_string(),
+            author_id: author.id.unwrap(),
+            status: PullRequestStatus::Open,
+            created_at: None,
+            updated_at: None,
+        }.create(db.get_connection()).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn pull_request_status_is_not_changed_through_another_repository() {
+        let db = test_db();
+        let owner = create_user(&db, "prowner");
+        let alpha = test_support::create_repo(&db, &owner, "alpha", true);
+        test_support::create_repo(&db, &owner, "beta", true);
+        let pr_id = open_pull_request(&db, &alpha, &owner);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(db.clone())
+                .route("/api/repos/{repo_name}/pulls/{pr_id}/status", web::put().to(update_pull_request_status))
+        ).await;
+
+        for status in ["closed", "merged"] {
+            let req = test::TestRequest::put()
+                .uri(&format!("/api/repos/beta/pulls/{}/status", pr_id))
+                .insert_header(basic_auth(&owner))
+                .set_json(serde_json::json!({ "status": status }))
+                .to_request();
+            let response = test::call_service(&app, req).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{}", status);
+        }
+
+        let pr = PullRequest::find_by_id(pr_id, db.get_connection()).unwrap().unwrap();
+        assert_eq!(pr.status, PullRequestStatus::Open);
+    }
+
+    #[actix_web::test]
+    async fn pull_requests_of_private_repositories_are_hidden_from_other_users() {
+        let db = test_db();
+        let owner = create_user(&db, "privowner");
+        let stranger = create_user(&db, "stranger");
+        let secret = test_support::create_repo(&db, &owner, "secret", false);
+        let pr_id = open_pull_request(&db, &secret, &owner);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(db.clone())
+                .route("/api/repos/{repo_name}/pulls", web::post().to(create_pull_request))
+                .route("/api/repos/{repo_name}/pulls/{pr_id}", web::get().to(get_pull_request))
+                .route("/api/repos/{repo_name}/pulls/{pr_id}/comments", web::post().to(add_comment_to_pull_request))
+        ).await;
+
+        let owner_query = format!("?owner={}", owner.username);
+        let requests = [
+            test::TestRequest::post()
+                .uri(&format!("/api/repos/secret/pulls{}", owner_query))
+                .set_json(serde_json::json!({ "title": "Mine", "source_branch": "a", "target_branch": "main" })),
+            test::TestRequest::get().uri(&format!("/api/repos/secret/pulls/{}{}", pr_id, owner_query)),
+            test::TestRequest::post()
+                .uri(&format!("/api/repos/secret/pulls/{}/comments{}", pr_id, owner_query))
+                .set_json(serde_json::json!({ "content": "hello" })),
+        ];
+        for req in requests {
+            let response = test::call_service(&app, req.insert_header(basic_auth(&stranger)).to_request()).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+
+        let prs: i64 = db.get_connection().lock().unwrap().query_row(
+            "SELECT COUNT(*) FROM pull_requests WHERE repository_id = ?1", params![secret.id], |row| row.get(0),
+        ).unwrap();
+        let comments: i64 = db.get_connection().lock().unwrap().query_row(
+            "SELECT COUNT(*) FROM pull_request_comments WHERE pull_request_id = ?1", params![pr_id], |row| row.get(0),
+        ).unwrap();
+        assert_eq!((prs, comments), (1, 0));
+    }
+
+    #[actix_web::test]
+    async fn pull_requests_are_not_read_or_commented_through_another_repository() {
+        let db = test_db();
+        let owner = create_user(&db, "prowner");
+        let alpha = test_support::create_repo(&db, &owner, "alpha", true);
+        test_support::create_repo(&db, &owner, "beta", true);
+        let pr_id = open_pull_request(&db, &alpha, &owner);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(db.clone())
+                .route("/api/repos/{repo_name}/pulls/{pr_id}", web::get().to(get_pull_request))
+                .route("/api/repos/{repo_name}/pulls/{pr_id}/comments", web::post().to(add_comment_to_pull_request))
+        ).await;
+
+        let requests = [
+            test::TestRequest::get().uri(&format!("/api/repos/beta/pulls/{}", pr_id)),
+            test::TestRequest::post()
+                .uri(&format!("/api/repos/beta/pulls/{}/comments", pr_id))
+                .set_json(serde_json::json!({ "content": "hello" })),
+        ];
+        for req in requests {
+            let response = test::call_service(&app, req.insert_header(basic_auth(&owner)).to_request()).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/repos/alpha/pulls/{}", pr_id))
+            .insert_header(basic_auth(&owner))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn create_is_rejected_once_the_owner_is_over_quota() {
+        let db = test_db();
+        let user = create_user(&db, "quota");
+        let existing = test_support::create_repo(&db, &user, "existing", true);
+        test_support::commit_files(&existing, &[("data.bin", &[0u8; 4096])]);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(db.clone())
+                .route("/api/repos", web::post().to(create_repo))
+        ).await;
+        let create = |name: &str| test::TestRequest::post()
+            .uri("/api/repos")
+            .insert_header(basic_auth(&user))
+            .set_json(serde_json::json!({ "name": name, "is_public": true }))
+            .to_request();
+
+        let set_quota = |bytes: i64| db.get_connection().lock().unwrap().execute(
+            "UPDATE users SET storage_quota_bytes = ?1 WHERE id = ?2", params![bytes, user.id],
+        ).unwrap();
+
+        set_quota(1 << 30);
+        assert_eq!(test::call_service(&app, create("roomy")).await.status(), StatusCode::OK);
+
+        set_quota(1024);
+        let response = test::call_service(&app, create("crowded")).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body: Value = test::read_body_json(response).await;
+        assert_eq!(body["message"], "Storage quota exceeded");
+    }
+
+    #[actix_web::test]
+    async fn create_rejects_a_name_differing_only_in_case() {
+        let db = test_db();
+        let owner = create_user(&db, "case");
+        let other = create_user(&db, "case");
+        let app = test::init_service(
+            App::new()
+                .app_data(db.clone())
+                .route("/api/repos", web::post().to(create_repo))
+        ).await;
+        let create = |user: &User, name: &str| test::TestRequest::post()
+            .uri("/api/repos")
+            .insert_header(basic_auth(user))
+            .set_json(serde_json::json!({ "name": name, "is_public": true }))
+            .to_request();
+
+        assert_eq!(test::call_service(&app, create(&owner, "Foo")).await.status(), StatusCode::OK);
+        let response = test::call_service(&app, create(&owner, "foo")).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body: Value = test::read_body_json(response).await;
+        assert!(body["message"].as_str().unwrap().contains("Foo"), "{}", body);
+
+        // Другой владелец хранит репозитории в своём каталоге
+        assert_eq!(test::call_service(&app, create(&other, "foo")).await.status(), StatusCode::OK);
+        assert!(Path::new(REPOS_ROOT).join(&owner.username).join("Foo.git").is_dir());
+        assert!(!Path::new(REPOS_ROOT).join(&owner.username).join("foo.git").exists());
+    }
+}
@@ -0,0 +1,135 @@
+use actix_web::{http::header, web, HttpResponse, HttpRequest, Result, ResponseError};
+use crate::git::{is_tag, resolve_commit};
+use crate::git::archive::{ArchiveFormat, ArchiveStream};
+use crate::git::pool::run_blocking;
+use crate::git::replica::resolve_read_repo;
+use crate::handlers::api::{etag_matches, find_readable_repo, ApiResponse};
+use crate::models::db::Database;
+
+/// Время кэширования архивов веток (ветка может сдвинуться в любой момент)
+const BRANCH_MAX_AGE_SECS: u32 = 60;
+/// Время кэширования архивов тегов и полных SHA
+const IMMUTABLE_MAX_AGE_SECS: u32 = 365 * 24 * 60 * 60;
+
+/// Скачивание архива ревизии: `/archive/{ref}.tar.gz`, `.tgz`, `.tar` или `.zip`
+///
+/// ETag строится из SHA коммита и формата, поэтому повторные загрузки того же
+/// коммита отвечают `304` по `If-None-Match`. Архивы тегов и полных SHA
+/// кэшируются надолго, архивы веток - на короткое время. Архив отправляется
+/// по мере того, как его собирает git (см. `ArchiveStream`).
+pub async fn download_archive(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let (repo_name, spec) = path.into_inner();
+
+    let repo = match find_readable_repo(&req, &db, &repo_name) {
+        Ok((repo, _)) => repo,
+        Err(response) => return Ok(response),
+    };
+
+    let (rev, format) = match ArchiveFormat::split_spec(&spec) {
+        Some(parts) => parts,
+        None => {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Expected {ref}.tar.gz, {ref}.tgz, {ref}.tar or {ref}.zip".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    let (repo_path, _) = match resolve_read_repo(&repo.full_name()) {
+        Ok(resolved) => resolved,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    let sha = match resolve_commit(&repo_path, rev) {
+        Ok(sha) => sha,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    let immutable = rev.eq_ignore_ascii_case(&sha) || is_tag(&repo_path, rev);
+    let visibility = if repo.is_public { "public" } else { "private" };
+    let cache_control = if immutable {
+        format!("{}, max-age={}, immutable", visibility, IMMUTABLE_MAX_AGE_SECS)
+    } else {
+        format!("{}, max-age={}", visibility, BRANCH_MAX_AGE_SECS)
+    };
+    let etag = format!("\"{}.{}\"", sha, format.extension());
+
+    if etag_matches(&req, &etag) {
+        return Ok(HttpResponse::NotModified()
+            .insert_header((header::ETAG, etag))
+            .insert_header((header::CACHE_CONTROL, cache_control))
+            .finish());
+    }
+
+    // Префикс зависит только от SHA, чтобы один ETag всегда соответствовал одним байтам
+    let prefix = format!("{}-{}", repo_name, &sha[..12]);
+    let safe_rev: String = rev
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || "._-".contains(c) { c } else { '-' })
+        .collect();
+    let file_name = format!("{}-{}.{}", repo_name, safe_rev, format.extension());
+
+    let started = run_blocking(move || ArchiveStream::start(&repo_path, &sha, &prefix, format)).await;
+    match started {
+        Ok(Ok(archive)) => Ok(HttpResponse::Ok()
+            .content_type(format.content_type())
+            .insert_header((header::ETAG, etag))
+            .insert_header((header::CACHE_CONTROL, cache_control))
+            .insert_header((header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", file_name)))
+            .body(archive)),
+        Ok(Err(e)) | Err(e) => Ok(e.error_response()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use std::process::Command;
+    use crate::test_support::{basic_auth, commit_files, create_repo, create_user, test_db, unique, work_dir};
+
+    #[actix_web::test]
+    async fn export_ignored_paths_are_left_out_of_the_archive() {
+        let db = test_db();
+        let owner = create_user(&db, "archive");
+        let repo = create_repo(&db, &owner, "archived", true);
+        commit_files(&repo, &[
+            (".gitattributes", b"secret.txt export-ignore\n"),
+            ("README.md", b"readme\n"),
+            ("secret.txt", b"internal\n"),
+        ]);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(db.clone())
+                .route("/api/repos/{repo_name}/archive/{spec:.*}", web::get().to(download_archive))
+        ).await;
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/repos/archived/archive/main.tar?owner={}", owner.username))
+            .insert_header(basic_auth(&owner))
+            .to_request();
+        let response = test::call_service(&app, req).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = test::read_body(response).await;
+
+        let tar = work_dir().join(format!("{}.tar", unique("archive")));
+        std::fs::write(&tar, &body).unwrap();
+        let listing = Command::new("tar").arg("-tf").arg(&tar).output().expect("run tar");
+        assert!(listing.status.success());
+        let files: Vec<String> = String::from_utf8_lossy(&listing.stdout)
+            .lines()
+            .filter_map(|line| line.split_once('/').map(|(_, file)| file.to_string()))
+            .filter(|file| !file.is_empty())
+            .collect();
+
+        assert!(files.contains(&"README.md".to_string()), "{:?}", files);
+        assert!(files.contains(&".gitattributes".to_string()), "{:?}", files);
+        assert!(!files.contains(&"secret.txt".to_string()), "{:?}", files);
+    }
+}
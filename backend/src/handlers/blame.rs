@@ -0,0 +1,126 @@
+use actix_web::{web, HttpResponse, HttpRequest, Result, ResponseError};
+use serde::Deserialize;
+use std::path::Path;
+use crate::git::blame::{blame_line, line_count};
+use crate::git::contents::validate_path;
+use crate::git::log::file_history;
+use crate::git::pool::run_blocking;
+use crate::git::{resolve_commit, resolve_repo, REPOS_ROOT};
+use crate::handlers::api::{find_readable_repo, page_bounds, ApiResponse};
+use crate::models::db::Database;
+
+/// Параметры blame одной строки
+#[derive(Deserialize)]
+pub struct BlameLineQuery {
+    /// Номер строки, начиная с 1
+    pub line: usize,
+}
+
+/// Коммит, последним изменивший одну строку файла
+///
+/// Нужен для перехода от строки в просмотре файла к коммиту: blame запускается
+/// только для этой строки (`-L N,N`), а не для всего файла.
+pub async fn get_blame_line(
+    req: HttpRequest,
+    path: web::Path<(String, String, String)>,
+    query: web::Query<BlameLineQuery>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let (repo_name, rev, file_path) = path.into_inner();
+
+    let repo = match find_readable_repo(&req, &db, &repo_name) {
+        Ok((repo, _)) => repo,
+        Err(response) => return Ok(response),
+    };
+
+    let repo_path = match resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()) {
+        Ok(path) => path,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    let sha = match resolve_commit(&repo_path, &rev) {
+        Ok(sha) => sha,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    let lines = match line_count(&repo_path, &sha, &file_path) {
+        Ok(Some(lines)) => lines,
+        Ok(None) => {
+            return Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                message: Some("File not found".to_string()),
+                data: None,
+            }));
+        }
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    let line = query.line;
+    if line == 0 || line > lines {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: Some(format!("line must be between 1 and {}", lines)),
+            data: None,
+        }));
+    }
+
+    match run_blocking(move || blame_line(&repo_path, &sha, &file_path, line)).await {
+        Ok(Ok(blame)) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(blame),
+        })),
+        Ok(Err(e)) | Err(e) => Ok(e.error_response()),
+    }
+}
+
+/// Параметры постраничного вывода истории файла
+#[derive(Deserialize)]
+pub struct FileHistoryQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+/// Коммиты, изменявшие файл, от новых к старым
+///
+/// История идёт через переименования (`git log --follow`), у каждого коммита -
+/// путь к файлу в нём и, если файл в нём переименован, прежний путь. Путь, которого
+/// в истории ревизии никогда не было, даёт пустой список, а не 404.
+pub async fn get_file_history(
+    req: HttpRequest,
+    path: web::Path<(String, String, String)>,
+    query: web::Query<FileHistoryQuery>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let (repo_name, rev, file_path) = path.into_inner();
+
+    let repo = match find_readable_repo(&req, &db, &repo_name) {
+        Ok((repo, _)) => repo,
+        Err(response) => return Ok(response),
+    };
+
+    let file_path = file_path.trim_end_matches('/').to_string();
+    if let Err(e) = validate_path(&file_path) {
+        return Ok(e.error_response());
+    }
+
+    let repo_path = match resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()) {
+        Ok(path) => path,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    let (limit, offset) = page_bounds(query.page, query.per_page);
+    let result = run_blocking(move || {
+        let sha = resolve_commit(&repo_path, &rev)?;
+        file_history(&repo_path, &sha, &file_path, limit as usize, offset as usize)
+    }).await;
+
+    match result {
+        Ok(Ok(history)) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(history),
+        })),
+        Ok(Err(e)) | Err(e) => Ok(e.error_response()),
+    }
+}
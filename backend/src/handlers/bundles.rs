@@ -0,0 +1,175 @@
+use actix_web::{http::{header, StatusCode}, web, HttpResponse, HttpRequest, Result, ResponseError};
+use log::error;
+use crate::clock::SystemClock;
+use crate::config::CONFIG;
+use crate::error::AppError;
+use crate::git::bundle::verify_bundle;
+use crate::git::pool::run_blocking;
+use crate::git::{validate_repo_name, TempDir};
+use crate::handlers::api::{check_auth, check_creation_rate, name_conflict, over_quota, ApiResponse};
+use crate::models::db::Database;
+use crate::models::repository::Repository;
+use crate::multipart;
+use crate::webhooks;
+
+/// Ответ 400 с сообщением
+fn bad_request(message: &str) -> HttpResponse {
+    HttpResponse::BadRequest().json(ApiResponse::<()> {
+        success: false,
+        message: Some(message.to_string()),
+        data: None,
+    })
+}
+
+/// Создание репозитория из загруженного git bundle
+///
+/// Форма `multipart/form-data`: поля `name`, `description`, `is_public` и файл
+/// `bundle`. Bundle проверяется `git bundle verify` до создания репозитория;
+/// размер запроса ограничен GIT_HTTP_MAX_BODY_BYTES.
+pub async fn import_bundle(
+    req: HttpRequest,
+    body: Result<web::Bytes, actix_web::Error>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let user = match check_auth(&req, &db) {
+        Some(user) => user,
+        None => {
+            return Ok(HttpResponse::Unauthorized().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Unauthorized".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    if let Err(response) = check_creation_rate(&user, &db, &SystemClock) {
+        return Ok(response);
+    }
+
+    let body = match body {
+        Ok(body) => body,
+        Err(e) if e.as_response_error().status_code() == StatusCode::PAYLOAD_TOO_LARGE => {
+            return Ok(HttpResponse::PayloadTooLarge().json(ApiResponse::<()> {
+                success: false,
+                message: Some(format!("Request body exceeds {} bytes", CONFIG.max_body_bytes)),
+                data: None,
+            }));
+        }
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    let boundary = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(multipart::boundary);
+    let boundary = match boundary {
+        Some(boundary) => boundary,
+        None => return Ok(bad_request("Content-Type must be multipart/form-data")),
+    };
+
+    let parts = match multipart::parse(&body, &boundary) {
+        Ok(parts) => parts,
+        Err(e) => return Ok(bad_request(&e)),
+    };
+
+    let field = |name: &str| parts.iter().find(|part| part.name == name);
+    let name = match field("name") {
+        Some(part) => part.text().trim().to_string(),
+        None => return Ok(bad_request("name is required")),
+    };
+    let description = field("description").map(|part| part.text()).filter(|text| !text.is_empty());
+    let is_public = field("is_public")
+        .map(|part| matches!(part.text().trim(), "true" | "1" | "on"))
+        .unwrap_or(false);
+    let bundle = match field("bundle") {
+        Some(part) if part.filename.is_some() => body.slice_ref(part.data),
+        _ => return Ok(bad_request("bundle file is required")),
+    };
+
+    if let Err(e) = validate_repo_name(&name) {
+        return Ok(bad_request(&e.to_string()));
+    }
+
+    let mut repo = Repository {
+        id: None,
+        name,
+        owner_id: user.id.unwrap(),
+        owner: user.username.clone(),
+        description,
+        is_public,
+        created_at: None,
+        forked_from: None,
+    };
+
+    let conflict = match name_conflict(&repo, db.get_connection()) {
+        Ok(conflict) => conflict,
+        Err(e) => {
+            error!("Database error: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }));
+        }
+    };
+    if let Some(message) = conflict {
+        return Ok(HttpResponse::Conflict().json(ApiResponse::<()> {
+            success: false,
+            message: Some(message),
+            data: None,
+        }));
+    }
+
+    match over_quota(&user, &db).await {
+        Ok(false) => {}
+        Ok(true) => {
+            return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Storage quota exceeded".to_string()),
+                data: None,
+            }));
+        }
+        Err(AppError::Database(e)) => {
+            error!("Failed to check storage quota: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }));
+        }
+        Err(e) => return Ok(e.error_response()),
+    }
+
+    // Bundle и временный репозиторий для проверки удаляются вместе с `temp_dir`
+    let job_db = db.clone();
+    let imported = run_blocking(move || -> std::result::Result<Repository, AppError> {
+        let temp_dir = TempDir::new("import_bundle")
+            .map_err(|e| AppError::Git(format!("Failed to create temporary directory: {}", e)))?;
+        let bundle_path = temp_dir.path().join("repo.bundle");
+        std::fs::write(&bundle_path, &bundle)
+            .map_err(|e| AppError::Git(format!("Failed to save bundle: {}", e)))?;
+
+        verify_bundle(&bundle_path, &temp_dir.path().join("verify.git"))?;
+
+        match repo.create_from_bundle(&bundle_path, job_db.get_connection()) {
+            Ok(id) => {
+                repo.id = Some(id);
+                Ok(repo)
+            }
+            Err(e) => Err(AppError::Git(format!("Failed to import bundle: {}", e))),
+        }
+    }).await;
+
+    match imported {
+        Ok(Ok(repo)) => {
+            webhooks::fire_repository_event("repository_created", &repo, &user, &db);
+            Ok(HttpResponse::Created().json(ApiResponse {
+                success: true,
+                message: Some("Repository imported successfully".to_string()),
+                data: Some(repo),
+            }))
+        }
+        Ok(Err(e)) | Err(e) => Ok(e.error_response()),
+    }
+}
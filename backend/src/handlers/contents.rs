@@ -0,0 +1,245 @@
+use actix_web::{web, HttpResponse, HttpRequest, Result, ResponseError};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use log::error;
+use serde::{Serialize, Deserialize};
+use std::path::Path;
+use crate::error::AppError;
+use crate::git::contents::{delete_file, write_file, Author, FileCommit};
+use crate::git::pool::run_blocking;
+use crate::git::refs::{push_stats, receive_protection};
+use crate::git::{lock_repo, resolve_repo, REPOS_ROOT};
+use crate::handlers::api::{bump_ref_generation, find_owned_repo, over_quota, ApiResponse};
+use crate::handlers::git::on_push;
+use crate::models::db::Database;
+use crate::models::repository::Repository;
+use crate::models::user::User;
+
+/// Запрос на создание или изменение файла
+#[derive(Deserialize)]
+pub struct PutContentsRequest {
+    /// Сообщение коммита
+    pub message: String,
+    /// Новое содержимое файла в base64
+    pub content: String,
+    /// Ветка, на которую добавляется коммит
+    pub branch: String,
+    /// SHA текущего блоба файла; обязателен при изменении существующего файла
+    pub sha: Option<String>,
+}
+
+/// Созданный через API коммит
+#[derive(Serialize)]
+pub struct ContentsCommit {
+    /// Путь к файлу
+    pub path: String,
+    /// Ветка
+    pub branch: String,
+    /// SHA нового коммита
+    pub commit: String,
+    /// SHA блоба файла после коммита; None, если файл удалён
+    pub sha: Option<String>,
+}
+
+/// Ответ 400 с сообщением
+fn bad_request(message: &str) -> HttpResponse {
+    HttpResponse::BadRequest().json(ApiResponse::<()> {
+        success: false,
+        message: Some(message.to_string()),
+        data: None,
+    })
+}
+
+/// Создание или изменение файла коммитом на сервере, без clone (только владелец репозитория)
+///
+/// Коммит создаётся git plumbing командами прямо в bare репозитории (см.
+/// `contents::write_file`). Для существующего файла нужен `sha` его текущего
+/// блоба: если файл с тех пор изменился, ответ 409. Новый файл - 201, изменённый - 200.
+/// Защита ветки и квота владельца проверяются в `commit_change`.
+pub async fn put_contents(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    body: web::Json<PutContentsRequest>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let (repo_name, file_path) = path.into_inner();
+
+    let (repo, user) = match find_owned_repo(&req, &db, &repo_name) {
+        Ok(found) => found,
+        Err(response) => return Ok(response),
+    };
+
+    let PutContentsRequest { message, content, branch, sha } = body.into_inner();
+    if message.trim().is_empty() {
+        return Ok(bad_request("message is required"));
+    }
+    let content = match BASE64.decode(content.trim()) {
+        Ok(content) => content,
+        Err(_) => return Ok(bad_request("content must be base64-encoded")),
+    };
+
+    let job_path = file_path.clone();
+    let job_branch = branch.clone();
+    let result = commit_change(&repo, &user, &branch, &db, move |repo_path, author| {
+        write_file(repo_path, &job_branch, &job_path, &content, sha.as_deref(), &message, author)
+    }).await;
+    let commit = match result {
+        Ok(commit) => commit,
+        Err(response) => return Ok(response),
+    };
+
+    let data = ContentsCommit {
+        path: file_path,
+        branch,
+        commit: commit.update.new.clone().unwrap_or_default(),
+        sha: commit.blob.clone(),
+    };
+    let (mut response, message) = if commit.created {
+        (HttpResponse::Created(), "File created successfully")
+    } else {
+        (HttpResponse::Ok(), "File updated successfully")
+    };
+    Ok(response.json(ApiResponse {
+        success: true,
+        message: Some(message.to_string()),
+        data: Some(data),
+    }))
+}
+
+/// Запрос на удаление файла
+#[derive(Deserialize)]
+pub struct DeleteContentsRequest {
+    /// Сообщение коммита
+    pub message: String,
+    /// Ветка, на которую добавляется коммит
+    pub branch: String,
+    /// SHA текущего блоба файла
+    pub sha: String,
+}
+
+/// Удаление файла коммитом на сервере, без clone (только владелец репозитория)
+///
+/// Парный к `put_contents`: `sha` должен совпадать с текущим блобом файла (иначе 409),
+/// отсутствующий файл - 404. Защита ветки проверяется так же.
+pub async fn delete_contents(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    body: web::Json<DeleteContentsRequest>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let (repo_name, file_path) = path.into_inner();
+
+    let (repo, user) = match find_owned_repo(&req, &db, &repo_name) {
+        Ok(found) => found,
+        Err(response) => return Ok(response),
+    };
+
+    let DeleteContentsRequest { message, branch, sha } = body.into_inner();
+    if message.trim().is_empty() {
+        return Ok(bad_request("message is required"));
+    }
+
+    let job_path = file_path.clone();
+    let job_branch = branch.clone();
+    let result = commit_change(&repo, &user, &branch, &db, move |repo_path, author| {
+        delete_file(repo_path, &job_branch, &job_path, &sha, &message, author)
+    }).await;
+    let commit = match result {
+        Ok(commit) => commit,
+        Err(response) => return Ok(response),
+    };
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: Some("File deleted successfully".to_string()),
+        data: Some(ContentsCommit {
+            path: file_path,
+            branch,
+            commit: commit.update.new.clone().unwrap_or_default(),
+            sha: None,
+        }),
+    }))
+}
+
+/// Создаёт коммит с изменением файла под блокировкой репозитория и выполняет
+/// реакции на push
+///
+/// Проверяет квоту владельца и защиту ветки: сервер не подписывает коммиты,
+/// поэтому в репозиториях, требующих подписей, изменение отклоняется.
+///
+/// # Параметры
+///
+/// * `repo` - Репозиторий
+/// * `user` - Автор изменения
+/// * `branch` - Ветка, для сообщений об ошибках
+/// * `db` - База данных
+/// * `change` - Создание коммита (`contents::write_file` или `contents::delete_file`)
+///
+/// # Возвращает
+///
+/// * `Result<FileCommit, HttpResponse>` - Коммит или готовый ответ с ошибкой
+async fn commit_change<F>(
+    repo: &Repository,
+    user: &User,
+    branch: &str,
+    db: &web::Data<Database>,
+    change: F,
+) -> Result<FileCommit, HttpResponse>
+where
+    F: FnOnce(&Path, &Author) -> Result<FileCommit, AppError> + Send + 'static,
+{
+    match over_quota(user, db).await {
+        Ok(false) => {}
+        Ok(true) => {
+            return Err(HttpResponse::Forbidden().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Storage quota of the repository owner is exceeded".to_string()),
+                data: None,
+            }));
+        }
+        Err(e @ AppError::Busy(_)) => return Err(e.error_response()),
+        Err(e) => error!("Failed to check storage quota: {}", e),
+    }
+
+    let repo_path = resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()).map_err(|e| e.error_response())?;
+
+    let email = user.email.clone().unwrap_or_else(|| format!("{}@users.noreply", user.username));
+    let username = user.username.clone();
+    let locked_repo = repo.full_name();
+    let branch = branch.to_string();
+    let job_db = db.clone();
+    let result = run_blocking(move || -> Result<(FileCommit, Option<_>), AppError> {
+        let _lock = match lock_repo(&locked_repo) {
+            Some(lock) => lock,
+            None => return Err(AppError::Busy(format!("Timed out waiting for lock on repo: {}", locked_repo))),
+        };
+
+        if receive_protection(&repo_path)?.require_signed_commits {
+            return Err(AppError::Conflict(format!(
+                "Branch '{}' requires signed commits, which the server cannot create; push a signed commit instead",
+                branch
+            )));
+        }
+
+        let author = Author { name: &username, email: &email };
+        bump_ref_generation(&locked_repo, &job_db);
+        let result = change(&repo_path, &author);
+        bump_ref_generation(&locked_repo, &job_db);
+        let commit = result?;
+
+        let stats = push_stats(&repo_path, &commit.update)
+            .map_err(|e| error!("Failed to compute stats for {}: {}", commit.update.name, e))
+            .ok();
+        Ok((commit, stats))
+    }).await;
+
+    let (commit, stats) = match result {
+        Ok(Ok(result)) => result,
+        Ok(Err(e)) | Err(e) => return Err(e.error_response()),
+    };
+
+    let (push_repo, pusher, push_db, update) = (repo.clone(), user.clone(), db.clone(), commit.update.clone());
+    if let Err(e) = web::block(move || on_push(&push_repo, &vec![(update, stats)], &pusher, &push_db)).await {
+        error!("Failed to process push to {}: {}", repo.full_name(), e);
+    }
+    Ok(commit)
+}
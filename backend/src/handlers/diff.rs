@@ -0,0 +1,419 @@
+use actix_web::{web, HttpResponse, HttpRequest, Result, ResponseError};
+use serde::{Serialize, Deserialize};
+use std::path::Path;
+use log::error;
+use crate::config::CONFIG;
+use crate::error::AppError;
+use crate::git::{resolve_commit, resolve_repo, REPOS_ROOT};
+use crate::git::diff::{
+    commit_parents, format_patch, get_diff, Diff, DiffOptions, DiffTarget, IgnoreWhitespace, MergeDiff, MAX_DIFF_CONTEXT,
+};
+use crate::git::merge::merge_bases;
+use crate::git::pool::run_blocking;
+use crate::handlers::api::{find_readable_repo, require_pull_requests, ApiResponse};
+use crate::models::db::Database;
+use crate::models::pull_request::PullRequest;
+
+/// Дифф одного коммита
+#[derive(Serialize)]
+struct CommitDetails {
+    sha: String,
+    parents: Vec<String>,
+    /// Как показан коммит слияния; для обычных коммитов отсутствует
+    #[serde(skip_serializing_if = "Option::is_none")]
+    against: Option<&'static str>,
+    #[serde(flatten)]
+    diff: Diff,
+}
+
+/// Параметры просмотра коммита
+#[derive(Deserialize)]
+pub struct CommitDiffQuery {
+    /// Для коммитов слияния: `first-parent` (по умолчанию) или `combined`
+    against: Option<String>,
+    /// `none` (по умолчанию), `change` или `all`
+    ignore_whitespace: Option<String>,
+    /// Число строк контекста, по умолчанию 3
+    context: Option<u32>,
+}
+
+/// Параметры диффа между ревизиями
+#[derive(Deserialize)]
+pub struct DiffQuery {
+    /// `none` (по умолчанию), `change` или `all`
+    ignore_whitespace: Option<String>,
+    /// Число строк контекста, по умолчанию 3
+    context: Option<u32>,
+}
+
+/// Дифф между двумя ревизиями
+#[derive(Serialize)]
+struct CompareDetails {
+    base: String,
+    head: String,
+    #[serde(flatten)]
+    diff: Diff,
+}
+
+/// Получение изменений, внесённых коммитом
+///
+/// Коммит слияния по умолчанию показывается относительно первого родителя;
+/// `?against=combined` возвращает комбинированный дифф (`git diff-tree --cc`)
+/// с файлами, в которых слияние что-то меняло само.
+pub async fn get_commit_diff(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    query: web::Query<CommitDiffQuery>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let (repo_name, rev) = path.into_inner();
+
+    let mode = match query.against.as_deref() {
+        None => MergeDiff::FirstParent,
+        Some(value) => match MergeDiff::parse(value) {
+            Some(mode) => mode,
+            None => {
+                return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                    success: false,
+                    message: Some("against must be first-parent or combined".to_string()),
+                    data: None,
+                }));
+            }
+        },
+    };
+
+    let options = match diff_options(query.ignore_whitespace.as_deref(), query.context) {
+        Ok(options) => options,
+        Err(response) => return Ok(response),
+    };
+
+    let repo = match find_readable_repo(&req, &db, &repo_name) {
+        Ok((repo, _)) => repo,
+        Err(response) => return Ok(response),
+    };
+
+    let repo_path = match resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()) {
+        Ok(path) => path,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    let sha = match resolve_commit(&repo_path, &rev) {
+        Ok(sha) => sha,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    let parents = match commit_parents(&repo_path, &sha) {
+        Ok(parents) => parents,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    let is_merge = parents.len() > 1;
+    let target = if is_merge {
+        DiffTarget::Merge { sha: &sha, mode }
+    } else {
+        DiffTarget::Commit(&sha)
+    };
+
+    match get_diff(&repo_path, &target, &options, CONFIG.max_diff_bytes, CONFIG.max_diff_files) {
+        Ok(diff) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(CommitDetails {
+                sha,
+                parents,
+                against: is_merge.then(|| mode.as_str()),
+                diff,
+            }),
+        })),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+/// Получение коммита в виде патча для `git am`
+pub async fn get_commit_patch(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let (repo_name, rev) = path.into_inner();
+
+    let repo = match find_readable_repo(&req, &db, &repo_name) {
+        Ok((repo, _)) => repo,
+        Err(response) => return Ok(response),
+    };
+
+    let repo_path = match resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()) {
+        Ok(path) => path,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    let sha = match resolve_commit(&repo_path, &rev) {
+        Ok(sha) => sha,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    Ok(patch_response(format_patch(&repo_path, &DiffTarget::Commit(&sha))))
+}
+
+/// Сравнение двух ревизий в формате `base...head`
+pub async fn compare(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    query: web::Query<DiffQuery>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let (repo_name, spec) = path.into_inner();
+
+    let options = match diff_options(query.ignore_whitespace.as_deref(), query.context) {
+        Ok(options) => options,
+        Err(response) => return Ok(response),
+    };
+
+    let repo = match find_readable_repo(&req, &db, &repo_name) {
+        Ok((repo, _)) => repo,
+        Err(response) => return Ok(response),
+    };
+
+    let (base, head) = match spec.split_once("...") {
+        Some(parts) => parts,
+        None => {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Expected comparison in the form base...head".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    Ok(range_diff_response(&repo.full_name(), base, head, &options))
+}
+
+/// Параметры поиска общего предка
+#[derive(Deserialize)]
+pub struct MergeBaseQuery {
+    /// Первая ревизия
+    pub a: String,
+    /// Вторая ревизия
+    pub b: String,
+    /// Вернуть всех лучших общих предков (`git merge-base --all`)
+    pub all: Option<bool>,
+}
+
+/// Общий предок двух ревизий
+#[derive(Serialize)]
+struct MergeBase {
+    merge_base: String,
+    /// Все лучшие общие предки; только при `all=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    merge_bases: Option<Vec<String>>,
+}
+
+/// Общий предок двух ревизий (`git merge-base`)
+///
+/// Обе ревизии сначала разрешаются в коммиты: несуществующая ревизия - 404
+/// с её именем. Если у ревизий нет общей истории, ответ тоже 404.
+pub async fn merge_base(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<MergeBaseQuery>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let repo = match find_readable_repo(&req, &db, &repo_name) {
+        Ok((repo, _)) => repo,
+        Err(response) => return Ok(response),
+    };
+
+    let repo_path = match resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()) {
+        Ok(path) => path,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    let MergeBaseQuery { a, b, all } = query.into_inner();
+    let all = all.unwrap_or(false);
+    let result = run_blocking(move || -> Result<Vec<String>, AppError> {
+        let a = resolve_commit(&repo_path, &a)?;
+        let b = resolve_commit(&repo_path, &b)?;
+        merge_bases(&repo_path, &a, &b, all)
+    }).await;
+
+    let bases = match result {
+        Ok(Ok(bases)) => bases,
+        Ok(Err(e)) | Err(e) => return Ok(e.error_response()),
+    };
+
+    match bases.first().cloned() {
+        Some(first) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(MergeBase {
+                merge_base: first,
+                merge_bases: all.then_some(bases),
+            }),
+        })),
+        None => Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            message: Some("Refs do not share history".to_string()),
+            data: None,
+        })),
+    }
+}
+
+/// Получение изменений пул-реквеста относительно целевой ветки
+pub async fn get_pull_request_diff(
+    req: HttpRequest,
+    path: web::Path<(String, i64)>,
+    query: web::Query<DiffQuery>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let (repo_name, pr_id) = path.into_inner();
+
+    let options = match diff_options(query.ignore_whitespace.as_deref(), query.context) {
+        Ok(options) => options,
+        Err(response) => return Ok(response),
+    };
+
+    let repo = match find_readable_repo(&req, &db, &repo_name) {
+        Ok((repo, _)) => repo,
+        Err(response) => return Ok(response),
+    };
+
+    if let Err(response) = require_pull_requests(&repo, &db) {
+        return Ok(response);
+    }
+
+    match PullRequest::find_by_id(pr_id, db.get_connection()) {
+        Ok(Some(pr)) if Some(pr.repository_id) == repo.id => {
+            Ok(range_diff_response(&repo.full_name(), &pr.target_branch, &pr.source_branch, &options))
+        },
+        Ok(_) => {
+            Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Pull request not found".to_string()),
+                data: None,
+            }))
+        },
+        Err(e) => {
+            error!("Database error: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
+
+/// Получение коммитов пул-реквеста в виде серии патчей для `git am`
+pub async fn get_pull_request_patch(
+    req: HttpRequest,
+    path: web::Path<(String, i64)>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let (repo_name, pr_id) = path.into_inner();
+
+    let repo = match find_readable_repo(&req, &db, &repo_name) {
+        Ok((repo, _)) => repo,
+        Err(response) => return Ok(response),
+    };
+
+    if let Err(response) = require_pull_requests(&repo, &db) {
+        return Ok(response);
+    }
+
+    let pr = match PullRequest::find_by_id(pr_id, db.get_connection()) {
+        Ok(Some(pr)) if Some(pr.repository_id) == repo.id => pr,
+        Ok(_) => {
+            return Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Pull request not found".to_string()),
+                data: None,
+            }));
+        },
+        Err(e) => {
+            error!("Database error: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    let repo_path = match resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()) {
+        Ok(path) => path,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    let (base, head) = match (resolve_commit(&repo_path, &pr.target_branch), resolve_commit(&repo_path, &pr.source_branch)) {
+        (Ok(base), Ok(head)) => (base, head),
+        (Err(e), _) | (_, Err(e)) => return Ok(e.error_response()),
+    };
+
+    Ok(patch_response(format_patch(&repo_path, &DiffTarget::Range { base: &base, head: &head })))
+}
+
+/// Отдаёт патч как `text/plain`
+fn patch_response(patch: Result<Vec<u8>, AppError>) -> HttpResponse {
+    match patch {
+        Ok(patch) => HttpResponse::Ok()
+            .content_type("text/plain; charset=utf-8")
+            .body(patch),
+        Err(e) => e.error_response(),
+    }
+}
+
+/// Проверяет параметры `ignore_whitespace` и `context`
+fn diff_options(ignore_whitespace: Option<&str>, context: Option<u32>) -> Result<DiffOptions, HttpResponse> {
+    let mut options = DiffOptions::default();
+
+    if let Some(value) = ignore_whitespace {
+        match IgnoreWhitespace::parse(value) {
+            Some(mode) => options.ignore_whitespace = mode,
+            None => {
+                return Err(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                    success: false,
+                    message: Some("ignore_whitespace must be none, change or all".to_string()),
+                    data: None,
+                }));
+            }
+        }
+    }
+
+    if let Some(context) = context {
+        if context > MAX_DIFF_CONTEXT {
+            return Err(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: Some(format!("context must be between 0 and {}", MAX_DIFF_CONTEXT)),
+                data: None,
+            }));
+        }
+        options.context = context;
+    }
+
+    Ok(options)
+}
+
+/// Строит ответ с диффом между двумя ревизиями
+fn range_diff_response(full_name: &str, base: &str, head: &str, options: &DiffOptions) -> HttpResponse {
+    let repo_path = match resolve_repo(Path::new(REPOS_ROOT), full_name) {
+        Ok(path) => path,
+        Err(e) => return e.error_response(),
+    };
+
+    let (base, head) = match (resolve_commit(&repo_path, base), resolve_commit(&repo_path, head)) {
+        (Ok(base), Ok(head)) => (base, head),
+        (Err(e), _) | (_, Err(e)) => return e.error_response(),
+    };
+
+    let target = DiffTarget::Range { base: &base, head: &head };
+    match get_diff(&repo_path, &target, options, CONFIG.max_diff_bytes, CONFIG.max_diff_files) {
+        Ok(diff) => HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(CompareDetails { base, head, diff }),
+        }),
+        Err(e) => e.error_response(),
+    }
+}
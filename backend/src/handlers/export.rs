@@ -0,0 +1,222 @@
+use actix_web::body::{BodySize, MessageBody};
+use actix_web::web::Bytes;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use log::error;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::error::Error as StdError;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use crate::handlers::api::{check_auth, external_base_url, ApiResponse};
+use crate::models::db::Database;
+use crate::models::notification::Notification;
+use crate::models::pull_request::{PullRequest, PullRequestComment};
+use crate::models::repository::Repository;
+use crate::timestamps::to_rfc3339;
+
+/// Сколько записей читается из базы данных за один шаг выгрузки
+const EXPORT_BATCH: i64 = 100;
+
+/// Разделы выгрузки в порядке их следования в документе
+#[derive(Clone, Copy)]
+enum Section {
+    Repositories,
+    PullRequests,
+    Comments,
+    Notifications,
+}
+
+impl Section {
+    /// Ключ раздела в JSON документе
+    fn key(self) -> &'static str {
+        match self {
+            Section::Repositories => "repositories",
+            Section::PullRequests => "pull_requests",
+            Section::Comments => "comments",
+            Section::Notifications => "notifications",
+        }
+    }
+
+    /// Следующий раздел или None, если этот последний
+    fn next(self) -> Option<Section> {
+        match self {
+            Section::Repositories => Some(Section::PullRequests),
+            Section::PullRequests => Some(Section::Comments),
+            Section::Comments => Some(Section::Notifications),
+            Section::Notifications => None,
+        }
+    }
+}
+
+/// Репозиторий в выгрузке: данные git не вкладываются, вместо них адрес клонирования
+#[derive(Serialize)]
+struct ExportedRepository {
+    #[serde(flatten)]
+    repo: Repository,
+    clone_url: String,
+}
+
+/// Тело ответа с выгрузкой, которое собирается по мере отправки
+///
+/// Записи читаются из базы данных порциями по `EXPORT_BATCH` по возрастанию ID,
+/// и соединение захватывается только на время чтения одной порции, поэтому ни
+/// сервер, ни база данных не держат в памяти всю выгрузку большого аккаунта.
+struct ExportBody {
+    conn: Arc<Mutex<Connection>>,
+    user_id: i64,
+    /// Адрес сервера для ссылок на клонирование
+    base_url: String,
+    /// Начало документа с профилем, пока оно не отправлено
+    header: Option<Vec<u8>>,
+    /// Текущий раздел; None, когда документ закончен
+    section: Option<Section>,
+    /// ID последней отправленной записи текущего раздела
+    after_id: i64,
+    /// В текущем разделе ещё не было записей (перед записью не нужна запятая)
+    first: bool,
+}
+
+impl ExportBody {
+    /// Следующий кусок документа или None, если документ отправлен целиком
+    fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, Box<dyn StdError>> {
+        if let Some(header) = self.header.take() {
+            return Ok(Some(header));
+        }
+
+        let section = match self.section {
+            Some(section) => section,
+            None => return Ok(None),
+        };
+
+        let mut chunk = Vec::new();
+        let count = match section {
+            Section::Repositories => {
+                let repos = Repository::find_by_owner_after(self.user_id, self.after_id, EXPORT_BATCH, self.conn.clone())?;
+                let count = repos.len();
+                for repo in repos {
+                    let id = repo.id.unwrap_or_default();
+                    let clone_url = format!("{}/git/{}", self.base_url, repo.full_name());
+                    self.write_item(&mut chunk, id, &ExportedRepository { repo, clone_url })?;
+                }
+                count
+            }
+            Section::PullRequests => {
+                let prs = PullRequest::find_by_author_after(self.user_id, self.after_id, EXPORT_BATCH, self.conn.clone())?;
+                for pr in &prs {
+                    self.write_item(&mut chunk, pr.pull_request.id.unwrap_or_default(), pr)?;
+                }
+                prs.len()
+            }
+            Section::Comments => {
+                let comments = PullRequestComment::find_by_author_after(self.user_id, self.after_id, EXPORT_BATCH, self.conn.clone())?;
+                for comment in &comments {
+                    self.write_item(&mut chunk, comment.id.unwrap_or_default(), comment)?;
+                }
+                comments.len()
+            }
+            Section::Notifications => {
+                let notifications = Notification::find_by_user_after(self.user_id, self.after_id, EXPORT_BATCH, self.conn.clone())?;
+                for notification in &notifications {
+                    self.write_item(&mut chunk, notification.id.unwrap_or_default(), notification)?;
+                }
+                notifications.len()
+            }
+        };
+
+        // Неполная порция - раздел закончился
+        if (count as i64) < EXPORT_BATCH {
+            chunk.push(b']');
+            self.section = section.next();
+            self.after_id = 0;
+            self.first = true;
+            match self.section {
+                Some(next) => chunk.extend_from_slice(format!(",\"{}\":[", next.key()).as_bytes()),
+                None => chunk.push(b'}'),
+            }
+        }
+
+        Ok(Some(chunk))
+    }
+
+    /// Дописывает запись текущего раздела в кусок документа
+    fn write_item<T: Serialize>(&mut self, chunk: &mut Vec<u8>, id: i64, item: &T) -> Result<(), Box<dyn StdError>> {
+        if !self.first {
+            chunk.push(b',');
+        }
+        serde_json::to_writer(&mut *chunk, item)?;
+        self.first = false;
+        self.after_id = id;
+        Ok(())
+    }
+}
+
+impl MessageBody for ExportBody {
+    type Error = Box<dyn StdError>;
+
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        // Ошибка посреди выгрузки обрывает соединение: клиент получит неполный JSON,
+        // а не документ, который выглядит целым
+        match self.get_mut().next_chunk() {
+            Ok(Some(chunk)) => Poll::Ready(Some(Ok(Bytes::from(chunk)))),
+            Ok(None) => Poll::Ready(None),
+            Err(e) => {
+                error!("User data export failed: {}", e);
+                Poll::Ready(Some(Err(e)))
+            }
+        }
+    }
+}
+
+/// Выгрузка всех данных текущего пользователя одним JSON документом
+///
+/// В документ входят профиль, свои репозитории (с адресом клонирования вместо
+/// содержимого), свои пул-реквесты и комментарии и уведомления. Пул-реквесты и
+/// комментарии в чужих приватных репозиториях не выгружаются, как и чужие
+/// комментарии к пул-реквестам пользователя. Документ отправляется по частям.
+pub async fn export_user_data(req: HttpRequest, db: web::Data<Database>) -> Result<HttpResponse> {
+    let user = match check_auth(&req, &db) {
+        Some(user) => user,
+        None => {
+            return Ok(HttpResponse::Unauthorized().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Unauthorized".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    let mut header = format!("{{\"exported_at\":\"{}\",\"profile\":", to_rfc3339(&Utc::now())).into_bytes();
+    if let Err(e) = serde_json::to_writer(&mut header, &user.to_view(true)) {
+        error!("Failed to serialize user profile: {}", e);
+        return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            message: Some("Failed to export user data".to_string()),
+            data: None,
+        }));
+    }
+    header.extend_from_slice(format!(",\"{}\":[", Section::Repositories.key()).as_bytes());
+
+    let body = ExportBody {
+        conn: db.get_connection(),
+        user_id: user.id.unwrap_or_default(),
+        base_url: external_base_url(&req),
+        header: Some(header),
+        section: Some(Section::Repositories),
+        after_id: 0,
+        first: true,
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/json")
+        .insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"{}-export.json\"", user.username),
+        ))
+        .body(body))
+}
@@ -0,0 +1,181 @@
+use actix_web::{web, HttpResponse, HttpRequest, Result, ResponseError};
+use serde::Deserialize;
+use log::error;
+use crate::clock::SystemClock;
+use crate::error::AppError;
+use crate::git::pool::run_blocking;
+use crate::git::validate_repo_name;
+use crate::handlers::api::{check_auth, check_creation_rate, find_readable_repo, name_conflict, over_quota, page_bounds, ApiResponse};
+use crate::models::db::Database;
+use crate::models::repository::Repository;
+use crate::webhooks;
+
+/// Запрос на создание форка
+#[derive(Deserialize)]
+pub struct CreateForkRequest {
+    /// Имя нового репозитория; по умолчанию как у источника
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// По умолчанию форк публичен, если публичен источник
+    pub is_public: Option<bool>,
+}
+
+/// Параметры постраничного вывода форков
+#[derive(Deserialize)]
+pub struct ForksQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+/// Список форков репозитория, от старых к новым
+///
+/// Приватные форки видны только их владельцам.
+pub async fn list_forks(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<ForksQuery>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let (repo, user) = match find_readable_repo(&req, &db, &repo_name) {
+        Ok(found) => found,
+        Err(response) => return Ok(response),
+    };
+
+    let viewer_id = user.and_then(|user| user.id);
+    let (limit, offset) = page_bounds(query.page, query.per_page);
+    match Repository::find_forks(repo.id.unwrap(), viewer_id, limit, offset, db.get_connection()) {
+        Ok(forks) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(forks),
+        })),
+        Err(e) => {
+            error!("Failed to fetch forks: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to fetch forks".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
+
+/// Создаёт форк репозитория, доступного текущему пользователю
+pub async fn create_fork(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<CreateForkRequest>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let user = match check_auth(&req, &db) {
+        Some(user) => user,
+        None => {
+            return Ok(HttpResponse::Unauthorized().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Unauthorized".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    if let Err(response) = check_creation_rate(&user, &db, &SystemClock) {
+        return Ok(response);
+    }
+
+    let (source, _) = match find_readable_repo(&req, &db, &repo_name) {
+        Ok(found) => found,
+        Err(response) => return Ok(response),
+    };
+
+    let body = body.into_inner();
+    let name = body.name.unwrap_or_else(|| source.name.clone());
+    if let Err(e) = validate_repo_name(&name) {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: Some(e.to_string()),
+            data: None,
+        }));
+    }
+
+    match over_quota(&user, &db).await {
+        Ok(false) => {}
+        Ok(true) => {
+            return Ok(HttpResponse::Forbidden().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Storage quota exceeded".to_string()),
+                data: None,
+            }));
+        }
+        Err(AppError::Database(e)) => {
+            error!("Failed to check storage quota: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }));
+        }
+        Err(e) => return Ok(e.error_response()),
+    }
+
+    let mut fork = Repository {
+        id: None,
+        name,
+        owner_id: user.id.unwrap(),
+        owner: user.username.clone(),
+        description: body.description.or_else(|| source.description.clone()),
+        is_public: body.is_public.unwrap_or(source.is_public),
+        created_at: None,
+        forked_from: source.id,
+    };
+
+    match name_conflict(&fork, db.get_connection()) {
+        Ok(None) => {}
+        Ok(Some(message)) => {
+            return Ok(HttpResponse::Conflict().json(ApiResponse::<()> {
+                success: false,
+                message: Some(message),
+                data: None,
+            }));
+        }
+        Err(e) => {
+            error!("Database error: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }));
+        }
+    }
+
+    let job_db = db.clone();
+    let created = run_blocking(move || {
+        fork.create_fork(&source, job_db.get_connection()).map(|id| {
+            fork.id = Some(id);
+            fork
+        })
+    }).await;
+
+    match created {
+        Ok(Ok(fork)) => {
+            webhooks::fire_repository_event("repository_created", &fork, &user, &db);
+            Ok(HttpResponse::Created().json(ApiResponse {
+                success: true,
+                message: Some("Repository forked successfully".to_string()),
+                data: Some(fork),
+            }))
+        },
+        Ok(Err(e)) => {
+            error!("Failed to fork repository: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to fork repository".to_string()),
+                data: None,
+            }))
+        }
+        Err(e) => Ok(e.error_response()),
+    }
+}
@@ -0,0 +1,1673 @@
+use actix_files::NamedFile;
+use actix_web::{http::header, http::header::HttpDate, http::StatusCode, web, HttpMessage, HttpResponse, HttpResponseBuilder, HttpRequest, ResponseError};
+use std::process::{Command, Output, Stdio};
+use std::path::{Path, PathBuf};
+use std::io::Write;
+use log::{debug, error};
+use serde::Deserialize;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use crate::auth::challenge;
+use crate::charset;
+use crate::config::CONFIG;
+use crate::git::{dir_size, full_repo_name, git_command, info_packs, lock_repo, resolve_commit, resolve_repo, run_git, GitService, REPOS_ROOT};
+use crate::git::advertise::advertisement;
+use crate::git::bundle::{bundle_path, bundle_uri_enabled};
+use crate::git::errors::git_failure;
+use crate::git::fetch::{check_upload_pack_body, FetchRequest};
+use crate::git::log::last_modified;
+use crate::error::AppError;
+use crate::git::pktline::{read_pkt, write_flush, write_pkt, Packet};
+use crate::git::pool::run_blocking;
+use crate::git::replica::{resolve_read_repo, RepoRoot};
+use crate::git::refs::{diff_refs, list_refs, push_stats, PushStats, RefChange, RefUpdate};
+use crate::git::hooks::pre_receive_hooks_dir;
+use crate::git::secrets::{scan_update, ScanMode, SCAN_MODE};
+use crate::git::throttle::try_acquire_upload_pack;
+use crate::git::version::GitCapabilities;
+use crate::handlers::api;
+use crate::i18n::localize_message;
+use crate::models::db::Database;
+use crate::models::store::Store;
+use crate::motd;
+use crate::models::notification::Notification;
+use crate::models::pull_request::PullRequest;
+use crate::models::push::Push;
+use crate::models::repository::Repository;
+use crate::models::secret_alert::SecretAlert;
+use crate::models::user::User;
+use crate::trace::{trace_packets, Direction};
+use crate::webhooks;
+
+/// Извлекает значение заголовка `Git-Protocol` (например, `version=2`)
+///
+/// Значение передаётся git через переменную окружения GIT_PROTOCOL, поэтому
+/// допускаются только символы, встречающиеся в параметрах протокола.
+fn git_protocol(req: &HttpRequest) -> Option<String> {
+    let value = req.headers().get("Git-Protocol")?.to_str().ok()?;
+
+    let valid = !value.is_empty()
+        && value.len() <= 256
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || "=:._-".contains(c));
+
+    valid.then(|| value.to_string())
+}
+
+/// Номер side-band канала для фатальных ошибок
+const SIDEBAND_ERROR: u8 = 3;
+
+/// Ошибка этапа обнаружения ссылок (`info/refs`) для git клиента
+///
+/// Текст ответа `text/plain` git выводит построчно как `remote: ...` перед
+/// своим сообщением об ошибке, поэтому причина видна в терминале.
+fn discovery_error(status: StatusCode, message: &str) -> HttpResponse {
+    HttpResponse::build(status)
+        .content_type("text/plain; charset=utf-8")
+        .body(format!("{}\n", message))
+}
+
+/// Ошибка запроса upload-pack или receive-pack для git клиента
+///
+/// Тело ответа с кодом ошибки на эти запросы git не показывает (только
+/// `RPC failed; HTTP 403`), поэтому причина отдаётся со статусом 200.
+/// upload-pack получает pkt-line пакет `ERR` (`remote error: ...`). Клиент
+/// receive-pack с side-band читает ответ через демультиплексор, который пакет
+/// `ERR` не понимает, поэтому сообщение уходит в канал ошибок 3 (`remote: ...`).
+fn rpc_error(service: GitService, message: &str, sideband: bool) -> HttpResponse {
+    let message = localize_message(message);
+    let mut body = Vec::new();
+    let encoded = if sideband {
+        let mut payload = vec![SIDEBAND_ERROR];
+        payload.extend_from_slice(format!("{}\n", message).as_bytes());
+        write_pkt(&mut body, &payload).and_then(|_| write_flush(&mut body))
+    } else {
+        write_pkt(&mut body, format!("ERR {}\n", message).as_bytes())
+    };
+    if let Err(e) = encoded {
+        error!("Failed to encode pkt-line: {}", e);
+        return HttpResponse::InternalServerError().finish();
+    }
+
+    HttpResponse::Ok()
+        .content_type(format!("application/x-{}-result", service.name()))
+        .body(body)
+}
+
+/// Ошибка приложения на этапе обнаружения ссылок; ошибки сервера остаются JSON ответами
+fn discovery_app_error(e: AppError) -> HttpResponse {
+    match e.status_code() {
+        status if status.is_client_error() => discovery_error(status, &e.client_message()),
+        _ => e.error_response(),
+    }
+}
+
+/// Ошибка приложения в запросе upload-pack или receive-pack; ошибки сервера
+/// сохраняют свой HTTP статус
+fn rpc_app_error(service: GitService, e: AppError, sideband: bool) -> HttpResponse {
+    match e.status_code() {
+        status if status.is_client_error() => rpc_error(service, &e.client_message(), sideband),
+        _ => e.error_response(),
+    }
+}
+
+/// Репозиторий, к которому обращается git клиент
+struct ServedRepo {
+    /// Полное имя `{owner}/{name}`: путь на диске, блокировки и ключи кэшей
+    full_name: String,
+    /// Запись в базе данных; каталог без записи доступен только по `/git/{owner}/{repo_name}`
+    record: Option<Repository>,
+}
+
+/// Находит репозиторий из маршрута git
+///
+/// `/git/{owner}/{repo_name}` задаёт репозиторий однозначно. В маршруте без
+/// владельца (`/git/{repo_name}`) репозиторий выбирается, как в `api::lookup_repo`.
+fn find_served_repo(req: &HttpRequest, user: Option<&User>) -> Result<ServedRepo, AppError> {
+    let db = req.app_data::<web::Data<Database>>().unwrap();
+    let repo_name = req.match_info().get("repo_name").unwrap();
+
+    match api::lookup_repo(req, db, repo_name, user) {
+        Ok(Some(repo)) => Ok(ServedRepo { full_name: repo.full_name(), record: Some(repo) }),
+        Ok(None) => match api::requested_owner(req) {
+            Some(owner) => Ok(ServedRepo { full_name: full_repo_name(&owner, repo_name), record: None }),
+            None => Err(AppError::RepoNotFound(repo_name.to_string())),
+        },
+        Err(AppError::Database(e)) => {
+            error!("Failed to look up repository {}: {}", repo_name, e);
+            Err(AppError::Database(e))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Находит bare репозиторий, с которым работает git клиент
+///
+/// Чтение (`UploadPack`, к нему относятся и объекты, и файлы) может идти с реплики,
+/// запись - только в основной каталог. Если каталога нет, а запись в базе данных
+/// есть, возвращается `StorageMissing`, чтобы пропавшее хранилище не выглядело как
+/// опечатка в имени репозитория.
+fn resolve_served_repo(served: &ServedRepo, service: GitService) -> Result<(PathBuf, RepoRoot), AppError> {
+    let resolved = match service {
+        GitService::UploadPack => resolve_read_repo(&served.full_name),
+        GitService::ReceivePack => resolve_repo(Path::new(REPOS_ROOT), &served.full_name).map(|path| (path, RepoRoot::Primary)),
+    };
+
+    match resolved {
+        Err(AppError::RepoNotFound(name)) if served.record.is_some() => {
+            error!("Repository {} exists in the database but not on disk", served.full_name);
+            Err(AppError::StorageMissing(name))
+        }
+        result => result,
+    }
+}
+
+/// Проверяет, что пользователь может работать с репозиторием через сервис git
+///
+/// Видимость читается из базы данных на каждый запрос, поэтому репозиторий,
+/// ставший приватным, сразу перестаёт отдаваться остальным пользователям.
+/// Приватный репозиторий без доступа выглядит как несуществующий, и для
+/// push тоже: иначе по ответу можно было бы узнать, что он существует.
+/// Push разрешён только владельцу; остальные, кто видит репозиторий, получают 403.
+/// Каталог без записи в базе данных принадлежит пользователю из маршрута `/git/{owner}/...`.
+///
+/// # Параметры
+///
+/// * `served` - Репозиторий из маршрута
+/// * `user` - Аутентифицированный пользователь
+/// * `service` - Запрошенный сервис: `UploadPack` читает, `ReceivePack` пишет
+///
+/// # Возвращает
+///
+/// * `Result<(), AppError>` - `RepoNotFound` без доступа на чтение, `Forbidden` без доступа на запись
+fn check_access(served: &ServedRepo, user: &User, service: GitService) -> Result<(), AppError> {
+    let is_owner = match &served.record {
+        Some(repo) if !api::can_access(repo, Some(user)) => return Err(AppError::RepoNotFound(repo.name.clone())),
+        Some(repo) => user.id == Some(repo.owner_id),
+        None => served.full_name.split_once('/').is_some_and(|(owner, _)| owner == user.username),
+    };
+
+    match service {
+        GitService::ReceivePack if !is_owner => Err(AppError::Forbidden(format!(
+            "You do not have write access to {}",
+            served.full_name
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Проверяет доступ на чтение для dumb протокола и файлов (`objects/*`, `file/*`)
+///
+/// В отличие от smart протокола, публичные репозитории здесь доступны без
+/// учётных данных. Анонимный запрос к приватному или несуществующему
+/// репозиторию получает 401, чтобы git запросил пароль и ответ не выдавал,
+/// существует ли репозиторий. Пользователь без доступа видит 404, как в
+/// `check_access`.
+///
+/// # Возвращает
+///
+/// * `Result<ServedRepo, HttpResponse>` - Репозиторий или готовый ответ 401/404/409/500
+fn authorize_dumb_read(req: &HttpRequest) -> Result<ServedRepo, HttpResponse> {
+    let db = req.app_data::<web::Data<Database>>().unwrap();
+    let user = api::check_auth(req, db);
+    let repo_name = req.match_info().get("repo_name").unwrap();
+
+    match find_served_repo(req, user.as_ref()) {
+        Ok(served) if served.record.as_ref().is_some_and(|repo| api::can_access(repo, user.as_ref())) => Ok(served),
+        Ok(_) | Err(AppError::RepoNotFound(_)) if user.is_none() => Err(authentication_required()),
+        Ok(_) | Err(AppError::RepoNotFound(_)) => Err(AppError::RepoNotFound(repo_name.to_string()).error_response()),
+        Err(e) => Err(e.error_response()),
+    }
+}
+
+/// Ответ 401 с запросом учётных данных
+///
+/// Статус сохраняется и для POST запросов: по нему git запрашивает пароль.
+fn authentication_required() -> HttpResponse {
+    HttpResponse::Unauthorized()
+        .append_header(("WWW-Authenticate", challenge()))
+        .content_type("text/plain; charset=utf-8")
+        .body("Authentication required\n")
+}
+
+/// Сообщение о сервисе, выключенном в GIT_HTTP_SERVICES
+fn service_disabled_message(service: GitService) -> &'static str {
+    match service {
+        GitService::UploadPack => "Fetching is disabled on this server",
+        GitService::ReceivePack => "Push is disabled on this server",
+    }
+}
+
+/// Просит ли клиент receive-pack передавать сообщения по side-band
+///
+/// Возможности перечислены после NUL в первой команде push. Если тела нет
+/// (например, оно слишком большое), считаем, что просит: git делает так всегда,
+/// когда сервер объявляет `side-band-64k`.
+fn requests_sideband(body: Option<&[u8]>) -> bool {
+    let first = body.and_then(|mut body| read_pkt(&mut body).ok().flatten());
+    match first {
+        Some(Packet::Data(command)) => match command.iter().position(|&b| b == 0) {
+            Some(nul) => String::from_utf8_lossy(&command[nul + 1..])
+                .split_whitespace()
+                .any(|capability| capability == "side-band-64k" || capability == "side-band"),
+            None => false,
+        },
+        _ => true,
+    }
+}
+
+/// Тело POST запроса upload-pack или receive-pack либо готовый ответ, если его не удалось прочитать
+///
+/// Слишком большое тело (GIT_HTTP_UPLOAD_PACK_MAX_BODY_BYTES для upload-pack,
+/// GIT_HTTP_MAX_BODY_BYTES для receive-pack) объясняется сообщением об ошибке для git.
+fn rpc_body(service: GitService, body: Result<web::Bytes, actix_web::Error>) -> Result<web::Bytes, HttpResponse> {
+    match body {
+        Ok(body) => Ok(body),
+        Err(e) if e.as_response_error().status_code() == StatusCode::PAYLOAD_TOO_LARGE => {
+            debug!("{} request body exceeds {} bytes", service.name(), service.max_body_bytes());
+            let message = format!("Request is too large: the server accepts at most {} bytes", service.max_body_bytes());
+            Err(rpc_error(service, &message, service == GitService::ReceivePack && requests_sideband(None)))
+        }
+        Err(e) => Err(e.error_response()),
+    }
+}
+
+/// Обработчик для /info/refs - первый этап Git протокола
+/// Когда клиент выполняет git clone/pull/push, он сначала запрашивает этот эндпоинт
+/// чтобы узнать, какие ссылки (refs) доступны на сервере и какие операции поддерживаются
+pub async fn handle_info_refs(req: HttpRequest) -> HttpResponse {
+    // Проверяем авторизацию
+    let user = match api::check_auth(&req, req.app_data::<web::Data<Database>>().unwrap()) {
+        Some(user) => user,
+        None => return authentication_required(),
+    };
+
+    let repo_name = req.match_info().get("repo_name").unwrap();
+    let service = req.query_string();
+
+    debug!("Handling info/refs for repo: {}, service: {}", repo_name, service);
+
+    // Извлекаем имя сервиса (git-upload-pack или git-receive-pack)
+    let service = match service.strip_prefix("service=") {
+        Some(s) => s,
+        None => return discovery_error(StatusCode::BAD_REQUEST, "Only the smart HTTP protocol is supported")
+    };
+
+    // Неизвестный или выключенный сервис не должен превращаться в другой
+    let service = match GitService::from_name(service) {
+        Some(service) if service.is_enabled() => service,
+        Some(service) => return discovery_error(StatusCode::FORBIDDEN, service_disabled_message(service)),
+        None => return discovery_error(StatusCode::FORBIDDEN, "Unknown git service"),
+    };
+
+    let served = match find_served_repo(&req, Some(&user)) {
+        Ok(served) => served,
+        Err(e) => return discovery_app_error(e),
+    };
+    if let Err(e) = check_access(&served, &user, service) {
+        return discovery_app_error(e);
+    }
+
+    let (repo_path, root) = match resolve_served_repo(&served, service) {
+        Ok(resolved) => resolved,
+        Err(e) => return discovery_app_error(e),
+    };
+
+    // Объявление зависит от ссылок, сервиса и версии протокола. Счётчик читается до
+    // запуска git: если ссылки изменятся в процессе, ETag окажется устаревшим, а не новым
+    let protocol = git_protocol(&req);
+    let db = req.app_data::<web::Data<Database>>().unwrap();
+    // Поколение описывает ссылки основного каталога, поэтому объявление с реплики
+    // не кэшируется и не получает ETag
+    let generation = match db.ref_generation(&served.full_name) {
+        Ok(generation) => generation.filter(|_| root == RepoRoot::Primary),
+        Err(e) => {
+            error!("Failed to read ref generation of {}: {}", served.full_name, e);
+            None
+        }
+    };
+    let etag = generation.map(|generation| format!(
+        "\"refs-{}-{}-{}\"",
+        generation,
+        service.name(),
+        protocol.as_deref().unwrap_or("v0")
+    ));
+
+    if let Some(etag) = &etag {
+        if api::etag_matches(&req, etag) {
+            return HttpResponse::NotModified()
+                .insert_header((header::ETAG, etag.as_str()))
+                .insert_header((header::CACHE_CONTROL, "no-cache"))
+                .finish();
+        }
+    }
+
+    // Запускаем git команду с флагом --advertise-refs для получения списка ссылок
+    let mut command = git_command();
+    command
+        .arg(service.command())
+        .arg("--advertise-refs")
+        .arg(&repo_path);
+
+    // Клиент, запросивший protocol v2, получит объявление возможностей v2
+    if let Some(protocol) = &protocol {
+        command.env("GIT_PROTOCOL", protocol);
+    }
+
+    // Одновременные одинаковые запросы (например, CI клонирует репозиторий из
+    // многих задач сразу) ждут один git процесс; результат кэшируется до изменения ссылок
+    let key = (served.full_name.clone(), service.name(), protocol.clone().unwrap_or_default());
+    let advertised = advertisement(key, generation, move || match command.output() {
+        Ok(output) if output.status.success() => Ok(output.stdout),
+        Ok(output) => Err(git_failure(service.command(), &output)),
+        Err(e) => {
+            error!("Failed to execute git command: {}", e);
+            Err(AppError::Git(e.to_string()))
+        }
+    }).await;
+    let advertised = match advertised {
+        Ok(advertised) => advertised,
+        Err(AppError::Git(_)) => return HttpResponse::InternalServerError().finish(),
+        Err(e) => return discovery_app_error(e),
+    };
+
+    // Формируем ответ в формате Smart HTTP Protocol: заголовок сервиса и flush-пакет
+    let mut response = Vec::new();
+    let service_header = format!("# service={}\n", service.name());
+    if let Err(e) = write_pkt(&mut response, service_header.as_bytes()).and_then(|_| write_flush(&mut response)) {
+        error!("Failed to encode service header: {}", e);
+        return HttpResponse::InternalServerError().finish();
+    }
+
+    // Добавляем вывод git-*-pack --advertise-refs
+    response.extend_from_slice(&advertised);
+
+    // Возвращаем результат; кэш обязан перепроверять объявление при каждом запросе
+    let mut builder = HttpResponse::Ok();
+    builder
+        .content_type(format!("application/x-{}-advertisement", service.name()))
+        .insert_header((header::CACHE_CONTROL, "no-cache"));
+    if let Some(etag) = etag {
+        builder.insert_header((header::ETAG, etag));
+    }
+    builder.body(response)
+}
+
+/// Обработчик для git-upload-pack - используется при git clone/fetch
+/// Клиент запрашивает определенные объекты, сервер их упаковывает и отправляет
+///
+/// Каждый раунд согласования (`have` -> `ACK`/`NAK`, затем `ready` или `done`)
+/// приходит отдельным POST: в режиме stateless-rpc клиент сам повторяет в теле
+/// `want` и накопленные `have`, а сервер не хранит состояние между запросами.
+/// Поэтому тело передаётся git без изменений (только снимается Content-Encoding),
+/// и ответы `ACK`/`ready` формирует сам git.
+pub async fn handle_upload_pack(req: HttpRequest, body: Result<web::Bytes, actix_web::Error>) -> HttpResponse {
+    let started = Instant::now();
+    let service = GitService::UploadPack;
+    if !service.is_enabled() {
+        return rpc_error(service, service_disabled_message(service), false);
+    }
+
+    // Проверяем авторизацию
+    let user = match api::check_auth(&req, req.app_data::<web::Data<Database>>().unwrap()) {
+        Some(user) => user,
+        None => return authentication_required(),
+    };
+
+    let body = match rpc_body(service, body) {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+
+    // Явный мусор отклоняется до запуска git; настоящий клиент такой запрос не пришлёт
+    if let Err(message) = check_upload_pack_body(&body) {
+        debug!("Rejecting malformed upload-pack request: {}", message);
+        return discovery_error(StatusCode::BAD_REQUEST, &message);
+    }
+
+    let served = match find_served_repo(&req, Some(&user)) {
+        Ok(served) => served,
+        Err(e) => return rpc_app_error(service, e, false),
+    };
+    if let Err(e) = check_access(&served, &user, service) {
+        return rpc_app_error(service, e, false);
+    }
+    let (repo_path, _) = match resolve_served_repo(&served, service) {
+        Ok(resolved) => resolved,
+        Err(e) => return rpc_app_error(service, e, false),
+    };
+    let repo_name = served.full_name.as_str();
+
+    debug!("Handling upload-pack for repo: {}", repo_name);
+
+    let request_id = uuid::Uuid::new_v4().to_string();
+    trace_packets(&request_id, repo_name, "upload-pack", Direction::Request, &body);
+
+    // Разрешение переносится в задачу пула и освобождается, когда git завершится,
+    // в том числе при ошибке или отключении клиента
+    let permit = match CONFIG.max_clones_per_repo {
+        Some(limit) => match try_acquire_upload_pack(repo_name, limit) {
+            Some(permit) => Some(permit),
+            None => {
+                debug!("Too many concurrent upload-packs for {}", repo_name);
+                return AppError::Busy(format!("Too many concurrent clones of {}, retry later", repo_name))
+                    .error_response();
+            }
+        },
+        None => None,
+    };
+
+    if let Some(max_bytes) = CONFIG.full_clone_max_bytes {
+        if FetchRequest::parse(&body).is_full_clone() {
+            let size_path = repo_path.clone();
+            match run_blocking(move || dir_size(&size_path)).await {
+                Ok(size) if size > max_bytes => {
+                    debug!("Refusing full clone of {} ({} bytes)", repo_name, size);
+                    let partial_clone = req.app_data::<web::Data<GitCapabilities>>().is_some_and(|git| git.filter);
+                    return rpc_error(service, &full_clone_refused_message(size, max_bytes, partial_clone), false);
+                }
+                Ok(_) => {}
+                Err(e) => return e.error_response(),
+            }
+        }
+    }
+
+    // Запускаем git-upload-pack в режиме stateless-rpc (для HTTP протокола)
+    let keepalive = format!("uploadpack.keepAlive={}", CONFIG.upload_pack_keepalive_secs);
+    let mut command = git_command();
+    command
+        .args(["-c", &keepalive])
+        .arg(GitService::UploadPack.command())
+        .arg("--stateless-rpc")  // Важно для HTTP протокола
+        .arg(&repo_path);
+
+    // Без GIT_PROTOCOL git ответит в v0 на команды v2 после согласования в info/refs
+    if let Some(protocol) = git_protocol(&req) {
+        command.env("GIT_PROTOCOL", protocol);
+    }
+
+    // Передаем запрос клиента в git-upload-pack
+    let result = run_blocking(move || {
+        let _permit = permit;
+        let git_started = Instant::now();
+        run_with_input(&mut command, &body).map(|output| (output, git_started.elapsed()))
+    }).await;
+    let (output, git_time) = match result {
+        Ok(Ok((output, git_time))) if output.status.success() => (output, git_time),
+        Ok(Ok((output, _))) => {
+            error!("git-upload-pack failed: {}", String::from_utf8_lossy(&output.stderr));
+            return HttpResponse::InternalServerError().finish();
+        }
+        Ok(Err(e)) => {
+            error!("Failed to run git-upload-pack: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+        Err(e) => return e.error_response(),
+    };
+
+    trace_packets(&request_id, repo_name, "upload-pack", Direction::Response, &output.stdout);
+
+    // Сообщение дня выводится клиенту как `remote: ...` в начале packfile
+    let body = motd::insert_into_upload_pack(&output.stdout).unwrap_or(output.stdout);
+
+    let mut response = HttpResponse::Ok();
+    response.content_type("application/x-git-upload-pack-result");
+    add_server_timing(&mut response, git_time, started.elapsed());
+    response.body(body)
+}
+
+/// Причина отказа в полном клоне слишком большого репозитория
+fn full_clone_refused_message(size: u64, max_bytes: u64, partial_clone: bool) -> String {
+    // partial clone предлагаем, только если upload-pack его поддерживает
+    let alternatives = if partial_clone {
+        "use a shallow clone (git clone --depth=1) or a partial clone (git clone --filter=blob:none)"
+    } else {
+        "use a shallow clone (git clone --depth=1)"
+    };
+    format!(
+        "full clones of this repository are disabled ({} bytes, limit {} bytes); {}",
+        size, max_bytes, alternatives
+    )
+}
+
+/// Ссылки, изменённые push'ем, со статистикой (None - посчитать не удалось)
+pub type PushedRefs = Vec<(RefUpdate, Option<PushStats>)>;
+
+/// Результат receive-pack, выполненного в пуле
+struct PushOutcome {
+    output: Output,
+    updates: PushedRefs,
+    /// Время работы самого git-receive-pack, без ожидания блокировки
+    git_time: Duration,
+}
+
+/// Обработчик для git-receive-pack - используется при git push
+/// Клиент отправляет новые объекты, сервер их принимает и обновляет ссылки
+pub async fn handle_receive_pack(req: HttpRequest, body: Result<web::Bytes, actix_web::Error>) -> HttpResponse {
+    let started = Instant::now();
+    let service = GitService::ReceivePack;
+    if !service.is_enabled() {
+        return rpc_error(service, service_disabled_message(service), requests_sideband(None));
+    }
+
+    // Проверяем авторизацию
+    let pusher = match api::check_auth(&req, req.app_data::<web::Data<Database>>().unwrap()) {
+        Some(user) => user,
+        None => return authentication_required(),
+    };
+
+    let body = match rpc_body(service, body) {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let sideband = requests_sideband(Some(&body));
+
+    let served = match find_served_repo(&req, Some(&pusher)) {
+        Ok(served) => served,
+        Err(e) => return rpc_app_error(service, e, sideband),
+    };
+    if let Err(e) = check_access(&served, &pusher, service) {
+        return rpc_app_error(service, e, sideband);
+    }
+    let (repo_path, _) = match resolve_served_repo(&served, service) {
+        Ok(resolved) => resolved,
+        Err(e) => return rpc_app_error(service, e, sideband),
+    };
+    let repo_name = served.full_name.as_str();
+
+    debug!("Handling receive-pack for repo: {}", repo_name);
+
+    // Место занимает владелец репозитория, поэтому проверяем его квоту
+    let db = req.app_data::<web::Data<Database>>().unwrap();
+    match owner_over_quota(served.record.as_ref(), db).await {
+        Ok(false) => {}
+        Ok(true) => return rpc_error(service, "Storage quota of the repository owner is exceeded", sideband),
+        Err(e) => return rpc_app_error(service, e, sideband),
+    }
+
+    let request_id = uuid::Uuid::new_v4().to_string();
+    trace_packets(&request_id, repo_name, "receive-pack", Direction::Request, &body);
+
+    let mut command = git_command();
+    // Поиск секретов в режиме strict и проверка подписей выполняются хуком до обновления ссылок
+    if let Some(hooks_dir) = pre_receive_hooks_dir(&repo_path) {
+        command.arg("-c").arg(format!("core.hooksPath={}", hooks_dir.display()));
+    }
+    command
+        .arg(GitService::ReceivePack.command())
+        .arg("--stateless-rpc")
+        .arg(&repo_path);
+
+    if let Some(protocol) = git_protocol(&req) {
+        command.env("GIT_PROTOCOL", protocol);
+    }
+
+    // Ожидание блокировки и сам push выполняются в пуле git процессов
+    let locked_repo = repo_name.to_string();
+    let job_db = db.clone();
+    let result = run_blocking(move || -> Result<PushOutcome, AppError> {
+        // Сериализуем push'и и слияния в один репозиторий; блокировка снимается при выходе
+        let _lock = match lock_repo(&locked_repo) {
+            Some(lock) => lock,
+            None => return Err(AppError::Busy(format!("Timed out waiting for lock on repo: {}", locked_repo))),
+        };
+
+        // Снимок ссылок до push; реакции считаются по разнице с состоянием после него
+        let refs_before = list_refs(&repo_path);
+        // Счётчик для ETag объявления ссылок: до и после, чтобы объявление,
+        // снятое во время push, не совпало с итоговым
+        api::bump_ref_generation(&locked_repo, &job_db);
+        let git_started = Instant::now();
+        let output = run_with_input(&mut command, &body);
+        let git_time = git_started.elapsed();
+        api::bump_ref_generation(&locked_repo, &job_db);
+        let output = output?;
+        if !output.status.success() {
+            return Ok(PushOutcome { output, updates: Vec::new(), git_time });
+        }
+
+        // Push может обновить только часть ссылок (например, при отклонённых non-fast-forward),
+        // поэтому разбирать запрос нельзя - перечитываем фактическое состояние
+        let updates = match (refs_before, list_refs(&repo_path)) {
+            (Ok(before), Ok(after)) => diff_refs(&before, &after),
+            (Err(e), _) | (_, Err(e)) => {
+                error!("Failed to read refs after push to {}: {}", locked_repo, e);
+                Vec::new()
+            }
+        };
+
+        // Статистику считаем, пока держим блокировку: ссылки ещё в состоянии после этого push
+        let updates = updates
+            .into_iter()
+            .map(|update| {
+                let stats = push_stats(&repo_path, &update)
+                    .map_err(|e| error!("Failed to compute stats for {}: {}", update.name, e))
+                    .ok();
+                (update, stats)
+            })
+            .collect();
+        Ok(PushOutcome { output, updates, git_time })
+    }).await;
+
+    let PushOutcome { output, updates, git_time } = match result {
+        Ok(Ok(result)) => result,
+        Ok(Err(e)) | Err(e) => {
+            error!("git-receive-pack failed: {}", e);
+            return e.error_response();
+        }
+    };
+
+    if !output.status.success() {
+        error!("git-receive-pack failed: {}", String::from_utf8_lossy(&output.stderr));
+        return HttpResponse::InternalServerError().finish();
+    }
+
+    trace_packets(&request_id, repo_name, "receive-pack", Direction::Response, &output.stdout);
+
+    // Запись push'ей, пул-реквесты и webhook'и - несколько записей в базу с повторами,
+    // поэтому они выполняются вне рабочего потока actix
+    if let Some(repo) = served.record.clone() {
+        let db = db.clone();
+        if let Err(e) = web::block(move || on_push(&repo, &updates, &pusher, &db)).await {
+            error!("Failed to process push to {}: {}", repo_name, e);
+        }
+    }
+
+    let body = if sideband {
+        motd::prepend_to_receive_pack(&output.stdout).unwrap_or(output.stdout)
+    } else {
+        output.stdout
+    };
+
+    let mut response = HttpResponse::Ok();
+    response.content_type("application/x-git-receive-pack-result");
+    add_server_timing(&mut response, git_time, started.elapsed());
+    response.body(body)
+}
+
+/// Добавляет `Server-Timing` и `X-Git-Duration`, если включён GIT_HTTP_SERVER_TIMING
+///
+/// Показывает, сколько из общего времени обработчика занял git процесс.
+fn add_server_timing(response: &mut HttpResponseBuilder, git_time: Duration, total: Duration) {
+    if !CONFIG.server_timing {
+        return;
+    }
+
+    response
+        .insert_header((
+            "Server-Timing",
+            format!(
+                "git;desc=\"git subprocess\";dur={:.1}, total;dur={:.1}",
+                git_time.as_secs_f64() * 1000.0,
+                total.as_secs_f64() * 1000.0
+            ),
+        ))
+        .insert_header(("X-Git-Duration", format!("{:.3}", git_time.as_secs_f64())));
+}
+
+/// Запускает процесс, передаёт ему `input` на stdin и дожидается завершения
+fn run_with_input(command: &mut Command, input: &[u8]) -> Result<Output, AppError> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| AppError::Git(format!("Failed to spawn git: {}", e)))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input).map_err(|e| AppError::Git(format!("Failed to write to git stdin: {}", e)))?;
+        drop(stdin);  // Важно закрыть stdin, чтобы процесс знал, что ввод закончен
+    }
+
+    child.wait_with_output().map_err(|e| AppError::Git(format!("Failed to wait for git: {}", e)))
+}
+
+/// Реакции сервера на изменение ссылок после push
+///
+/// Ошибки только логируются: push к этому моменту уже выполнен.
+pub fn on_push(repo: &Repository, updates: &PushedRefs, pusher: &User, db: &web::Data<Database>) {
+    let (repository_id, pusher_id) = match (repo.id, pusher.id) {
+        (Some(repository_id), Some(pusher_id)) => (repository_id, pusher_id),
+        _ => return,
+    };
+
+    for (update, stats) in updates {
+        if let Some(stats) = stats {
+            if let Err(e) = Push::new(repository_id, update, stats, pusher_id).create(db.get_connection()) {
+                error!("Failed to record push to {}: {}", update.name, e);
+            }
+        }
+
+        if let (Some(branch), Some(new_sha)) = (update.branch(), update.new.as_deref()) {
+            if let Err(e) = PullRequest::on_source_branch_pushed(repository_id, branch, new_sha, pusher_id, db.get_connection()) {
+                error!("Failed to update pull requests for {}: {}", update.name, e);
+            }
+        }
+    }
+
+    let refs: Vec<serde_json::Value> = updates
+        .iter()
+        .map(|(update, _)| serde_json::json!({
+            "ref": update.name,
+            "before": update.old,
+            "after": update.new,
+        }))
+        .collect();
+    webhooks::fire(repository_id, "push", serde_json::json!({
+        "repository": repo.name,
+        "pusher": pusher.username,
+        "refs": refs,
+    }), db);
+
+    for (update, _) in updates {
+        let (ref_type, short_name) = match (update.branch(), update.tag()) {
+            (Some(branch), _) => ("branch", branch),
+            (_, Some(tag)) => ("tag", tag),
+            _ => continue,
+        };
+        let event = match update.change() {
+            RefChange::Create => "create",
+            RefChange::Delete => "delete",
+            RefChange::Update => continue,
+        };
+        webhooks::fire(repository_id, event, serde_json::json!({
+            "ref_type": ref_type,
+            "ref": short_name,
+            "repository": repo.name,
+            "sender": pusher.username,
+        }), db);
+    }
+
+    if *SCAN_MODE == ScanMode::Async {
+        let updates = updates.iter().map(|(update, _)| update.clone()).collect();
+        spawn_secret_scan(repo.clone(), updates, db.clone());
+    }
+}
+
+/// Ищет секреты в принесённых push'ем коммитах, не задерживая ответ клиенту
+///
+/// Находки сохраняются как предупреждения; владелец репозитория получает
+/// уведомление, если появились новые.
+fn spawn_secret_scan(repo: Repository, updates: Vec<RefUpdate>, db: web::Data<Database>) {
+    actix_web::rt::spawn(async move {
+        let repo_path = match resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()) {
+            Ok(path) => path,
+            Err(e) => {
+                error!("Secret scan skipped for {}: {}", repo.full_name(), e);
+                return;
+            }
+        };
+
+        let scanned = run_blocking(move || {
+            updates
+                .into_iter()
+                .map(|update| {
+                    let findings = scan_update(&repo_path, &update, true);
+                    (update, findings)
+                })
+                .collect::<Vec<_>>()
+        }).await;
+
+        let scanned = match scanned {
+            Ok(scanned) => scanned,
+            Err(e) => {
+                error!("Secret scan skipped for {}: {}", repo.name, e);
+                return;
+            }
+        };
+
+        let repository_id = repo.id.unwrap_or_default();
+        for (update, findings) in scanned {
+            let findings = match findings {
+                Ok(findings) => findings,
+                Err(e) => {
+                    error!("Secret scan failed for {}: {}", update.name, e);
+                    continue;
+                }
+            };
+
+            let mut rules = Vec::new();
+            for finding in &findings {
+                match SecretAlert::new(repository_id, &update.name, finding).create_once(db.get_connection()) {
+                    Ok(true) => rules.push(finding.rule.clone()),
+                    Ok(false) => {}
+                    Err(e) => error!("Failed to record secret alert: {}", e),
+                }
+            }
+            if rules.is_empty() {
+                continue;
+            }
+            rules.sort();
+            rules.dedup();
+
+            let notification = Notification {
+                id: None,
+                notification_type: "secret_alert".to_string(),
+                title: format!("Possible secrets pushed to {}", repo.name),
+                content: format!(
+                    "Push to '{}' added lines matching: {}. See the repository's secret alerts.",
+                    update.name,
+                    rules.join(", ")
+                ),
+                user_id: repo.owner_id,
+                is_read: false,
+                created_at: None,
+            };
+            let key = format!(
+                "secret:{}:{}:{}:user:{}",
+                repository_id,
+                update.name,
+                update.new.as_deref().unwrap_or_default(),
+                repo.owner_id
+            );
+            if let Err(e) = notification.create_once(&key, db.get_connection()) {
+                error!("Failed to create notification: {}", e);
+            }
+        }
+    });
+}
+
+/// Проверяет, превысил ли владелец репозитория квоту на хранение
+///
+/// Репозитории без записи в базе данных и ошибки базы данных квотой не ограничиваются.
+/// Размер считается в пуле git (см. `api::over_quota`), поэтому при заполненной
+/// очереди пула возвращается `AppError::Busy`.
+async fn owner_over_quota(repo: Option<&Repository>, db: &web::Data<Database>) -> Result<bool, AppError> {
+    let owner = match repo {
+        Some(repo) => db.find_user_by_id(repo.owner_id),
+        None => Ok(None),
+    };
+
+    match owner {
+        Ok(Some(owner)) => match api::over_quota(&owner, db).await {
+            Err(AppError::Database(e)) => {
+                error!("Failed to check storage quota: {}", e);
+                Ok(false)
+            }
+            result => result,
+        },
+        Ok(None) => Ok(false),
+        Err(e) => {
+            error!("Failed to find repository owner: {}", e);
+            Ok(false)
+        }
+    }
+}
+
+/// Обработчик для objects/info/packs - возвращает список доступных pack-файлов
+/// Pack-файлы содержат сжатые Git объекты для эффективной передачи
+pub async fn handle_info_packs(req: HttpRequest) -> HttpResponse {
+    let served = match authorize_dumb_read(&req) {
+        Ok(served) => served,
+        Err(response) => return response,
+    };
+    let (repo_path, _) = match resolve_served_repo(&served, GitService::UploadPack) {
+        Ok(resolved) => resolved,
+        Err(e) => return e.error_response(),
+    };
+
+    // Список строится по objects/pack: сохранённый `update-server-info` файл может устареть
+    match info_packs(&repo_path) {
+        Ok(content) => HttpResponse::Ok()
+            .content_type("text/plain")
+            .body(content),
+        Err(e) => storage_error_response(&e, &repo_path.join("objects/pack")),
+    }
+}
+
+/// Обработчик для получения конкретного pack-файла
+///
+/// Отдаётся через `NamedFile`: он потоково читает файл, выставляет
+/// Content-Length, ETag и Last-Modified и отвечает на HEAD без тела.
+pub async fn handle_pack_file(req: HttpRequest) -> HttpResponse {
+    let pack_file = req.match_info().get("pack_file").unwrap();
+
+    // Имя pack-файла не должно позволять выйти за пределы objects/pack
+    if !pack_file.starts_with("pack-") || pack_file.contains("..") {
+        return HttpResponse::NotFound().finish();
+    }
+    let served = match authorize_dumb_read(&req) {
+        Ok(served) => served,
+        Err(response) => return response,
+    };
+
+    let (repo_path, _) = match resolve_served_repo(&served, GitService::UploadPack) {
+        Ok(resolved) => resolved,
+        Err(e) => return e.error_response(),
+    };
+
+    serve_object_file(&req, &repo_path.join("objects/pack").join(pack_file), "application/x-git-pack")
+}
+
+/// Обработчик для получения loose-объекта (`objects/xx/yyyy...`) по dumb протоколу
+pub async fn handle_loose_object(req: HttpRequest) -> HttpResponse {
+    let dir = req.match_info().get("dir").unwrap();
+    let file = req.match_info().get("file").unwrap();
+
+    // Путь объекта - это его SHA-1, разбитый на 2 + 38 шестнадцатеричных символов
+    let is_hex = |s: &str| s.chars().all(|c| c.is_ascii_hexdigit());
+    if dir.len() != 2 || file.len() != 38 || !is_hex(dir) || !is_hex(file) {
+        return HttpResponse::NotFound().finish();
+    }
+    let served = match authorize_dumb_read(&req) {
+        Ok(served) => served,
+        Err(response) => return response,
+    };
+
+    let (repo_path, _) = match resolve_served_repo(&served, GitService::UploadPack) {
+        Ok(resolved) => resolved,
+        Err(e) => return e.error_response(),
+    };
+
+    serve_object_file(&req, &repo_path.join("objects").join(dir).join(file), "application/x-git-loose-object")
+}
+
+/// Обработчик для bundle репозитория, на который upload-pack ссылается через `bundle-uri`
+///
+/// Доступ проверяется так же, как для clone. Пока bundle не создан фоновой
+/// задачей или если `bundle-uri` выключен, ответ 404.
+pub async fn handle_bundle(req: HttpRequest) -> HttpResponse {
+    let user = match api::check_auth(&req, req.app_data::<web::Data<Database>>().unwrap()) {
+        Some(user) => user,
+        None => return authentication_required(),
+    };
+
+    let served = match find_served_repo(&req, Some(&user)) {
+        Ok(served) => served,
+        Err(e) => return e.error_response(),
+    };
+    if let Err(e) = check_access(&served, &user, GitService::UploadPack) {
+        return e.error_response();
+    }
+    // Bundle создаётся из основного каталога, поэтому и настройка читается оттуда
+    let repo_path = match resolve_repo(Path::new(REPOS_ROOT), &served.full_name) {
+        Ok(path) => path,
+        Err(e) => return e.error_response(),
+    };
+
+    match bundle_uri_enabled(&repo_path) {
+        Ok(true) => {}
+        Ok(false) => return HttpResponse::NotFound().finish(),
+        Err(e) => return e.error_response(),
+    }
+    let path = match bundle_path(&served.full_name) {
+        Ok(path) => path,
+        Err(e) => return e.error_response(),
+    };
+
+    serve_object_file(&req, &path, "application/x-git-bundle")
+}
+
+/// Отдаёт файл из каталога objects с заданным Content-Type
+fn serve_object_file(req: &HttpRequest, path: &Path, content_type: &str) -> HttpResponse {
+    let content_type = match content_type.parse() {
+        Ok(mime) => mime,
+        Err(_) => return HttpResponse::InternalServerError().finish(),
+    };
+
+    match NamedFile::open(path) {
+        Ok(file) => file
+            .set_content_type(content_type)
+            .disable_content_disposition()
+            .into_response(req),
+        Err(e) => storage_error_response(&e, path),
+    }
+}
+
+/// Ответ на ошибку чтения из каталога репозитория
+///
+/// Только отсутствующий файл - это 404. Нехватка прав даёт 403, остальные ошибки
+/// ввода-вывода - 500, и обе пишутся в лог: обычно это неправильно настроенное
+/// хранилище, а не запрос несуществующего объекта.
+fn storage_error_response(e: &std::io::Error, path: &Path) -> HttpResponse {
+    match e.kind() {
+        std::io::ErrorKind::NotFound => HttpResponse::NotFound().finish(),
+        std::io::ErrorKind::PermissionDenied => {
+            error!("Permission denied reading {}: {}", path.display(), e);
+            HttpResponse::Forbidden().finish()
+        }
+        _ => {
+            error!("Failed to read {}: {}", path.display(), e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Параметры запроса файла
+#[derive(Deserialize)]
+pub struct TextFileQuery {
+    /// Ветка, тег или SHA коммита; по умолчанию HEAD
+    #[serde(rename = "ref")]
+    pub rev: Option<String>,
+    /// Первая строка фрагмента, начиная с 1
+    pub start: Option<usize>,
+    /// Последняя строка фрагмента включительно
+    pub end: Option<usize>,
+}
+
+/// Заголовок с общим числом строк текстового файла
+const TOTAL_LINES_HEADER: &str = "X-Total-Lines";
+
+/// Заголовок с кодировкой, в которой файл хранится в репозитории
+const SOURCE_CHARSET_HEADER: &str = "X-Source-Charset";
+
+/// Обработчик для получения текстовых файлов из репозитория
+/// Используется, например, для просмотра README, LICENSE и других файлов
+///
+/// ETag - SHA блоба, поэтому он меняется только вместе с содержимым файла.
+/// Last-Modified - дата последнего коммита, изменившего файл. Файл из коммита,
+/// заданного полным SHA, измениться не может, поэтому кэшируется надолго.
+///
+/// `?start=N&end=M` возвращает только строки с N по M включительно; `end` за концом
+/// файла урезается, а `start` за концом файла даёт 416. Общее число строк текстового
+/// файла приходит в `X-Total-Lines`. Для бинарных файлов диапазон не применяется.
+///
+/// Кодировка текста определяется по BOM и содержимому (см. `charset::detect`) и
+/// приходит в `X-Source-Charset`. Markdown и UTF-16 перекодируются в UTF-8, остальные
+/// файлы отдаются без изменений с найденной кодировкой в `Content-Type`; строки
+/// диапазона считаются в отданном тексте.
+pub async fn handle_text_file(req: HttpRequest, query: web::Query<TextFileQuery>) -> HttpResponse {
+    let repo_name = req.match_info().get("repo_name").unwrap();
+    let path = req.match_info().get("tail").unwrap();
+
+    debug!("Getting file: {} from repo: {}", path, repo_name);
+    let served = match authorize_dumb_read(&req) {
+        Ok(served) => served,
+        Err(response) => return response,
+    };
+
+    let (repo_path, _) = match resolve_served_repo(&served, GitService::UploadPack) {
+        Ok(resolved) => resolved,
+        Err(e) => return e.error_response(),
+    };
+
+    let rev = query.rev.as_deref().unwrap_or("HEAD");
+    let commit = match resolve_commit(&repo_path, rev) {
+        Ok(sha) => sha,
+        Err(_) if query.rev.is_none() => return HttpResponse::NotFound().finish(),
+        Err(e) => return e.error_response(),
+    };
+
+    // Сначала определяем блоб, чтобы по его SHA сформировать ETag
+    let blob = match run_git(&repo_path, &["rev-parse", "--verify", "--quiet", &format!("{}:{}", commit, path)]) {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        _ => return HttpResponse::NotFound().finish(),
+    };
+
+    let etag = format!("\"{}\"", blob);
+    let last_modified = match last_modified(&repo_path, &commit, path) {
+        Ok(time) => time
+            .and_then(|secs| u64::try_from(secs).ok())
+            .map(|secs| HttpDate::from(UNIX_EPOCH + Duration::from_secs(secs))),
+        Err(_) => None,
+    };
+    let cache_control = if rev.eq_ignore_ascii_case(&commit) {
+        "max-age=31536000, immutable"
+    } else {
+        "no-cache"
+    };
+
+    let unchanged = not_modified(&req, &etag, last_modified);
+    let mut response = if unchanged {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    response
+        .insert_header((header::ETAG, etag))
+        .insert_header((header::CACHE_CONTROL, cache_control));
+    if let Some(last_modified) = last_modified {
+        response.insert_header(header::LastModified(last_modified));
+    }
+    if unchanged {
+        return response.finish();
+    }
+
+    let content = match run_git(&repo_path, &["cat-file", "blob", &blob]) {
+        Ok(output) if output.status.success() => output.stdout,
+        _ => return HttpResponse::NotFound().finish(),
+    };
+
+    let charset = match charset::detect(&content) {
+        Some(charset) => charset,
+        None => return response.content_type("text/plain").body(content),
+    };
+    response.insert_header((SOURCE_CHARSET_HEADER, charset.name()));
+
+    // Markdown отдаётся в UTF-8 для отрисовки, UTF-16 - чтобы строки делились по `\n`;
+    // остальное и то, что не декодируется, отдаётся как есть с найденной кодировкой
+    let transcode = is_markdown(path) || !charset.is_ascii_compatible();
+    let (content, content_type) = match transcode.then(|| charset::decode(&content, charset)).flatten() {
+        Some(text) => (text.into_bytes(), "text/plain; charset=utf-8".to_string()),
+        None => (content, format!("text/plain; charset={}", charset.name())),
+    };
+
+    let lines: Vec<&[u8]> = content.split_inclusive(|&byte| byte == b'\n').collect();
+    response.insert_header((TOTAL_LINES_HEADER, lines.len().to_string()));
+    if query.start.is_none() && query.end.is_none() {
+        return response.content_type(content_type).body(content);
+    }
+
+    let start = query.start.unwrap_or(1);
+    let end = query.end.unwrap_or(lines.len()).min(lines.len());
+    if start == 0 || start > end {
+        return HttpResponse::RangeNotSatisfiable()
+            .insert_header((TOTAL_LINES_HEADER, lines.len().to_string()))
+            .content_type("text/plain")
+            .body(format!("Line range is outside the file, which has {} lines", lines.len()));
+    }
+
+    response.content_type(content_type).body(lines[start - 1..end].concat())
+}
+
+/// Markdown ли файл, по расширению
+fn is_markdown(path: &str) -> bool {
+    let extension = match path.rsplit('/').next().and_then(|name| name.rsplit_once('.')) {
+        Some((_, extension)) => extension.to_lowercase(),
+        None => return false,
+    };
+    matches!(extension.as_str(), "md" | "markdown" | "mdown" | "mkd")
+}
+
+/// Можно ли ответить 304 на условный запрос
+///
+/// `If-None-Match` важнее `If-Modified-Since`: дата проверяется, только если
+/// клиент не прислал ETag.
+fn not_modified(req: &HttpRequest, etag: &str, last_modified: Option<HttpDate>) -> bool {
+    if req.headers().contains_key(header::IF_NONE_MATCH) {
+        return api::etag_matches(req, etag);
+    }
+
+    match (req.get_header::<header::IfModifiedSince>(), last_modified) {
+        (Some(header::IfModifiedSince(since)), Some(modified)) => modified <= since,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App};
+    use crate::git::refs::{list_refs, set_hidden_refs, set_receive_protection, ReceiveProtection};
+    use crate::models::pull_request::PullRequestStatus;
+    use crate::test_support::{
+        basic_auth, client_git, commit_files, create_repo, create_user, remote_url, repo_git, serve, test_db, unique, work_dir,
+    };
+
+    /// Статус ответа и тело запроса к git маршрутам
+    async fn send(db: &web::Data<Database>, req: test::TestRequest) -> (StatusCode, String) {
+        let app = test::init_service(
+            App::new()
+                .app_data(db.clone())
+                .configure(|cfg| crate::git_routes(cfg, "/git/{owner}/{repo_name}"))
+                .configure(|cfg| crate::git_routes(cfg, "/git/{repo_name}"))
+        ).await;
+        let response = test::call_service(&app, req.to_request()).await;
+        let status = response.status();
+        let body = test::read_body(response).await;
+        (status, String::from_utf8_lossy(&body).into_owned())
+    }
+
+    fn info_refs(path: &str, service: &str, user: &User) -> test::TestRequest {
+        test::TestRequest::get()
+            .uri(&format!("{}/info/refs?service={}", path, service))
+            .insert_header(basic_auth(user))
+    }
+
+    #[actix_web::test]
+    async fn private_repo_is_hidden_from_other_users_for_both_services() {
+        let db = test_db();
+        let owner = create_user(&db, "owner");
+        let other = create_user(&db, "other");
+        let repo = create_repo(&db, &owner, "private", false);
+
+        for path in [format!("/git/{}", repo.full_name()), "/git/private".to_string()] {
+            for service in ["git-upload-pack", "git-receive-pack"] {
+                let (status, body) = send(&db, info_refs(&path, service, &other)).await;
+                assert_eq!(status, StatusCode::NOT_FOUND, "{} {}", path, service);
+                assert!(body.contains("Repository not found"), "{}", body);
+            }
+        }
+
+        let (status, _) = send(&db, info_refs(&format!("/git/{}", repo.full_name()), "git-receive-pack", &owner)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn only_the_owner_can_push_to_a_public_repo() {
+        let db = test_db();
+        let owner = create_user(&db, "owner");
+        let other = create_user(&db, "other");
+        let repo = create_repo(&db, &owner, "public", true);
+        let path = format!("/git/{}", repo.full_name());
+
+        let (status, _) = send(&db, info_refs(&path, "git-upload-pack", &other)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, body) = send(&db, info_refs(&path, "git-receive-pack", &other)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(body.contains("You do not have write access"), "{}", body);
+
+        let (status, _) = send(&db, info_refs(&path, "git-receive-pack", &owner)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn receive_pack_rejects_other_users() {
+        let db = test_db();
+        let owner = create_user(&db, "owner");
+        let other = create_user(&db, "other");
+        let private = create_repo(&db, &owner, "private", false);
+        let public = create_repo(&db, &owner, "public", true);
+
+        let push = |repo: &Repository| test::TestRequest::post()
+            .uri(&format!("/git/{}/git-receive-pack", repo.full_name()))
+            .insert_header(basic_auth(&other))
+            .insert_header((header::CONTENT_TYPE, "application/x-git-receive-pack-request"))
+            .set_payload("0000");
+
+        let (_, body) = send(&db, push(&private)).await;
+        assert!(body.contains("Repository not found"), "{}", body);
+        let (_, body) = send(&db, push(&public)).await;
+        assert!(body.contains("You do not have write access"), "{}", body);
+    }
+
+    #[actix_web::test]
+    async fn anonymous_discovery_asks_for_credentials() {
+        let db = test_db();
+        let owner = create_user(&db, "owner");
+        let repo = create_repo(&db, &owner, "public", true);
+
+        let req = test::TestRequest::get().uri(&format!("/git/{}/info/refs?service=git-upload-pack", repo.full_name()));
+        let (status, _) = send(&db, req).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn file_endpoint_reports_and_transcodes_charsets() {
+        let db = test_db();
+        let owner = create_user(&db, "owner");
+        let repo = create_repo(&db, &owner, "charsets", true);
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend("Привет\nмир\n".encode_utf16().flat_map(u16::to_le_bytes));
+        let latin1 = b"Gr\xFC\xDFe\ncaf\xE9\n";
+        commit_files(&repo, &[("utf16.txt", &utf16), ("latin1.txt", latin1), ("latin1.md", latin1)]);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(db.clone())
+                .configure(|cfg| crate::git_routes(cfg, "/git/{owner}/{repo_name}"))
+        ).await;
+        let get = |file: &str| test::TestRequest::get()
+            .uri(&format!("/git/{}/file/{}", repo.full_name(), file))
+            .to_request();
+
+        // UTF-16 перекодируется, BOM отбрасывается, строки считаются в тексте UTF-8
+        let response = test::call_service(&app, get("utf16.txt")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(SOURCE_CHARSET_HEADER).unwrap(), "utf-16le");
+        assert_eq!(response.headers().get(header::CONTENT_TYPE).unwrap(), "text/plain; charset=utf-8");
+        assert_eq!(response.headers().get(TOTAL_LINES_HEADER).unwrap(), "2");
+        assert_eq!(test::read_body(response).await, "Привет\nмир\n".as_bytes());
+
+        // Latin-1 отдаётся как есть с кодировкой в Content-Type
+        let response = test::call_service(&app, get("latin1.txt")).await;
+        assert_eq!(response.headers().get(SOURCE_CHARSET_HEADER).unwrap(), "iso-8859-1");
+        assert_eq!(response.headers().get(header::CONTENT_TYPE).unwrap(), "text/plain; charset=iso-8859-1");
+        assert_eq!(test::read_body(response).await, &latin1[..]);
+
+        // Markdown в Latin-1 перекодируется в UTF-8
+        let response = test::call_service(&app, get("latin1.md")).await;
+        assert_eq!(response.headers().get(header::CONTENT_TYPE).unwrap(), "text/plain; charset=utf-8");
+        assert_eq!(test::read_body(response).await, "Grüße\ncafé\n".as_bytes());
+    }
+
+    /// Пути dumb протокола и файлов репозитория: pack, loose объект, `info/packs` и файл
+    fn dumb_paths(repo: &Repository) -> Vec<String> {
+        commit_files(repo, &[("README.md", b"packed\n")]);
+        repo_git(repo, &["repack", "-a", "-d", "-q"]);
+        let commit = commit_files(repo, &[("README.md", b"loose\n")]);
+        let pack = repo_git(repo, &["count-objects", "-v"]);
+        assert!(pack.contains("packs: 1"), "{}", pack);
+        let pack_dir = resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()).unwrap().join("objects/pack");
+        let pack_file = std::fs::read_dir(pack_dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .find(|name| name.ends_with(".pack"))
+            .unwrap();
+
+        let base = format!("/git/{}", repo.full_name());
+        vec![
+            format!("{}/objects/pack/{}", base, pack_file),
+            format!("{}/objects/{}/{}", base, &commit[..2], &commit[2..]),
+            format!("{}/objects/info/packs", base),
+            format!("{}/file/README.md", base),
+        ]
+    }
+
+    #[actix_web::test]
+    async fn dumb_reads_of_a_private_repo_need_access() {
+        let db = test_db();
+        let owner = create_user(&db, "owner");
+        let other = create_user(&db, "other");
+        let repo = create_repo(&db, &owner, "private", false);
+
+        for path in dumb_paths(&repo) {
+            let (status, _) = send(&db, test::TestRequest::get().uri(&path)).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED, "anonymous {}", path);
+
+            let (status, _) = send(&db, test::TestRequest::get().uri(&path).insert_header(basic_auth(&other))).await;
+            assert_eq!(status, StatusCode::NOT_FOUND, "other user {}", path);
+
+            let (status, _) = send(&db, test::TestRequest::get().uri(&path).insert_header(basic_auth(&owner))).await;
+            assert_eq!(status, StatusCode::OK, "owner {}", path);
+        }
+    }
+
+    #[actix_web::test]
+    async fn dumb_reads_of_a_public_repo_need_no_credentials() {
+        let db = test_db();
+        let owner = create_user(&db, "owner");
+        let repo = create_repo(&db, &owner, "public", true);
+
+        for path in dumb_paths(&repo) {
+            let (status, _) = send(&db, test::TestRequest::get().uri(&path)).await;
+            assert_eq!(status, StatusCode::OK, "{}", path);
+        }
+    }
+
+    #[actix_web::test]
+    async fn dumb_reads_do_not_reveal_missing_repos() {
+        let db = test_db();
+        let user = create_user(&db, "user");
+
+        let path = format!("/git/{}/missing/objects/info/packs", user.username);
+        let (status, _) = send(&db, test::TestRequest::get().uri(&path)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send(&db, test::TestRequest::get().uri(&path).insert_header(basic_auth(&user))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn upload_pack_accepts_a_long_negotiation() {
+        let db = test_db();
+        let owner = create_user(&db, "owner");
+        let repo = create_repo(&db, &owner, "negotiation", false);
+        let commit = commit_files(&repo, &[("README.md", b"hello")]);
+
+        // Клиент без общей истории перечисляет тысячи своих коммитов, и при stateless RPC
+        // каждый раунд повторяет все предыдущие `have`: тело легко превышает сотни КиБ
+        let mut body = Vec::new();
+        write_pkt(&mut body, format!("want {} multi_ack_detailed side-band-64k ofs-delta\n", commit).as_bytes()).unwrap();
+        write_flush(&mut body).unwrap();
+        for i in 0..25_000u32 {
+            write_pkt(&mut body, format!("have {:040x}\n", i).as_bytes()).unwrap();
+        }
+        write_pkt(&mut body, b"done\n").unwrap();
+        assert!(body.len() > 1024 * 1024);
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/git/{}/git-upload-pack", repo.full_name()))
+            .insert_header(basic_auth(&owner))
+            .insert_header((header::CONTENT_TYPE, "application/x-git-upload-pack-request"))
+            .set_payload(body);
+        let (status, body) = send(&db, req).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.starts_with("0008NAK\n"), "{}", &body[..body.len().min(200)]);
+        assert!(body.contains("PACK"));
+    }
+
+    #[actix_web::test]
+    async fn push_is_rejected_once_the_owner_is_over_quota() {
+        let db = test_db();
+        let owner = create_user(&db, "quota");
+        let repo = create_repo(&db, &owner, "full", true);
+        commit_files(&repo, &[("data.bin", &[0u8; 4096])]);
+        db.get_connection().lock().unwrap().execute(
+            "UPDATE users SET storage_quota_bytes = 1024 WHERE id = ?1", rusqlite::params![owner.id],
+        ).unwrap();
+
+        let push = test::TestRequest::post()
+            .uri(&format!("/git/{}/git-receive-pack", repo.full_name()))
+            .insert_header(basic_auth(&owner))
+            .insert_header((header::CONTENT_TYPE, "application/x-git-receive-pack-request"))
+            .set_payload("0000");
+        let (status, body) = send(&db, push).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("Storage quota of the repository owner is exceeded"), "{}", body);
+    }
+
+    /// Число loose объектов в репозитории клиента
+    fn loose_objects(dir: &Path) -> u64 {
+        client_git(dir, &["count-objects", "-v"])
+            .lines()
+            .find_map(|line| line.strip_prefix("count: "))
+            .and_then(|count| count.parse().ok())
+            .expect("loose object count")
+    }
+
+    #[actix_web::test]
+    async fn fetch_after_a_push_receives_only_the_new_objects() {
+        let db = test_db();
+        let owner = create_user(&db, "fetch");
+        let repo = create_repo(&db, &owner, "fetched", true);
+        commit_files(&repo, &[("a.txt", b"a\n"), ("b.txt", b"b\n"), ("c.txt", b"c\n")]);
+        let url = remote_url(&serve(&db), &owner, &repo);
+
+        let pusher = work_dir().join(unique("pusher"));
+        let fetcher = work_dir().join(unique("fetcher"));
+        for dir in [&pusher, &fetcher] {
+            client_git(work_dir(), &["clone", "-q", &url, dir.to_str().unwrap()]);
+        }
+        // Клон хранит объекты в pack, а маленький fetch распаковывается в loose объекты
+        assert_eq!(loose_objects(&fetcher), 0);
+
+        // Новый коммит: сам коммит, дерево и один blob
+        std::fs::write(pusher.join("a.txt"), "changed\n").unwrap();
+        client_git(&pusher, &["commit", "-q", "-am", "change a"]);
+        client_git(&pusher, &["push", "-q", "origin", "main"]);
+        let head = client_git(&pusher, &["rev-parse", "HEAD"]);
+
+        client_git(&fetcher, &["-c", "fetch.unpackLimit=1000", "fetch", "-q", "origin"]);
+        assert_eq!(client_git(&fetcher, &["rev-parse", "origin/main"]), head);
+        assert_eq!(loose_objects(&fetcher), 3);
+    }
+
+    #[actix_web::test]
+    async fn repo_made_private_is_no_longer_served_anonymously() {
+        let db = test_db();
+        let owner = create_user(&db, "owner");
+        let other = create_user(&db, "other");
+        let repo = create_repo(&db, &owner, "hidden", true);
+        let paths = dumb_paths(&repo);
+        let base = format!("/git/{}", repo.full_name());
+
+        let app = test::init_service(
+            App::new()
+                .app_data(db.clone())
+                .configure(|cfg| crate::git_routes(cfg, "/git/{owner}/{repo_name}"))
+                .route("/api/repos/{repo_name}/visibility", web::put().to(crate::handlers::api::set_visibility))
+        ).await;
+
+        // Пока репозиторий публичный, его можно скачать без учётных данных
+        let mut etags = Vec::new();
+        for path in &paths {
+            let response = test::call_service(&app, test::TestRequest::get().uri(path).to_request()).await;
+            assert_eq!(response.status(), StatusCode::OK, "anonymous {}", path);
+            etags.push(response.headers().get(header::ETAG).cloned());
+        }
+        // Объявление ссылок попадает в кэш
+        let response = test::call_service(&app, info_refs(&base, "git-upload-pack", &other).to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/repos/hidden/visibility?owner={}", owner.username))
+            .insert_header(basic_auth(&owner))
+            .set_json(serde_json::json!({ "is_public": false }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        // Сразу после смены видимости ни кэш, ни ETag не открывают репозиторий
+        for (path, etag) in paths.iter().zip(etags) {
+            let mut req = test::TestRequest::get().uri(path);
+            if let Some(etag) = etag {
+                req = req.insert_header((header::IF_NONE_MATCH, etag));
+            }
+            let response = test::call_service(&app, req.to_request()).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "anonymous {}", path);
+        }
+        let response = test::call_service(&app, info_refs(&base, "git-upload-pack", &other).to_request()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn clone_over_protocol_v2() {
+        let db = test_db();
+        let owner = create_user(&db, "v2");
+        let repo = create_repo(&db, &owner, "v2", false);
+        let head = commit_files(&repo, &[("README.md", b"v2\n")]);
+        let url = remote_url(&serve(&db), &owner, &repo);
+
+        let clone = work_dir().join(unique("v2clone"));
+        let trace = work_dir().join(unique("v2trace"));
+        let output = crate::git::git_command()
+            .current_dir(work_dir())
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .env("GIT_TRACE_PACKET", &trace)
+            .args(["-c", "protocol.version=2", "clone", "-q", &url])
+            .arg(&clone)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+        // Сервер ответил в v2, и объекты пришли командой fetch v2
+        let trace = std::fs::read_to_string(&trace).unwrap();
+        assert!(trace.contains("git< version 2"), "{}", trace);
+        assert!(trace.contains("clone> command=ls-refs"), "{}", trace);
+        assert!(trace.contains("clone> command=fetch"), "{}", trace);
+        assert!(trace.contains("clone< packfile"), "{}", trace);
+        assert_eq!(client_git(&clone, &["rev-parse", "HEAD"]), head);
+        assert_eq!(std::fs::read_to_string(clone.join("README.md")).unwrap(), "v2\n");
+    }
+
+    #[actix_web::test]
+    async fn push_of_several_refs_reacts_only_to_the_accepted_ones() {
+        let db = test_db();
+        let owner = create_user(&db, "multi");
+        let author = create_user(&db, "author");
+        let repo = create_repo(&db, &owner, "multi", true);
+        let base = commit_files(&repo, &[("README.md", b"base\n")]);
+        let tip = commit_files(&repo, &[("README.md", b"tip\n")]);
+        for branch in ["feature", "stale"] {
+            repo_git(&repo, &["branch", branch, &tip]);
+            PullRequest {
+                id: None,
+                title: format!("Work on {}", branch),
+                description: None,
+                repository_id: repo.id.unwrap(),
+                source_branch: branch.to_string(),
+                target_branch: "main".to_string(),
+                author_id: author.id.unwrap(),
+                status: PullRequestStatus::Open,
+                created_at: None,
+                updated_at: None,
+            }.create(db.get_connection()).unwrap();
+        }
+        let repo_path = resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()).unwrap();
+        set_receive_protection(&repo_path, ReceiveProtection {
+            deny_non_fast_forwards: true,
+            ..Default::default()
+        }).unwrap();
+        let url = remote_url(&serve(&db), &owner, &repo);
+
+        let clone = work_dir().join(unique("multi"));
+        client_git(work_dir(), &["clone", "-q", &url, clone.to_str().unwrap()]);
+        // feature продолжается, stale переписывается с base (отклоняется), added создаётся
+        client_git(&clone, &["checkout", "-q", "-b", "feature", "origin/feature"]);
+        client_git(&clone, &["commit", "-q", "--allow-empty", "-m", "feature work"]);
+        client_git(&clone, &["checkout", "-q", "-b", "stale", &base]);
+        client_git(&clone, &["commit", "-q", "--allow-empty", "-m", "rewritten"]);
+        client_git(&clone, &["branch", "added", "feature"]);
+        let feature = client_git(&clone, &["rev-parse", "feature"]);
+
+        let output = git_command()
+            .current_dir(&clone)
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .args(["push", "--porcelain", "--force", "origin", "feature", "stale", "added"])
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        let report = String::from_utf8_lossy(&output.stdout);
+        assert!(report.contains("refs/heads/feature:refs/heads/feature"), "{}", report);
+        assert!(report.lines().any(|line| line.starts_with("!\trefs/heads/stale")), "{}", report);
+
+        // Ссылки и реакции соответствуют тому, что сервер принял
+        assert_eq!(repo_git(&repo, &["rev-parse", "feature"]), feature);
+        assert_eq!(repo_git(&repo, &["rev-parse", "added"]), feature);
+        assert_eq!(repo_git(&repo, &["rev-parse", "stale"]), tip);
+
+        let pushes = Push::find_by_repository(repo.id.unwrap(), 10, 0, None, db.get_connection()).unwrap();
+        let mut pushed: Vec<&str> = pushes.iter().map(|push| push.ref_name.as_str()).collect();
+        pushed.sort();
+        assert_eq!(pushed, ["refs/heads/added", "refs/heads/feature"]);
+
+        let notifications = Notification::find_by_user_id(author.id.unwrap(), 10, 0, None, db.get_connection()).unwrap();
+        assert_eq!(notifications.len(), 1, "{:?}", notifications.iter().map(|n| &n.content).collect::<Vec<_>>());
+        assert!(notifications[0].content.contains("Work on feature"), "{}", notifications[0].content);
+    }
+
+    #[actix_web::test]
+    async fn mirror_clone_has_every_branch_and_tag_but_hidden_refs() {
+        let db = test_db();
+        let owner = create_user(&db, "mirror");
+        let repo = create_repo(&db, &owner, "mirrored", false);
+        let first = commit_files(&repo, &[("README.md", b"one\n")]);
+        let second = commit_files(&repo, &[("README.md", b"two\n")]);
+        repo_git(&repo, &["branch", "develop", &first]);
+        repo_git(&repo, &["branch", "release/1.x", &second]);
+        repo_git(&repo, &["tag", "v1.0", &first]);
+        repo_git(&repo, &["tag", "-a", "-m", "second", "v2.0", &second]);
+        repo_git(&repo, &["update-ref", "refs/keep/internal", &first]);
+        let repo_path = resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()).unwrap();
+        set_hidden_refs(&repo_path, &["refs/keep/".to_string()]).unwrap();
+        let url = remote_url(&serve(&db), &owner, &repo);
+
+        let mirror = work_dir().join(unique("mirror"));
+        client_git(work_dir(), &["clone", "-q", "--mirror", &url, mirror.to_str().unwrap()]);
+
+        let mut expected = list_refs(&repo_path).unwrap();
+        assert!(expected.remove("refs/keep/internal").is_some());
+        assert_eq!(expected.len(), 5, "{:?}", expected);
+        assert_eq!(list_refs(&mirror).unwrap(), expected);
+    }
+
+    #[actix_web::test]
+    async fn info_packs_lists_the_pack_of_a_new_push() {
+        let db = test_db();
+        let owner = create_user(&db, "packs");
+        let repo = create_repo(&db, &owner, "packs", true);
+        commit_files(&repo, &[("README.md", b"packs\n")]);
+        // Сервер сохраняет принятый pack как есть, а не распаковывает маленький push
+        repo_git(&repo, &["config", "receive.unpackLimit", "1"]);
+        let url = remote_url(&serve(&db), &owner, &repo);
+
+        let clone = work_dir().join(unique("packs"));
+        client_git(work_dir(), &["clone", "-q", &url, clone.to_str().unwrap()]);
+        std::fs::write(clone.join("README.md"), "pushed\n").unwrap();
+        client_git(&clone, &["commit", "-q", "-am", "pushed"]);
+        client_git(&clone, &["push", "-q", "origin", "main"]);
+
+        let pack_dir = resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()).unwrap().join("objects/pack");
+        let packs: Vec<String> = std::fs::read_dir(pack_dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| name.ends_with(".pack"))
+            .collect();
+        assert_eq!(packs.len(), 1, "{:?}", packs);
+
+        let path = format!("/git/{}/objects/info/packs", repo.full_name());
+        let (status, body) = send(&db, test::TestRequest::get().uri(&path).insert_header(basic_auth(&owner))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, format!("P {}\n\n", packs[0]));
+
+        let path = format!("/git/{}/objects/pack/{}", repo.full_name(), packs[0]);
+        let (status, _) = send(&db, test::TestRequest::get().uri(&path).insert_header(basic_auth(&owner))).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
@@ -0,0 +1,285 @@
+use actix_web::{web, HttpResponse, HttpRequest, Result};
+use serde::Deserialize;
+use log::error;
+use crate::handlers::api::{find_owned_repo, page_bounds, ApiResponse};
+use crate::models::db::Database;
+use crate::models::webhook::{Webhook, WebhookDelivery};
+use crate::webhooks::{deliver_and_record, parse_url, SUPPORTED_EVENTS};
+
+/// Запрос на создание webhook
+#[derive(Deserialize)]
+pub struct CreateHookRequest {
+    pub url: String,
+    /// События; по умолчанию `push`
+    pub events: Option<Vec<String>>,
+    pub active: Option<bool>,
+    /// Секрет для подписи доставок (`X-Hub-Signature-256`)
+    pub secret: Option<String>,
+}
+
+/// Параметры постраничного вывода доставок
+#[derive(Deserialize)]
+pub struct DeliveriesQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+/// Проверяет URL и события нового webhook
+///
+/// # Параметры
+///
+/// * `url` - URL получателя
+/// * `events` - События, на которые подписывается webhook
+/// * `supported` - Допустимые события
+///
+/// # Возвращает
+///
+/// * `Result<(), String>` - Сообщение об ошибке для ответа 400
+pub fn validate_hook(url: &str, events: &[String], supported: &[&str]) -> Result<(), String> {
+    parse_url(url)?;
+
+    if events.is_empty() {
+        return Err("At least one event is required".to_string());
+    }
+    if let Some(unknown) = events.iter().find(|event| !supported.contains(&event.as_str())) {
+        return Err(format!(
+            "Unsupported event '{}', supported events: {}",
+            unknown,
+            supported.join(", ")
+        ));
+    }
+
+    Ok(())
+}
+
+/// Находит webhook в репозитории, которым владеет текущий пользователь
+///
+/// # Возвращает
+///
+/// * `Result<Webhook, HttpResponse>` - Webhook или готовый ответ с ошибкой (401/403/404/500)
+fn find_owned_hook(
+    req: &HttpRequest,
+    db: &web::Data<Database>,
+    repo_name: &str,
+    hook_id: i64,
+) -> Result<Webhook, HttpResponse> {
+    let (repo, _) = find_owned_repo(req, db, repo_name)?;
+
+    match Webhook::find_by_id(repo.id.unwrap(), hook_id, db.get_connection()) {
+        Ok(Some(hook)) => Ok(hook),
+        Ok(None) => Err(HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            message: Some("Webhook not found".to_string()),
+            data: None,
+        })),
+        Err(e) => {
+            error!("Failed to fetch webhook: {}", e);
+            Err(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to fetch webhook".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
+
+/// Создаёт webhook репозитория (только владелец)
+pub async fn create_hook(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<CreateHookRequest>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let (repo, _) = match find_owned_repo(&req, &db, &repo_name) {
+        Ok(found) => found,
+        Err(response) => return Ok(response),
+    };
+
+    let body = body.into_inner();
+    let events = body.events.unwrap_or_else(|| vec!["push".to_string()]);
+    if let Err(message) = validate_hook(&body.url, &events, SUPPORTED_EVENTS) {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: Some(message),
+            data: None,
+        }));
+    }
+
+    let mut hook = Webhook {
+        id: None,
+        repository_id: repo.id.unwrap(),
+        url: body.url,
+        events,
+        active: body.active.unwrap_or(true),
+        created_at: None,
+        secret: body.secret.filter(|secret| !secret.is_empty()),
+    };
+
+    match hook.create(db.get_connection()) {
+        Ok(id) => {
+            hook.id = Some(id);
+            Ok(HttpResponse::Created().json(ApiResponse {
+                success: true,
+                message: Some("Webhook created successfully".to_string()),
+                data: Some(hook),
+            }))
+        }
+        Err(e) => {
+            error!("Failed to create webhook: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to create webhook".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
+
+/// Список webhook'ов репозитория (только владелец)
+pub async fn list_hooks(
+    req: HttpRequest,
+    path: web::Path<String>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let (repo, _) = match find_owned_repo(&req, &db, &repo_name) {
+        Ok(found) => found,
+        Err(response) => return Ok(response),
+    };
+
+    match Webhook::find_by_repository(repo.id.unwrap(), db.get_connection()) {
+        Ok(hooks) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(hooks),
+        })),
+        Err(e) => {
+            error!("Failed to fetch webhooks: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to fetch webhooks".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
+
+/// Удаляет webhook вместе с журналом доставок (только владелец)
+pub async fn delete_hook(
+    req: HttpRequest,
+    path: web::Path<(String, i64)>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let (repo_name, hook_id) = path.into_inner();
+
+    let hook = match find_owned_hook(&req, &db, &repo_name, hook_id) {
+        Ok(hook) => hook,
+        Err(response) => return Ok(response),
+    };
+
+    match Webhook::delete(hook.id.unwrap(), db.get_connection()) {
+        Ok(()) => Ok(HttpResponse::Ok().json(ApiResponse::<()> {
+            success: true,
+            message: Some("Webhook deleted successfully".to_string()),
+            data: None,
+        })),
+        Err(e) => {
+            error!("Failed to delete webhook: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to delete webhook".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
+
+/// Журнал доставок webhook, от новых к старым (только владелец)
+pub async fn list_deliveries(
+    req: HttpRequest,
+    path: web::Path<(String, i64)>,
+    query: web::Query<DeliveriesQuery>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let (repo_name, hook_id) = path.into_inner();
+
+    let hook = match find_owned_hook(&req, &db, &repo_name, hook_id) {
+        Ok(hook) => hook,
+        Err(response) => return Ok(response),
+    };
+
+    let (limit, offset) = page_bounds(query.page, query.per_page);
+    match WebhookDelivery::find_by_hook(hook.id.unwrap(), limit, offset, db.get_connection()) {
+        Ok(deliveries) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(deliveries),
+        })),
+        Err(e) => {
+            error!("Failed to fetch webhook deliveries: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to fetch webhook deliveries".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
+
+/// Повторно отправляет тело прошлой доставки (только владелец)
+///
+/// Повтор записывается в журнал как новая доставка и возвращается в ответе.
+/// `X-Delivery-Id` у повтора тот же, что у исходной доставки, а метка времени и подпись новые.
+pub async fn redeliver(
+    req: HttpRequest,
+    path: web::Path<(String, i64, i64)>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let (repo_name, hook_id, delivery_id) = path.into_inner();
+
+    let hook = match find_owned_hook(&req, &db, &repo_name, hook_id) {
+        Ok(hook) => hook,
+        Err(response) => return Ok(response),
+    };
+
+    let delivery = match WebhookDelivery::find_by_id(hook.id.unwrap(), delivery_id, db.get_connection()) {
+        Ok(Some(delivery)) => delivery,
+        Ok(None) => {
+            return Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Delivery not found".to_string()),
+                data: None,
+            }));
+        }
+        Err(e) => {
+            error!("Failed to fetch webhook delivery: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to fetch webhook delivery".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    let job_db = db.clone();
+    let redelivered = web::block(move || {
+        let guid = delivery.guid.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        deliver_and_record(&hook, &delivery.event, &guid, &delivery.payload, &job_db)
+    }).await;
+
+    match redelivered {
+        Ok(Some(delivery)) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(delivery),
+        })),
+        Ok(None) | Err(_) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            message: Some("Failed to redeliver webhook".to_string()),
+            data: None,
+        })),
+    }
+}
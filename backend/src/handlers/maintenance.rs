@@ -0,0 +1,178 @@
+use actix_web::{web, HttpResponse, HttpRequest, ResponseError, Result};
+use std::path::Path;
+use log::{error, warn};
+use crate::error::AppError;
+use crate::git::fsck::{run_fsck, try_start, unreachable_objects, FsckReport};
+use crate::git::pool::run_blocking;
+use crate::git::{resolve_repo, REPOS_ROOT};
+use crate::handlers::admin::is_admin;
+use crate::handlers::api::{check_auth, lookup_repo, ApiResponse};
+use crate::models::db::Database;
+use crate::models::notification::Notification;
+use crate::models::repository::Repository;
+
+/// Проверяет целостность репозитория через `git fsck --full` (владелец или администратор)
+///
+/// Проверка идёт в пуле git процессов; одновременно для одного репозитория
+/// выполняется не больше одной. О найденных повреждениях владелец получает уведомление.
+pub async fn fsck_repo(
+    req: HttpRequest,
+    path: web::Path<String>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let repo = match find_maintained_repo(&req, &db, &repo_name) {
+        Ok(repo) => repo,
+        Err(response) => return Ok(response),
+    };
+
+    let repo_path = match resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()) {
+        Ok(path) => path,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    let guard = match try_start(&repo.full_name()) {
+        Some(guard) => guard,
+        None => {
+            return Ok(AppError::Conflict("fsck is already running for this repository".to_string()).error_response());
+        }
+    };
+
+    let result = run_blocking(move || {
+        let _guard = guard;
+        run_fsck(&repo_path)
+    }).await;
+
+    let report = match result {
+        Ok(Ok(report)) => report,
+        Ok(Err(e)) | Err(e) => return Ok(e.error_response()),
+    };
+
+    if !report.ok {
+        warn!("git fsck found problems in {}: {:?}", repo.full_name(), report.errors);
+        notify_corruption(&repo, &report, &db);
+    }
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: None,
+        data: Some(report),
+    }))
+}
+
+/// Объекты, недостижимые ни из одной ссылки, - то, что удалит gc (владелец или администратор)
+///
+/// Запускает `git fsck --unreachable` в пуле git процессов с тем же таймаутом и
+/// ограничением «один fsck на репозиторий», что и `fsck_repo`.
+pub async fn list_unreachable(
+    req: HttpRequest,
+    path: web::Path<String>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let repo = match find_maintained_repo(&req, &db, &repo_name) {
+        Ok(repo) => repo,
+        Err(response) => return Ok(response),
+    };
+
+    let repo_path = match resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()) {
+        Ok(path) => path,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    let guard = match try_start(&repo.full_name()) {
+        Some(guard) => guard,
+        None => {
+            return Ok(AppError::Conflict("fsck is already running for this repository".to_string()).error_response());
+        }
+    };
+
+    let result = run_blocking(move || {
+        let _guard = guard;
+        unreachable_objects(&repo_path)
+    }).await;
+
+    match result {
+        Ok(Ok(report)) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(report),
+        })),
+        Ok(Err(e)) | Err(e) => Ok(e.error_response()),
+    }
+}
+
+/// Находит репозиторий для обслуживания: доступен владельцу и администратору
+///
+/// # Параметры
+///
+/// * `req` - HTTP запрос
+/// * `db` - База данных
+/// * `repo_name` - Имя репозитория
+///
+/// # Возвращает
+///
+/// * `Result<Repository, HttpResponse>` - Репозиторий или готовый ответ: 401, 403 для
+///   публичного чужого репозитория, 404 для приватного
+fn find_maintained_repo(req: &HttpRequest, db: &web::Data<Database>, repo_name: &str) -> Result<Repository, HttpResponse> {
+    let user = match check_auth(req, db) {
+        Some(user) => user,
+        None => {
+            return Err(HttpResponse::Unauthorized().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Unauthorized".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    match lookup_repo(req, db, repo_name, Some(&user)) {
+        Ok(Some(repo)) if repo.owner_id == user.id.unwrap_or_default() || is_admin(&user) => Ok(repo),
+        Ok(Some(repo)) if repo.is_public => {
+            Err(HttpResponse::Forbidden().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Only repository owner or an administrator can run fsck".to_string()),
+                data: None,
+            }))
+        }
+        Ok(_) => {
+            Err(HttpResponse::NotFound().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Repository not found".to_string()),
+                data: None,
+            }))
+        }
+        Err(AppError::Database(e)) => {
+            error!("Database error: {}", e);
+            Err(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }))
+        }
+        Err(e) => Err(e.error_response()),
+    }
+}
+
+/// Уведомляет владельца о найденных fsck повреждениях
+fn notify_corruption(repo: &Repository, report: &FsckReport, db: &web::Data<Database>) {
+    let notification = Notification {
+        id: None,
+        notification_type: "repository_corruption".to_string(),
+        title: format!("git fsck found problems in {}", repo.name),
+        content: format!(
+            "{} problem(s) reported, first: {}",
+            report.errors.len(),
+            report.errors.first().map(String::as_str).unwrap_or("git fsck exited with an error")
+        ),
+        user_id: repo.owner_id,
+        is_read: false,
+        created_at: None,
+    };
+
+    if let Err(e) = notification.create(db.get_connection()) {
+        error!("Failed to create corruption notification: {}", e);
+    }
+}
@@ -0,0 +1,111 @@
+use actix_web::{http::header, web, HttpRequest, HttpResponse, Result};
+use serde::Serialize;
+use crate::config::CONFIG;
+use crate::git::version::GitCapabilities;
+use crate::handlers::api::{external_base_url, ApiResponse};
+use crate::readiness::{is_ready, RETRY_AFTER_SECS};
+
+/// Возможности сервера, от которых зависит поведение клиентов
+#[derive(Serialize)]
+struct Features {
+    lfs: bool,
+    protocol_v2: bool,
+    partial_clone: bool,
+    webhooks: bool,
+}
+
+/// Ограничения сервера
+#[derive(Serialize)]
+struct Limits {
+    /// Максимальный размер тела запроса в байтах
+    max_body: usize,
+    /// Максимальный размер репозитория в байтах; None - без ограничений
+    max_repo_size: Option<u64>,
+}
+
+/// Описание сервера для `GET /api/meta`
+#[derive(Serialize)]
+struct Meta {
+    version: &'static str,
+    git_version: Option<String>,
+    features: Features,
+    limits: Limits,
+}
+
+/// Версия сервера, версия git, поддерживаемые возможности и ограничения
+pub async fn get_meta(git: web::Data<GitCapabilities>) -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: None,
+        data: Some(Meta {
+            version: env!("CARGO_PKG_VERSION"),
+            git_version: git.version.clone(),
+            features: Features {
+                lfs: false,
+                // Заголовок Git-Protocol передаётся git как есть, поэтому v2 зависит от версии git
+                protocol_v2: git.protocol_v2,
+                partial_clone: git.filter,
+                webhooks: true,
+            },
+            limits: Limits {
+                max_body: CONFIG.max_body_bytes,
+                max_repo_size: None,
+            },
+        }),
+    }))
+}
+
+/// Описание сервера для `GET /`
+#[derive(Serialize)]
+struct Index {
+    name: &'static str,
+    version: &'static str,
+    /// Документация (GIT_HTTP_DOCS_URL)
+    docs_url: Option<String>,
+    /// Адрес REST API
+    api_base: String,
+}
+
+/// Корневой адрес: по нему можно понять, что это git сервер, и найти API
+///
+/// Не требует авторизации и не обращается к базе данных и git.
+pub async fn index(req: HttpRequest) -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: None,
+        data: Some(Index {
+            name: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            docs_url: CONFIG.docs_url.clone(),
+            api_base: format!("{}/api", external_base_url(&req)),
+        }),
+    }))
+}
+
+/// Состояние сервера для `GET /health`
+#[derive(Serialize)]
+struct Health {
+    /// `starting` - задачи запуска ещё выполняются, `ok` - сервер готов
+    status: &'static str,
+}
+
+/// Проверка готовности для балансировщиков и оркестраторов
+///
+/// Пока сервер запускается, отвечает 503 с `Retry-After`, после - 200.
+pub async fn health() -> Result<HttpResponse> {
+    if !is_ready() {
+        return Ok(HttpResponse::ServiceUnavailable()
+            .insert_header((header::RETRY_AFTER, RETRY_AFTER_SECS.to_string()))
+            .json(ApiResponse {
+                success: false,
+                message: Some("Server is starting, retry later".to_string()),
+                data: Some(Health { status: "starting" }),
+            }));
+    }
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: None,
+        data: Some(Health { status: "ok" }),
+    }))
+}
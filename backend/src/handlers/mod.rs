@@ -1 +1,20 @@
-pub mod api; 
\ No newline at end of file
+pub mod admin;
+pub mod api;
+pub mod archive;
+pub mod bundles;
+pub mod blame;
+pub mod contents;
+pub mod diff;
+pub mod export;
+pub mod forks;
+pub mod git;
+pub mod hooks;
+pub mod maintenance;
+pub mod meta;
+pub mod pins;
+pub mod refs;
+pub mod releases;
+pub mod secrets;
+pub mod stats;
+pub mod subscriptions;
+pub mod topics;
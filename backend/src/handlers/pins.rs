@@ -0,0 +1,150 @@
+use actix_web::{web, HttpResponse, HttpRequest, Result};
+use serde::Deserialize;
+use log::error;
+use crate::git::full_repo_name;
+use crate::handlers::api::{can_access, check_auth, ApiResponse};
+use crate::models::db::Database;
+use crate::models::store::Store;
+use crate::models::repository::Repository;
+use crate::models::user::User;
+
+/// Сколько репозиториев можно закрепить в профиле
+pub const MAX_PINNED_REPOS: usize = 6;
+
+/// Запрос на изменение закреплённых репозиториев
+#[derive(Deserialize)]
+pub struct PinnedReposRequest {
+    /// Репозитории в порядке показа: `{owner}/{name}` или имя своего репозитория;
+    /// пустой список открепляет все
+    pub repos: Vec<String>,
+}
+
+/// Закреплённые репозитории пользователя, видимые `viewer`
+///
+/// Ошибка базы данных не ломает профиль: в лог пишется ошибка, а список остаётся пустым.
+pub fn visible_pinned_repos(user: &User, viewer: Option<&User>, db: &web::Data<Database>) -> Vec<Repository> {
+    let viewer_id = viewer.and_then(|viewer| viewer.id);
+    match Repository::find_pinned(user.id.unwrap_or_default(), viewer_id, db.get_connection()) {
+        Ok(repos) => repos,
+        Err(e) => {
+            error!("Failed to fetch pinned repositories: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Получение закреплённых в профиле репозиториев пользователя
+///
+/// Приватные репозитории видит только их владелец.
+pub async fn get_pinned_repos(
+    req: HttpRequest,
+    path: web::Path<String>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let username = path.into_inner();
+
+    match db.find_user_by_username(&username) {
+        Ok(Some(user)) => {
+            let viewer = check_auth(&req, &db);
+            Ok(HttpResponse::Ok().json(ApiResponse {
+                success: true,
+                message: None,
+                data: Some(visible_pinned_repos(&user, viewer.as_ref(), &db)),
+            }))
+        },
+        Ok(None) => Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+            success: false,
+            message: Some("User not found".to_string()),
+            data: None,
+        })),
+        Err(e) => {
+            error!("Database error: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
+
+/// Замена списка закреплённых в профиле репозиториев текущего пользователя
+///
+/// Закрепить можно до `MAX_PINNED_REPOS` репозиториев, которые пользователь может
+/// читать: свои или публичные. Порядок в запросе сохраняется.
+pub async fn set_pinned_repos(
+    req: HttpRequest,
+    body: web::Json<PinnedReposRequest>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let user = match check_auth(&req, &db) {
+        Some(user) => user,
+        None => {
+            return Ok(HttpResponse::Unauthorized().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Unauthorized".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    let names = body.into_inner().repos;
+    if names.len() > MAX_PINNED_REPOS {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: Some(format!("At most {} repositories can be pinned", MAX_PINNED_REPOS)),
+            data: None,
+        }));
+    }
+
+    let mut repo_ids: Vec<i64> = Vec::with_capacity(names.len());
+    for name in &names {
+        // Имя без владельца - репозиторий самого пользователя
+        let full_name = match name.contains('/') {
+            true => name.clone(),
+            false => full_repo_name(&user.username, name),
+        };
+        let repo_id = match db.find_repository(&full_name) {
+            Ok(Some(repo)) if can_access(&repo, Some(&user)) => repo.id.unwrap_or_default(),
+            Ok(_) => {
+                return Ok(HttpResponse::NotFound().json(ApiResponse::<()> {
+                    success: false,
+                    message: Some(format!("Repository {} not found", name)),
+                    data: None,
+                }));
+            },
+            Err(e) => {
+                error!("Database error: {}", e);
+                return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                    success: false,
+                    message: Some("Database error".to_string()),
+                    data: None,
+                }));
+            }
+        };
+
+        if repo_ids.contains(&repo_id) {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: Some(format!("Repository {} is listed more than once", name)),
+                data: None,
+            }));
+        }
+        repo_ids.push(repo_id);
+    }
+
+    if let Err(e) = Repository::set_pinned(user.id.unwrap_or_default(), &repo_ids, db.get_connection()) {
+        error!("Failed to update pinned repositories: {}", e);
+        return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            message: Some("Failed to update pinned repositories".to_string()),
+            data: None,
+        }));
+    }
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: Some("Pinned repositories updated".to_string()),
+        data: Some(visible_pinned_repos(&user, Some(&user), &db)),
+    }))
+}
@@ -0,0 +1,476 @@
+use actix_web::{web, HttpResponse, HttpRequest, Result, ResponseError};
+use serde::{Serialize, Deserialize};
+use std::path::Path;
+use log::{error, warn};
+use crate::bundle_uri;
+use crate::clock::{Clock, SystemClock};
+use crate::cursor::Cursor;
+use crate::git::{advertise, resolve_repo, REPOS_ROOT};
+use crate::git::bundle::{bundle_uri_enabled, disable_bundle_uri, enable_bundle_uri};
+use crate::git::branches::stale_branches;
+use crate::git::pool::run_blocking;
+use crate::git::refs::{hidden_refs, receive_protection, resolve_refs, set_hidden_refs, set_receive_protection, ReceiveProtection};
+use crate::git::version::GitCapabilities;
+use crate::handlers::api::{
+    bump_ref_generation, cursor_page_response, external_base_url, find_owned_repo, find_readable_repo, page_bounds, parse_cursor,
+    ApiResponse,
+};
+use crate::models::db::Database;
+use crate::models::push::Push;
+use crate::models::repository::{MergeMethods, Repository};
+
+/// Список скрытых ссылок репозитория
+#[derive(Serialize, Deserialize)]
+pub struct HiddenRefs {
+    /// Префиксы ссылок, например `refs/keep/`
+    pub hidden_refs: Vec<String>,
+}
+
+/// Получение префиксов ссылок, скрытых от clone/fetch/push
+pub async fn get_hidden_refs(
+    req: HttpRequest,
+    path: web::Path<String>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let repo = match find_owned_repo(&req, &db, &repo_name) {
+        Ok((repo, _)) => repo,
+        Err(response) => return Ok(response),
+    };
+
+    let repo_path = match resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()) {
+        Ok(path) => path,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    match hidden_refs(&repo_path) {
+        Ok(hidden_refs) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(HiddenRefs { hidden_refs }),
+        })),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+/// Замена списка скрытых ссылок (только владелец репозитория)
+pub async fn update_hidden_refs(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<HiddenRefs>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let repo = match find_owned_repo(&req, &db, &repo_name) {
+        Ok((repo, _)) => repo,
+        Err(response) => return Ok(response),
+    };
+
+    let repo_path = match resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()) {
+        Ok(path) => path,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    // Скрытые ссылки меняют объявление ссылок так же, как push
+    bump_ref_generation(&repo.full_name(), &db);
+    let result = set_hidden_refs(&repo_path, &body.hidden_refs);
+    bump_ref_generation(&repo.full_name(), &db);
+
+    match result {
+        Ok(()) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: Some("Hidden refs updated successfully".to_string()),
+            data: Some(body.into_inner()),
+        })),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+/// Параметры постраничного вывода истории push'ей
+#[derive(Deserialize)]
+pub struct PushesQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+    /// `next_cursor` предыдущей страницы; вместе с ним `page` не учитывается
+    pub cursor: Option<String>,
+}
+
+/// Последние push'и в репозиторий со статистикой изменений по каждой ссылке
+///
+/// Для листания истории предпочтителен `cursor`: он не сдвигается от новых push'ей.
+pub async fn list_pushes(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<PushesQuery>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let (repo, _) = match find_readable_repo(&req, &db, &repo_name) {
+        Ok(found) => found,
+        Err(response) => return Ok(response),
+    };
+
+    let before = match parse_cursor(query.cursor.as_deref()) {
+        Ok(before) => before,
+        Err(response) => return Ok(response),
+    };
+    let (limit, offset) = page_bounds(query.page, query.per_page);
+    let offset = if before.is_some() { 0 } else { offset };
+
+    match Push::find_by_repository(repo.id.unwrap(), limit + 1, offset, before.as_ref(), db.get_connection()) {
+        Ok(pushes) => Ok(cursor_page_response(pushes, limit, |push| {
+            Cursor::after(push.created_at.as_ref(), push.id.unwrap_or_default())
+        })),
+        Err(e) => {
+            error!("Failed to fetch pushes: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to fetch pushes".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
+
+/// Настройки репозитория
+#[derive(Serialize)]
+struct RepoSettings {
+    /// Запрещено удаление веток и тегов через push
+    deny_deletes: bool,
+    /// Запрещён force-push
+    deny_non_fast_forwards: bool,
+    /// Пул-реквесты сливаются только перемоткой
+    require_linear_history: bool,
+    /// Push принимается только с подписанными коммитами
+    require_signed_commits: bool,
+    /// Включены ли пул-реквесты (хранится в базе данных, а не в конфигурации git)
+    pull_requests_enabled: bool,
+    /// Разрешённые способы слияния пул-реквестов (тоже в базе данных)
+    #[serde(flatten)]
+    merge_methods: MergeMethods,
+    /// Первый clone получает историю из заранее созданного bundle (`bundle-uri`)
+    bundle_uri: bool,
+}
+
+impl RepoSettings {
+    fn new(protection: ReceiveProtection, pull_requests_enabled: bool, merge_methods: MergeMethods, bundle_uri: bool) -> Self {
+        RepoSettings {
+            deny_deletes: protection.deny_deletes,
+            deny_non_fast_forwards: protection.deny_non_fast_forwards,
+            require_linear_history: protection.require_linear_history,
+            require_signed_commits: protection.require_signed_commits,
+            pull_requests_enabled,
+            merge_methods,
+            bundle_uri,
+        }
+    }
+}
+
+/// Запрос на изменение настроек; отсутствующие поля не меняются
+#[derive(Deserialize)]
+pub struct UpdateRepoSettingsRequest {
+    pub deny_deletes: Option<bool>,
+    pub deny_non_fast_forwards: Option<bool>,
+    pub require_linear_history: Option<bool>,
+    pub require_signed_commits: Option<bool>,
+    pub pull_requests_enabled: Option<bool>,
+    pub allow_merge_commit: Option<bool>,
+    pub allow_squash: Option<bool>,
+    pub allow_rebase: Option<bool>,
+    pub bundle_uri: Option<bool>,
+}
+
+/// Получение настроек репозитория (только владелец репозитория)
+///
+/// `pull_requests_enabled` - собственная настройка репозитория; если пул-реквесты
+/// выключены на сервере, она ни на что не влияет.
+pub async fn get_settings(
+    req: HttpRequest,
+    path: web::Path<String>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let (repo, _) = match find_owned_repo(&req, &db, &repo_name) {
+        Ok(found) => found,
+        Err(response) => return Ok(response),
+    };
+
+    let repo_path = match resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()) {
+        Ok(path) => path,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    let pull_requests_enabled = match Repository::pull_requests_enabled(repo.id.unwrap(), db.get_connection()) {
+        Ok(enabled) => enabled,
+        Err(e) => {
+            error!("Failed to read repository settings: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }));
+        }
+    };
+    let merge_methods = match Repository::merge_methods(repo.id.unwrap(), db.get_connection()) {
+        Ok(methods) => methods,
+        Err(e) => {
+            error!("Failed to read repository settings: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    let bundle_uri = match bundle_uri_enabled(&repo_path) {
+        Ok(enabled) => enabled,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    match receive_protection(&repo_path) {
+        Ok(protection) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(RepoSettings::new(protection, pull_requests_enabled, merge_methods, bundle_uri)),
+        })),
+        Err(e) => Ok(e.error_response()),
+    }
+}
+
+/// Изменение настроек репозитория (только владелец репозитория)
+///
+/// Включение `bundle_uri` ставит создание bundle в очередь: upload-pack начнёт
+/// объявлять его, когда bundle будет готов. Выключение сразу убирает объявление
+/// и удаляет bundle. Запретить все способы слияния нельзя - ответ 400.
+pub async fn update_settings(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<UpdateRepoSettingsRequest>,
+    db: web::Data<Database>,
+    git: web::Data<GitCapabilities>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let (repo, _) = match find_owned_repo(&req, &db, &repo_name) {
+        Ok(found) => found,
+        Err(response) => return Ok(response),
+    };
+    let repo_id = repo.id.unwrap();
+
+    let repo_path = match resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()) {
+        Ok(path) => path,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    let mut merge_methods = match Repository::merge_methods(repo_id, db.get_connection()) {
+        Ok(methods) => methods,
+        Err(e) => {
+            error!("Failed to read repository settings: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }));
+        }
+    };
+    if let Some(allow_merge_commit) = body.allow_merge_commit {
+        merge_methods.allow_merge_commit = allow_merge_commit;
+    }
+    if let Some(allow_squash) = body.allow_squash {
+        merge_methods.allow_squash = allow_squash;
+    }
+    if let Some(allow_rebase) = body.allow_rebase {
+        merge_methods.allow_rebase = allow_rebase;
+    }
+    if merge_methods.allowed().is_empty() {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: Some("At least one merge method must be allowed".to_string()),
+            data: None,
+        }));
+    }
+    if let Err(e) = Repository::set_merge_methods(repo_id, &merge_methods, db.get_connection()) {
+        error!("Failed to update repository settings: {}", e);
+        return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+            success: false,
+            message: Some("Database error".to_string()),
+            data: None,
+        }));
+    }
+
+    if let Some(enabled) = body.pull_requests_enabled {
+        if let Err(e) = Repository::set_pull_requests_enabled(repo_id, enabled, db.get_connection()) {
+            error!("Failed to update repository settings: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }));
+        }
+    }
+    let pull_requests_enabled = match Repository::pull_requests_enabled(repo_id, db.get_connection()) {
+        Ok(enabled) => enabled,
+        Err(e) => {
+            error!("Failed to read repository settings: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Database error".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    let mut protection = match receive_protection(&repo_path) {
+        Ok(protection) => protection,
+        Err(e) => return Ok(e.error_response()),
+    };
+    if let Some(deny_deletes) = body.deny_deletes {
+        protection.deny_deletes = deny_deletes;
+    }
+    if let Some(deny_non_fast_forwards) = body.deny_non_fast_forwards {
+        protection.deny_non_fast_forwards = deny_non_fast_forwards;
+    }
+    if let Some(require_linear_history) = body.require_linear_history {
+        protection.require_linear_history = require_linear_history;
+    }
+    if let Some(require_signed_commits) = body.require_signed_commits {
+        protection.require_signed_commits = require_signed_commits;
+    }
+
+    if let Err(e) = set_receive_protection(&repo_path, protection) {
+        return Ok(e.error_response());
+    }
+
+    let mut bundle_uri = match bundle_uri_enabled(&repo_path) {
+        Ok(enabled) => enabled,
+        Err(e) => return Ok(e.error_response()),
+    };
+    match body.bundle_uri {
+        Some(true) if !bundle_uri => {
+            if !git.bundle_uri {
+                warn!("git {} cannot advertise bundle URIs, {} will only host its bundle",
+                    git.version.as_deref().unwrap_or("unknown"), repo.full_name());
+            }
+            let uri = format!("{}/git/{}/bundle", external_base_url(&req), repo.full_name());
+            if let Err(e) = enable_bundle_uri(&repo_path, &uri) {
+                return Ok(e.error_response());
+            }
+            bundle_uri::schedule(&repo.full_name(), 0, &db);
+            bundle_uri = true;
+        }
+        Some(false) if bundle_uri => {
+            if let Err(e) = disable_bundle_uri(&repo_path, &repo.full_name()) {
+                return Ok(e.error_response());
+            }
+            // Объявление bundle-uri входит в ответ info/refs
+            advertise::purge(&repo.full_name());
+            bump_ref_generation(&repo.full_name(), &db);
+            bundle_uri = false;
+        }
+        _ => {}
+    }
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: Some("Repository settings updated successfully".to_string()),
+        data: Some(RepoSettings::new(protection, pull_requests_enabled, merge_methods, bundle_uri)),
+    }))
+}
+
+/// Максимальное количество ссылок в одном запросе на разрешение
+const MAX_RESOLVE_REFS: usize = 100;
+
+/// Запрос на разрешение нескольких ссылок
+#[derive(Deserialize)]
+pub struct ResolveRefsRequest {
+    /// Имена веток, тегов, полные имена ссылок или SHA
+    pub refs: Vec<String>,
+}
+
+/// Разрешение нескольких ссылок в SHA за один запрос
+///
+/// Неразрешённые (в том числе недопустимые) имена получают `null`.
+pub async fn resolve_refs_batch(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<ResolveRefsRequest>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let repo = match find_readable_repo(&req, &db, &repo_name) {
+        Ok((repo, _)) => repo,
+        Err(response) => return Ok(response),
+    };
+
+    if body.refs.len() > MAX_RESOLVE_REFS {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: Some(format!("At most {} refs can be resolved per request", MAX_RESOLVE_REFS)),
+            data: None,
+        }));
+    }
+
+    let repo_path = match resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()) {
+        Ok(path) => path,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    let refs = body.into_inner().refs;
+    match run_blocking(move || resolve_refs(&repo_path, &refs)).await {
+        Ok(Ok(resolved)) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(resolved),
+        })),
+        Ok(Err(e)) | Err(e) => Ok(e.error_response()),
+    }
+}
+
+/// Порог по умолчанию для заброшенных веток, в днях
+const DEFAULT_STALE_DAYS: u32 = 90;
+
+/// Параметры поиска заброшенных веток
+#[derive(Deserialize)]
+pub struct StaleBranchesQuery {
+    /// Ветка без коммитов дольше этого числа дней считается заброшенной
+    pub days: Option<u32>,
+}
+
+/// Ветки, влитые в ветку по умолчанию или без коммитов `days` дней (по умолчанию 90)
+///
+/// Ветка по умолчанию в список не попадает.
+pub async fn list_stale_branches(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<StaleBranchesQuery>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let repo = match find_readable_repo(&req, &db, &repo_name) {
+        Ok((repo, _)) => repo,
+        Err(response) => return Ok(response),
+    };
+
+    let repo_path = match resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()) {
+        Ok(path) => path,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    let days = query.days.unwrap_or(DEFAULT_STALE_DAYS);
+    let older_than = SystemClock.now().timestamp() - i64::from(days) * 24 * 60 * 60;
+    match run_blocking(move || stale_branches(&repo_path, older_than)).await {
+        Ok(Ok(branches)) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(branches),
+        })),
+        Ok(Err(e)) | Err(e) => Ok(e.error_response()),
+    }
+}
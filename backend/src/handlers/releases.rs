@@ -0,0 +1,103 @@
+use actix_web::{web, HttpResponse, HttpRequest, Result, ResponseError};
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use crate::config::CONFIG;
+use crate::git::{resolve_commit, resolve_repo, REPOS_ROOT};
+use crate::git::log::{commits_between, CommitSummary};
+use crate::handlers::api::{find_readable_repo, ApiResponse};
+use crate::models::db::Database;
+
+/// Параметры запроса заметок к релизу
+#[derive(Deserialize)]
+pub struct ReleaseNotesQuery {
+    /// Предыдущий релиз (тег или любая ревизия)
+    pub from: String,
+    /// Новый релиз
+    pub to: String,
+    /// Группировать коммиты по типу conventional commits (`feat:`, `fix:` и т.д.)
+    #[serde(default)]
+    pub group: bool,
+}
+
+/// Заметки к релизу
+#[derive(Serialize)]
+struct ReleaseNotes {
+    from: String,
+    to: String,
+    commits: Vec<CommitSummary>,
+    /// Коммитов больше GIT_HTTP_MAX_LOG_COMMITS, в `commits` только самые новые
+    truncated: bool,
+    /// Коммиты по типам; ключ `other` - для сообщений без префикса типа
+    groups: Option<BTreeMap<String, Vec<CommitSummary>>>,
+}
+
+/// Генерация заметок к релизу по коммитам между двумя ревизиями
+pub async fn release_notes(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<ReleaseNotesQuery>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let repo = match find_readable_repo(&req, &db, &repo_name) {
+        Ok((repo, _)) => repo,
+        Err(response) => return Ok(response),
+    };
+
+    let repo_path = match resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()) {
+        Ok(path) => path,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    // Убеждаемся, что обе ревизии существуют, прежде чем запускать git log
+    let (from, to) = match (resolve_commit(&repo_path, &query.from), resolve_commit(&repo_path, &query.to)) {
+        (Ok(from), Ok(to)) => (from, to),
+        (Err(e), _) | (_, Err(e)) => return Ok(e.error_response()),
+    };
+
+    // Лишний коммит показывает, что список обрезан
+    let mut commits = match commits_between(&repo_path, &from, &to, true, CONFIG.max_log_commits + 1) {
+        Ok(commits) => commits,
+        Err(e) => return Ok(e.error_response()),
+    };
+    let truncated = commits.len() > CONFIG.max_log_commits;
+    commits.truncate(CONFIG.max_log_commits);
+
+    let groups = query.group.then(|| {
+        let mut groups: BTreeMap<String, Vec<CommitSummary>> = BTreeMap::new();
+        for commit in &commits {
+            groups.entry(conventional_type(&commit.subject)).or_default().push(commit.clone());
+        }
+        groups
+    });
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: None,
+        data: Some(ReleaseNotes {
+            from: query.from.clone(),
+            to: query.to.clone(),
+            commits,
+            truncated,
+            groups,
+        }),
+    }))
+}
+
+/// Определяет тип коммита по префиксу `type(scope)!: subject`
+fn conventional_type(subject: &str) -> String {
+    let prefix = match subject.split_once(':') {
+        Some((prefix, _)) => prefix,
+        None => return "other".to_string(),
+    };
+
+    let kind = prefix.split('(').next().unwrap_or_default().trim_end_matches('!');
+
+    if !kind.is_empty() && kind.chars().all(|c| c.is_ascii_alphabetic()) {
+        kind.to_lowercase()
+    } else {
+        "other".to_string()
+    }
+}
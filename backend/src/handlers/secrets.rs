@@ -0,0 +1,45 @@
+use actix_web::{web, HttpResponse, HttpRequest, Result};
+use serde::Deserialize;
+use log::error;
+use crate::handlers::api::{find_owned_repo, page_bounds, ApiResponse};
+use crate::models::db::Database;
+use crate::models::secret_alert::SecretAlert;
+
+/// Параметры постраничного вывода предупреждений
+#[derive(Deserialize)]
+pub struct SecretAlertsQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+/// Предупреждения о возможных секретах в коммитах репозитория (только владелец)
+pub async fn list_secret_alerts(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<SecretAlertsQuery>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let (repo, _) = match find_owned_repo(&req, &db, &repo_name) {
+        Ok(found) => found,
+        Err(response) => return Ok(response),
+    };
+
+    let (limit, offset) = page_bounds(query.page, query.per_page);
+    match SecretAlert::find_by_repository(repo.id.unwrap(), limit, offset, db.get_connection()) {
+        Ok(alerts) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(alerts),
+        })),
+        Err(e) => {
+            error!("Failed to fetch secret alerts: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to fetch secret alerts".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
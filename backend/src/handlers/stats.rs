@@ -0,0 +1,155 @@
+use actix_web::{web, HttpResponse, HttpRequest, Result, ResponseError};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use log::error;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use crate::error::AppError;
+use crate::git::contributors::contributors;
+use crate::git::languages::language_breakdown;
+use crate::git::pool::run_blocking;
+use crate::git::{resolve_commit, resolve_repo, REPOS_ROOT};
+use crate::handlers::api::{find_readable_repo, ApiResponse};
+use crate::models::db::Database;
+use crate::models::user::User;
+use crate::timestamps::parse_datetime;
+
+/// Размер файлов по языкам в ветке по умолчанию: `{ язык: байты }`
+///
+/// Для пустого репозитория возвращается пустой объект.
+pub async fn get_languages(
+    req: HttpRequest,
+    path: web::Path<String>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let repo = match find_readable_repo(&req, &db, &repo_name) {
+        Ok((repo, _)) => repo,
+        Err(response) => return Ok(response),
+    };
+
+    let repo_path = match resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()) {
+        Ok(path) => path,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    let full_name = repo.full_name();
+    let result = run_blocking(move || match resolve_commit(&repo_path, "HEAD") {
+        Ok(sha) => language_breakdown(&full_name, &repo_path, &sha),
+        Err(AppError::RefNotFound(_)) => Ok(BTreeMap::new()),
+        Err(e) => Err(e),
+    }).await;
+
+    match result {
+        Ok(Ok(languages)) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(languages),
+        })),
+        Ok(Err(e)) | Err(e) => Ok(e.error_response()),
+    }
+}
+
+/// Параметры запроса авторов
+#[derive(Deserialize)]
+pub struct ContributorsQuery {
+    /// Учитывать коммиты начиная с этой даты (`YYYY-MM-DD` или RFC3339)
+    pub since: Option<String>,
+    /// Учитывать коммиты до этой даты включительно (`YYYY-MM-DD` или RFC3339)
+    pub until: Option<String>,
+}
+
+/// Разбирает дату из параметра запроса
+///
+/// Дата без времени означает начало дня, а для `end_of_day` - его последнюю секунду,
+/// чтобы `until=2024-03-31` включал 31 марта.
+///
+/// # Параметры
+///
+/// * `name` - Имя параметра для сообщения об ошибке
+/// * `value` - Значение параметра
+/// * `end_of_day` - Дату без времени считать концом дня
+///
+/// # Возвращает
+///
+/// * `Result<DateTime<Utc>, HttpResponse>` - Дата в UTC или ответ 400
+fn parse_date_param(name: &str, value: &str, end_of_day: bool) -> Result<DateTime<Utc>, HttpResponse> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let time = if end_of_day {
+            NaiveTime::from_hms_opt(23, 59, 59).unwrap_or_default()
+        } else {
+            NaiveTime::MIN
+        };
+        return Ok(date.and_time(time).and_utc());
+    }
+
+    parse_datetime(value).ok_or_else(|| {
+        HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: Some(format!("{} must be a date (YYYY-MM-DD) or an RFC3339 timestamp", name)),
+            data: None,
+        })
+    })
+}
+
+/// Авторы коммитов ветки по умолчанию за период, по убыванию числа коммитов
+///
+/// Автор, чей email совпадает с email зарегистрированного пользователя, получает
+/// `username`; остальные отдаются с именем и email из git. Для пустого
+/// репозитория возвращается пустой список.
+pub async fn get_contributors(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<ContributorsQuery>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let repo = match find_readable_repo(&req, &db, &repo_name) {
+        Ok((repo, _)) => repo,
+        Err(response) => return Ok(response),
+    };
+
+    let since = match query.since.as_deref().map(|value| parse_date_param("since", value, false)).transpose() {
+        Ok(since) => since,
+        Err(response) => return Ok(response),
+    };
+    let until = match query.until.as_deref().map(|value| parse_date_param("until", value, true)).transpose() {
+        Ok(until) => until,
+        Err(response) => return Ok(response),
+    };
+
+    let repo_path = match resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()) {
+        Ok(path) => path,
+        Err(e) => return Ok(e.error_response()),
+    };
+
+    let result = run_blocking(move || match resolve_commit(&repo_path, "HEAD") {
+        Ok(sha) => contributors(&repo_path, &sha, since, until),
+        Err(AppError::RefNotFound(_)) => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }).await;
+
+    let mut authors = match result {
+        Ok(Ok(authors)) => authors,
+        Ok(Err(e)) | Err(e) => return Ok(e.error_response()),
+    };
+
+    // Связь с пользователями не обязательна: без неё авторы отдаются как в git
+    let emails: Vec<String> = authors.iter().map(|author| author.email.clone()).collect();
+    match User::usernames_by_email(&emails, db.get_connection()) {
+        Ok(usernames) => {
+            for author in &mut authors {
+                author.username = usernames.get(&author.email.to_lowercase()).cloned();
+            }
+        }
+        Err(e) => error!("Failed to map contributors to users: {}", e),
+    }
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: None,
+        data: Some(authors),
+    }))
+}
@@ -0,0 +1,101 @@
+use actix_web::{web, HttpResponse, HttpRequest, Result};
+use serde::{Serialize, Deserialize};
+use log::error;
+use crate::handlers::api::{find_readable_repo, ApiResponse};
+use crate::models::db::Database;
+use crate::models::repository::Repository;
+use crate::models::subscription::{Subscription, SubscriptionLevel};
+use crate::models::user::User;
+
+/// Запрос на изменение подписки
+#[derive(Deserialize)]
+pub struct SubscriptionRequest {
+    /// `watch`, `ignore` или `participating`
+    pub level: SubscriptionLevel,
+}
+
+/// Подписка текущего пользователя на репозиторий
+#[derive(Serialize)]
+struct SubscriptionView {
+    level: SubscriptionLevel,
+}
+
+/// Находит репозиторий, доступный текущему пользователю; подписка требует авторизации
+fn find_subscribable_repo(
+    req: &HttpRequest,
+    db: &web::Data<Database>,
+    repo_name: &str,
+) -> std::result::Result<(Repository, User), HttpResponse> {
+    match find_readable_repo(req, db, repo_name)? {
+        (repo, Some(user)) => Ok((repo, user)),
+        (_, None) => Err(HttpResponse::Unauthorized().json(ApiResponse::<()> {
+            success: false,
+            message: Some("Unauthorized".to_string()),
+            data: None,
+        })),
+    }
+}
+
+/// Уровень подписки текущего пользователя на уведомления репозитория
+pub async fn get_subscription(
+    req: HttpRequest,
+    path: web::Path<String>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let (repo, user) = match find_subscribable_repo(&req, &db, &repo_name) {
+        Ok(found) => found,
+        Err(response) => return Ok(response),
+    };
+
+    match Subscription::level(user.id.unwrap(), repo.id.unwrap(), db.get_connection()) {
+        Ok(level) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: None,
+            data: Some(SubscriptionView { level }),
+        })),
+        Err(e) => {
+            error!("Failed to fetch subscription: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to fetch subscription".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
+
+/// Подписка на уведомления репозитория или отказ от них
+///
+/// `watch` - уведомления обо всех пул-реквестах и комментариях, `ignore` -
+/// никаких уведомлений, `participating` - только о своём (по умолчанию).
+pub async fn update_subscription(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<SubscriptionRequest>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let (repo, user) = match find_subscribable_repo(&req, &db, &repo_name) {
+        Ok(found) => found,
+        Err(response) => return Ok(response),
+    };
+
+    match Subscription::set(user.id.unwrap(), repo.id.unwrap(), body.level, db.get_connection()) {
+        Ok(()) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: Some("Subscription updated".to_string()),
+            data: Some(SubscriptionView { level: body.level }),
+        })),
+        Err(e) => {
+            error!("Failed to update subscription: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to update subscription".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
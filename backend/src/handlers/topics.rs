@@ -0,0 +1,188 @@
+use actix_web::{web, HttpResponse, HttpRequest, Result};
+use log::error;
+use serde::{Serialize, Deserialize};
+use crate::handlers::api::{check_auth, find_owned_repo, page_bounds, ApiResponse};
+use crate::models::db::Database;
+use crate::models::repository::Repository;
+
+/// Максимальное число тем у репозитория
+pub const MAX_TOPICS: usize = 20;
+/// Максимальная длина темы
+const MAX_TOPIC_LEN: usize = 35;
+
+/// Приводит тему к виду, в котором она хранится
+///
+/// Тема - строчные латинские буквы, цифры и дефисы, начинается с буквы или цифры,
+/// не длиннее `MAX_TOPIC_LEN` символов. Пробелы по краям убираются, регистр
+/// не учитывается.
+///
+/// # Параметры
+///
+/// * `topic` - Тема от клиента
+///
+/// # Возвращает
+///
+/// * `Result<String, String>` - Тема или сообщение об ошибке
+pub fn normalize_topic(topic: &str) -> Result<String, String> {
+    let topic = topic.trim().to_lowercase();
+
+    let valid = topic.len() <= MAX_TOPIC_LEN
+        && topic.chars().next().is_some_and(|c| c.is_ascii_alphanumeric())
+        && topic.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !valid {
+        return Err(format!(
+            "Invalid topic '{}': use lowercase letters, digits and hyphens, start with a letter or digit, at most {} characters",
+            topic, MAX_TOPIC_LEN
+        ));
+    }
+
+    Ok(topic)
+}
+
+/// Запрос на замену тем репозитория
+#[derive(Deserialize)]
+pub struct SetTopicsRequest {
+    pub topics: Vec<String>,
+}
+
+/// Темы репозитория
+#[derive(Serialize)]
+struct TopicsView {
+    topics: Vec<String>,
+}
+
+/// Заменяет темы репозитория (только владелец)
+///
+/// Темы нормализуются (`normalize_topic`), повторы убираются, порядок - по алфавиту.
+/// Пустой список убирает все темы.
+pub async fn set_topics(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<SetTopicsRequest>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let repo_name = path.into_inner();
+
+    let (repo, _) = match find_owned_repo(&req, &db, &repo_name) {
+        Ok(found) => found,
+        Err(response) => return Ok(response),
+    };
+
+    let mut topics = Vec::with_capacity(body.topics.len());
+    for topic in &body.topics {
+        match normalize_topic(topic) {
+            Ok(topic) => topics.push(topic),
+            Err(message) => {
+                return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                    success: false,
+                    message: Some(message),
+                    data: None,
+                }));
+            }
+        }
+    }
+    topics.sort();
+    topics.dedup();
+
+    if topics.len() > MAX_TOPICS {
+        return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+            success: false,
+            message: Some(format!("A repository can have at most {} topics", MAX_TOPICS)),
+            data: None,
+        }));
+    }
+
+    match Repository::set_topics(repo.id.unwrap(), &topics, db.get_connection()) {
+        Ok(()) => Ok(HttpResponse::Ok().json(ApiResponse {
+            success: true,
+            message: Some("Topics updated successfully".to_string()),
+            data: Some(TopicsView { topics }),
+        })),
+        Err(e) => {
+            error!("Failed to update topics: {}", e);
+            Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to update topics".to_string()),
+                data: None,
+            }))
+        }
+    }
+}
+
+/// Параметры обзора репозиториев
+#[derive(Deserialize)]
+pub struct ExploreQuery {
+    /// Только репозитории с этой темой
+    pub topic: Option<String>,
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+/// Репозиторий в обзоре: с владельцем и темами
+#[derive(Serialize)]
+struct ExploreView {
+    #[serde(flatten)]
+    repo: Repository,
+    topics: Vec<String>,
+}
+
+/// Обзор репозиториев, от новых к старым, с фильтром по теме
+///
+/// Видны публичные репозитории и приватные репозитории текущего пользователя.
+pub async fn explore(
+    req: HttpRequest,
+    query: web::Query<ExploreQuery>,
+    db: web::Data<Database>
+) -> Result<HttpResponse> {
+    let topic = match query.topic.as_deref().map(normalize_topic).transpose() {
+        Ok(topic) => topic,
+        Err(message) => {
+            return Ok(HttpResponse::BadRequest().json(ApiResponse::<()> {
+                success: false,
+                message: Some(message),
+                data: None,
+            }));
+        }
+    };
+
+    let viewer_id = check_auth(&req, &db).and_then(|user| user.id);
+    let (limit, offset) = page_bounds(query.page, query.per_page);
+    let repos = match Repository::explore(topic.as_deref(), viewer_id, limit, offset, db.get_connection()) {
+        Ok(repos) => repos,
+        Err(e) => {
+            error!("Failed to fetch repositories: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to fetch repositories".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    let ids: Vec<i64> = repos.iter().filter_map(|repo| repo.id).collect();
+    let mut topics = match Repository::topics_by_repository(&ids, db.get_connection()) {
+        Ok(topics) => topics,
+        Err(e) => {
+            error!("Failed to fetch topics: {}", e);
+            return Ok(HttpResponse::InternalServerError().json(ApiResponse::<()> {
+                success: false,
+                message: Some("Failed to fetch repositories".to_string()),
+                data: None,
+            }));
+        }
+    };
+
+    Ok(HttpResponse::Ok().json(ApiResponse {
+        success: true,
+        message: None,
+        data: Some(
+            repos
+                .into_iter()
+                .map(|repo| ExploreView {
+                    topics: repo.id.and_then(|id| topics.remove(&id)).unwrap_or_default(),
+                    repo,
+                })
+                .collect::<Vec<_>>()
+        ),
+    }))
+}
@@ -0,0 +1,427 @@
+use std::collections::HashMap;
+use actix_web::body::{self, BodySize, BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::Error;
+use lazy_static::lazy_static;
+use log::warn;
+
+/// Язык сообщений для клиента
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Locale {
+    /// Английский: язык, на котором написаны сообщения в коде
+    En,
+    Ru,
+}
+
+impl Locale {
+    /// Тег языка для `Content-Language`
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Ru => "ru",
+        }
+    }
+}
+
+/// Ответы больше этого размера не переводятся: это не сообщения, а данные
+const MAX_TRANSLATED_BODY_BYTES: u64 = 64 * 1024;
+
+/// Русские сообщения; ключ - английский текст из кода
+///
+/// `{}` в ключе совпадает с любым текстом и подставляется в перевод в том же порядке.
+/// Сообщения без перевода отдаются по-английски.
+const RU: &[(&str, &str)] = &[
+    // Общие
+    ("Unauthorized", "Требуется авторизация"),
+    ("Database error", "Ошибка базы данных"),
+    ("Administrator access required", "Требуются права администратора"),
+    ("Message of the day updated", "Сообщение дня обновлено"),
+    ("Message of the day must not contain control characters", "Сообщение дня не должно содержать управляющих символов"),
+    (
+        "Message of the day must be at most {} lines and {} characters",
+        "Сообщение дня должно быть не длиннее {} строк и {} символов",
+    ),
+    ("Permission denied", "Доступ запрещён"),
+    ("Server is starting, retry later", "Сервер запускается, повторите запрос позже"),
+    ("Invalid cursor", "Некорректный курсор"),
+    ("Request body exceeds {} bytes", "Тело запроса превышает {} байт"),
+    ("Too many git operations in progress", "Выполняется слишком много операций git"),
+    ("Timed out waiting for lock on repo: {}", "Истекло время ожидания блокировки репозитория: {}"),
+    // Пользователи и вход
+    ("Login successful", "Вход выполнен"),
+    ("Invalid username or password", "Неверное имя пользователя или пароль"),
+    (
+        "Account is locked after too many failed login attempts, retry in {} seconds",
+        "Аккаунт заблокирован после слишком многих неудачных попыток входа, повторите через {} с",
+    ),
+    ("User registered successfully", "Пользователь зарегистрирован"),
+    ("User created successfully", "Пользователь создан"),
+    ("User not found", "Пользователь не найден"),
+    ("User with this username already exists", "Пользователь с таким именем уже существует"),
+    ("User with this username or email already exists", "Пользователь с таким именем или email уже существует"),
+    ("Email already in use", "Этот email уже используется"),
+    ("Registration is closed on this server", "Регистрация на этом сервере закрыта"),
+    ("Registration requires an invite token", "Для регистрации нужен токен приглашения"),
+    ("Invite token is invalid or has already been used", "Токен приглашения недействителен или уже использован"),
+    ("Invite created successfully", "Приглашение создано"),
+    ("Failed to create user", "Не удалось создать пользователя"),
+    ("Failed to create invite", "Не удалось создать приглашение"),
+    ("Failed to fetch invites", "Не удалось получить приглашения"),
+    ("Failed to fetch users", "Не удалось получить пользователей"),
+    ("Failed to export user data", "Не удалось выгрузить данные пользователя"),
+    ("Pinned repositories updated", "Закреплённые репозитории обновлены"),
+    ("Failed to update pinned repositories", "Не удалось обновить закреплённые репозитории"),
+    ("At most {} repositories can be pinned", "Можно закрепить не больше {} репозиториев"),
+    ("Repository {} is listed more than once", "Репозиторий {} указан несколько раз"),
+    ("Notification marked as read", "Уведомление отмечено как прочитанное"),
+    ("Failed to fetch notifications", "Не удалось получить уведомления"),
+    ("Failed to mark notification as read", "Не удалось отметить уведомление как прочитанное"),
+    ("Subscription updated", "Подписка обновлена"),
+    ("Failed to fetch subscription", "Не удалось получить подписку"),
+    ("Failed to update subscription", "Не удалось обновить подписку"),
+    // Репозитории
+    ("Repository not found", "Репозиторий не найден"),
+    ("Repository {} not found", "Репозиторий {} не найден"),
+    ("Repository not found: {}", "Репозиторий не найден: {}"),
+    ("Repository storage missing: {}", "Данные репозитория отсутствуют: {}"),
+    ("Invalid repository name: {}", "Недопустимое имя репозитория: {}"),
+    ("Invalid username: {}", "Недопустимое имя пользователя: {}"),
+    ("Several users have a repository named {}; add ?owner= or use /git/{owner}/{}", "Репозиторий {} есть у нескольких пользователей; добавьте ?owner= или используйте /git/{owner}/{}"),
+    ("You do not have write access to {}", "Нет прав на запись в {}"),
+    ("Repository with this name already exists", "Репозиторий с таким именем уже существует"),
+    ("Repository created successfully", "Репозиторий создан"),
+    ("Repository imported successfully", "Репозиторий импортирован"),
+    ("Repository forked successfully", "Форк репозитория создан"),
+    ("Repository settings updated successfully", "Настройки репозитория обновлены"),
+    ("Hidden refs updated successfully", "Скрытые ссылки обновлены"),
+    ("Only repository owner can change repository settings", "Менять настройки репозитория может только владелец"),
+    ("Only repository owner or an administrator can run fsck", "Запустить fsck может только владелец репозитория или администратор"),
+    ("fsck is already running for this repository", "fsck для этого репозитория уже выполняется"),
+    ("Storage quota exceeded", "Квота хранилища исчерпана"),
+    ("Storage quota of the repository owner is exceeded", "Квота хранилища владельца репозитория исчерпана"),
+    ("Uploaded file is not a valid git bundle", "Загруженный файл не является git bundle"),
+    ("Failed to create repository", "Не удалось создать репозиторий"),
+    ("Repository deleted successfully", "Репозиторий удалён"),
+    ("Failed to delete repository", "Не удалось удалить репозиторий"),
+    ("Topics updated successfully", "Темы обновлены"),
+    ("Failed to update topics", "Не удалось обновить темы"),
+    ("A repository can have at most {} topics", "У репозитория может быть не больше {} тем"),
+    (
+        "Invalid topic '{}': use lowercase letters, digits and hyphens, start with a letter or digit, at most {} characters",
+        "Недопустимая тема '{}': используйте строчные латинские буквы, цифры и дефисы, начинайте с буквы или цифры, не больше {} символов",
+    ),
+    ("Failed to fork repository", "Не удалось создать форк репозитория"),
+    ("Failed to fetch repositories", "Не удалось получить репозитории"),
+    ("Failed to fetch forks", "Не удалось получить форки"),
+    ("Failed to fetch pushes", "Не удалось получить историю push"),
+    ("Failed to fetch secret alerts", "Не удалось получить предупреждения о секретах"),
+    ("Failed to fetch audit log", "Не удалось получить журнал аудита"),
+    ("Failed to fetch jobs", "Не удалось получить задачи"),
+    ("status must be pending, running or dead", "status должен быть pending, running или dead"),
+    // Ссылки, файлы и диффы
+    ("Invalid ref: {}", "Недопустимая ссылка: {}"),
+    ("Ref not found: {}", "Ссылка не найдена: {}"),
+    ("Revision not found", "Ревизия не найдена"),
+    ("Path not found", "Путь не найден"),
+    ("File not found", "Файл не найден"),
+    ("File deleted successfully", "Файл удалён"),
+    ("Invalid file path: {}", "Недопустимый путь к файлу: {}"),
+    ("{} is not a file", "{} не является файлом"),
+    ("{} is not a regular file", "{} не является обычным файлом"),
+    ("{} conflicts with an existing file", "{} конфликтует с существующим файлом"),
+    ("{} already exists on '{}'; pass its sha to update it", "{} уже существует в '{}'; передайте его sha, чтобы изменить"),
+    ("{} does not exist on '{}'", "{} не существует в '{}'"),
+    ("Branch '{}' was updated meanwhile, please retry", "Ветка '{}' изменилась за это время, повторите запрос"),
+    ("Refs do not share history", "У ссылок нет общей истории"),
+    ("At most {} refs can be resolved per request", "За один запрос можно разрешить не больше {} ссылок"),
+    ("Expected comparison in the form base...head", "Ожидается сравнение в виде base...head"),
+    ("Expected {ref}.tar.gz, {ref}.tgz, {ref}.tar or {ref}.zip", "Ожидается {ref}.tar.gz, {ref}.tgz, {ref}.tar или {ref}.zip"),
+    ("ignore_whitespace must be none, change or all", "ignore_whitespace должен быть none, change или all"),
+    ("against must be first-parent or combined", "against должен быть first-parent или combined"),
+    ("context must be between 0 and {}", "context должен быть от 0 до {}"),
+    ("line must be between 1 and {}", "line должен быть от 1 до {}"),
+    ("{} must be a date (YYYY-MM-DD) or an RFC3339 timestamp", "{} должен быть датой (YYYY-MM-DD) или временем в RFC3339"),
+    ("Line range is outside the file, which has {} lines", "Диапазон строк выходит за пределы файла, в нём {} строк"),
+    // Пул-реквесты
+    ("Pull request not found", "Пул-реквест не найден"),
+    ("Pull request created successfully", "Пул-реквест создан"),
+    ("Pull request updated successfully", "Пул-реквест обновлён"),
+    ("Pull request status updated successfully", "Статус пул-реквеста обновлён"),
+    ("Pull request merged successfully", "Пул-реквест слит"),
+    ("Pull requests are disabled for this repository", "Пул-реквесты в этом репозитории отключены"),
+    ("Pull requests are disabled on this server", "Пул-реквесты на этом сервере отключены"),
+    ("Only repository owner can update pull request status", "Менять статус пул-реквеста может только владелец репозитория"),
+    ("Only the pull request author or repository owner can edit it", "Изменять пул-реквест может только его автор или владелец репозитория"),
+    ("Title must not be empty", "Заголовок не может быть пустым"),
+    ("state must be one of open, closed, merged, all", "state должен быть одним из open, closed, merged, all"),
+    ("role must be author or reviewer", "role должен быть author или reviewer"),
+    ("merge_method must be one of merge, squash, rebase", "merge_method должен быть одним из merge, squash, rebase"),
+    (
+        "Merge method '{}' is not allowed in this repository; allowed: {}",
+        "Способ слияния '{}' запрещён в этом репозитории; разрешены: {}",
+    ),
+    ("At least one merge method must be allowed", "Должен быть разрешён хотя бы один способ слияния"),
+    ("Failed to create pull request", "Не удалось создать пул-реквест"),
+    ("Failed to fetch pull requests", "Не удалось получить пул-реквесты"),
+    ("Failed to update pull request", "Не удалось обновить пул-реквест"),
+    ("Failed to update pull request status", "Не удалось обновить статус пул-реквеста"),
+    ("Comment added successfully", "Комментарий добавлен"),
+    ("Comment deleted successfully", "Комментарий удалён"),
+    ("Comment not found", "Комментарий не найден"),
+    ("Only the comment author or repository owner can delete this comment", "Удалить комментарий может только его автор или владелец репозитория"),
+    ("Failed to create comment", "Не удалось создать комментарий"),
+    ("Failed to delete comment", "Не удалось удалить комментарий"),
+    // Вебхуки
+    ("Webhook created successfully", "Вебхук создан"),
+    ("Webhook deleted successfully", "Вебхук удалён"),
+    ("Webhook not found", "Вебхук не найден"),
+    ("Delivery not found", "Доставка не найдена"),
+    ("At least one event is required", "Нужно указать хотя бы одно событие"),
+    ("Failed to create webhook", "Не удалось создать вебхук"),
+    ("Failed to delete webhook", "Не удалось удалить вебхук"),
+    ("Failed to fetch webhook", "Не удалось получить вебхук"),
+    ("Failed to fetch webhooks", "Не удалось получить вебхуки"),
+    ("Failed to fetch webhook delivery", "Не удалось получить доставку вебхука"),
+    ("Failed to fetch webhook deliveries", "Не удалось получить доставки вебхука"),
+    ("Failed to redeliver webhook", "Не удалось повторить доставку вебхука"),
+    // git протокол
+    ("Git operation failed", "Ошибка операции git"),
+    ("Invalid git request", "Некорректный git запрос"),
+    ("Authentication required", "Требуется аутентификация"),
+    ("Only the smart HTTP protocol is supported", "Поддерживается только smart HTTP протокол"),
+    ("Unknown git service", "Неизвестная служба git"),
+    ("Fetching is disabled on this server", "Получение данных на этом сервере отключено"),
+    ("Push is disabled on this server", "Push на этом сервере отключён"),
+    ("Too many concurrent clones of {}, retry later", "Слишком много одновременных clone {}, повторите позже"),
+    ("Request is too large: the server accepts at most {} bytes", "Запрос слишком большой: сервер принимает не больше {} байт"),
+    (
+        "full clones of this repository are disabled ({} bytes, limit {} bytes); use a shallow clone (git clone --depth=1) or a partial clone (git clone --filter=blob:none)",
+        "полный clone этого репозитория запрещён ({} байт, предел {} байт); используйте shallow clone (git clone --depth=1) или partial clone (git clone --filter=blob:none)",
+    ),
+    (
+        "full clones of this repository are disabled ({} bytes, limit {} bytes); use a shallow clone (git clone --depth=1)",
+        "полный clone этого репозитория запрещён ({} байт, предел {} байт); используйте shallow clone (git clone --depth=1)",
+    ),
+];
+
+/// Сообщения одного языка: точные ключи и шаблоны с `{}`
+struct Bundle {
+    exact: HashMap<&'static str, &'static str>,
+    templates: Vec<(Vec<&'static str>, &'static str)>,
+}
+
+impl Bundle {
+    fn new(messages: &[(&'static str, &'static str)]) -> Self {
+        let mut exact = HashMap::new();
+        let mut templates = Vec::new();
+        for &(key, translation) in messages {
+            if key.contains("{}") {
+                templates.push((key.split("{}").collect(), translation));
+            } else {
+                exact.insert(key, translation);
+            }
+        }
+        Bundle { exact, templates }
+    }
+
+    fn translate(&self, message: &str) -> Option<String> {
+        if let Some(translation) = self.exact.get(message) {
+            return Some(translation.to_string());
+        }
+
+        self.templates.iter().find_map(|(parts, translation)| {
+            let args = match_template(parts, message)?;
+            let mut result = String::with_capacity(translation.len() + message.len());
+            let mut pieces = translation.split("{}");
+            result.push_str(pieces.next().unwrap_or_default());
+            for (arg, piece) in args.iter().zip(pieces) {
+                result.push_str(arg);
+                result.push_str(piece);
+            }
+            Some(result)
+        })
+    }
+}
+
+/// Сопоставляет сообщение с шаблоном, разбитым по `{}`
+///
+/// # Возвращает
+///
+/// * `Option<Vec<&str>>` - Подставленные в шаблон значения или None, если сообщение другое
+fn match_template<'a>(parts: &[&str], message: &'a str) -> Option<Vec<&'a str>> {
+    let (first, rest) = parts.split_first()?;
+    let (last, middle) = rest.split_last()?;
+
+    let mut remaining = message.strip_prefix(first)?.strip_suffix(last)?;
+    let mut args = Vec::with_capacity(rest.len());
+    for part in middle {
+        let at = remaining.find(part)?;
+        args.push(&remaining[..at]);
+        remaining = &remaining[at + part.len()..];
+    }
+    args.push(remaining);
+
+    args.iter().all(|arg| !arg.is_empty()).then_some(args)
+}
+
+lazy_static! {
+    static ref RU_BUNDLE: Bundle = Bundle::new(RU);
+}
+
+tokio::task_local! {
+    /// Язык обрабатываемого запроса, выставляется middleware `localize`
+    static LOCALE: Locale;
+}
+
+/// Выбирает язык по заголовку `Accept-Language`
+///
+/// Берётся поддерживаемый язык с наибольшим `q`, при равных - указанный раньше.
+/// Региональные варианты (`ru-RU`) относятся к своему языку. Без подходящего
+/// языка используется английский.
+///
+/// # Параметры
+///
+/// * `header` - Значение заголовка
+///
+/// # Возвращает
+///
+/// * `Locale` - Язык сообщений
+pub fn negotiate(header: &str) -> Locale {
+    let mut best: Option<(Locale, f32)> = None;
+    for range in header.split(',') {
+        let mut params = range.split(';');
+        let tag = params.next().unwrap_or_default().trim().to_ascii_lowercase();
+        let quality = params
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        if quality <= 0.0 {
+            continue;
+        }
+
+        let locale = match tag.split('-').next().unwrap_or_default() {
+            "ru" => Locale::Ru,
+            "en" | "*" => Locale::En,
+            _ => continue,
+        };
+        if best.is_none_or(|(_, best_quality)| quality > best_quality) {
+            best = Some((locale, quality));
+        }
+    }
+
+    best.map(|(locale, _)| locale).unwrap_or(Locale::En)
+}
+
+/// Переводит сообщение на язык обрабатываемого запроса
+///
+/// Нужен там, где сообщение нельзя перевести в готовом ответе, например в
+/// pkt-line ошибках git протокола. Вне запроса и без перевода возвращает сообщение как есть.
+///
+/// # Параметры
+///
+/// * `message` - Сообщение на английском
+///
+/// # Возвращает
+///
+/// * `String` - Сообщение для клиента
+pub fn localize_message(message: &str) -> String {
+    let locale = LOCALE.try_with(|locale| *locale).unwrap_or(Locale::En);
+    translate(message, locale).unwrap_or_else(|| message.to_string())
+}
+
+/// Перевод сообщения или None, если язык английский или перевода нет
+pub fn translate(message: &str, locale: Locale) -> Option<String> {
+    match locale {
+        Locale::En => None,
+        Locale::Ru => RU_BUNDLE.translate(message),
+    }
+}
+
+/// Middleware: переводит сообщения ответов на язык из `Accept-Language`
+///
+/// Переводится поле `message` JSON ответов (`ApiResponse`) и текст ошибок
+/// `text/plain`, включая ошибки git клиенту на этапе `info/refs`. Ответы с
+/// данными (файлы, архивы, потоки) не трогаются. Такие ответы получают
+/// `Vary: Accept-Language` и `Content-Language`.
+pub async fn localize(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let locale = req
+        .headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .map(negotiate)
+        .unwrap_or(Locale::En);
+
+    let res = LOCALE.scope(locale, next.call(req)).await?.map_into_boxed_body();
+
+    let content_type = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let is_json = content_type.starts_with("application/json");
+    let is_error_text = content_type.starts_with("text/plain")
+        && (res.status().is_client_error() || res.status().is_server_error());
+    let small = matches!(res.response().body().size(), BodySize::Sized(size) if size <= MAX_TRANSLATED_BODY_BYTES);
+    if !(is_json || is_error_text) || !small {
+        return Ok(res);
+    }
+
+    let (req, mut res) = res.into_parts();
+    let headers = res.headers_mut();
+    headers.append(header::VARY, HeaderValue::from_static("Accept-Language"));
+    headers.insert(header::CONTENT_LANGUAGE, HeaderValue::from_static(locale.tag()));
+    if locale == Locale::En {
+        return Ok(ServiceResponse::new(req, res));
+    }
+
+    let (res, body) = res.into_parts();
+    let bytes = match body::to_bytes(body).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!("Failed to read response body for translation: {}", e);
+            return Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(()))));
+        }
+    };
+
+    let translated = if is_json {
+        translate_json(&bytes, locale)
+    } else {
+        std::str::from_utf8(&bytes)
+            .ok()
+            .and_then(|text| translate(text.trim_end(), locale))
+            .map(|text| format!("{}\n", text).into_bytes())
+    };
+
+    let body = match translated {
+        Some(translated) => BoxBody::new(translated),
+        None => BoxBody::new(bytes),
+    };
+    Ok(ServiceResponse::new(req, res.set_body(body)))
+}
+
+/// Переводит `message` в JSON ответе; None, если переводить нечего
+///
+/// Заменяется только само значение, остальной ответ остаётся байт в байт: в
+/// `ApiResponse` поле `message` сериализуется раньше `data`, поэтому первое
+/// вхождение ключа - поле верхнего уровня.
+fn translate_json(bytes: &[u8], locale: Locale) -> Option<Vec<u8>> {
+    let value: serde_json::Value = serde_json::from_slice(bytes).ok()?;
+    let message = value.get("message")?.as_str()?;
+    let translated = translate(message, locale)?;
+
+    let old = format!("\"message\":{}", serde_json::to_string(message).ok()?);
+    let new = format!("\"message\":{}", serde_json::to_string(&translated).ok()?);
+    let at = bytes.windows(old.len()).position(|window| window == old.as_bytes())?;
+
+    let mut result = Vec::with_capacity(bytes.len() + new.len());
+    result.extend_from_slice(&bytes[..at]);
+    result.extend_from_slice(new.as_bytes());
+    result.extend_from_slice(&bytes[at + old.len()..]);
+    Some(result)
+}
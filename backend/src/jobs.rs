@@ -0,0 +1,240 @@
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+use lazy_static::lazy_static;
+use log::{debug, error, info, warn};
+use crate::bundle_uri;
+use crate::config::CONFIG;
+use crate::models::db::Database;
+use crate::models::job::Job;
+use crate::temp_cleanup;
+use crate::webhooks;
+
+/// Как часто проверять очередь, если никто не разбудил обработчик
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Максимальная задержка между повторами задачи в секундах
+const MAX_RETRY_DELAY_SECS: u64 = 60 * 60;
+
+lazy_static! {
+    /// Появилась ли новая задача с момента последней проверки очереди
+    static ref WAKE: (Mutex<bool>, Condvar) = (Mutex::new(false), Condvar::new());
+}
+
+/// Ставит задачу в очередь и будит фоновый обработчик
+///
+/// Задача хранится в базе данных, поэтому переживает перезапуск сервера.
+/// Обработчик для `job_type` должен быть описан в `run`.
+///
+/// # Параметры
+///
+/// * `job_type` - Тип задачи
+/// * `payload` - Параметры задачи
+/// * `db` - База данных
+///
+/// # Возвращает
+///
+/// * `rusqlite::Result<i64>` - ID задачи
+pub fn enqueue(job_type: &str, payload: serde_json::Value, db: &Database) -> rusqlite::Result<i64> {
+    let id = Job::enqueue(job_type, &payload, db.get_connection())?;
+    debug!("Enqueued {} job {}", job_type, id);
+
+    let (pending, wake) = &*WAKE;
+    *pending.lock().unwrap_or_else(|e| e.into_inner()) = true;
+    wake.notify_one();
+
+    Ok(id)
+}
+
+/// Ставит задачу в очередь с задержкой, если такая же задача ещё не ждёт выполнения
+///
+/// # Параметры
+///
+/// * `job_type` - Тип задачи
+/// * `payload` - Параметры задачи
+/// * `delay_secs` - Через сколько секунд выполнить задачу
+/// * `db` - База данных
+///
+/// # Возвращает
+///
+/// * `rusqlite::Result<Option<i64>>` - ID задачи или None, если такая уже в очереди
+pub fn schedule(job_type: &str, payload: serde_json::Value, delay_secs: u64, db: &Database) -> rusqlite::Result<Option<i64>> {
+    let id = Job::schedule(job_type, &payload, delay_secs, db.get_connection())?;
+    match id {
+        Some(id) => debug!("Scheduled {} job {} in {}s", job_type, id, delay_secs),
+        None => debug!("{} job for {} is already scheduled", job_type, payload),
+    }
+
+    if id.is_some() && delay_secs == 0 {
+        let (pending, wake) = &*WAKE;
+        *pending.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        wake.notify_one();
+    }
+
+    Ok(id)
+}
+
+/// Запускает поток, который выполняет задачи из очереди по мере наступления их срока
+///
+/// Вызывается после миграций базы данных. Задачи, прерванные прошлой остановкой
+/// сервера, сначала возвращаются в очередь.
+///
+/// # Параметры
+///
+/// * `db` - База данных
+pub fn start_worker(db: Database) {
+    match Job::requeue_interrupted(db.get_connection()) {
+        Ok(0) => {}
+        Ok(count) => info!("Requeued {} interrupted jobs", count),
+        Err(e) => error!("Failed to requeue interrupted jobs: {}", e),
+    }
+
+    thread::Builder::new()
+        .name("jobs".to_string())
+        .spawn(move || loop {
+            match Job::claim_next(db.get_connection()) {
+                Ok(Some(job)) => process(&job, &db),
+                Ok(None) => wait_for_jobs(),
+                Err(e) => {
+                    error!("Failed to claim job: {}", e);
+                    wait_for_jobs();
+                }
+            }
+        })
+        .expect("Failed to spawn job worker thread");
+}
+
+/// Ждёт новую задачу или истечения интервала опроса
+fn wait_for_jobs() {
+    let (pending, wake) = &*WAKE;
+    let guard = pending.lock().unwrap_or_else(|e| e.into_inner());
+    let (mut guard, _) = wake
+        .wait_timeout_while(guard, POLL_INTERVAL, |pending| !*pending)
+        .unwrap_or_else(|e| e.into_inner());
+    *guard = false;
+}
+
+/// Выполняет задачу и записывает результат: удаляет её, откладывает или отмечает мёртвой
+fn process(job: &Job, db: &Database) {
+    let error = match run(job, db) {
+        Ok(()) => {
+            debug!("Job {} ({}) completed", job.id, job.job_type);
+            if let Err(e) = Job::complete(job.id, db.get_connection()) {
+                error!("Failed to remove completed job {}: {}", job.id, e);
+            }
+            return;
+        }
+        Err(error) => error,
+    };
+
+    let retry_in = (job.attempts < i64::from(CONFIG.job_max_attempts)).then(|| retry_delay(job.attempts));
+    match retry_in {
+        Some(secs) => warn!("Job {} ({}) failed, retrying in {}s: {}", job.id, job.job_type, secs, error),
+        None => error!("Job {} ({}) failed after {} attempts: {}", job.id, job.job_type, job.attempts, error),
+    }
+
+    if let Err(e) = job.fail(&error, retry_in, db.get_connection()) {
+        error!("Failed to record failure of job {}: {}", job.id, e);
+    }
+}
+
+/// Запускает обработчик, соответствующий типу задачи
+fn run(job: &Job, db: &Database) -> Result<(), String> {
+    match job.job_type.as_str() {
+        webhooks::DELIVERY_JOB => webhooks::run_delivery_job(&job.payload, db),
+        webhooks::SERVER_DELIVERY_JOB => webhooks::run_server_delivery_job(&job.payload, db),
+        bundle_uri::BUNDLE_JOB => bundle_uri::run_bundle_job(&job.payload, db),
+        temp_cleanup::TEMP_CLEANUP_JOB => temp_cleanup::run_cleanup_job(db),
+        other => Err(format!("Unknown job type: {}", other)),
+    }
+}
+
+/// Задержка перед следующей попыткой: удваивается с каждой неудачей
+fn retry_delay(attempts: i64) -> u64 {
+    let doublings = attempts.clamp(1, 32) as u32 - 1;
+    CONFIG
+        .job_retry_secs
+        .saturating_mul(2u64.saturating_pow(doublings))
+        .min(MAX_RETRY_DELAY_SECS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::params;
+    use crate::models::job::{STATUS_DEAD, STATUS_PENDING};
+    use crate::test_support::test_db;
+
+    /// Переносит срок задачи в прошлое, как будто задержка перед повтором истекла
+    fn make_due(id: i64, db: &Database) {
+        db.get_connection().lock().unwrap()
+            .execute("UPDATE jobs SET run_after = datetime('now', '-1 seconds') WHERE id = ?1", params![id])
+            .unwrap();
+    }
+
+    /// Через сколько секунд от текущего момента задача станет доступна
+    fn due_in_secs(id: i64, db: &Database) -> i64 {
+        db.get_connection().lock().unwrap()
+            .query_row(
+                "SELECT CAST(strftime('%s', run_after) AS INTEGER) - CAST(strftime('%s', 'now') AS INTEGER) FROM jobs WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn failing_job_is_retried_then_dead_lettered() {
+        let db = test_db();
+        // У неизвестного типа нет обработчика, поэтому каждая попытка завершается ошибкой
+        let id = Job::enqueue("always-fails", &serde_json::json!({ "n": 1 }), db.get_connection()).unwrap();
+        let max_attempts = i64::from(CONFIG.job_max_attempts);
+
+        for attempt in 1..=max_attempts {
+            let job = Job::claim_next(db.get_connection()).unwrap().expect("job is due");
+            assert_eq!((job.id, job.attempts), (id, attempt));
+            process(&job, &db);
+
+            if attempt < max_attempts {
+                // До истечения задержки задача не выдаётся снова
+                assert!(Job::claim_next(db.get_connection()).unwrap().is_none());
+                let pending = Job::find_by_status(STATUS_PENDING, 10, 0, db.get_connection()).unwrap();
+                assert_eq!(pending.len(), 1);
+                assert_eq!(pending[0].last_error.as_deref(), Some("Unknown job type: always-fails"));
+                let delay = retry_delay(attempt) as i64;
+                assert!((delay - 2..=delay).contains(&due_in_secs(id, &db)), "attempt {}", attempt);
+                make_due(id, &db);
+            }
+        }
+
+        let dead = Job::find_by_status(STATUS_DEAD, 10, 0, db.get_connection()).unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!((dead[0].id, dead[0].attempts), (id, max_attempts));
+        assert_eq!(dead[0].last_error.as_deref(), Some("Unknown job type: always-fails"));
+        assert!(Job::find_by_status(STATUS_PENDING, 10, 0, db.get_connection()).unwrap().is_empty());
+
+        // Мёртвая задача больше не выполняется
+        make_due(id, &db);
+        assert!(Job::claim_next(db.get_connection()).unwrap().is_none());
+    }
+
+    #[test]
+    fn retry_delay_doubles_up_to_an_hour() {
+        let base = CONFIG.job_retry_secs;
+        assert_eq!(retry_delay(1), base.min(MAX_RETRY_DELAY_SECS));
+        assert_eq!(retry_delay(2), (base * 2).min(MAX_RETRY_DELAY_SECS));
+        assert_eq!(retry_delay(3), (base * 4).min(MAX_RETRY_DELAY_SECS));
+        assert_eq!(retry_delay(40), MAX_RETRY_DELAY_SECS);
+    }
+
+    #[test]
+    fn interrupted_jobs_are_requeued() {
+        let db = test_db();
+        let id = Job::enqueue("always-fails", &serde_json::json!({}), db.get_connection()).unwrap();
+        let job = Job::claim_next(db.get_connection()).unwrap().unwrap();
+        assert_eq!(job.id, id);
+
+        assert_eq!(Job::requeue_interrupted(db.get_connection()).unwrap(), 1);
+        let job = Job::claim_next(db.get_connection()).unwrap().unwrap();
+        assert_eq!((job.id, job.attempts), (id, 2));
+    }
+}
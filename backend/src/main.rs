@@ -1,282 +1,272 @@
-use actix_web::{web, App, HttpServer, HttpResponse, HttpRequest, middleware};
+use actix_web::{web, App, HttpServer, middleware};
+use log::{error, info};
 use actix_cors::Cors;
-use std::process::{Command, Stdio};
-use std::path::PathBuf;
-use std::io::Write;
-use log::{debug, error};
-use std::fs;
+use std::time::Duration;
 
 // Импортируем наши модули
+mod auth;
 mod models;
 mod handlers;
-
-use models::db::Database;
-use handlers::api;
+mod error;
+mod git;
+mod config;
+mod trace;
+mod avatar;
+mod webhooks;
+mod jobs;
+mod readiness;
+mod multipart;
+mod charset;
+mod timestamps;
+mod bundle_uri;
+mod security_headers;
+mod clock;
+mod cursor;
+mod i18n;
+mod temp_cleanup;
+mod motd;
+#[cfg(test)]
+mod test_support;
+
+use config::CONFIG;
+use models::db::{self, Database};
+use handlers::{admin, api, archive, blame, bundles, contents, diff, export, forks, git as git_http, hooks, maintenance, meta, pins, refs, releases, secrets, stats, subscriptions, topics};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    // Для проверок до обновления ссылок git запускает этот же бинарник как pre-receive хук
+    if std::env::args().nth(1).as_deref() == Some(git::hooks::PRE_RECEIVE_ARG) {
+        std::process::exit(git::hooks::run_pre_receive_hook());
+    }
+
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("debug"));
 
-    // Создаем каталог для репозиториев, если он не существует
-    if !std::path::Path::new("repositories").exists() {
-        std::fs::create_dir("repositories")?;
-    }
-    
-    // Инициализируем базу данных
-    let db = Database::new().expect("Failed to initialize database");
+    // Открываем базу данных; таблицы создаются уже после запуска сервера
+    info!("Using database {}", CONFIG.database_path.display());
+    let db = Database::open(&CONFIG.database_path).expect("Failed to open database");
+    let startup_db = db.clone();
+    let git_capabilities = web::Data::new(git::version::GitCapabilities::detect());
+
+    let server = HttpServer::new(move || {
+        // Фабрика выполняется в каждом рабочем потоке: запросы к базе в нём не засыпают
+        db::mark_async_worker();
 
-    HttpServer::new(move || {
         // Настройка CORS для взаимодействия с React
         let cors = Cors::default()
             .allowed_origin("http://localhost:3000")
-            .allowed_methods(vec!["GET", "POST", "PUT", "DELETE"])
+            .allowed_methods(vec!["GET", "POST", "PUT", "PATCH", "DELETE"])
             .allowed_headers(vec!["Authorization", "Content-Type"])
             .supports_credentials()
             .max_age(3600);
 
         App::new()
-            // Добавляем middleware
+            // Добавляем middleware; до завершения запуска отвечаем 503
+            .wrap(middleware::from_fn(readiness::gate))
+            .wrap(middleware::from_fn(i18n::localize))
+            .wrap(middleware::from_fn(security_headers::apply))
             .wrap(middleware::Logger::default())
             .wrap(cors)
             // Данные приложения
             .app_data(web::Data::new(db.clone()))
+            .app_data(git_capabilities.clone())
+            .app_data(web::PayloadConfig::new(CONFIG.max_body_bytes))
+            .app_data(web::JsonConfig::default().error_handler(error::json_error_handler))
+            
+            .service(web::resource("/").route(web::get().to(meta::index)))
+            .service(web::resource(readiness::HEALTH_PATH).route(web::get().to(meta::health)))
+            .service(web::resource("/api/meta").route(web::get().to(meta::get_meta)))
             
             // API для аутентификации и пользователей
             .service(web::resource("/api/auth/login").route(web::post().to(api::login)))
             .service(web::resource("/api/auth/register").route(web::post().to(api::register)))
+            .service(web::resource("/api/auth/check").route(web::get().to(api::check_credentials)))
             .service(web::resource("/api/user/profile").route(web::get().to(api::user_profile)))
+            .service(web::resource("/api/user/export").route(web::get().to(export::export_user_data)))
+            .service(web::resource("/api/user/pinned").route(web::put().to(pins::set_pinned_repos)))
+            .service(web::resource("/api/users/{username}").route(web::get().to(api::get_user)))
+            .service(web::resource("/api/users/{username}/pinned").route(web::get().to(pins::get_pinned_repos)))
+            .service(web::resource("/api/user/pulls").route(web::get().to(api::list_user_pull_requests)))
+
+            // API для администраторов (GIT_HTTP_ADMINS)
+            .service(web::resource("/api/admin/users")
+                .route(web::get().to(admin::list_users))
+                .route(web::post().to(admin::create_user)))
+            .service(web::resource("/api/admin/invites")
+                .route(web::get().to(admin::list_invites))
+                .route(web::post().to(admin::create_invite)))
+            .service(web::resource("/api/admin/jobs").route(web::get().to(admin::list_jobs)))
+            .service(web::resource("/api/admin/audit").route(web::get().to(admin::list_audit_log)))
+            .service(web::resource("/api/admin/integrity").route(web::get().to(admin::check_integrity)))
+            .service(web::resource("/api/admin/motd")
+                .route(web::get().to(admin::get_motd))
+                .route(web::put().to(admin::set_motd)))
+            .service(web::resource("/api/admin/hooks")
+                .route(web::get().to(admin::list_server_hooks))
+                .route(web::post().to(admin::create_server_hook)))
+            .service(web::resource("/api/admin/hooks/{hook_id}").route(web::delete().to(admin::delete_server_hook)))
             
             // API для репозиториев
-            .service(web::resource("/api/repos").route(web::get().to(api::list_repos)))
-            .service(web::resource("/api/repos").route(web::post().to(api::create_repo)))
-            .service(web::resource("/api/repos/{repo_name}").route(web::get().to(api::get_repo)))
+            .service(web::resource("/api/repos")
+                .route(web::get().to(api::list_repos))
+                .route(web::post().to(api::create_repo)))
+            .service(web::resource("/api/repos/check-name").route(web::get().to(api::check_repo_name)))
+            .service(web::resource("/api/explore").route(web::get().to(topics::explore)))
+            .service(web::resource("/api/repos/import-bundle").route(web::post().to(bundles::import_bundle)))
+            .service(web::resource("/api/repos/{repo_name}")
+                .route(web::get().to(api::get_repo))
+                .route(web::delete().to(api::delete_repo)))
+            .service(web::resource("/api/repos/{repo_name}/visibility").route(web::put().to(api::set_visibility)))
+            .service(web::resource("/api/repos/{repo_name}/topics").route(web::put().to(topics::set_topics)))
+            .service(web::resource("/api/repos/{repo_name}/commit/{sha}.patch").route(web::get().to(diff::get_commit_patch)))
+            .service(web::resource("/api/repos/{repo_name}/commit/{sha}").route(web::get().to(diff::get_commit_diff)))
+            .service(web::resource("/api/repos/{repo_name}/compare/{spec:.*}").route(web::get().to(diff::compare)))
+            .service(web::resource("/api/repos/{repo_name}/merge-base").route(web::get().to(diff::merge_base)))
+            .service(web::resource("/api/repos/{repo_name}/releases/notes").route(web::get().to(releases::release_notes)))
+            .service(web::resource("/api/repos/{repo_name}/archive/{spec:.*}").route(web::get().to(archive::download_archive)))
+            .service(web::resource("/api/repos/{repo_name}/contents/{path:.*}")
+                .route(web::put().to(contents::put_contents))
+                .route(web::delete().to(contents::delete_contents)))
+            .service(web::resource("/api/repos/{repo_name}/blame/{rev}/{path:.*}").route(web::get().to(blame::get_blame_line)))
+            .service(web::resource("/api/repos/{repo_name}/history/{rev}/{path:.*}").route(web::get().to(blame::get_file_history)))
+            .service(web::resource("/api/repos/{repo_name}/hidden-refs")
+                .route(web::get().to(refs::get_hidden_refs))
+                .route(web::put().to(refs::update_hidden_refs)))
+            .service(web::resource("/api/repos/{repo_name}/settings")
+                .route(web::get().to(refs::get_settings))
+                .route(web::patch().to(refs::update_settings)))
+            .service(web::resource("/api/repos/{repo_name}/branches/stale").route(web::get().to(refs::list_stale_branches)))
+            .service(web::resource("/api/repos/{repo_name}/refs/resolve").route(web::post().to(refs::resolve_refs_batch)))
+            .service(web::resource("/api/repos/{repo_name}/forks")
+                .route(web::get().to(forks::list_forks))
+                .route(web::post().to(forks::create_fork)))
+            .service(web::resource("/api/repos/{repo_name}/contributors").route(web::get().to(stats::get_contributors)))
+            .service(web::resource("/api/repos/{repo_name}/languages").route(web::get().to(stats::get_languages)))
+            .service(web::resource("/api/repos/{repo_name}/pushes").route(web::get().to(refs::list_pushes)))
+            .service(web::resource("/api/repos/{repo_name}/subscription")
+                .route(web::get().to(subscriptions::get_subscription))
+                .route(web::put().to(subscriptions::update_subscription)))
+            .service(web::resource("/api/repos/{repo_name}/secret-alerts").route(web::get().to(secrets::list_secret_alerts)))
+            .service(web::resource("/api/repos/{repo_name}/fsck").route(web::post().to(maintenance::fsck_repo)))
+            .service(web::resource("/api/repos/{repo_name}/unreachable").route(web::get().to(maintenance::list_unreachable)))
+            .service(web::resource("/api/repos/{repo_name}/hooks")
+                .route(web::get().to(hooks::list_hooks))
+                .route(web::post().to(hooks::create_hook)))
+            .service(web::resource("/api/repos/{repo_name}/hooks/{hook_id}").route(web::delete().to(hooks::delete_hook)))
+            .service(web::resource("/api/repos/{repo_name}/hooks/{hook_id}/deliveries").route(web::get().to(hooks::list_deliveries)))
+            .service(web::resource("/api/repos/{repo_name}/hooks/{hook_id}/deliveries/{delivery_id}/redeliver").route(web::post().to(hooks::redeliver)))
+            
+            // API для пул-реквестов
+            .service(web::resource("/api/repos/{repo_name}/pulls").route(web::post().to(api::create_pull_request)))
+            .service(web::resource("/api/repos/{repo_name}/pulls/{pr_id}.patch").route(web::get().to(diff::get_pull_request_patch)))
+            .service(web::resource("/api/repos/{repo_name}/pulls/{pr_id}").route(web::get().to(api::get_pull_request)).route(web::patch().to(api::update_pull_request)))
+            .service(web::resource("/api/repos/{repo_name}/pulls/{pr_id}/diff").route(web::get().to(diff::get_pull_request_diff)))
+            .service(web::resource("/api/repos/{repo_name}/pulls/{pr_id}/comments").route(web::post().to(api::add_comment_to_pull_request)))
+            .service(web::resource("/api/repos/{repo_name}/pulls/{pr_id}/comments/{comment_id}").route(web::delete().to(api::delete_pull_request_comment)))
+            .service(web::resource("/api/repos/{repo_name}/pulls/{pr_id}/status").route(web::put().to(api::update_pull_request_status)))
             
-            // Smart HTTP Protocol endpoints для Git
-            .service(web::resource("/git/{repo_name}/info/refs")
-                .route(web::get().to(handle_info_refs)))
-            .service(web::resource("/git/{repo_name}/git-upload-pack")
-                .route(web::post().to(handle_upload_pack)))
-            .service(web::resource("/git/{repo_name}/git-receive-pack")
-                .route(web::post().to(handle_receive_pack)))
-            // Pack files endpoints
-            .service(web::resource("/git/{repo_name}/objects/info/packs")
-                .route(web::get().to(handle_info_packs)))
-            .service(web::resource("/git/{repo_name}/objects/pack/{pack_file}")
-                .route(web::get().to(handle_pack_file)))
-            // Text file endpoint
-            .service(web::resource("/git/{repo_name}/file/{tail:.*}")
-                .route(web::get().to(handle_text_file)))
+            // API для уведомлений
+            .service(web::resource("/api/notifications").route(web::get().to(api::get_notifications)))
+            .service(web::resource("/api/notifications/{id}/read").route(web::put().to(api::mark_notification_as_read)))
+            
+            // Smart HTTP Protocol endpoints для Git: `/git/{owner}/{repo_name}` и старый
+            // `/git/{repo_name}`, в котором владелец выбирается по имени
+            .configure(|cfg| git_routes(cfg, "/git/{owner}/{repo_name}"))
+            .configure(|cfg| git_routes(cfg, "/git/{repo_name}"))
     })
+    // Защита от медленных клиентов; тела запросов и ответов (clone, push) эти
+    // таймауты не ограничивают
+    .client_request_timeout(Duration::from_secs(CONFIG.client_request_timeout_secs))
+    .client_disconnect_timeout(Duration::from_secs(CONFIG.client_disconnect_timeout_secs))
+    .keep_alive(Duration::from_secs(CONFIG.keep_alive_secs))
+    // Лимиты действуют на каждый рабочий поток (по умолчанию их столько, сколько ядер)
+    .max_connections(CONFIG.max_connections)
+    .max_connection_rate(CONFIG.max_connection_rate)
     .bind("127.0.0.1:8000")?
-    .run()
-    .await
-}
-
-/// Обработчик для /info/refs - первый этап Git протокола
-/// Когда клиент выполняет git clone/pull/push, он сначала запрашивает этот эндпоинт
-/// чтобы узнать, какие ссылки (refs) доступны на сервере и какие операции поддерживаются
-async fn handle_info_refs(req: HttpRequest) -> HttpResponse {
-    // Проверяем авторизацию
-    if api::check_auth(&req, &req.app_data::<web::Data<Database>>().unwrap()).is_none() {
-        return HttpResponse::Unauthorized()
-            .append_header(("WWW-Authenticate", "Basic realm=\"Git\""))
-            .finish();
-    }
-
-    let repo_name = req.match_info().get("repo_name").unwrap();
-    let service = req.query_string();
-    
-    debug!("Handling info/refs for repo: {}, service: {}", repo_name, service);
-    
-    // Извлекаем имя сервиса (git-upload-pack или git-receive-pack)
-    let service = match service.strip_prefix("service=") {
-        Some(s) => s,
-        None => return HttpResponse::BadRequest().finish()
-    };
-
-    let repo_path = PathBuf::from("repositories").join(format!("{}.git", repo_name));
-
-    // Выбираем команду в зависимости от запрошенного сервиса
-    let git_command = if service == "git-upload-pack" { "upload-pack" } else { "receive-pack" };
-
-    // Запускаем git команду с флагом --advertise-refs для получения списка ссылок
-    let output = Command::new("git")
-        .arg(git_command)
-        .arg("--advertise-refs")
-        .arg(&repo_path)
-        .output()
-        .expect("Failed to execute git command");
-
-    if !output.status.success() {
-        error!("git command failed: {}", String::from_utf8_lossy(&output.stderr));
-        return HttpResponse::InternalServerError().finish();
-    }
-
-    // Формируем ответ в формате Smart HTTP Protocol
-    let mut response = Vec::new();
-    
-    // PKT-LINE формат:
-    // <4-byte length><payload>
-    // Где <4-byte length> - это ASCII hex длина пакета (включая 4 байта длины)
-    
-    // Сервисный заголовок
-    let service_header = format!("# service={}\n", service);
-    let header_length = service_header.len() + 4; // +4 для самой длины
-    response.extend_from_slice(format!("{:04x}", header_length).as_bytes());
-    response.extend_from_slice(service_header.as_bytes());
-    
-    // Разделитель
-    response.extend_from_slice(b"0000");
-    
-    // Добавляем вывод git-*-pack --advertise-refs
-    response.extend_from_slice(&output.stdout);
-    
-    // Возвращаем результат
-    HttpResponse::Ok()
-        .content_type(format!("application/x-{}-advertisement", service))
-        .body(response)
-}
-
-/// Обработчик для git-upload-pack - используется при git clone/fetch
-/// Клиент запрашивает определенные объекты, сервер их упаковывает и отправляет
-async fn handle_upload_pack(req: HttpRequest, body: web::Bytes) -> HttpResponse {
-    // Проверяем авторизацию
-    if api::check_auth(&req, &req.app_data::<web::Data<Database>>().unwrap()).is_none() {
-        return HttpResponse::Unauthorized()
-            .append_header(("WWW-Authenticate", "Basic realm=\"Git\""))
-            .finish();
-    }
-
-    let repo_name = req.match_info().get("repo_name").unwrap();
-    let repo_path = PathBuf::from("repositories").join(format!("{}.git", repo_name));
-
-    debug!("Handling upload-pack for repo: {}", repo_name);
-
-    // Запускаем git-upload-pack в режиме stateless-rpc (для HTTP протокола)
-    let mut child = Command::new("git")
-        .arg("upload-pack")
-        .arg("--stateless-rpc")  // Важно для HTTP протокола
-        .arg(&repo_path)
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .expect("Failed to spawn git-upload-pack");
-
-    // Передаем запрос клиента в git-upload-pack
-    if let Some(mut stdin) = child.stdin.take() {
-        stdin.write_all(&body).expect("Failed to write to git-upload-pack stdin");
-        drop(stdin);  // Важно закрыть stdin, чтобы процесс знал, что ввод закончен
-    }
-
-    let output = child.wait_with_output().expect("Failed to wait for git-upload-pack");
-
-    if !output.status.success() {
-        error!("git-upload-pack failed: {}", String::from_utf8_lossy(&output.stderr));
-        return HttpResponse::InternalServerError().finish();
-    }
-
-    HttpResponse::Ok()
-        .content_type("application/x-git-upload-pack-result")
-        .body(output.stdout)
+    .run();
+
+    // Задачи запуска выполняются, пока сервер уже принимает соединения:
+    // балансировщик видит `starting` в /health, а не ошибку соединения
+    actix_web::rt::spawn(async move {
+        let result = web::block(move || startup(&startup_db)).await;
+        match result {
+            Ok(Ok(())) => {
+                readiness::mark_ready();
+                info!("Server is ready");
+            }
+            Ok(Err(e)) => {
+                error!("Startup failed: {}", e);
+                std::process::exit(1);
+            }
+            Err(e) => {
+                error!("Startup task failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+    });
+
+    server.await
 }
 
-/// Обработчик для git-receive-pack - используется при git push
-/// Клиент отправляет новые объекты, сервер их принимает и обновляет ссылки
-async fn handle_receive_pack(req: HttpRequest, body: web::Bytes) -> HttpResponse {
-    // Проверяем авторизацию
-    let _username = match api::check_auth(&req, &req.app_data::<web::Data<Database>>().unwrap()) {
-        Some(user) => user.username,
-        None => return HttpResponse::Unauthorized()
-            .append_header(("WWW-Authenticate", "Basic realm=\"Git\""))
-            .finish()
-    };
-
-    let repo_name = req.match_info().get("repo_name").unwrap();
-    let repo_path = PathBuf::from("repositories").join(format!("{}.git", repo_name));
-
-    debug!("Handling receive-pack for repo: {}", repo_name);
-
-    let mut child = Command::new("git")
-        .arg("receive-pack")
-        .arg("--stateless-rpc")
-        .arg(&repo_path)
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .expect("Failed to spawn git-receive-pack");
+/// Задачи запуска: проверка конфигурации, каталог репозиториев, миграции базы данных
+/// и раскладки хранилища, очистка брошенных временных каталогов и обработчик фоновых задач
+fn startup(db: &Database) -> Result<(), String> {
+    CONFIG.validate()?;
 
-    if let Some(mut stdin) = child.stdin.take() {
-        stdin.write_all(&body).expect("Failed to write to git-receive-pack stdin");
-        drop(stdin);
-    }
-
-    let output = child.wait_with_output().expect("Failed to wait for git-receive-pack");
-
-    if !output.status.success() {
-        error!("git-receive-pack failed: {}", String::from_utf8_lossy(&output.stderr));
-        return HttpResponse::InternalServerError().finish();
+    // Создаем каталог для репозиториев, если он не существует
+    if !std::path::Path::new(git::REPOS_ROOT).exists() {
+        std::fs::create_dir(git::REPOS_ROOT)
+            .map_err(|e| format!("Failed to create {}: {}", git::REPOS_ROOT, e))?;
     }
 
-    HttpResponse::Ok()
-        .content_type("application/x-git-receive-pack-result")
-        .body(output.stdout)
-}
-
-/// Обработчик для objects/info/packs - возвращает список доступных pack-файлов
-/// Pack-файлы содержат сжатые Git объекты для эффективной передачи
-async fn handle_info_packs(req: HttpRequest) -> HttpResponse {
-    let repo_name = req.match_info().get("repo_name").unwrap();
-    let repo_path = PathBuf::from("repositories")
-        .join(format!("{}.git", repo_name))
-        .join("objects/info/packs");
-
-    match fs::read(&repo_path) {
-        Ok(content) => HttpResponse::Ok()
-            .content_type("text/plain")
-            .body(content),
-        Err(_) => HttpResponse::NotFound().finish()
+    // Инициализируем базу данных
+    db.migrate().map_err(|e| format!("Failed to initialize database: {}", e))?;
+    let moved = models::repository::Repository::migrate_storage_layout(std::path::Path::new(git::REPOS_ROOT), db.get_connection())
+        .map_err(|e| format!("Failed to migrate repository storage: {}", e))?;
+    if moved > 0 {
+        info!("Moved {} repositories to the per-owner layout", moved);
     }
-}
+    motd::load(db);
 
-/// Обработчик для получения конкретного pack-файла
-async fn handle_pack_file(req: HttpRequest) -> HttpResponse {
-    let repo_name = req.match_info().get("repo_name").unwrap();
-    let pack_file = req.match_info().get("pack_file").unwrap();
-    
-    let repo_path = PathBuf::from("repositories")
-        .join(format!("{}.git", repo_name))
-        .join("objects/pack")
-        .join(pack_file);
+    // Каталоги, оставшиеся после прошлого аварийного завершения
+    temp_cleanup::sweep();
 
-    match fs::read(&repo_path) {
-        Ok(content) => HttpResponse::Ok()
-            .content_type("application/x-git-pack")
-            .body(content),
-        Err(_) => HttpResponse::NotFound().finish()
-    }
+    // Очередь задач читается из базы данных, поэтому обработчик запускается после миграций
+    jobs::start_worker(db.clone());
+    temp_cleanup::schedule(CONFIG.temp_cleanup_interval_secs, db);
+    Ok(())
 }
 
-/// Обработчик для получения текстовых файлов из репозитория
-/// Используется, например, для просмотра README, LICENSE и других файлов
-async fn handle_text_file(req: HttpRequest) -> HttpResponse {
-    let repo_name = req.match_info().get("repo_name").unwrap();
-    let path = req.match_info().get("tail").unwrap();
-    
-    debug!("Getting file: {} from repo: {}", path, repo_name);
-    
-    // Используем git show для получения содержимого файла
-    let output = Command::new("git")
-        .args(&["--git-dir", &format!("repositories/{}.git", repo_name), "show", &format!("HEAD:{}", path)])
-        .output();
-    
-    match output {
-        Ok(output) if output.status.success() => {
-            HttpResponse::Ok()
-                .content_type("text/plain")
-                .body(output.stdout)
-        },
-        _ => HttpResponse::NotFound().finish()
-    }
+/// Маршруты smart HTTP и dumb протокола git с префиксом репозитория
+///
+/// # Параметры
+///
+/// * `cfg` - Конфигурация приложения
+/// * `prefix` - `/git/{owner}/{repo_name}` или `/git/{repo_name}`
+fn git_routes(cfg: &mut web::ServiceConfig, prefix: &str) {
+    cfg
+        .service(web::resource(format!("{}/info/refs", prefix))
+            .route(web::get().to(git_http::handle_info_refs)))
+        .service(web::resource(format!("{}/git-upload-pack", prefix))
+            .app_data(web::PayloadConfig::new(CONFIG.upload_pack_max_body_bytes))
+            .route(web::post().to(git_http::handle_upload_pack)))
+        .service(web::resource(format!("{}/git-receive-pack", prefix))
+            .route(web::post().to(git_http::handle_receive_pack)))
+        // Bundle для `bundle-uri`
+        .service(web::resource(format!("{}/bundle", prefix))
+            .route(web::get().to(git_http::handle_bundle))
+            .route(web::head().to(git_http::handle_bundle)))
+        // Pack files endpoints
+        .service(web::resource(format!("{}/objects/info/packs", prefix))
+            .route(web::get().to(git_http::handle_info_packs))
+            .route(web::head().to(git_http::handle_info_packs)))
+        .service(web::resource(format!("{}/objects/pack/{{pack_file}}", prefix))
+            .route(web::get().to(git_http::handle_pack_file))
+            .route(web::head().to(git_http::handle_pack_file)))
+        .service(web::resource(format!("{}/objects/{{dir}}/{{file}}", prefix))
+            .route(web::get().to(git_http::handle_loose_object))
+            .route(web::head().to(git_http::handle_loose_object)))
+        // Text file endpoint
+        .service(web::resource(format!("{}/file/{{tail:.*}}", prefix))
+            .route(web::get().to(git_http::handle_text_file))
+            .route(web::head().to(git_http::handle_text_file)));
 }
@@ -0,0 +1,116 @@
+use rusqlite::{params, Result};
+use std::sync::{Arc, Mutex};
+use serde::Serialize;
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use crate::models::db::with_retry;
+use crate::timestamps::parse_datetime;
+
+/// Запись журнала аудита: кто и что изменил в настройках доступа
+///
+/// Объект хранится по имени, а не по ID, чтобы запись оставалась понятной
+/// и после удаления репозитория.
+#[derive(Debug, Serialize, Clone)]
+pub struct AuditEntry {
+    /// Идентификатор записи
+    pub id: Option<i64>,
+    /// ID пользователя, выполнившего действие
+    #[serde(skip_serializing)]
+    pub actor_id: i64,
+    /// Имя пользователя, выполнившего действие (заполняется при выборке)
+    pub actor: Option<String>,
+    /// Действие, например `repo.visibility`
+    pub action: String,
+    /// Объект действия, например имя репозитория
+    pub target: String,
+    /// Подробности, например `public -> private`
+    pub details: String,
+    /// Дата действия
+    #[serde(serialize_with = "crate::timestamps::serialize_rfc3339")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl AuditEntry {
+    /// Создаёт запись для сохранения
+    ///
+    /// # Параметры
+    ///
+    /// * `actor_id` - ID пользователя, выполнившего действие
+    /// * `action` - Действие
+    /// * `target` - Объект действия
+    /// * `details` - Подробности
+    pub fn new(actor_id: i64, action: &str, target: &str, details: String) -> Self {
+        AuditEntry {
+            id: None,
+            actor_id,
+            actor: None,
+            action: action.to_string(),
+            target: target.to_string(),
+            details,
+            created_at: None,
+        }
+    }
+
+    /// Сохраняет запись в журнал
+    ///
+    /// # Параметры
+    ///
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<i64>` - ID созданной записи
+    pub fn create(&self, conn: Arc<Mutex<Connection>>) -> Result<i64> {
+        with_retry(&conn, |conn| {
+            conn.execute(
+                "INSERT INTO audit_log (actor_id, action, target, details) VALUES (?1, ?2, ?3, ?4)",
+                params![self.actor_id, self.action, self.target, self.details]
+            )?;
+            Ok(conn.last_insert_rowid())
+        })
+    }
+
+    /// Возвращает записи журнала, от новых к старым
+    ///
+    /// # Параметры
+    ///
+    /// * `limit` - Максимальное количество записей
+    /// * `offset` - Сколько записей пропустить
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<Vec<AuditEntry>>` - Записи журнала
+    pub fn list(limit: i64, offset: i64, conn: Arc<Mutex<Connection>>) -> Result<Vec<AuditEntry>> {
+        let conn_guard = conn.lock().unwrap();
+
+        let mut stmt = conn_guard.prepare(
+            "SELECT a.id, a.actor_id, u.username, a.action, a.target, a.details, a.created_at
+             FROM audit_log a
+             LEFT JOIN users u ON u.id = a.actor_id
+             ORDER BY a.id DESC
+             LIMIT ?1 OFFSET ?2"
+        )?;
+
+        let entries = stmt.query_map(params![limit, offset], |row| {
+            let created_at_str: String = row.get(6)?;
+
+            Ok(AuditEntry {
+                id: Some(row.get(0)?),
+                actor_id: row.get(1)?,
+                actor: row.get(2)?,
+                action: row.get(3)?,
+                target: row.get(4)?,
+                details: row.get(5)?,
+                created_at: parse_datetime(&created_at_str),
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for entry in entries {
+            result.push(entry?);
+        }
+
+        Ok(result)
+    }
+}
@@ -1,5 +1,30 @@
-use rusqlite::{Connection, Result};
+use rusqlite::{Connection, ErrorCode, Result};
+use std::cell::Cell;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use log::warn;
+
+/// Сколько раз повторять операцию, получившую SQLITE_BUSY/SQLITE_LOCKED
+const MAX_RETRIES: u32 = 5;
+/// Начальная задержка перед повтором; удваивается с каждой попыткой
+const INITIAL_BACKOFF: Duration = Duration::from_millis(20);
+/// Сколько SQLite сам ждёт снятия блокировки, прежде чем вернуть SQLITE_BUSY
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+thread_local! {
+    /// Поток обслуживает запросы actix, и `with_retry` не должен в нём засыпать
+    static ASYNC_WORKER: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Отмечает текущий поток как рабочий поток actix
+///
+/// Вызывается из фабрики приложения, которая выполняется в каждом рабочем потоке.
+/// В отмеченном потоке `with_retry` делает одну попытку без пауз.
+pub fn mark_async_worker() {
+    ASYNC_WORKER.with(|flag| flag.set(true));
+}
 
 /// База данных для хранения информации о пользователях, репозиториях и других данных
 #[derive(Clone)]
@@ -9,15 +34,33 @@ pub struct Database {
 }
 
 impl Database {
-    /// Создаёт новый экземпляр базы данных и инициализирует необходимые таблицы
-    /// 
+    /// Открывает базу данных, не создавая таблиц
+    ///
+    /// Пока не выполнен `migrate`, запросы к таблицам могут завершаться ошибкой.
+    ///
+    /// # Параметры
+    ///
+    /// * `path` - Путь к файлу SQLite; файл создаётся, если его нет
+    ///
     /// # Возвращает
-    /// 
-    /// * `Result<Database>` - Результат создания базы данных
-    pub fn new() -> Result<Self> {
-        let conn = Connection::open(
-            "gitea.db"
-        )?;
+    ///
+    /// * `Result<Database>` - Результат открытия базы данных
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+
+        Ok(Database {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Создаёт необходимые таблицы и добавляет недостающие столбцы
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<()>` - Результат миграции
+    pub fn migrate(&self) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
         
         // Создаём таблицы, если они ещё не существуют
         conn.execute(
@@ -93,15 +136,239 @@ impl Database {
             [],
         )?;
 
+        // Создаем таблицу для истории push'ей
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS pushes (
+                id INTEGER PRIMARY KEY,
+                repository_id INTEGER NOT NULL,
+                ref_name TEXT NOT NULL,
+                before_sha TEXT,
+                after_sha TEXT,
+                commits INTEGER NOT NULL DEFAULT 0,
+                files_changed INTEGER NOT NULL DEFAULT 0,
+                additions INTEGER NOT NULL DEFAULT 0,
+                deletions INTEGER NOT NULL DEFAULT 0,
+                pusher_id INTEGER NOT NULL,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (repository_id) REFERENCES repositories (id),
+                FOREIGN KEY (pusher_id) REFERENCES users (id)
+            )",
+            [],
+        )?;
+
+        // Создаем таблицу для предупреждений о секретах в коммитах
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS secret_alerts (
+                id INTEGER PRIMARY KEY,
+                repository_id INTEGER NOT NULL,
+                ref_name TEXT NOT NULL,
+                commit_sha TEXT NOT NULL,
+                path TEXT NOT NULL,
+                line INTEGER NOT NULL,
+                rule TEXT NOT NULL,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (repository_id) REFERENCES repositories (id),
+                UNIQUE (repository_id, commit_sha, path, line, rule)
+            )",
+            [],
+        )?;
+
+        // Таблица webhook'ов репозиториев
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS webhooks (
+                id INTEGER PRIMARY KEY,
+                repository_id INTEGER NOT NULL,
+                url TEXT NOT NULL,
+                events TEXT NOT NULL,
+                active INTEGER NOT NULL DEFAULT 1,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (repository_id) REFERENCES repositories (id)
+            )",
+            [],
+        )?;
+
+        // Журнал доставок webhook'ов
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS webhook_deliveries (
+                id INTEGER PRIMARY KEY,
+                hook_id INTEGER NOT NULL,
+                event TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                response_status INTEGER,
+                response_body TEXT,
+                duration_ms INTEGER NOT NULL,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (hook_id) REFERENCES webhooks (id)
+            )",
+            [],
+        )?;
+
+        // Webhook'и сервера: события о репозиториях целиком, настраиваются администратором
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS server_webhooks (
+                id INTEGER PRIMARY KEY,
+                url TEXT NOT NULL,
+                events TEXT NOT NULL,
+                active INTEGER NOT NULL DEFAULT 1,
+                secret TEXT,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+
+        // Настройки сервера, которые администратор меняет без перезапуска
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS server_settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        // Приглашения на регистрацию
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS invites (
+                id INTEGER PRIMARY KEY,
+                token TEXT NOT NULL UNIQUE,
+                created_by INTEGER NOT NULL,
+                used_by TEXT,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                used_at TIMESTAMP,
+                FOREIGN KEY (created_by) REFERENCES users (id)
+            )",
+            [],
+        )?;
+
+        // Подписки пользователей на уведомления репозиториев
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS repo_subscriptions (
+                user_id INTEGER NOT NULL,
+                repository_id INTEGER NOT NULL,
+                level TEXT NOT NULL,
+                PRIMARY KEY (user_id, repository_id),
+                FOREIGN KEY (user_id) REFERENCES users (id),
+                FOREIGN KEY (repository_id) REFERENCES repositories (id)
+            )",
+            [],
+        )?;
+
+        // Репозитории, закреплённые в профиле пользователя, в порядке `position`
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS profile_pinned_repos (
+                user_id INTEGER NOT NULL,
+                repository_id INTEGER NOT NULL,
+                position INTEGER NOT NULL,
+                PRIMARY KEY (user_id, repository_id),
+                FOREIGN KEY (user_id) REFERENCES users (id),
+                FOREIGN KEY (repository_id) REFERENCES repositories (id)
+            )",
+            [],
+        )?;
+
+        // Темы репозиториев для поиска (`rust`, `web`)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS repo_topics (
+                repository_id INTEGER NOT NULL,
+                topic TEXT NOT NULL,
+                PRIMARY KEY (repository_id, topic),
+                FOREIGN KEY (repository_id) REFERENCES repositories (id)
+            )",
+            [],
+        )?;
+
+        // Очередь отложенных задач (webhook'и и другая фоновая работа)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS jobs (
+                id INTEGER PRIMARY KEY,
+                job_type TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                run_after TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                attempts INTEGER NOT NULL DEFAULT 0,
+                status TEXT NOT NULL DEFAULT 'pending',
+                last_error TEXT,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+
+        // Журнал аудита изменений доступа
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS audit_log (
+                id INTEGER PRIMARY KEY,
+                actor_id INTEGER NOT NULL,
+                action TEXT NOT NULL,
+                target TEXT NOT NULL,
+                details TEXT NOT NULL,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+
+        // Индексы для частых выборок
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_pull_requests_author ON pull_requests (author_id)",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_pull_requests_branches
+             ON pull_requests (repository_id, source_branch, target_branch, status)",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_pushes_repository ON pushes (repository_id, created_at)",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_hook ON webhook_deliveries (hook_id)",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_jobs_due ON jobs (status, run_after)",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_repo_topics_topic ON repo_topics (topic)",
+            [],
+        )?;
+
+        // Миграции для баз данных, созданных до появления новых столбцов
+        add_column_if_missing(&conn, "users", "storage_quota_bytes", "INTEGER")?;
+        add_column_if_missing(&conn, "pull_request_comments", "deleted", "INTEGER NOT NULL DEFAULT 0")?;
+        add_column_if_missing(&conn, "notifications", "dedupe_key", "TEXT")?;
+        add_column_if_missing(&conn, "repositories", "ref_generation", "INTEGER NOT NULL DEFAULT 0")?;
+        add_column_if_missing(&conn, "repositories", "forked_from", "INTEGER REFERENCES repositories (id)")?;
+        add_column_if_missing(&conn, "users", "last_login_at", "TIMESTAMP")?;
+        add_column_if_missing(&conn, "users", "last_login_ip", "TEXT")?;
+        add_column_if_missing(&conn, "users", "failed_login_count", "INTEGER NOT NULL DEFAULT 0")?;
+        add_column_if_missing(&conn, "users", "locked_until", "TIMESTAMP")?;
+        add_column_if_missing(&conn, "repositories", "pull_requests_enabled", "INTEGER NOT NULL DEFAULT 1")?;
+        add_column_if_missing(&conn, "repositories", "allow_merge_commit", "INTEGER NOT NULL DEFAULT 1")?;
+        add_column_if_missing(&conn, "repositories", "allow_squash", "INTEGER NOT NULL DEFAULT 1")?;
+        add_column_if_missing(&conn, "repositories", "allow_rebase", "INTEGER NOT NULL DEFAULT 1")?;
+        add_column_if_missing(&conn, "webhooks", "secret", "TEXT")?;
+        add_column_if_missing(&conn, "webhook_deliveries", "guid", "TEXT")?;
+        create_repo_name_index(&conn)?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_repositories_forked_from ON repositories (forked_from, created_at)",
+            [],
+        )?;
+        conn.execute(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_notifications_dedupe ON notifications (dedupe_key)",
+            [],
+        )?;
+
+        // Даты, записанные старыми версиями в RFC3339, приводятся к формату SQLite
+        for (table, column) in TIMESTAMP_COLUMNS {
+            normalize_timestamps(&conn, table, column)?;
+        }
+
         // Добавим тестового пользователя, если он ещё не существует
         conn.execute(
             "INSERT OR IGNORE INTO users (username, password, email) VALUES ('Kazilsky', 'password123', 'test@example.com')",
             [],
         )?;
 
-        Ok(Database {
-            conn: Arc::new(Mutex::new(conn)),
-        })
+        Ok(())
     }
 
     /// Получает соединение с базой данных
@@ -113,3 +380,226 @@ impl Database {
         self.conn.clone()
     }
 }
+
+/// Выполняет операцию с базой данных, повторяя её при временной блокировке
+///
+/// Повторяются только ошибки SQLITE_BUSY и SQLITE_LOCKED, с экспоненциально
+/// растущей задержкой; остальные ошибки возвращаются сразу. Соединение
+/// блокируется только на время попытки и освобождается перед ожиданием,
+/// чтобы другие запросы не простаивали за повторяющейся операцией. Если
+/// несколько запросов должны выполниться без вмешательства других потоков
+/// (например, INSERT и `last_insert_rowid`), они помещаются в одну операцию.
+///
+/// В рабочем потоке actix (см. `mark_async_worker`) пауза остановила бы все его
+/// запросы, поэтому там операция выполняется один раз, а ждёт только сам SQLite
+/// (`BUSY_TIMEOUT`). Записи, которые должны пережить конкуренцию за базу, например
+/// обработка push (`handlers::git::on_push`), выполняются в `web::block` или
+/// в пуле git, где повторы с паузами разрешены.
+///
+/// # Параметры
+///
+/// * `conn` - Соединение с базой данных
+/// * `op` - Операция с базой данных
+///
+/// # Возвращает
+///
+/// * `Result<T>` - Результат последней попытки
+pub fn with_retry<T, F: FnMut(&Connection) -> Result<T>>(conn: &Mutex<Connection>, mut op: F) -> Result<T> {
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 0;
+
+    loop {
+        let result = {
+            let guard = conn.lock().unwrap();
+            op(&guard)
+        };
+        match result {
+            Err(e) if is_busy(&e) && attempt < MAX_RETRIES && !ASYNC_WORKER.with(Cell::get) => {
+                attempt += 1;
+                warn!("Database is busy, retrying in {:?} (attempt {}/{})", backoff, attempt, MAX_RETRIES);
+                thread::sleep(backoff);
+                backoff *= 2;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Проверяет, что ошибка вызвана временной блокировкой базы данных
+fn is_busy(error: &rusqlite::Error) -> bool {
+    matches!(
+        error,
+        rusqlite::Error::SqliteFailure(e, _)
+            if e.code == ErrorCode::DatabaseBusy || e.code == ErrorCode::DatabaseLocked
+    )
+}
+
+/// Столбцы с датами: все они хранятся в формате `crate::timestamps::DB_FORMAT` и в UTC
+const TIMESTAMP_COLUMNS: &[(&str, &str)] = &[
+    ("users", "created_at"),
+    ("users", "last_login_at"),
+    ("users", "locked_until"),
+    ("repositories", "created_at"),
+    ("notifications", "created_at"),
+    ("pull_requests", "created_at"),
+    ("pull_requests", "updated_at"),
+    ("pull_request_comments", "created_at"),
+    ("pushes", "created_at"),
+    ("secret_alerts", "created_at"),
+    ("webhooks", "created_at"),
+    ("webhook_deliveries", "created_at"),
+    ("invites", "created_at"),
+    ("invites", "used_at"),
+    ("jobs", "run_after"),
+    ("jobs", "created_at"),
+];
+
+/// Переводит даты столбца из RFC3339 (`2024-05-01T12:30:00+03:00`) в формат SQLite в UTC
+///
+/// Значения, которые SQLite не может разобрать, не трогаются.
+///
+/// # Параметры
+///
+/// * `conn` - Соединение с базой данных
+/// * `table` - Имя таблицы
+/// * `column` - Имя столбца с датой
+fn normalize_timestamps(conn: &Connection, table: &str, column: &str) -> Result<()> {
+    conn.execute(
+        &format!(
+            "UPDATE {table} SET {column} = datetime({column})
+             WHERE {column} LIKE '%T%' AND datetime({column}) IS NOT NULL"
+        ),
+        [],
+    )?;
+    Ok(())
+}
+
+/// Создаёт уникальный индекс имён репозиториев владельца без учёта регистра
+///
+/// Каталоги репозиториев лежат в `{owner}/{name}.git`, поэтому имя уникально
+/// только в пределах владельца.
+/// `Foo` и `foo` одного владельца - один каталог на нечувствительных к регистру
+/// файловых системах. Если такие пары уже есть в базе, индекс не создаётся,
+/// а имена выводятся в лог: их нужно переименовать вручную.
+fn create_repo_name_index(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT u.username || '/' || lower(r.name) FROM repositories r
+        JOIN users u ON u.id = r.owner_id
+        GROUP BY r.owner_id, lower(r.name) HAVING COUNT(*) > 1"
+    )?;
+    let duplicates = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<String>>>()?;
+
+    if !duplicates.is_empty() {
+        warn!(
+            "Repositories of one owner differ only in case, rename them to enforce unique names: {}",
+            duplicates.join(", ")
+        );
+        return Ok(());
+    }
+
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_repositories_owner_name ON repositories (owner_id, name COLLATE NOCASE)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Добавляет столбец в таблицу, если его ещё нет
+///
+/// SQLite не поддерживает `ADD COLUMN IF NOT EXISTS`, поэтому наличие столбца
+/// проверяется через `PRAGMA table_info`.
+///
+/// # Параметры
+///
+/// * `conn` - Соединение с базой данных
+/// * `table` - Имя таблицы
+/// * `column` - Имя столбца
+/// * `definition` - Тип и ограничения столбца
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|name| name.ok())
+        .any(|name| name == column);
+
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use crate::test_support::{unique, work_dir};
+
+    #[test]
+    fn with_retry_releases_the_connection_while_backing_off() {
+        let path = work_dir().join(format!("{}.db", unique("busy")));
+        let db = Database::open(&path).unwrap();
+        db.migrate().unwrap();
+        let conn = db.get_connection();
+        conn.lock().unwrap().busy_timeout(Duration::from_millis(1)).unwrap();
+
+        // Второе соединение держит блокировку записи, как другой процесс
+        let blocker = Connection::open(&path).unwrap();
+        blocker.execute_batch("BEGIN IMMEDIATE").unwrap();
+
+        let attempts = Arc::new(AtomicU32::new(0));
+        let writer = {
+            let conn = conn.clone();
+            let attempts = attempts.clone();
+            thread::spawn(move || {
+                with_retry(&conn, |conn| {
+                    attempts.fetch_add(1, Ordering::SeqCst);
+                    conn.execute("INSERT INTO server_settings (key, value) VALUES ('busy', 'done')", [])
+                })
+            })
+        };
+
+        while attempts.load(Ordering::SeqCst) == 0 {
+            thread::sleep(Duration::from_millis(1));
+        }
+        // Пока операция ждёт повтора, соединение доступно другим запросам
+        let reader_got_lock = (0..100).any(|_| {
+            let free = conn.try_lock().is_ok();
+            thread::sleep(Duration::from_millis(1));
+            free
+        });
+        assert!(reader_got_lock);
+
+        blocker.execute_batch("COMMIT").unwrap();
+        assert_eq!(writer.join().unwrap().unwrap(), 1);
+        assert!(attempts.load(Ordering::SeqCst) > 1);
+    }
+
+    #[test]
+    fn with_retry_does_not_sleep_on_an_actix_worker() {
+        let path = work_dir().join(format!("{}.db", unique("worker")));
+        let db = Database::open(&path).unwrap();
+        db.migrate().unwrap();
+        let conn = db.get_connection();
+        conn.lock().unwrap().busy_timeout(Duration::from_millis(1)).unwrap();
+
+        let blocker = Connection::open(&path).unwrap();
+        blocker.execute_batch("BEGIN IMMEDIATE").unwrap();
+
+        let (attempts, result) = thread::spawn(move || {
+            mark_async_worker();
+            let mut attempts = 0;
+            let result = with_retry(&conn, |conn| {
+                attempts += 1;
+                conn.execute("INSERT INTO server_settings (key, value) VALUES ('worker', 'done')", [])
+            });
+            (attempts, result)
+        }).join().unwrap();
+
+        assert_eq!(attempts, 1);
+        assert!(result.is_err_and(|e| is_busy(&e)));
+        blocker.execute_batch("ROLLBACK").unwrap();
+    }
+}
@@ -0,0 +1,148 @@
+use rusqlite::{params, Result};
+use std::sync::{Arc, Mutex};
+use serde::Serialize;
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use crate::models::db::with_retry;
+use crate::timestamps::parse_datetime;
+
+/// Одноразовое приглашение на регистрацию
+#[derive(Debug, Serialize, Clone)]
+pub struct Invite {
+    /// Идентификатор приглашения
+    pub id: Option<i64>,
+    /// Токен, который передаётся в `POST /api/auth/register`
+    pub token: String,
+    /// ID администратора, создавшего приглашение
+    #[serde(skip_serializing)]
+    pub created_by: i64,
+    /// Имя пользователя, зарегистрированного по приглашению
+    pub used_by: Option<String>,
+    /// Дата создания
+    #[serde(serialize_with = "crate::timestamps::serialize_rfc3339")]
+    pub created_at: Option<DateTime<Utc>>,
+    /// Дата использования
+    #[serde(serialize_with = "crate::timestamps::serialize_rfc3339")]
+    pub used_at: Option<DateTime<Utc>>,
+}
+
+impl Invite {
+    /// Создаёт новое приглашение со случайным токеном
+    ///
+    /// # Параметры
+    ///
+    /// * `created_by` - ID администратора
+    pub fn new(created_by: i64) -> Self {
+        Invite {
+            id: None,
+            token: uuid::Uuid::new_v4().simple().to_string(),
+            created_by,
+            used_by: None,
+            created_at: None,
+            used_at: None,
+        }
+    }
+
+    /// Сохраняет приглашение в базе данных
+    ///
+    /// # Параметры
+    ///
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<i64>` - ID созданного приглашения
+    pub fn create(&self, conn: Arc<Mutex<Connection>>) -> Result<i64> {
+        with_retry(&conn, |conn| {
+            conn.execute(
+                "INSERT INTO invites (token, created_by) VALUES (?1, ?2)",
+                params![self.token, self.created_by],
+            )?;
+            Ok(conn.last_insert_rowid())
+        })
+    }
+
+    /// Возвращает все приглашения, от новых к старым
+    ///
+    /// # Параметры
+    ///
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<Vec<Invite>>` - Приглашения
+    pub fn find_all(conn: Arc<Mutex<Connection>>) -> Result<Vec<Invite>> {
+        let conn_guard = conn.lock().unwrap();
+
+        let mut stmt = conn_guard.prepare(
+            "SELECT id, token, created_by, used_by, created_at, used_at FROM invites ORDER BY id DESC"
+        )?;
+        let invites = stmt.query_map([], |row| {
+            let created_at_str: String = row.get(4)?;
+            let used_at_str: Option<String> = row.get(5)?;
+
+            Ok(Invite {
+                id: Some(row.get(0)?),
+                token: row.get(1)?,
+                created_by: row.get(2)?,
+                used_by: row.get(3)?,
+                created_at: parse_datetime(&created_at_str),
+                used_at: used_at_str.as_deref().and_then(parse_datetime),
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for invite in invites {
+            result.push(invite?);
+        }
+
+        Ok(result)
+    }
+
+    /// Помечает приглашение использованным, если оно существует и ещё свободно
+    ///
+    /// Проверка и пометка выполняются одним запросом, поэтому один токен
+    /// не может быть использован дважды при одновременной регистрации.
+    ///
+    /// # Параметры
+    ///
+    /// * `token` - Токен приглашения
+    /// * `username` - Имя регистрирующегося пользователя
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<bool>` - true, если приглашение было свободно и теперь занято
+    pub fn claim(token: &str, username: &str, conn: Arc<Mutex<Connection>>) -> Result<bool> {
+        let updated = with_retry(&conn, |conn| {
+            conn.execute(
+                "UPDATE invites SET used_by = ?1, used_at = CURRENT_TIMESTAMP
+                WHERE token = ?2 AND used_by IS NULL",
+                params![username, token],
+            )
+        })?;
+
+        Ok(updated > 0)
+    }
+
+    /// Освобождает приглашение, если регистрация после `claim` не удалась
+    ///
+    /// # Параметры
+    ///
+    /// * `token` - Токен приглашения
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<()>` - Результат операции
+    pub fn release(token: &str, conn: Arc<Mutex<Connection>>) -> Result<()> {
+        with_retry(&conn, |conn| {
+            conn.execute(
+                "UPDATE invites SET used_by = NULL, used_at = NULL WHERE token = ?1",
+                params![token],
+            )
+        })?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,245 @@
+use rusqlite::{params, Result};
+use std::sync::{Arc, Mutex};
+use serde::Serialize;
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use crate::models::db::with_retry;
+use crate::timestamps::parse_datetime;
+
+/// Столбцы, из которых собирается задача в `Job::from_row`
+const JOB_COLUMNS: &str = "id, job_type, payload, run_after, attempts, status, last_error, created_at";
+
+/// Задача ждёт выполнения (в том числе повторной попытки)
+pub const STATUS_PENDING: &str = "pending";
+/// Задача выполняется фоновым обработчиком
+pub const STATUS_RUNNING: &str = "running";
+/// Попытки исчерпаны; задача больше не выполняется
+pub const STATUS_DEAD: &str = "dead";
+
+/// Отложенная задача из очереди `jobs`
+///
+/// Выполненные задачи удаляются; в таблице остаются ожидающие, выполняющиеся
+/// и «мёртвые» задачи, у которых закончились попытки.
+#[derive(Debug, Serialize, Clone)]
+pub struct Job {
+    /// Идентификатор задачи
+    pub id: i64,
+    /// Тип задачи, по которому выбирается обработчик
+    pub job_type: String,
+    /// Параметры задачи в JSON
+    pub payload: serde_json::Value,
+    /// Раньше этого момента задача не запускается
+    #[serde(serialize_with = "crate::timestamps::serialize_rfc3339")]
+    pub run_after: Option<DateTime<Utc>>,
+    /// Сколько раз задача уже запускалась
+    pub attempts: i64,
+    /// Состояние: `pending`, `running` или `dead`
+    pub status: String,
+    /// Ошибка последней неудачной попытки
+    pub last_error: Option<String>,
+    /// Дата постановки в очередь
+    #[serde(serialize_with = "crate::timestamps::serialize_rfc3339")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl Job {
+    /// Ставит задачу в очередь для немедленного выполнения
+    ///
+    /// # Параметры
+    ///
+    /// * `job_type` - Тип задачи
+    /// * `payload` - Параметры задачи
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<i64>` - ID задачи
+    pub fn enqueue(job_type: &str, payload: &serde_json::Value, conn: Arc<Mutex<Connection>>) -> Result<i64> {
+        with_retry(&conn, |conn| {
+            conn.execute(
+                "INSERT INTO jobs (job_type, payload) VALUES (?1, ?2)",
+                params![job_type, payload.to_string()],
+            )?;
+            Ok(conn.last_insert_rowid())
+        })
+    }
+
+    /// Ставит задачу в очередь с задержкой, если такой же задачи ещё нет среди ожидающих
+    ///
+    /// Нужна для периодических задач: повторная постановка не создаёт вторую цепочку.
+    ///
+    /// # Параметры
+    ///
+    /// * `job_type` - Тип задачи
+    /// * `payload` - Параметры задачи
+    /// * `delay_secs` - Через сколько секунд задачу можно выполнять
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<Option<i64>>` - ID новой задачи или None, если такая уже ждёт
+    pub fn schedule(job_type: &str, payload: &serde_json::Value, delay_secs: u64, conn: Arc<Mutex<Connection>>) -> Result<Option<i64>> {
+        let payload = payload.to_string();
+
+        with_retry(&conn, |conn| {
+            let pending: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM jobs WHERE status = ?1 AND job_type = ?2 AND payload = ?3",
+                params![STATUS_PENDING, job_type, payload],
+                |row| row.get(0),
+            )?;
+            if pending > 0 {
+                return Ok(None);
+            }
+
+            conn.execute(
+                "INSERT INTO jobs (job_type, payload, run_after) VALUES (?1, ?2, datetime('now', ?3))",
+                params![job_type, payload, format!("+{} seconds", delay_secs)],
+            )?;
+            Ok(Some(conn.last_insert_rowid()))
+        })
+    }
+
+    /// Забирает самую раннюю задачу, срок которой наступил, и отмечает её выполняющейся
+    ///
+    /// # Параметры
+    ///
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<Option<Job>>` - Задача (с уже увеличенным `attempts`) или None, если ждать нечего
+    pub fn claim_next(conn: Arc<Mutex<Connection>>) -> Result<Option<Job>> {
+        with_retry(&conn, |conn| {
+            let job = {
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT {} FROM jobs WHERE status = ?1 AND run_after <= datetime('now')
+                    ORDER BY run_after, id LIMIT 1",
+                    JOB_COLUMNS
+                ))?;
+                let mut rows = stmt.query(params![STATUS_PENDING])?;
+                match rows.next()? {
+                    Some(row) => Self::from_row(row)?,
+                    None => return Ok(None),
+                }
+            };
+
+            conn.execute(
+                "UPDATE jobs SET status = ?1, attempts = attempts + 1 WHERE id = ?2",
+                params![STATUS_RUNNING, job.id],
+            )?;
+
+            Ok(Some(Job { status: STATUS_RUNNING.to_string(), attempts: job.attempts + 1, ..job }))
+        })
+    }
+
+    /// Удаляет выполненную задачу
+    ///
+    /// # Параметры
+    ///
+    /// * `id` - ID задачи
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<()>` - Результат операции
+    pub fn complete(id: i64, conn: Arc<Mutex<Connection>>) -> Result<()> {
+        with_retry(&conn, |conn| conn.execute("DELETE FROM jobs WHERE id = ?1", params![id]))?;
+
+        Ok(())
+    }
+
+    /// Записывает неудачную попытку: откладывает задачу или, если попытки
+    /// исчерпаны, отмечает её мёртвой
+    ///
+    /// # Параметры
+    ///
+    /// * `error` - Описание ошибки
+    /// * `retry_in_secs` - Через сколько секунд повторить; None - больше не повторять
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<()>` - Результат операции
+    pub fn fail(&self, error: &str, retry_in_secs: Option<u64>, conn: Arc<Mutex<Connection>>) -> Result<()> {
+        with_retry(&conn, |conn| match retry_in_secs {
+            Some(secs) => conn.execute(
+                "UPDATE jobs SET status = ?1, last_error = ?2, run_after = datetime('now', ?3) WHERE id = ?4",
+                params![STATUS_PENDING, error, format!("+{} seconds", secs), self.id],
+            ),
+            None => conn.execute(
+                "UPDATE jobs SET status = ?1, last_error = ?2 WHERE id = ?3",
+                params![STATUS_DEAD, error, self.id],
+            ),
+        })?;
+
+        Ok(())
+    }
+
+    /// Возвращает в очередь задачи, прерванные остановкой сервера
+    ///
+    /// Вызывается при запуске, до первого `claim_next`: выполняющихся задач
+    /// в этот момент быть не может, прерванная попытка засчитывается.
+    ///
+    /// # Параметры
+    ///
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<usize>` - Сколько задач возвращено
+    pub fn requeue_interrupted(conn: Arc<Mutex<Connection>>) -> Result<usize> {
+        with_retry(&conn, |conn| {
+            conn.execute(
+                "UPDATE jobs SET status = ?1 WHERE status = ?2",
+                params![STATUS_PENDING, STATUS_RUNNING],
+            )
+        })
+    }
+
+    /// Возвращает задачи в указанном состоянии, от новых к старым
+    ///
+    /// # Параметры
+    ///
+    /// * `status` - Состояние задач
+    /// * `limit` - Максимальное количество записей
+    /// * `offset` - Сколько записей пропустить
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<Vec<Job>>` - Задачи
+    pub fn find_by_status(status: &str, limit: i64, offset: i64, conn: Arc<Mutex<Connection>>) -> Result<Vec<Job>> {
+        let conn_guard = conn.lock().unwrap();
+
+        let mut stmt = conn_guard.prepare(&format!(
+            "SELECT {} FROM jobs WHERE status = ?1 ORDER BY id DESC LIMIT ?2 OFFSET ?3",
+            JOB_COLUMNS
+        ))?;
+        let jobs = stmt.query_map(params![status, limit, offset], Self::from_row)?;
+
+        let mut result = Vec::new();
+        for job in jobs {
+            result.push(job?);
+        }
+
+        Ok(result)
+    }
+
+    /// Собирает задачу из строки выборки со столбцами `JOB_COLUMNS`
+    fn from_row(row: &rusqlite::Row) -> Result<Job> {
+        let payload: String = row.get(2)?;
+        let run_after_str: String = row.get(3)?;
+        let created_at_str: String = row.get(7)?;
+
+        Ok(Job {
+            id: row.get(0)?,
+            job_type: row.get(1)?,
+            payload: serde_json::from_str(&payload).unwrap_or(serde_json::Value::Null),
+            run_after: parse_datetime(&run_after_str),
+            attempts: row.get(4)?,
+            status: row.get(5)?,
+            last_error: row.get(6)?,
+            created_at: parse_datetime(&created_at_str),
+        })
+    }
+}
@@ -4,7 +4,25 @@ pub mod user;
 pub mod repository;
 /// Модуль для работы с базой данных
 pub mod db;
+/// Трейт хранилища, через который запросы находят пользователей и репозитории
+pub mod store;
 /// Модуль для работы с уведомлениями
 pub mod notification;
 /// Модуль для работы с пул-реквестами
 pub mod pull_request;
+/// Модуль для работы с историей push'ей
+pub mod push;
+/// Модуль для работы с предупреждениями о секретах в коммитах
+pub mod secret_alert;
+/// Модуль для работы с webhook'ами и журналом их доставок
+pub mod webhook;
+/// Модуль для работы с приглашениями на регистрацию
+pub mod invite;
+/// Модуль для работы с очередью отложенных задач
+pub mod job;
+/// Модуль для работы с подписками на уведомления репозиториев
+pub mod subscription;
+/// Модуль для работы с журналом аудита
+pub mod audit;
+/// Модуль для работы с настройками сервера, изменяемыми во время работы
+pub mod setting;
@@ -1,8 +1,11 @@
 use rusqlite::{params, Result};
 use std::sync::{Arc, Mutex};
 use serde::{Serialize, Deserialize};
-use chrono::{DateTime, Utc, NaiveDateTime};
+use chrono::{DateTime, Utc};
 use rusqlite::Connection;
+use crate::cursor::Cursor;
+use crate::models::db::with_retry;
+use crate::timestamps::parse_datetime;
 
 /// Модель уведомления в системе
 /// 
@@ -23,6 +26,7 @@ pub struct Notification {
     /// Флаг прочтения уведомления
     pub is_read: bool,
     /// Дата создания уведомления
+    #[serde(serialize_with = "crate::timestamps::serialize_rfc3339")]
     pub created_at: Option<DateTime<Utc>>,
 }
 
@@ -37,45 +41,95 @@ impl Notification {
     /// 
     /// * `Result<i64>` - ID созданного уведомления
     pub fn create(&self, conn: Arc<Mutex<Connection>>) -> Result<i64> {
-        let conn_guard = conn.lock().unwrap();
+        with_retry(&conn, |conn| {
+            conn.execute(
+                "INSERT INTO notifications 
+                (notification_type, title, content, user_id, is_read) 
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    self.notification_type,
+                    self.title,
+                    self.content,
+                    self.user_id,
+                    self.is_read
+                ]
+            )?;
+            Ok(conn.last_insert_rowid())
+        })
+    }
 
-        conn_guard.execute(
-            "INSERT INTO notifications 
-            (notification_type, title, content, user_id, is_read) 
-            VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![
-                self.notification_type,
-                self.title,
-                self.content,
-                self.user_id,
-                self.is_read
-            ]
-        )?;
+    /// Создаёт уведомление, если уведомление с таким ключом ещё не создавалось
+    /// 
+    /// Используется для реакций, которые могут выполниться повторно (например,
+    /// обработка одного и того же push): повтор не создаёт дубликат.
+    /// 
+    /// # Параметры
+    /// 
+    /// * `dedupe_key` - Ключ, уникально описывающий событие и получателя
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<bool>` - true, если уведомление создано, false - если уже существовало
+    pub fn create_once(&self, dedupe_key: &str, conn: Arc<Mutex<Connection>>) -> Result<bool> {
+        let inserted = with_retry(&conn, |conn| {
+            conn.execute(
+                "INSERT OR IGNORE INTO notifications 
+                (notification_type, title, content, user_id, is_read, dedupe_key) 
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    self.notification_type,
+                    self.title,
+                    self.content,
+                    self.user_id,
+                    self.is_read,
+                    dedupe_key
+                ]
+            )
+        })?;
 
-        Ok(conn_guard.last_insert_rowid())
+        Ok(inserted > 0)
     }
 
-    /// Находит уведомления по ID пользователя
+    /// Находит уведомления пользователя, от новых к старым
     /// 
     /// # Параметры
     /// 
     /// * `user_id` - ID пользователя
+    /// * `limit` - Максимальное количество записей
+    /// * `offset` - Сколько записей пропустить
+    /// * `before` - Вернуть только записи после этой позиции списка
     /// * `conn` - Соединение с базой данных
     /// 
     /// # Возвращает
     /// 
     /// * `Result<Vec<Notification>>` - Список уведомлений пользователя
-    pub fn find_by_user_id(user_id: i64, conn: Arc<Mutex<Connection>>) -> Result<Vec<Notification>> {
+    pub fn find_by_user_id(
+        user_id: i64,
+        limit: i64,
+        offset: i64,
+        before: Option<&Cursor>,
+        conn: Arc<Mutex<Connection>>,
+    ) -> Result<Vec<Notification>> {
         let conn_guard = conn.lock().unwrap();
         
         let mut stmt = conn_guard.prepare(
             "SELECT id, notification_type, title, content, user_id, is_read, created_at 
              FROM notifications 
              WHERE user_id = ?1 
-             ORDER BY created_at DESC"
+               AND (?4 IS NULL OR created_at < ?4 OR (created_at = ?4 AND id < ?5)) 
+             ORDER BY created_at DESC, id DESC 
+             LIMIT ?2 OFFSET ?3"
         )?;
         
-        let notifications = stmt.query_map(params![user_id], |row| {
+        let params = params![
+            user_id,
+            limit,
+            offset,
+            before.map(|cursor| cursor.created_at.as_str()),
+            before.map(|cursor| cursor.id),
+        ];
+        let notifications = stmt.query_map(params, |row| {
             let created_at_str: String = row.get(6)?;
             
             Ok(Notification {
@@ -85,19 +139,52 @@ impl Notification {
                 content: row.get(3)?,
                 user_id: row.get(4)?,
                 is_read: row.get(5)?,
-                created_at: match DateTime::parse_from_rfc3339(&created_at_str) {
-                    Ok(dt) => Some(dt.with_timezone(&Utc)),
-                    Err(_) => {
-                        // Пробуем формат SQLite
-                        let naive = NaiveDateTime::parse_from_str(&created_at_str, "%Y-%m-%d %H:%M:%S")
-                            .or_else(|_| NaiveDateTime::parse_from_str(&created_at_str, "%Y-%m-%dT%H:%M:%S"));
-                        
-                        match naive {
-                            Ok(ndt) => Some(DateTime::<Utc>::from_naive_utc_and_offset(ndt, Utc)),
-                            Err(_) => None
-                        }
-                    }
-                },
+                created_at: parse_datetime(&created_at_str),
+            })
+        })?;
+        
+        let mut result = Vec::new();
+        for notification in notifications {
+            result.push(notification?);
+        }
+        
+        Ok(result)
+    }
+
+    /// Получает следующую порцию уведомлений пользователя по возрастанию ID
+    /// 
+    /// # Параметры
+    /// 
+    /// * `user_id` - ID пользователя
+    /// * `after_id` - ID последнего уже полученного уведомления (0 - с начала)
+    /// * `limit` - Размер порции
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<Vec<Notification>>` - Уведомления с ID больше `after_id`
+    pub fn find_by_user_after(user_id: i64, after_id: i64, limit: i64, conn: Arc<Mutex<Connection>>) -> Result<Vec<Notification>> {
+        let conn_guard = conn.lock().unwrap();
+        
+        let mut stmt = conn_guard.prepare(
+            "SELECT id, notification_type, title, content, user_id, is_read, created_at 
+             FROM notifications 
+             WHERE user_id = ?1 AND id > ?2 
+             ORDER BY id 
+             LIMIT ?3"
+        )?;
+        
+        let notifications = stmt.query_map(params![user_id, after_id, limit], |row| {
+            let created_at_str: String = row.get(6)?;
+            
+            Ok(Notification {
+                id: Some(row.get(0)?),
+                notification_type: row.get(1)?,
+                title: row.get(2)?,
+                content: row.get(3)?,
+                user_id: row.get(4)?,
+                is_read: row.get(5)?,
+                created_at: parse_datetime(&created_at_str),
             })
         })?;
         
@@ -120,12 +207,12 @@ impl Notification {
     /// 
     /// * `Result<()>` - Результат операции
     pub fn mark_as_read(id: i64, conn: Arc<Mutex<Connection>>) -> Result<()> {
-        let conn_guard = conn.lock().unwrap();
-        
-        conn_guard.execute(
-            "UPDATE notifications SET is_read = 1 WHERE id = ?1",
-            params![id]
-        )?;
+        with_retry(&conn, |conn| {
+            conn.execute(
+                "UPDATE notifications SET is_read = 1 WHERE id = ?1",
+                params![id]
+            )
+        })?;
         
         Ok(())
     }
@@ -1,10 +1,20 @@
 use rusqlite::{params, Result};
 use std::sync::{Arc, Mutex};
 use serde::{Serialize, Deserialize};
-use chrono::{DateTime, Utc, NaiveDateTime};
+use chrono::{DateTime, Utc};
 use rusqlite::Connection;
+use crate::models::db::with_retry;
 use log::{debug, error};
 use crate::models::notification::Notification;
+use crate::models::subscription::Subscription;
+use crate::models::user::User;
+use crate::error::AppError;
+use crate::git::{full_repo_name, git_command, lock_repo, resolve_commit, resolve_repo, TempDir, REPOS_ROOT};
+use crate::git::merge::{merge_kind, MergeKind};
+use crate::git::refs::{receive_protection, ReceiveProtection};
+use crate::config::CONFIG;
+use std::path::Path;
+use crate::timestamps::parse_datetime;
 
 /// Статус пул-реквеста
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -38,6 +48,49 @@ impl PullRequestStatus {
     }
 }
 
+/// Способ слияния пул-реквеста
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MergeMethod {
+    /// Коммит слияния (или перемотка, если ветки не разошлись)
+    Merge,
+    /// Все изменения одним новым коммитом поверх целевой ветки
+    Squash,
+    /// Коммиты исходной ветки переносятся поверх целевой по одному
+    Rebase,
+}
+
+impl MergeMethod {
+    /// Все способы в порядке, в котором их стоит предлагать
+    pub const ALL: [MergeMethod; 3] = [MergeMethod::Merge, MergeMethod::Squash, MergeMethod::Rebase];
+
+    /// Разбирает значение `merge_method`
+    ///
+    /// # Возвращает
+    ///
+    /// * `Option<MergeMethod>` - Способ или None для неизвестного значения
+    pub fn parse(method: &str) -> Option<Self> {
+        match method.to_lowercase().as_str() {
+            "merge" => Some(MergeMethod::Merge),
+            "squash" => Some(MergeMethod::Squash),
+            "rebase" => Some(MergeMethod::Rebase),
+            _ => None,
+        }
+    }
+
+    /// Преобразует enum в строковое представление
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MergeMethod::Merge => "merge",
+            MergeMethod::Squash => "squash",
+            MergeMethod::Rebase => "rebase",
+        }
+    }
+}
+
+/// Столбцы, из которых собирается пул-реквест в `PullRequest::from_row` (таблица под псевдонимом `pr`)
+const PR_COLUMNS: &str = "pr.id, pr.title, pr.description, pr.repository_id, pr.source_branch, \
+    pr.target_branch, pr.author_id, pr.status, pr.created_at, pr.updated_at";
+
 /// Модель пул-реквеста
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PullRequest {
@@ -58,11 +111,23 @@ pub struct PullRequest {
     /// Статус пул-реквеста
     pub status: PullRequestStatus,
     /// Дата создания пул-реквеста
+    #[serde(serialize_with = "crate::timestamps::serialize_rfc3339")]
     pub created_at: Option<DateTime<Utc>>,
     /// Дата последнего обновления пул-реквеста
+    #[serde(serialize_with = "crate::timestamps::serialize_rfc3339")]
     pub updated_at: Option<DateTime<Utc>>,
 }
 
+/// Пул-реквест вместе с именем репозитория, для списков по нескольким репозиториям
+#[derive(Debug, Serialize, Clone)]
+pub struct PullRequestWithRepo {
+    /// Пул-реквест
+    #[serde(flatten)]
+    pub pull_request: PullRequest,
+    /// Имя репозитория
+    pub repository_name: String,
+}
+
 /// Модель комментария к пул-реквесту
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PullRequestComment {
@@ -75,7 +140,32 @@ pub struct PullRequestComment {
     /// Содержимое комментария
     pub content: String,
     /// Дата создания комментария
+    #[serde(serialize_with = "crate::timestamps::serialize_rfc3339")]
     pub created_at: Option<DateTime<Utc>>,
+    /// Комментарий удалён; содержимое очищено, запись оставлена для целостности обсуждения
+    #[serde(default)]
+    pub deleted: bool,
+}
+
+/// Причина `mergeable: false`: целевая ветка требует линейной истории, а ветки разошлись
+pub const REQUIRES_LINEAR_HISTORY: &str = "requires_linear_history";
+/// Причина `mergeable: false`: репозиторий требует подписанных коммитов, а сервер
+/// не подписывает коммиты слияния, поэтому допустима только перемотка
+pub const REQUIRES_SIGNED_COMMITS: &str = "requires_signed_commits";
+
+/// Можно ли слить пул-реквест в его текущем состоянии
+#[derive(Debug, Serialize)]
+pub struct Mergeability {
+    /// Слияние будет принято
+    pub mergeable: bool,
+    /// Целевая ветка просто передвинется вперёд, без коммита слияния
+    pub fast_forward: bool,
+    /// Машиночитаемая причина отказа
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<&'static str>,
+    /// Что сделать, чтобы слияние стало возможным
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
 }
 
 impl PullRequest {
@@ -89,24 +179,25 @@ impl PullRequest {
     /// 
     /// * `Result<i64>` - ID созданного пул-реквеста
     pub fn create(&self, conn: Arc<Mutex<Connection>>) -> Result<i64> {
-        let conn_guard = conn.lock().unwrap();
-        
-        conn_guard.execute(
-            "INSERT INTO pull_requests 
-            (title, description, repository_id, source_branch, target_branch, author_id, status) 
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            params![
-                self.title,
-                self.description,
-                self.repository_id,
-                self.source_branch,
-                self.target_branch,
-                self.author_id,
-                self.status.to_str()
-            ],
-        )?;
+        let pr_id = with_retry(&conn, |conn| {
+            conn.execute(
+                "INSERT INTO pull_requests 
+                (title, description, repository_id, source_branch, target_branch, author_id, status) 
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    self.title,
+                    self.description,
+                    self.repository_id,
+                    self.source_branch,
+                    self.target_branch,
+                    self.author_id,
+                    self.status.to_str()
+                ],
+            )?;
+            Ok(conn.last_insert_rowid())
+        })?;
         
-        let pr_id = conn_guard.last_insert_rowid();
+        let conn_guard = conn.lock().unwrap();
         
         // Получаем ID владельца репозитория для отправки уведомления
         let mut stmt = conn_guard.prepare(
@@ -115,23 +206,36 @@ impl PullRequest {
         
         let owner_id: i64 = stmt.query_row(params![self.repository_id], |row| row.get(0))?;
         
-        // Если автор PR не является владельцем репозитория, отправляем уведомление
-        if owner_id != self.author_id {
-            // Создаем уведомление для владельца репозитория
+        // Освобождаем соединение: Notification::create захватывает его заново
+        drop(stmt);
+        drop(conn_guard);
+        
+        // Уведомляем владельца репозитория и подписчиков, кроме самого автора PR
+        let recipients = match Subscription::recipients(self.repository_id, &[owner_id], self.author_id, conn.clone()) {
+            Ok(recipients) => recipients,
+            Err(e) => {
+                error!("Failed to resolve notification recipients: {}", e);
+                Vec::new()
+            }
+        };
+        for user_id in recipients {
+            let content = if user_id == owner_id {
+                format!("A new pull request has been created in your repository: {}", self.title)
+            } else {
+                format!("A new pull request has been created in a repository you watch: {}", self.title)
+            };
             let notification = Notification {
                 id: None,
                 notification_type: "pull_request".to_string(),
                 title: format!("New pull request: {}", self.title),
-                content: format!("A new pull request has been created in your repository: {}", self.title),
-                user_id: owner_id,
+                content,
+                user_id,
                 is_read: false,
                 created_at: None,
             };
             
             // Сохраняем уведомление в базе данных
-            // Create a new connection for the notification
-            let new_conn = Arc::clone(&conn);
-            match notification.create(new_conn) {
+            match notification.create(Arc::clone(&conn)) {
                 Ok(_) => debug!("Notification created for pull request"),
                 Err(e) => error!("Failed to create notification: {}", e),
             }
@@ -153,32 +257,15 @@ impl PullRequest {
     pub fn find_by_repository(repository_id: i64, conn: Arc<Mutex<Connection>>) -> Result<Vec<PullRequest>> {
         let conn_guard = conn.lock().unwrap();
         
-        let mut stmt = conn_guard.prepare(
-            "SELECT id, title, description, repository_id, source_branch, target_branch, 
-                    author_id, status, created_at, updated_at 
-             FROM pull_requests 
-             WHERE repository_id = ?1 
-             ORDER BY created_at DESC"
-        )?;
+        let mut stmt = conn_guard.prepare(&format!(
+            "SELECT {} 
+             FROM pull_requests pr 
+             WHERE pr.repository_id = ?1 
+             ORDER BY pr.created_at DESC",
+            PR_COLUMNS
+        ))?;
         
-        let pull_requests = stmt.query_map(params![repository_id], |row| {
-            let created_at_str: String = row.get(8)?;
-            let updated_at_str: String = row.get(9)?;
-            let status_str: String = row.get(7)?;
-            
-            Ok(PullRequest {
-                id: Some(row.get(0)?),
-                title: row.get(1)?,
-                description: row.get(2)?,
-                repository_id: row.get(3)?,
-                source_branch: row.get(4)?,
-                target_branch: row.get(5)?,
-                author_id: row.get(6)?,
-                status: PullRequestStatus::from_str(&status_str),
-                created_at: parse_datetime(&created_at_str),
-                updated_at: parse_datetime(&updated_at_str),
-            })
-        })?;
+        let pull_requests = stmt.query_map(params![repository_id], Self::from_row)?;
         
         let mut result = Vec::new();
         for pr in pull_requests {
@@ -201,37 +288,217 @@ impl PullRequest {
     pub fn find_by_id(id: i64, conn: Arc<Mutex<Connection>>) -> Result<Option<PullRequest>> {
         let conn_guard = conn.lock().unwrap();
         
-        let mut stmt = conn_guard.prepare(
-            "SELECT id, title, description, repository_id, source_branch, target_branch, 
-                    author_id, status, created_at, updated_at 
-             FROM pull_requests 
-             WHERE id = ?1"
-        )?;
+        let mut stmt = conn_guard.prepare(&format!(
+            "SELECT {} 
+             FROM pull_requests pr 
+             WHERE pr.id = ?1",
+            PR_COLUMNS
+        ))?;
         
         let mut rows = stmt.query(params![id])?;
         
         if let Some(row) = rows.next()? {
-            let created_at_str: String = row.get(8)?;
-            let updated_at_str: String = row.get(9)?;
-            let status_str: String = row.get(7)?;
-            
-            Ok(Some(PullRequest {
-                id: Some(row.get(0)?),
-                title: row.get(1)?,
-                description: row.get(2)?,
-                repository_id: row.get(3)?,
-                source_branch: row.get(4)?,
-                target_branch: row.get(5)?,
-                author_id: row.get(6)?,
-                status: PullRequestStatus::from_str(&status_str),
-                created_at: parse_datetime(&created_at_str),
-                updated_at: parse_datetime(&updated_at_str),
-            }))
+            Ok(Some(Self::from_row(row)?))
         } else {
             Ok(None)
         }
     }
 
+    /// Находит открытый пул-реквест между теми же ветками репозитория
+    /// 
+    /// # Параметры
+    /// 
+    /// * `repository_id` - ID репозитория
+    /// * `source_branch` - Исходная ветка
+    /// * `target_branch` - Целевая ветка
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<Option<PullRequest>>` - Самый старый такой пул-реквест или None
+    pub fn find_open_by_branches(
+        repository_id: i64,
+        source_branch: &str,
+        target_branch: &str,
+        conn: Arc<Mutex<Connection>>,
+    ) -> Result<Option<PullRequest>> {
+        let conn_guard = conn.lock().unwrap();
+        
+        let mut stmt = conn_guard.prepare(&format!(
+            "SELECT {} 
+             FROM pull_requests pr 
+             WHERE pr.repository_id = ?1 AND pr.source_branch = ?2 AND pr.target_branch = ?3 AND pr.status = ?4
+             ORDER BY pr.id
+             LIMIT 1",
+            PR_COLUMNS
+        ))?;
+        
+        let mut rows = stmt.query(params![
+            repository_id,
+            source_branch,
+            target_branch,
+            PullRequestStatus::Open.to_str()
+        ])?;
+        
+        if let Some(row) = rows.next()? {
+            Ok(Some(Self::from_row(row)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Получает пул-реквесты, созданные пользователем, во всех репозиториях
+    /// 
+    /// # Параметры
+    /// 
+    /// * `author_id` - ID автора
+    /// * `status` - Фильтр по статусу (None - все статусы)
+    /// * `limit` - Максимальное количество записей
+    /// * `offset` - Сколько записей пропустить
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<Vec<PullRequestWithRepo>>` - Пул-реквесты с именами репозиториев
+    pub fn find_by_author(
+        author_id: i64,
+        status: Option<PullRequestStatus>,
+        limit: i64,
+        offset: i64,
+        conn: Arc<Mutex<Connection>>,
+    ) -> Result<Vec<PullRequestWithRepo>> {
+        Self::find_with_repo("pr.author_id = ?1", author_id, status, limit, offset, conn)
+    }
+
+    /// Получает пул-реквесты других пользователей в репозиториях владельца,
+    /// то есть те, которые ему предстоит рассмотреть
+    /// 
+    /// # Параметры
+    /// 
+    /// * `owner_id` - ID владельца репозиториев
+    /// * `status` - Фильтр по статусу (None - все статусы)
+    /// * `limit` - Максимальное количество записей
+    /// * `offset` - Сколько записей пропустить
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<Vec<PullRequestWithRepo>>` - Пул-реквесты с именами репозиториев
+    pub fn find_for_reviewer(
+        owner_id: i64,
+        status: Option<PullRequestStatus>,
+        limit: i64,
+        offset: i64,
+        conn: Arc<Mutex<Connection>>,
+    ) -> Result<Vec<PullRequestWithRepo>> {
+        Self::find_with_repo("r.owner_id = ?1 AND pr.author_id != ?1", owner_id, status, limit, offset, conn)
+    }
+
+    /// Получает следующую порцию пул-реквестов автора по возрастанию ID
+    /// 
+    /// Пул-реквесты в чужих приватных репозиториях пропускаются: автор мог
+    /// потерять к ним доступ.
+    /// 
+    /// # Параметры
+    /// 
+    /// * `author_id` - ID автора
+    /// * `after_id` - ID последнего уже полученного пул-реквеста (0 - с начала)
+    /// * `limit` - Размер порции
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<Vec<PullRequestWithRepo>>` - Пул-реквесты с ID больше `after_id`
+    pub fn find_by_author_after(
+        author_id: i64,
+        after_id: i64,
+        limit: i64,
+        conn: Arc<Mutex<Connection>>,
+    ) -> Result<Vec<PullRequestWithRepo>> {
+        let conn_guard = conn.lock().unwrap();
+
+        let mut stmt = conn_guard.prepare(&format!(
+            "SELECT {}, r.name 
+             FROM pull_requests pr 
+             JOIN repositories r ON r.id = pr.repository_id 
+             WHERE pr.author_id = ?1 AND pr.id > ?2 AND (r.is_public = 1 OR r.owner_id = ?1) 
+             ORDER BY pr.id 
+             LIMIT ?3",
+            PR_COLUMNS
+        ))?;
+
+        let pull_requests = stmt.query_map(params![author_id, after_id, limit], |row| {
+            Ok(PullRequestWithRepo {
+                pull_request: Self::from_row(row)?,
+                repository_name: row.get(10)?,
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for pr in pull_requests {
+            result.push(pr?);
+        }
+
+        Ok(result)
+    }
+
+    /// Общая выборка пул-реквестов с именем репозитория по условию на `?1`
+    fn find_with_repo(
+        condition: &str,
+        user_id: i64,
+        status: Option<PullRequestStatus>,
+        limit: i64,
+        offset: i64,
+        conn: Arc<Mutex<Connection>>,
+    ) -> Result<Vec<PullRequestWithRepo>> {
+        let conn_guard = conn.lock().unwrap();
+
+        let mut stmt = conn_guard.prepare(&format!(
+            "SELECT {}, r.name 
+             FROM pull_requests pr 
+             JOIN repositories r ON r.id = pr.repository_id 
+             WHERE {} AND (?2 IS NULL OR pr.status = ?2) 
+             ORDER BY pr.created_at DESC, pr.id DESC 
+             LIMIT ?3 OFFSET ?4",
+            PR_COLUMNS, condition
+        ))?;
+
+        let status = status.map(|s| s.to_str());
+        let pull_requests = stmt.query_map(params![user_id, status, limit, offset], |row| {
+            Ok(PullRequestWithRepo {
+                pull_request: Self::from_row(row)?,
+                repository_name: row.get(10)?,
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for pr in pull_requests {
+            result.push(pr?);
+        }
+
+        Ok(result)
+    }
+
+    /// Собирает пул-реквест из строки выборки, начинающейся со столбцов `PR_COLUMNS`
+    fn from_row(row: &rusqlite::Row) -> Result<PullRequest> {
+        let created_at_str: String = row.get(8)?;
+        let updated_at_str: String = row.get(9)?;
+        let status_str: String = row.get(7)?;
+        
+        Ok(PullRequest {
+            id: Some(row.get(0)?),
+            title: row.get(1)?,
+            description: row.get(2)?,
+            repository_id: row.get(3)?,
+            source_branch: row.get(4)?,
+            target_branch: row.get(5)?,
+            author_id: row.get(6)?,
+            status: PullRequestStatus::from_str(&status_str),
+            created_at: parse_datetime(&created_at_str),
+            updated_at: parse_datetime(&updated_at_str),
+        })
+    }
+
     /// Обновляет статус пул-реквеста
     /// 
     /// # Параметры
@@ -244,108 +511,388 @@ impl PullRequest {
     /// 
     /// * `Result<()>` - Результат операции
     pub fn update_status(id: i64, status: PullRequestStatus, conn: Arc<Mutex<Connection>>) -> Result<()> {
-        let conn_guard = conn.lock().unwrap();
-        
-        conn_guard.execute(
-            "UPDATE pull_requests SET status = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
-            params![status.to_str(), id],
-        )?;
+        with_retry(&conn, |conn| {
+            conn.execute(
+                "UPDATE pull_requests SET status = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
+                params![status.to_str(), id],
+            )
+        })?;
         
         Ok(())
     }
 
-    /// Сливает пул-реквест (выполняет git merge)
+    /// Реагирует на push в ветку: обновляет открытые пул-реквесты из этой ветки
+    /// и уведомляет их авторов и владельца репозитория
+    /// 
+    /// Вызывается по состоянию ссылок после завершения receive-pack. Уведомления
+    /// создаются с ключом (пул-реквест, новый SHA, получатель), поэтому повторная
+    /// обработка того же push не создаёт дубликатов.
+    /// 
+    /// # Параметры
+    /// 
+    /// * `repository_id` - ID репозитория
+    /// * `branch` - Обновлённая ветка
+    /// * `new_sha` - SHA ветки после push
+    /// * `pusher_id` - ID пользователя, выполнившего push (ему уведомление не отправляется)
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<usize>` - Число затронутых пул-реквестов
+    pub fn on_source_branch_pushed(
+        repository_id: i64,
+        branch: &str,
+        new_sha: &str,
+        pusher_id: i64,
+        conn: Arc<Mutex<Connection>>
+    ) -> Result<usize> {
+        let (owner_id, prs) = {
+            let conn_guard = conn.lock().unwrap();
+            
+            let owner_id: i64 = conn_guard.query_row(
+                "SELECT owner_id FROM repositories WHERE id = ?1",
+                params![repository_id],
+                |row| row.get(0),
+            )?;
+            
+            let mut stmt = conn_guard.prepare(&format!(
+                "SELECT {} FROM pull_requests pr 
+                 WHERE pr.repository_id = ?1 AND pr.source_branch = ?2 AND pr.status = ?3",
+                PR_COLUMNS
+            ))?;
+            let prs = stmt
+                .query_map(params![repository_id, branch, PullRequestStatus::Open.to_str()], Self::from_row)?
+                .collect::<Result<Vec<_>>>()?;
+            
+            (owner_id, prs)
+        };
+        
+        for pr in &prs {
+            with_retry(&conn, |conn| {
+                conn.execute(
+                    "UPDATE pull_requests SET updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
+                    params![pr.id],
+                )
+            })?;
+        }
+        
+        for pr in &prs {
+            let pr_id = pr.id.unwrap_or_default();
+            let recipients = match Subscription::recipients(repository_id, &[pr.author_id, owner_id], pusher_id, conn.clone()) {
+                Ok(recipients) => recipients,
+                Err(e) => {
+                    error!("Failed to resolve notification recipients: {}", e);
+                    continue;
+                }
+            };
+            
+            for user_id in recipients {
+                let notification = Notification {
+                    id: None,
+                    notification_type: "pull_request_push".to_string(),
+                    title: "New commits in pull request".to_string(),
+                    content: format!("Branch '{}' of pull request '{}' now points to {}", branch, pr.title, new_sha),
+                    user_id,
+                    is_read: false,
+                    created_at: None,
+                };
+                
+                let key = format!("pr:{}:push:{}:user:{}", pr_id, new_sha, user_id);
+                if let Err(e) = notification.create_once(&key, conn.clone()) {
+                    error!("Failed to create notification: {}", e);
+                }
+            }
+        }
+        
+        Ok(prs.len())
+    }
+
+    /// Обновляет заголовок и/или описание пул-реквеста
+    /// 
+    /// Ветки не меняются: это сделало бы недействительным уже проведённое ревью.
     /// 
     /// # Параметры
     /// 
     /// * `id` - ID пул-реквеста
+    /// * `title` - Новый заголовок (None - оставить как есть)
+    /// * `description` - Новое описание (None - оставить как есть)
     /// * `conn` - Соединение с базой данных
     /// 
     /// # Возвращает
     /// 
     /// * `Result<()>` - Результат операции
-    pub fn merge(id: i64, conn: Arc<Mutex<Connection>>) -> Result<()> {
+    pub fn update_details(
+        id: i64,
+        title: Option<&str>,
+        description: Option<&str>,
+        conn: Arc<Mutex<Connection>>
+    ) -> Result<()> {
+        with_retry(&conn, |conn| {
+            conn.execute(
+                "UPDATE pull_requests 
+                 SET title = COALESCE(?1, title), 
+                     description = COALESCE(?2, description), 
+                     updated_at = CURRENT_TIMESTAMP 
+                 WHERE id = ?3",
+                params![title, description, id],
+            )
+        })?;
+        
+        Ok(())
+    }
+
+    /// Сливает пул-реквест выбранным способом
+    /// 
+    /// SHA целевой ветки запоминается в начале слияния, и результат отправляется
+    /// с `--force-with-lease` на это значение: если ветку успели сдвинуть,
+    /// push отклоняется, и возвращается `AppError::Conflict`. Разрешён ли способ
+    /// в репозитории, проверяет вызывающий код. Функция блокирует поток на время
+    /// работы git, поэтому обработчики вызывают её через `run_blocking`.
+    /// 
+    /// # Параметры
+    /// 
+    /// * `id` - ID пул-реквеста
+    /// * `method` - Способ слияния
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<(), AppError>` - Результат операции; `Conflict`, если слияние
+    ///   не удалось из-за конфликта или целевая ветка изменилась, `Busy`, если
+    ///   не удалось дождаться блокировки репозитория
+    pub fn merge(id: i64, method: MergeMethod, conn: Arc<Mutex<Connection>>) -> std::result::Result<(), AppError> {
         // Получаем информацию о пул-реквесте
         let pr = match Self::find_by_id(id, conn.clone())? {
             Some(pr) => pr,
-            None => return Err(rusqlite::Error::QueryReturnedNoRows),
+            None => return Err(AppError::Database(format!("Pull request {} not found", id))),
         };
         
-        // Получаем имя репозитория
-        // Get repository name
+        // Получаем полное имя репозитория `{owner}/{name}`
         let repo_name = {
             let conn_guard = conn.lock().unwrap();
             let mut stmt = conn_guard.prepare(
-                "SELECT name FROM repositories WHERE id = ?1"
+                "SELECT u.username, r.name FROM repositories r JOIN users u ON u.id = r.owner_id WHERE r.id = ?1"
             )?;
             
-            stmt.query_row(params![pr.repository_id], |row| row.get::<_, String>(0))?
+            stmt.query_row(params![pr.repository_id], |row| {
+                Ok(full_repo_name(&row.get::<_, String>(0)?, &row.get::<_, String>(1)?))
+            })?
+        };
+        
+        // Squash-коммит приписывается автору пул-реквеста
+        let author = match User::find_by_id(pr.author_id, conn.clone())? {
+            Some(user) => {
+                let email = user.email.unwrap_or_else(|| format!("{}@users.noreply", user.username));
+                format!("{} <{}>", user.username, email)
+            }
+            None => "Git HTTP Server <git-server@localhost>".to_string(),
         };
         
         // Путь к репозиторию
-        let repo_path = format!("repositories/{}.git", repo_name);
+        let repo_path = resolve_repo(Path::new(REPOS_ROOT), &repo_name)?;
         
-        // Выполняем слияние веток с помощью git
-        // Это упрощенная реализация, в реальном проекте нужно больше проверок и обработки ошибок
-        use std::process::Command;
+        // Не даём push'ам и другим слияниям менять репозиторий во время слияния
+        let _lock = match lock_repo(&repo_name) {
+            Some(lock) => lock,
+            None => return Err(AppError::Busy(format!("Timed out waiting for lock on repo: {}", repo_name))),
+        };
         
-        // Клонируем репозиторий во временную директорию
-        let temp_dir = format!("temp_merge_{}", id);
-        let clone_status = Command::new("git")
-            .args(&["clone", &repo_path, &temp_dir])
-            .status();
+        // Запоминаем, на что указывает целевая ветка в момент начала слияния
+        let target_ref = format!("refs/heads/{}", pr.target_branch);
+        let source_ref = format!("refs/heads/{}", pr.source_branch);
+        let expected_target = resolve_commit(&repo_path, &target_ref)?;
+        let source = resolve_commit(&repo_path, &source_ref)?;
         
-        if let Err(e) = clone_status {
-            error!("Failed to clone repository: {}", e);
-            return Err(rusqlite::Error::ExecuteReturnedResults);
+        // При требовании линейной истории или подписей допустима только перемотка
+        let refusal = pr.merge_refusal(method, &receive_protection(&repo_path)?);
+        if let Some((_, message)) = &refusal {
+            // Squash создаёт новый коммит всегда, остальные способы - только если ветки разошлись
+            let kind = merge_kind(&repo_path, &expected_target, &source)?;
+            let creates_commit = match method {
+                MergeMethod::Squash => kind != MergeKind::UpToDate,
+                MergeMethod::Merge | MergeMethod::Rebase => kind == MergeKind::MergeCommit,
+            };
+            if creates_commit {
+                return Err(AppError::Conflict(message.clone()));
+            }
         }
         
-        // Переключаемся на целевую ветку
-        let checkout_status = Command::new("git")
-            .args(&["-C", &temp_dir, "checkout", &pr.target_branch])
-            .status();
+        // Выполняем слияние веток с помощью git во временной директории;
+        // она удаляется, когда `temp_dir` выходит из области видимости
+        let temp_dir = TempDir::new(&format!("temp_merge_{}", id))
+            .map_err(|e| AppError::Git(format!("Failed to create temporary directory: {}", e)))?;
+        let ff_only = refusal.map(|(_, message)| message);
+        let result = Self::merge_in(&repo_path, temp_dir.path(), &pr, &expected_target, method, &author, ff_only.as_deref());
         
-        if let Err(e) = checkout_status {
-            error!("Failed to checkout target branch: {}", e);
-            // Удаляем временную директорию
-            let _ = std::fs::remove_dir_all(&temp_dir);
-            return Err(rusqlite::Error::ExecuteReturnedResults);
+        if let Err(e) = result {
+            // Если push отклонён из-за того, что ветку сдвинули, просим повторить
+            let moved = resolve_commit(&repo_path, &target_ref).ok().as_deref() != Some(expected_target.as_str());
+            return if moved {
+                Err(AppError::Conflict(format!(
+                    "Target branch '{}' was updated during the merge, please retry",
+                    pr.target_branch
+                )))
+            } else {
+                Err(e)
+            };
         }
         
-        // Выполняем слияние
-        let merge_status = Command::new("git")
-            .args(&["-C", &temp_dir, "merge", &pr.source_branch])
-            .status();
+        // Обновляем статус пул-реквеста
+        let conn_clone = Arc::clone(&conn);
+        Self::update_status(id, PullRequestStatus::Merged, conn_clone)?;
         
-        if let Err(e) = merge_status {
-            error!("Failed to merge branches: {}", e);
-            // Удаляем временную директорию
-            let _ = std::fs::remove_dir_all(&temp_dir);
-            return Err(rusqlite::Error::ExecuteReturnedResults);
+        Ok(())
+    }
+
+    /// Проверяет, можно ли слить пул-реквест
+    /// 
+    /// Конфликты не проверяются; отказ возможен, только если защита репозитория
+    /// требует линейной истории или подписанных коммитов, а слияние создало бы
+    /// коммит слияния.
+    /// 
+    /// # Параметры
+    /// 
+    /// * `repo_path` - Путь к bare репозиторию пул-реквеста
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<Mergeability, AppError>` - Результат проверки; `RefNotFound`, если
+    ///   одной из веток нет
+    pub fn mergeability(&self, repo_path: &Path) -> std::result::Result<Mergeability, AppError> {
+        let target = resolve_commit(repo_path, &format!("refs/heads/{}", self.target_branch))?;
+        let source = resolve_commit(repo_path, &format!("refs/heads/{}", self.source_branch))?;
+        let kind = merge_kind(repo_path, &target, &source)?;
+        let fast_forward = kind != MergeKind::MergeCommit;
+
+        if !fast_forward {
+            if let Some((reason, message)) = self.merge_commit_refusal(&receive_protection(repo_path)?) {
+                return Ok(Mergeability { mergeable: false, fast_forward, reason: Some(reason), message: Some(message) });
+            }
         }
+
+        Ok(Mergeability { mergeable: true, fast_forward, reason: None, message: None })
+    }
+
+    /// Причина и подсказка, если защита репозитория не допускает коммиты, которые
+    /// создал бы `method`
+    ///
+    /// Squash и rebase оставляют историю линейной, но создают новые коммиты без подписи.
+    fn merge_refusal(&self, method: MergeMethod, protection: &ReceiveProtection) -> Option<(&'static str, String)> {
+        match method {
+            MergeMethod::Merge => self.merge_commit_refusal(protection),
+            MergeMethod::Squash if protection.require_signed_commits => Some((REQUIRES_SIGNED_COMMITS, format!(
+                "Branch '{}' requires signed commits, which the server cannot create; squash '{}' locally and push it again",
+                self.target_branch, self.source_branch
+            ))),
+            MergeMethod::Rebase if protection.require_signed_commits => Some((REQUIRES_SIGNED_COMMITS, format!(
+                "Branch '{}' requires signed commits; rebase '{}' onto '{}' and push it again",
+                self.target_branch, self.source_branch, self.target_branch
+            ))),
+            MergeMethod::Squash | MergeMethod::Rebase => None,
+        }
+    }
+
+    /// Причина и подсказка, если защита репозитория не допускает коммит слияния
+    fn merge_commit_refusal(&self, protection: &ReceiveProtection) -> Option<(&'static str, String)> {
+        let (reason, requirement) = if protection.require_linear_history {
+            (REQUIRES_LINEAR_HISTORY, "requires linear history")
+        } else if protection.require_signed_commits {
+            (REQUIRES_SIGNED_COMMITS, "requires signed commits")
+        } else {
+            return None;
+        };
+        Some((reason, format!(
+            "Branch '{}' {}; rebase '{}' onto '{}' and push it again",
+            self.target_branch, requirement, self.source_branch, self.target_branch
+        )))
+    }
+
+    /// Клонирует репозиторий в `temp_dir`, сливает исходную ветку в `expected_target`
+    /// способом `method` и отправляет результат, только если целевая ветка всё ещё
+    /// указывает на него
+    /// 
+    /// С `ff_only` (текстом отказа) допустима только перемотка, каким бы ни был
+    /// `method`: исходную ветку могли передвинуть после проверки в `merge`.
+    #[allow(clippy::too_many_arguments)]
+    fn merge_in(
+        repo_path: &Path,
+        temp_dir: &Path,
+        pr: &PullRequest,
+        expected_target: &str,
+        method: MergeMethod,
+        author: &str,
+        ff_only: Option<&str>,
+    ) -> std::result::Result<(), AppError> {
+        let git = |args: &[&str]| -> std::result::Result<(), String> {
+            match git_command().args(args).output() {
+                Ok(output) if output.status.success() => Ok(()),
+                Ok(output) => Err(String::from_utf8_lossy(&output.stderr).to_string()),
+                Err(e) => Err(e.to_string()),
+            }
+        };
         
-        // Отправляем изменения обратно в репозиторий
-        let push_status = Command::new("git")
-            .args(&["-C", &temp_dir, "push", "origin", &pr.target_branch])
-            .status();
+        let repo_path = repo_path.to_string_lossy();
+        let temp_dir = temp_dir.to_string_lossy();
+        let temp_dir = temp_dir.as_ref();
+        git(&["clone", "--quiet", &repo_path, temp_dir]).map_err(AppError::Git)?;
         
-        if let Err(e) = push_status {
-            error!("Failed to push changes: {}", e);
-            // Удаляем временную директорию
-            let _ = std::fs::remove_dir_all(&temp_dir);
-            return Err(rusqlite::Error::ExecuteReturnedResults);
-        }
+        // Сливаем именно запомненный коммит целевой ветки, а не то, что склонировалось
+        git(&["-C", temp_dir, "checkout", "--quiet", "--detach", expected_target]).map_err(AppError::Git)?;
         
-        // Удаляем временную директорию
-        if let Err(e) = std::fs::remove_dir_all(&temp_dir) {
-            error!("Failed to remove temporary directory: {}", e);
+        let origin_source = format!("origin/{}", pr.source_branch);
+        let identity = ["-C", temp_dir, "-c", "user.name=Git HTTP Server", "-c", "user.email=git-server@localhost"];
+        let merged = match (method, ff_only) {
+            (MergeMethod::Squash, None) => {
+                let message = match pr.description.as_deref().map(str::trim) {
+                    Some(description) if !description.is_empty() => {
+                        format!("{} (#{})\n\n{}", pr.title, pr.id.unwrap_or_default(), description)
+                    }
+                    _ => format!("{} (#{})", pr.title, pr.id.unwrap_or_default()),
+                };
+                git(&[&identity[..], &["merge", "--squash", &origin_source]].concat()).and_then(|_| {
+                    // Нечего сливать: коммит не нужен, целевая ветка остаётся как есть
+                    if git(&["-C", temp_dir, "diff", "--cached", "--quiet"]).is_ok() {
+                        return Ok(());
+                    }
+                    git(&[&identity[..], &["commit", "--quiet", "--author", author, "-m", &message]].concat())
+                })
+            }
+            (MergeMethod::Rebase, None) => {
+                // Переносит коммиты исходной ветки поверх запомненной целевой (HEAD
+                // отсоединён); авторы коммитов сохраняются, коммиттер - сервер
+                git(&[&identity[..], &["rebase", "--quiet", expected_target, &origin_source]].concat())
+            }
+            _ => {
+                let message = format!(
+                    "Merge pull request #{} from {} into {}",
+                    pr.id.unwrap_or_default(), pr.source_branch, pr.target_branch
+                );
+                let mut merge_args = [&identity[..], &["merge", "--no-edit", "-m", &message]].concat();
+                if ff_only.is_some() {
+                    merge_args.push("--ff-only");
+                }
+                merge_args.push(&origin_source);
+                git(&merge_args)
+            }
+        };
+        if let Err(stderr) = merged {
+            error!("Merge failed: {}", stderr);
+            if let Some(message) = ff_only {
+                return Err(AppError::Conflict(message.to_string()));
+            }
+            return Err(AppError::Conflict(format!(
+                "Branch '{}' cannot be merged into '{}' automatically",
+                pr.source_branch, pr.target_branch
+            )));
         }
         
-        // Обновляем статус пул-реквеста
-        let conn_clone = Arc::clone(&conn);
-        Self::update_status(id, PullRequestStatus::Merged, conn_clone)?;
-        
-        Ok(())
+        // --force-with-lease отклонит push, если целевая ветка изменилась с начала слияния
+        let lease = format!("--force-with-lease=refs/heads/{}:{}", pr.target_branch, expected_target);
+        let refspec = format!("HEAD:refs/heads/{}", pr.target_branch);
+        git(&["-C", temp_dir, "push", "--quiet", &lease, "origin", &refspec]).map_err(|stderr| {
+            error!("Failed to push merge result: {}", stderr);
+            AppError::Git(stderr)
+        })
     }
 }
 
@@ -360,40 +907,56 @@ impl PullRequestComment {
     /// 
     /// * `Result<i64>` - ID созданного комментария
     pub fn create(&self, conn: Arc<Mutex<Connection>>) -> Result<i64> {
-        let conn_guard = conn.lock().unwrap();
-        
-        conn_guard.execute(
-            "INSERT INTO pull_request_comments 
-            (pull_request_id, author_id, content) 
-            VALUES (?1, ?2, ?3)",
-            params![self.pull_request_id, self.author_id, self.content],
-        )?;
+        let comment_id = with_retry(&conn, |conn| {
+            conn.execute(
+                "INSERT INTO pull_request_comments 
+                (pull_request_id, author_id, content) 
+                VALUES (?1, ?2, ?3)",
+                params![self.pull_request_id, self.author_id, self.content],
+            )?;
+            Ok(conn.last_insert_rowid())
+        })?;
         
-        let comment_id = conn_guard.last_insert_rowid();
+        let conn_guard = conn.lock().unwrap();
         
         // Получаем информацию о пул-реквесте для отправки уведомления
         let mut stmt = conn_guard.prepare(
-            "SELECT author_id FROM pull_requests WHERE id = ?1"
+            "SELECT author_id, repository_id FROM pull_requests WHERE id = ?1"
         )?;
         
-        let pr_author_id: i64 = stmt.query_row(params![self.pull_request_id], |row| row.get(0))?;
+        let (pr_author_id, repository_id): (i64, i64) =
+            stmt.query_row(params![self.pull_request_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
         
-        // Если автор комментария не является автором PR, отправляем уведомление
-        if pr_author_id != self.author_id {
-            // Создаем уведомление для автора PR
+        // Освобождаем соединение: Notification::create захватывает его заново
+        drop(stmt);
+        drop(conn_guard);
+        
+        // Уведомляем автора PR и подписчиков репозитория, кроме автора комментария
+        let recipients = match Subscription::recipients(repository_id, &[pr_author_id], self.author_id, conn.clone()) {
+            Ok(recipients) => recipients,
+            Err(e) => {
+                error!("Failed to resolve notification recipients: {}", e);
+                Vec::new()
+            }
+        };
+        for user_id in recipients {
+            let (title, content) = if user_id == pr_author_id {
+                ("New comment on your pull request", format!("Someone commented on your pull request: {}", self.content))
+            } else {
+                ("New comment on a pull request", format!("Someone commented on a pull request you watch: {}", self.content))
+            };
             let notification = Notification {
                 id: None,
                 notification_type: "comment".to_string(),
-                title: "New comment on your pull request".to_string(),
-                content: format!("Someone commented on your pull request: {}", self.content),
-                user_id: pr_author_id,
+                title: title.to_string(),
+                content,
+                user_id,
                 is_read: false,
                 created_at: None,
             };
             
             // Сохраняем уведомление в базе данных
-            let new_conn = Arc::clone(&conn);
-            match notification.create(new_conn) {
+            match notification.create(Arc::clone(&conn)) {
                 Ok(_) => debug!("Notification created for comment"),
                 Err(e) => error!("Failed to create notification: {}", e),
             }
@@ -416,23 +979,56 @@ impl PullRequestComment {
         let conn_guard = conn.lock().unwrap();
         
         let mut stmt = conn_guard.prepare(
-            "SELECT id, pull_request_id, author_id, content, created_at 
+            "SELECT id, pull_request_id, author_id, content, created_at, deleted 
              FROM pull_request_comments 
              WHERE pull_request_id = ?1 
              ORDER BY created_at ASC"
         )?;
         
-        let comments = stmt.query_map(params![pull_request_id], |row| {
-            let created_at_str: String = row.get(4)?;
-            
-            Ok(PullRequestComment {
-                id: Some(row.get(0)?),
-                pull_request_id: row.get(1)?,
-                author_id: row.get(2)?,
-                content: row.get(3)?,
-                created_at: parse_datetime(&created_at_str),
-            })
-        })?;
+        let comments = stmt.query_map(params![pull_request_id], Self::from_row)?;
+        
+        let mut result = Vec::new();
+        for comment in comments {
+            result.push(comment?);
+        }
+        
+        Ok(result)
+    }
+
+    /// Получает следующую порцию комментариев автора по возрастанию ID
+    /// 
+    /// Как и в `PullRequest::find_by_author_after`, комментарии в чужих приватных
+    /// репозиториях пропускаются.
+    /// 
+    /// # Параметры
+    /// 
+    /// * `author_id` - ID автора
+    /// * `after_id` - ID последнего уже полученного комментария (0 - с начала)
+    /// * `limit` - Размер порции
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<Vec<PullRequestComment>>` - Комментарии с ID больше `after_id`
+    pub fn find_by_author_after(
+        author_id: i64,
+        after_id: i64,
+        limit: i64,
+        conn: Arc<Mutex<Connection>>,
+    ) -> Result<Vec<PullRequestComment>> {
+        let conn_guard = conn.lock().unwrap();
+        
+        let mut stmt = conn_guard.prepare(
+            "SELECT c.id, c.pull_request_id, c.author_id, c.content, c.created_at, c.deleted 
+             FROM pull_request_comments c 
+             JOIN pull_requests pr ON pr.id = c.pull_request_id 
+             JOIN repositories r ON r.id = pr.repository_id 
+             WHERE c.author_id = ?1 AND c.id > ?2 AND (r.is_public = 1 OR r.owner_id = ?1) 
+             ORDER BY c.id 
+             LIMIT ?3"
+        )?;
+        
+        let comments = stmt.query_map(params![author_id, after_id, limit], Self::from_row)?;
         
         let mut result = Vec::new();
         for comment in comments {
@@ -441,22 +1037,209 @@ impl PullRequestComment {
         
         Ok(result)
     }
+
+    /// Находит комментарий по ID
+    /// 
+    /// # Параметры
+    /// 
+    /// * `id` - ID комментария
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<Option<PullRequestComment>>` - Найденный комментарий или None
+    pub fn find_by_id(id: i64, conn: Arc<Mutex<Connection>>) -> Result<Option<PullRequestComment>> {
+        let conn_guard = conn.lock().unwrap();
+        
+        let mut stmt = conn_guard.prepare(
+            "SELECT id, pull_request_id, author_id, content, created_at, deleted 
+             FROM pull_request_comments 
+             WHERE id = ?1"
+        )?;
+        let mut rows = stmt.query(params![id])?;
+        
+        if let Some(row) = rows.next()? {
+            Ok(Some(Self::from_row(row)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Удаляет комментарий
+    /// 
+    /// При включённом `GIT_HTTP_SOFT_DELETE_COMMENTS` запись остаётся в обсуждении
+    /// с пустым содержимым и флагом `deleted`, иначе удаляется полностью.
+    /// 
+    /// # Параметры
+    /// 
+    /// * `id` - ID комментария
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<bool>` - true, если комментарий был найден и удалён
+    pub fn delete(id: i64, conn: Arc<Mutex<Connection>>) -> Result<bool> {
+        let affected = with_retry(&conn, |conn| {
+            if CONFIG.soft_delete_comments {
+                conn.execute(
+                    "UPDATE pull_request_comments SET content = '', deleted = 1 WHERE id = ?1 AND deleted = 0",
+                    params![id],
+                )
+            } else {
+                conn.execute(
+                    "DELETE FROM pull_request_comments WHERE id = ?1",
+                    params![id],
+                )
+            }
+        })?;
+        
+        Ok(affected > 0)
+    }
+
+    /// Собирает комментарий из строки выборки
+    fn from_row(row: &rusqlite::Row) -> Result<PullRequestComment> {
+        let created_at_str: String = row.get(4)?;
+        
+        Ok(PullRequestComment {
+            id: Some(row.get(0)?),
+            pull_request_id: row.get(1)?,
+            author_id: row.get(2)?,
+            content: row.get(3)?,
+            created_at: parse_datetime(&created_at_str),
+            deleted: row.get(5)?,
+        })
+    }
 }
 
-/// Вспомогательная функция для парсинга даты/времени из строки
-fn parse_datetime(datetime_str: &str) -> Option<DateTime<Utc>> {
-    // Пробуем разные форматы даты
-    if let Ok(dt) = DateTime::parse_from_rfc3339(datetime_str) {
-        return Some(dt.with_timezone(&Utc));
-    }
-    
-    // Если формат не RFC3339, возможно это формат SQLite (YYYY-MM-DD HH:MM:SS)
-    let naive = NaiveDateTime::parse_from_str(datetime_str, "%Y-%m-%d %H:%M:%S")
-        .or_else(|_| NaiveDateTime::parse_from_str(datetime_str, "%Y-%m-%dT%H:%M:%S"));
-    
-    if let Ok(ndt) = naive {
-        return Some(DateTime::<Utc>::from_naive_utc_and_offset(ndt, Utc));
-    }
-    
-    None
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use crate::git::refs::set_receive_protection;
+    use crate::models::db::Database;
+    use crate::models::repository::Repository;
+    use crate::test_support::{commit_files, create_repo, create_user, repo_git, test_db};
+
+    /// Создаёт коммит с файлами поверх `parent` и ставит на него ветку `branch`
+    fn commit_on(repo: &Repository, branch: &str, parent: &str, files: &[(&str, &[u8])]) -> String {
+        let main = repo_git(repo, &["rev-parse", "refs/heads/main"]);
+        repo_git(repo, &["update-ref", "refs/heads/main", parent]);
+        let sha = commit_files(repo, files);
+        repo_git(repo, &["update-ref", "refs/heads/main", &main]);
+        repo_git(repo, &["update-ref", &format!("refs/heads/{}", branch), &sha]);
+        sha
+    }
+
+    /// Репозиторий, где `main` и `feature` разошлись после общего коммита, и
+    /// открытый пул-реквест `feature` -> `main`
+    ///
+    /// # Возвращает
+    ///
+    /// * `(Repository, i64, String)` - Репозиторий, ID пул-реквеста и SHA `main`
+    fn diverged(db: &Database, prefix: &str) -> (Repository, i64, String) {
+        let owner = create_user(db, prefix);
+        let repo = create_repo(db, &owner, "merge", true);
+        let base = commit_files(&repo, &[("README", b"base")]);
+        let first = commit_on(&repo, "feature", &base, &[("README", b"base"), ("a.txt", b"a")]);
+        commit_on(&repo, "feature", &first, &[("README", b"base"), ("a.txt", b"a"), ("b.txt", b"b")]);
+        let main = commit_on(&repo, "main", &base, &[("README", b"base"), ("main.txt", b"main")]);
+        let pr_id = open(db, &repo, &owner);
+        (repo, pr_id, main)
+    }
+
+    fn open(db: &Database, repo: &Repository, author: &User) -> i64 {
+        PullRequest {
+            id: None,
+            title: "Add letters".to_string(),
+            description: None,
+            repository_id: repo.id.unwrap(),
+            source_branch: "feature".to_string(),
+            target_branch: "main".to_string(),
+            author_id: author.id.unwrap(),
+            status: PullRequestStatus::Open,
+            created_at: None,
+            updated_at: None,
+        }.create(db.get_connection()).unwrap()
+    }
+
+    fn status(db: &Database, pr_id: i64) -> PullRequestStatus {
+        PullRequest::find_by_id(pr_id, db.get_connection()).unwrap().unwrap().status
+    }
+
+    fn files(repo: &Repository, rev: &str) -> String {
+        repo_git(repo, &["ls-tree", "--name-only", rev]).replace('\n', " ")
+    }
+
+    #[test]
+    fn squash_adds_one_commit_on_top_of_the_target() {
+        let db = test_db();
+        let (repo, pr_id, main) = diverged(&db, "squash");
+
+        PullRequest::merge(pr_id, MergeMethod::Squash, db.get_connection()).unwrap();
+
+        assert_eq!(repo_git(&repo, &["rev-parse", "main^"]), main);
+        assert_eq!(repo_git(&repo, &["rev-list", "--count", "--merges", "main"]), "0");
+        assert_eq!(files(&repo, "main"), "README a.txt b.txt main.txt");
+        assert_eq!(repo_git(&repo, &["log", "-1", "--format=%s", "main"]), format!("Add letters (#{})", pr_id));
+        assert_eq!(status(&db, pr_id), PullRequestStatus::Merged);
+    }
+
+    #[test]
+    fn rebase_replays_each_source_commit_onto_the_target() {
+        let db = test_db();
+        let (repo, pr_id, main) = diverged(&db, "rebase");
+
+        PullRequest::merge(pr_id, MergeMethod::Rebase, db.get_connection()).unwrap();
+
+        assert_eq!(repo_git(&repo, &["rev-parse", "main~2"]), main);
+        assert_eq!(repo_git(&repo, &["rev-list", "--count", "--merges", "main"]), "0");
+        assert_eq!(files(&repo, "main~1"), "README a.txt main.txt");
+        assert_eq!(files(&repo, "main"), "README a.txt b.txt main.txt");
+        // Авторы коммитов сохраняются, коммиттер - сервер
+        assert_eq!(repo_git(&repo, &["log", "-1", "--format=%an|%cn", "main"]), "Test|Git HTTP Server");
+        assert_eq!(status(&db, pr_id), PullRequestStatus::Merged);
+    }
+
+    #[test]
+    fn linear_history_allows_only_a_fast_forward_merge() {
+        let db = test_db();
+        let (repo, pr_id, main) = diverged(&db, "linear");
+        let repo_path = resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()).unwrap();
+        set_receive_protection(&repo_path, ReceiveProtection { require_linear_history: true, ..Default::default() }).unwrap();
+
+        match PullRequest::merge(pr_id, MergeMethod::Merge, db.get_connection()) {
+            Err(AppError::Conflict(message)) => assert!(message.contains("requires linear history"), "{}", message),
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+        assert_eq!(repo_git(&repo, &["rev-parse", "main"]), main);
+        assert_eq!(status(&db, pr_id), PullRequestStatus::Open);
+
+        // После rebase исходной ветки на main слияние - перемотка, и оно разрешено
+        let rebased = commit_on(&repo, "feature", &main, &[("README", b"base"), ("main.txt", b"main"), ("a.txt", b"a")]);
+        PullRequest::merge(pr_id, MergeMethod::Merge, db.get_connection()).unwrap();
+        assert_eq!(repo_git(&repo, &["rev-parse", "main"]), rebased);
+        assert_eq!(status(&db, pr_id), PullRequestStatus::Merged);
+    }
+
+    #[test]
+    fn merge_is_a_conflict_when_the_target_moves_before_the_push() {
+        let db = test_db();
+        let (repo, pr_id, main) = diverged(&db, "moved");
+        let moved = commit_on(&repo, "moved", &main, &[("README", b"moved")]);
+
+        // Хук update срабатывает на push результата слияния и успевает сдвинуть main раньше него
+        let repo_path = resolve_repo(Path::new(REPOS_ROOT), &repo.full_name()).unwrap();
+        let hook = repo_path.join("hooks").join("update");
+        fs::create_dir_all(hook.parent().unwrap()).unwrap();
+        fs::write(&hook, format!("#!/bin/sh\ngit update-ref refs/heads/main {}\n", moved)).unwrap();
+        fs::set_permissions(&hook, fs::Permissions::from_mode(0o755)).unwrap();
+
+        match PullRequest::merge(pr_id, MergeMethod::Merge, db.get_connection()) {
+            Err(AppError::Conflict(message)) => assert!(message.contains("was updated during the merge"), "{}", message),
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+        assert_eq!(repo_git(&repo, &["rev-parse", "main"]), moved);
+        assert_eq!(status(&db, pr_id), PullRequestStatus::Open);
+    }
 }
@@ -0,0 +1,170 @@
+use rusqlite::{params, Result};
+use std::sync::{Arc, Mutex};
+use serde::Serialize;
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use crate::cursor::Cursor;
+use crate::git::refs::{PushStats, RefUpdate};
+use crate::models::db::with_retry;
+use crate::timestamps::parse_datetime;
+
+/// Запись о push: изменение одной ссылки со статистикой
+///
+/// Ссылки до и после берутся из снимков, снятых вокруг receive-pack, поэтому
+/// записываются только реально принятые изменения.
+#[derive(Debug, Serialize, Clone)]
+pub struct Push {
+    /// Идентификатор записи
+    pub id: Option<i64>,
+    /// ID репозитория
+    #[serde(skip_serializing)]
+    pub repository_id: i64,
+    /// Полное имя ссылки, например `refs/heads/master`
+    #[serde(rename = "ref")]
+    pub ref_name: String,
+    /// SHA до push; None - ссылка создана
+    pub before: Option<String>,
+    /// SHA после push; None - ссылка удалена
+    pub after: Option<String>,
+    /// Количество новых коммитов
+    pub commits: i64,
+    /// Количество изменённых файлов
+    pub files_changed: i64,
+    /// Количество добавленных строк
+    pub additions: i64,
+    /// Количество удалённых строк
+    pub deletions: i64,
+    /// ID пользователя, выполнившего push
+    #[serde(skip_serializing)]
+    pub pusher_id: i64,
+    /// Имя пользователя, выполнившего push (заполняется при выборке)
+    pub pusher: Option<String>,
+    /// Время push
+    #[serde(rename = "timestamp", serialize_with = "crate::timestamps::serialize_rfc3339")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl Push {
+    /// Создаёт запись о push для изменения ссылки
+    ///
+    /// # Параметры
+    ///
+    /// * `repository_id` - ID репозитория
+    /// * `update` - Изменение ссылки
+    /// * `stats` - Статистика изменения
+    /// * `pusher_id` - ID пользователя, выполнившего push
+    pub fn new(repository_id: i64, update: &RefUpdate, stats: &PushStats, pusher_id: i64) -> Self {
+        Push {
+            id: None,
+            repository_id,
+            ref_name: update.name.clone(),
+            before: update.old.clone(),
+            after: update.new.clone(),
+            commits: stats.commits as i64,
+            files_changed: stats.files_changed as i64,
+            additions: stats.additions as i64,
+            deletions: stats.deletions as i64,
+            pusher_id,
+            pusher: None,
+            created_at: None,
+        }
+    }
+
+    /// Сохраняет запись о push в базе данных
+    ///
+    /// # Параметры
+    ///
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<i64>` - ID созданной записи
+    pub fn create(&self, conn: Arc<Mutex<Connection>>) -> Result<i64> {
+        with_retry(&conn, |conn| {
+            conn.execute(
+                "INSERT INTO pushes 
+                (repository_id, ref_name, before_sha, after_sha, commits, files_changed, additions, deletions, pusher_id) 
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    self.repository_id,
+                    self.ref_name,
+                    self.before,
+                    self.after,
+                    self.commits,
+                    self.files_changed,
+                    self.additions,
+                    self.deletions,
+                    self.pusher_id
+                ]
+            )?;
+            Ok(conn.last_insert_rowid())
+        })
+    }
+
+    /// Возвращает последние push'и в репозиторий, от новых к старым
+    ///
+    /// # Параметры
+    ///
+    /// * `repository_id` - ID репозитория
+    /// * `limit` - Максимальное количество записей
+    /// * `offset` - Сколько записей пропустить
+    /// * `before` - Вернуть только записи после этой позиции списка
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<Vec<Push>>` - Записи с именами пользователей
+    pub fn find_by_repository(
+        repository_id: i64,
+        limit: i64,
+        offset: i64,
+        before: Option<&Cursor>,
+        conn: Arc<Mutex<Connection>>,
+    ) -> Result<Vec<Push>> {
+        let conn_guard = conn.lock().unwrap();
+
+        let mut stmt = conn_guard.prepare(
+            "SELECT p.id, p.repository_id, p.ref_name, p.before_sha, p.after_sha, p.commits, 
+                    p.files_changed, p.additions, p.deletions, p.pusher_id, u.username, p.created_at 
+             FROM pushes p 
+             LEFT JOIN users u ON u.id = p.pusher_id 
+             WHERE p.repository_id = ?1 
+               AND (?4 IS NULL OR p.created_at < ?4 OR (p.created_at = ?4 AND p.id < ?5)) 
+             ORDER BY p.created_at DESC, p.id DESC 
+             LIMIT ?2 OFFSET ?3"
+        )?;
+
+        let params = params![
+            repository_id,
+            limit,
+            offset,
+            before.map(|cursor| cursor.created_at.as_str()),
+            before.map(|cursor| cursor.id),
+        ];
+        let pushes = stmt.query_map(params, |row| {
+            let created_at_str: String = row.get(11)?;
+
+            Ok(Push {
+                id: Some(row.get(0)?),
+                repository_id: row.get(1)?,
+                ref_name: row.get(2)?,
+                before: row.get(3)?,
+                after: row.get(4)?,
+                commits: row.get(5)?,
+                files_changed: row.get(6)?,
+                additions: row.get(7)?,
+                deletions: row.get(8)?,
+                pusher_id: row.get(9)?,
+                pusher: row.get(10)?,
+                created_at: parse_datetime(&created_at_str),
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for push in pushes {
+            result.push(push?);
+        }
+
+        Ok(result)
+    }
+}
@@ -1,11 +1,49 @@
 use rusqlite::{params, Result};
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 use serde::{Serialize, Deserialize};
-use chrono::{DateTime, Utc, NaiveDateTime};
+use chrono::{DateTime, Utc};
 use rusqlite::Connection;
+use crate::models::db::with_retry;
 use std::process::Command;
 use std::path::Path;
-use log::{debug, error};
+use log::{debug, error, info};
+use crate::git::{full_repo_name, git_command, is_bare_repo, repo_path, REPOS_ROOT};
+use crate::git::bundle::head_branch;
+use crate::models::pull_request::MergeMethod;
+use crate::config::CONFIG;
+use crate::timestamps::{parse_datetime, DB_FORMAT};
+
+/// Способы слияния пул-реквестов, разрешённые в репозитории
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct MergeMethods {
+    /// Разрешён коммит слияния
+    pub allow_merge_commit: bool,
+    /// Разрешено слияние одним коммитом (squash)
+    pub allow_squash: bool,
+    /// Разрешён перенос коммитов поверх целевой ветки (rebase)
+    pub allow_rebase: bool,
+}
+
+impl MergeMethods {
+    /// Разрешён ли способ слияния
+    pub fn allows(&self, method: MergeMethod) -> bool {
+        match method {
+            MergeMethod::Merge => self.allow_merge_commit,
+            MergeMethod::Squash => self.allow_squash,
+            MergeMethod::Rebase => self.allow_rebase,
+        }
+    }
+
+    /// Названия разрешённых способов (`merge`, `squash`, `rebase`)
+    pub fn allowed(&self) -> Vec<&'static str> {
+        MergeMethod::ALL
+            .into_iter()
+            .filter(|method| self.allows(*method))
+            .map(|method| method.as_str())
+            .collect()
+    }
+}
 
 /// Модель репозитория Git
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -16,35 +54,26 @@ pub struct Repository {
     pub name: String,
     /// Идентификатор владельца репозитория
     pub owner_id: i64,
+    /// Имя владельца; вместе с именем репозитория образует полное имя `{owner}/{name}`
+    #[serde(default)]
+    pub owner: String,
     /// Описание репозитория
     pub description: Option<String>,
     /// Флаг публичности репозитория
     pub is_public: bool,
     /// Дата создания репозитория
+    #[serde(serialize_with = "crate::timestamps::serialize_rfc3339")]
     pub created_at: Option<DateTime<Utc>>,
-}
-
-/// Вспомогательная функция для парсинга даты/времени из строки
-fn parse_datetime(datetime_str: &str) -> Option<DateTime<Utc>> {
-    // Пробуем разные форматы даты
-    if let Ok(dt) = DateTime::parse_from_rfc3339(datetime_str) {
-        return Some(dt.with_timezone(&Utc));
-    }
-    
-    // Если формат не RFC3339, возможно это формат SQLite (YYYY-MM-DD HH:MM:SS)
-    let naive = NaiveDateTime::parse_from_str(datetime_str, "%Y-%m-%d %H:%M:%S")
-        .or_else(|_| NaiveDateTime::parse_from_str(datetime_str, "%Y-%m-%dT%H:%M:%S"));
-    
-    if let Ok(ndt) = naive {
-        return Some(DateTime::<Utc>::from_naive_utc_and_offset(ndt, Utc));
-    }
-    
-    None
+    /// ID репозитория, форком которого является этот
+    pub forked_from: Option<i64>,
 }
 
 impl Repository {
     /// Создаёт новый репозиторий в базе данных и на диске
     /// 
+    /// Чужой каталог с тем же именем не используется повторно. Если создать
+    /// репозиторий на диске не удалось, запись и недоделанный каталог удаляются.
+    /// 
     /// # Параметры
     /// 
     /// * `conn` - Соединение с базой данных
@@ -53,50 +82,37 @@ impl Repository {
     /// 
     /// * `Result<i64>` - ID созданного репозитория
     pub fn create(&self, conn: Arc<Mutex<Connection>>) -> Result<i64> {
-        let conn_guard = conn.lock().unwrap();
-        
+        // Проверяем имя до любых изменений в базе данных и на диске
+        let path = match repo_path(Path::new(REPOS_ROOT), &self.full_name()) {
+            Ok(path) => path,
+            Err(e) => {
+                error!("{}", e);
+                return Err(rusqlite::Error::InvalidParameterName(self.name.clone()));
+            }
+        };
+        if path.exists() {
+            error!("Каталог репозитория уже существует: {}", path.display());
+            return Err(rusqlite::Error::ExecuteReturnedResults);
+        }
+
         // Добавляем репозиторий в базу данных
-        conn_guard.execute(
-            "INSERT INTO repositories (name, owner_id, description, is_public) VALUES (?1, ?2, ?3, ?4)",
-            params![self.name, self.owner_id, self.description, self.is_public],
-        )?;
-        
-        let repo_id = conn_guard.last_insert_rowid();
-        drop(conn_guard); // Освобождаем блокировку
+        let repo_id = with_retry(&conn, |conn| {
+            conn.execute(
+                "INSERT INTO repositories (name, owner_id, description, is_public, forked_from) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![self.name, self.owner_id, self.description, self.is_public, self.forked_from],
+            )?;
+            Ok(conn.last_insert_rowid())
+        })?;
 
         // Создаём репозиторий на диске
-        let repo_path = format!("repositories/{}.git", self.name);
-        let path = Path::new(&repo_path);
-        
-        if !path.exists() {
-            // Создаём каталог для репозитория
-            if let Err(e) = std::fs::create_dir_all(path) {
-                error!("Не удалось создать каталог для репозитория: {}", e);
-                return Err(rusqlite::Error::ExecuteReturnedResults);
-            }
-            
-            // Инициализируем bare репозиторий Git
-            let output = Command::new("git")
-                .arg("init")
-                .arg("--bare")
-                .arg(path)
-                .output();
-                
-            match output {
-                Ok(output) if output.status.success() => {
-                    debug!("Репозиторий успешно инициализирован: {}", self.name);
-                }
-                Ok(output) => {
-                    error!("Ошибка при инициализации репозитория: {}", String::from_utf8_lossy(&output.stderr));
-                    return Err(rusqlite::Error::ExecuteReturnedResults);
-                }
-                Err(e) => {
-                    error!("Не удалось выполнить команду git init: {}", e);
-                    return Err(rusqlite::Error::ExecuteReturnedResults);
-                }
-            }
+        if let Err(e) = init_bare_repository(&path) {
+            error!("Ошибка при инициализации репозитория: {}", e);
+            let _ = std::fs::remove_dir_all(&path);
+            with_retry(&conn, |conn| conn.execute("DELETE FROM repositories WHERE id = ?1", params![repo_id]))?;
+            return Err(rusqlite::Error::ExecuteReturnedResults);
         }
-        
+
+        debug!("Репозиторий успешно инициализирован: {}", self.name);
         Ok(repo_id)
     }
 
@@ -114,7 +130,10 @@ impl Repository {
         let conn = conn.lock().unwrap();
         
         let mut stmt = conn.prepare(
-            "SELECT id, name, owner_id, description, is_public, created_at FROM repositories WHERE owner_id = ?1"
+            "SELECT r.id, r.name, r.owner_id, r.description, r.is_public, r.created_at, r.forked_from, u.username
+            FROM repositories r
+            JOIN users u ON u.id = r.owner_id
+            WHERE r.owner_id = ?1"
         )?;
         
         let repos = stmt.query_map(params![owner_id], |row| {
@@ -124,10 +143,12 @@ impl Repository {
                 id: Some(row.get(0)?),
                 name: row.get(1)?,
                 owner_id: row.get(2)?,
+                owner: row.get(7)?,
                 description: row.get(3)?,
                 is_public: row.get(4)?,
                 
                 created_at: parse_datetime(&created_at),
+                forked_from: row.get(6)?,
             })
         })?;
         
@@ -139,24 +160,84 @@ impl Repository {
         Ok(result)
     }
 
-    /// Находит репозиторий по имени
+    /// Получает следующую порцию репозиториев пользователя по возрастанию ID
     /// 
     /// # Параметры
     /// 
-    /// * `name` - Имя репозитория
+    /// * `owner_id` - ID пользователя
+    /// * `after_id` - ID последнего уже полученного репозитория (0 - с начала)
+    /// * `limit` - Размер порции
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<Vec<Repository>>` - Репозитории с ID больше `after_id`
+    pub fn find_by_owner_after(owner_id: i64, after_id: i64, limit: i64, conn: Arc<Mutex<Connection>>) -> Result<Vec<Repository>> {
+        let conn = conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT r.id, r.name, r.owner_id, r.description, r.is_public, r.created_at, r.forked_from, u.username
+            FROM repositories r
+            JOIN users u ON u.id = r.owner_id
+            WHERE r.owner_id = ?1 AND r.id > ?2
+            ORDER BY r.id
+            LIMIT ?3"
+        )?;
+
+        let repos = stmt.query_map(params![owner_id, after_id, limit], |row| {
+            let created_at: String = row.get(5)?;
+
+            Ok(Repository {
+                id: Some(row.get(0)?),
+                name: row.get(1)?,
+                owner_id: row.get(2)?,
+                owner: row.get(7)?,
+                description: row.get(3)?,
+                is_public: row.get(4)?,
+                created_at: parse_datetime(&created_at),
+                forked_from: row.get(6)?,
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for repo in repos {
+            result.push(repo?);
+        }
+
+        Ok(result)
+    }
+
+    /// Полное имя репозитория `{owner}/{name}`: по нему строятся путь на диске,
+    /// блокировки и ключи кэшей
+    pub fn full_name(&self) -> String {
+        full_repo_name(&self.owner, &self.name)
+    }
+
+    /// Находит репозиторий по полному имени `{owner}/{name}`
+    /// 
+    /// # Параметры
+    /// 
+    /// * `full_name` - Полное имя репозитория
     /// * `conn` - Соединение с базой данных
     /// 
     /// # Возвращает
     /// 
     /// * `Result<Option<Repository>>` - Найденный репозиторий или None
-    pub fn find_by_name(name: &str, conn: Arc<Mutex<Connection>>) -> Result<Option<Repository>> {
+    pub fn find_by_full_name(full_name: &str, conn: Arc<Mutex<Connection>>) -> Result<Option<Repository>> {
+        let (owner, name) = match full_name.split_once('/') {
+            Some(parts) => parts,
+            None => return Ok(None),
+        };
         let conn = conn.lock().unwrap();
         
         let mut stmt = conn.prepare(
-            "SELECT id, name, owner_id, description, is_public, created_at FROM repositories WHERE name = ?1"
+            "SELECT r.id, r.name, r.owner_id, r.description, r.is_public, r.created_at, r.forked_from, u.username
+            FROM repositories r
+            JOIN users u ON u.id = r.owner_id
+            WHERE u.username = ?1 AND r.name = ?2"
         )?;
         
-        let mut rows = stmt.query(params![name])?;
+        let mut rows = stmt.query(params![owner, name])?;
         
         if let Some(row) = rows.next()? {
             let created_at: String = row.get(5)?;
@@ -165,12 +246,912 @@ impl Repository {
                 id: Some(row.get(0)?),
                 name: row.get(1)?,
                 owner_id: row.get(2)?,
+                owner: row.get(7)?,
                 description: row.get(3)?,
                 is_public: row.get(4)?,
                 created_at: parse_datetime(&created_at),
+                forked_from: row.get(6)?,
             }))
         } else {
             Ok(None)
         }
     }
+
+    /// Находит репозитории всех владельцев с этим именем
+    /// 
+    /// Имя уникально только в пределах владельца, поэтому URL без владельца
+    /// (`/api/repos/{repo_name}`, `/git/{repo_name}`) может подходить нескольким
+    /// репозиториям; выбирает между ними `handlers::api::lookup_repo`.
+    /// 
+    /// # Параметры
+    /// 
+    /// * `name` - Имя репозитория
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<Vec<Repository>>` - Репозитории от старых к новым
+    pub fn find_all_by_name(name: &str, conn: Arc<Mutex<Connection>>) -> Result<Vec<Repository>> {
+        let conn = conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT r.id, r.name, r.owner_id, r.description, r.is_public, r.created_at, r.forked_from, u.username
+            FROM repositories r
+            JOIN users u ON u.id = r.owner_id
+            WHERE r.name = ?1
+            ORDER BY r.id"
+        )?;
+
+        let repos = stmt.query_map(params![name], |row| {
+            let created_at: String = row.get(5)?;
+
+            Ok(Repository {
+                id: Some(row.get(0)?),
+                name: row.get(1)?,
+                owner_id: row.get(2)?,
+                owner: row.get(7)?,
+                description: row.get(3)?,
+                is_public: row.get(4)?,
+                created_at: parse_datetime(&created_at),
+                forked_from: row.get(6)?,
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for repo in repos {
+            result.push(repo?);
+        }
+
+        Ok(result)
+    }
+
+    /// Время создания репозиториев владельца после момента `since`
+    /// 
+    /// # Параметры
+    /// 
+    /// * `owner_id` - ID владельца
+    /// * `since` - Начало окна
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<Vec<DateTime<Utc>>>` - Время создания от старых к новым
+    pub fn created_since(owner_id: i64, since: DateTime<Utc>, conn: Arc<Mutex<Connection>>) -> Result<Vec<DateTime<Utc>>> {
+        let conn = conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT created_at FROM repositories
+            WHERE owner_id = ?1 AND created_at > ?2
+            ORDER BY created_at"
+        )?;
+        let since = since.format(DB_FORMAT).to_string();
+        let rows = stmt.query_map(params![owner_id, since], |row| row.get::<_, String>(0))?;
+
+        let mut result = Vec::new();
+        for created_at in rows {
+            if let Some(created_at) = parse_datetime(&created_at?) {
+                result.push(created_at);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Возвращает полные имена всех репозиториев
+    /// 
+    /// # Параметры
+    /// 
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<Vec<String>>` - Полные имена `{owner}/{name}` по алфавиту
+    pub fn all_names(conn: Arc<Mutex<Connection>>) -> Result<Vec<String>> {
+        let conn = conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT u.username, r.name FROM repositories r JOIN users u ON u.id = r.owner_id ORDER BY u.username, r.name"
+        )?;
+        let names = stmt.query_map([], |row| Ok(full_repo_name(&row.get::<_, String>(0)?, &row.get::<_, String>(1)?)))?;
+
+        let mut result = Vec::new();
+        for name in names {
+            result.push(name?);
+        }
+
+        Ok(result)
+    }
+
+    /// Находит репозиторий владельца, имя которого отличается от `name` только регистром
+    ///
+    /// Имена репозиториев сохраняют регистр, но у одного владельца должны быть
+    /// уникальны без учёта регистра: `Foo.git` и `foo.git` - один и тот же каталог
+    /// на нечувствительных к регистру файловых системах, а в URL их легко спутать.
+    ///
+    /// # Параметры
+    ///
+    /// * `owner_id` - ID владельца
+    /// * `name` - Имя репозитория
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<Option<String>>` - Имя существующего репозитория или None
+    pub fn find_case_conflict(owner_id: i64, name: &str, conn: Arc<Mutex<Connection>>) -> Result<Option<String>> {
+        let conn = conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT name FROM repositories WHERE owner_id = ?2 AND name = ?1 COLLATE NOCASE AND name <> ?1 LIMIT 1"
+        )?;
+        let mut rows = stmt.query(params![name, owner_id])?;
+
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Возвращает счётчик изменений ссылок репозитория
+    /// 
+    /// Счётчик увеличивается при каждом изменении ссылок через сервер (push, слияние,
+    /// смена скрытых ссылок) и служит ETag для объявления ссылок.
+    /// 
+    /// # Параметры
+    /// 
+    /// * `full_name` - Полное имя репозитория `{owner}/{name}`
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<Option<i64>>` - Значение счётчика или None, если репозитория нет в базе
+    pub fn ref_generation(full_name: &str, conn: Arc<Mutex<Connection>>) -> Result<Option<i64>> {
+        let (owner, name) = match full_name.split_once('/') {
+            Some(parts) => parts,
+            None => return Ok(None),
+        };
+        let conn = conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT r.ref_generation FROM repositories r
+            JOIN users u ON u.id = r.owner_id
+            WHERE u.username = ?1 AND r.name = ?2"
+        )?;
+        let mut rows = stmt.query(params![owner, name])?;
+
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Увеличивает счётчик изменений ссылок репозитория
+    /// 
+    /// Вызывается и до, и после изменения: объявление, снятое во время изменения,
+    /// получит промежуточное значение и не совпадёт с итоговым.
+    /// 
+    /// # Параметры
+    /// 
+    /// * `full_name` - Полное имя репозитория `{owner}/{name}`
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<()>` - Результат операции
+    pub fn bump_ref_generation(full_name: &str, conn: Arc<Mutex<Connection>>) -> Result<()> {
+        let (owner, name) = match full_name.split_once('/') {
+            Some(parts) => parts,
+            None => return Ok(()),
+        };
+        with_retry(&conn, |conn| {
+            conn.execute(
+                "UPDATE repositories SET ref_generation = ref_generation + 1
+                WHERE name = ?2 AND owner_id = (SELECT id FROM users WHERE username = ?1)",
+                params![owner, name],
+            )
+        })?;
+
+        Ok(())
+    }
+
+    /// Включены ли пул-реквесты в репозитории (без учёта глобальной настройки)
+    /// 
+    /// # Параметры
+    /// 
+    /// * `repo_id` - ID репозитория
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<bool>` - Значение настройки; для неизвестного репозитория true
+    pub fn pull_requests_enabled(repo_id: i64, conn: Arc<Mutex<Connection>>) -> Result<bool> {
+        let conn = conn.lock().unwrap();
+
+        let mut stmt = conn.prepare("SELECT pull_requests_enabled FROM repositories WHERE id = ?1")?;
+        let mut rows = stmt.query(params![repo_id])?;
+
+        match rows.next()? {
+            Some(row) => row.get(0),
+            None => Ok(true),
+        }
+    }
+
+    /// Включает или выключает пул-реквесты в репозитории
+    /// 
+    /// # Параметры
+    /// 
+    /// * `repo_id` - ID репозитория
+    /// * `enabled` - Новое значение
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<()>` - Результат операции
+    pub fn set_pull_requests_enabled(repo_id: i64, enabled: bool, conn: Arc<Mutex<Connection>>) -> Result<()> {
+        with_retry(&conn, |conn| {
+            conn.execute(
+                "UPDATE repositories SET pull_requests_enabled = ?1 WHERE id = ?2",
+                params![enabled, repo_id],
+            )
+        })?;
+
+        Ok(())
+    }
+
+    /// Способы слияния пул-реквестов, разрешённые в репозитории
+    /// 
+    /// # Параметры
+    /// 
+    /// * `repo_id` - ID репозитория
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<MergeMethods>` - Настройки; для неизвестного репозитория разрешены все способы
+    pub fn merge_methods(repo_id: i64, conn: Arc<Mutex<Connection>>) -> Result<MergeMethods> {
+        let conn = conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT allow_merge_commit, allow_squash, allow_rebase FROM repositories WHERE id = ?1"
+        )?;
+        let mut rows = stmt.query(params![repo_id])?;
+
+        match rows.next()? {
+            Some(row) => Ok(MergeMethods {
+                allow_merge_commit: row.get(0)?,
+                allow_squash: row.get(1)?,
+                allow_rebase: row.get(2)?,
+            }),
+            None => Ok(MergeMethods { allow_merge_commit: true, allow_squash: true, allow_rebase: true }),
+        }
+    }
+
+    /// Сохраняет разрешённые способы слияния пул-реквестов
+    /// 
+    /// # Параметры
+    /// 
+    /// * `repo_id` - ID репозитория
+    /// * `methods` - Новые настройки
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<()>` - Результат операции
+    pub fn set_merge_methods(repo_id: i64, methods: &MergeMethods, conn: Arc<Mutex<Connection>>) -> Result<()> {
+        with_retry(&conn, |conn| {
+            conn.execute(
+                "UPDATE repositories SET allow_merge_commit = ?1, allow_squash = ?2, allow_rebase = ?3 WHERE id = ?4",
+                params![methods.allow_merge_commit, methods.allow_squash, methods.allow_rebase, repo_id],
+            )
+        })?;
+
+        Ok(())
+    }
+
+    /// Делает репозиторий публичным или приватным
+    /// 
+    /// # Параметры
+    /// 
+    /// * `repo_id` - ID репозитория
+    /// * `is_public` - Новое значение
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<bool>` - true, если видимость изменилась
+    pub fn set_visibility(repo_id: i64, is_public: bool, conn: Arc<Mutex<Connection>>) -> Result<bool> {
+        let updated = with_retry(&conn, |conn| {
+            conn.execute(
+                "UPDATE repositories SET is_public = ?1 WHERE id = ?2 AND is_public != ?1",
+                params![is_public, repo_id],
+            )
+        })?;
+
+        Ok(updated > 0)
+    }
+
+    /// Удаляет репозиторий: запись со всеми зависимыми данными и каталог на диске
+    ///
+    /// Пул-реквесты с комментариями, история push'ей, предупреждения о секретах,
+    /// webhook'и с журналом доставок, подписки, закрепления и темы удаляются в одной
+    /// транзакции; форки остаются и перестают ссылаться на источник. Каталог
+    /// удаляется после записи: если это не удалось, он останется в отчёте
+    /// `/api/admin/integrity` как лишний.
+    ///
+    /// # Параметры
+    ///
+    /// * `repo_id` - ID репозитория
+    /// * `full_name` - Полное имя репозитория `{owner}/{name}` (каталог на диске)
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<()>` - Результат операции
+    pub fn delete(repo_id: i64, full_name: &str, conn: Arc<Mutex<Connection>>) -> Result<()> {
+        with_retry(&conn, |conn| {
+            let tx = conn.unchecked_transaction()?;
+            tx.execute(
+                "DELETE FROM pull_request_comments WHERE pull_request_id IN
+                 (SELECT id FROM pull_requests WHERE repository_id = ?1)",
+                params![repo_id],
+            )?;
+            tx.execute("DELETE FROM pull_requests WHERE repository_id = ?1", params![repo_id])?;
+            tx.execute("DELETE FROM pushes WHERE repository_id = ?1", params![repo_id])?;
+            tx.execute("DELETE FROM secret_alerts WHERE repository_id = ?1", params![repo_id])?;
+            tx.execute(
+                "DELETE FROM webhook_deliveries WHERE hook_id IN (SELECT id FROM webhooks WHERE repository_id = ?1)",
+                params![repo_id],
+            )?;
+            tx.execute("DELETE FROM webhooks WHERE repository_id = ?1", params![repo_id])?;
+            tx.execute("DELETE FROM repo_subscriptions WHERE repository_id = ?1", params![repo_id])?;
+            tx.execute("DELETE FROM profile_pinned_repos WHERE repository_id = ?1", params![repo_id])?;
+            tx.execute("DELETE FROM repo_topics WHERE repository_id = ?1", params![repo_id])?;
+            tx.execute("UPDATE repositories SET forked_from = NULL WHERE forked_from = ?1", params![repo_id])?;
+            tx.execute("DELETE FROM repositories WHERE id = ?1", params![repo_id])?;
+            tx.commit()
+        })?;
+
+        match repo_path(Path::new(REPOS_ROOT), full_name) {
+            Ok(path) => {
+                if let Err(e) = std::fs::remove_dir_all(&path) {
+                    error!("Ошибка при удалении каталога {}: {}", path.display(), e);
+                }
+            }
+            Err(e) => error!("{}", e),
+        }
+
+        debug!("Репозиторий {} удалён", full_name);
+        Ok(())
+    }
+
+    /// Создаёт форк: запись в базе данных и копию репозитория-источника на диске
+    /// 
+    /// Объекты копируются `git clone --bare`, который для локального источника
+    /// использует жёсткие ссылки. Если копирование не удалось, запись удаляется.
+    /// 
+    /// # Параметры
+    /// 
+    /// * `source` - Репозиторий-источник
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<i64>` - ID созданного форка
+    pub fn create_fork(&self, source: &Repository, conn: Arc<Mutex<Connection>>) -> Result<i64> {
+        let (source_path, path) = match (
+            repo_path(Path::new(REPOS_ROOT), &source.full_name()),
+            repo_path(Path::new(REPOS_ROOT), &self.full_name()),
+        ) {
+            (Ok(source_path), Ok(path)) => (source_path, path),
+            (Err(e), _) | (_, Err(e)) => {
+                error!("{}", e);
+                return Err(rusqlite::Error::InvalidParameterName(self.name.clone()));
+            }
+        };
+
+        let repo_id = with_retry(&conn, |conn| {
+            conn.execute(
+                "INSERT INTO repositories (name, owner_id, description, is_public, forked_from) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![self.name, self.owner_id, self.description, self.is_public, source.id],
+            )?;
+            Ok(conn.last_insert_rowid())
+        })?;
+
+        if let Err(e) = copy_repository(&source_path, &path) {
+            error!("Ошибка при копировании репозитория: {}", e);
+            let _ = std::fs::remove_dir_all(&path);
+            with_retry(&conn, |conn| conn.execute("DELETE FROM repositories WHERE id = ?1", params![repo_id]))?;
+            return Err(rusqlite::Error::ExecuteReturnedResults);
+        }
+
+        debug!("Форк {} создан из {}", self.name, source.name);
+        Ok(repo_id)
+    }
+
+    /// Создаёт репозиторий из git bundle: запись в базе данных и репозиторий на диске
+    /// 
+    /// Bundle должен быть заранее проверен (`git::bundle::verify_bundle`). Если
+    /// загрузить его не удалось, запись и каталог удаляются.
+    /// 
+    /// # Параметры
+    /// 
+    /// * `bundle` - Путь к файлу bundle
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<i64>` - ID созданного репозитория
+    pub fn create_from_bundle(&self, bundle: &Path, conn: Arc<Mutex<Connection>>) -> Result<i64> {
+        let path = match repo_path(Path::new(REPOS_ROOT), &self.full_name()) {
+            Ok(path) => path,
+            Err(e) => {
+                error!("{}", e);
+                return Err(rusqlite::Error::InvalidParameterName(self.name.clone()));
+            }
+        };
+
+        let repo_id = with_retry(&conn, |conn| {
+            conn.execute(
+                "INSERT INTO repositories (name, owner_id, description, is_public, forked_from) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![self.name, self.owner_id, self.description, self.is_public, self.forked_from],
+            )?;
+            Ok(conn.last_insert_rowid())
+        })?;
+
+        if let Err(e) = import_bundle(bundle, &path) {
+            error!("Ошибка при загрузке bundle: {}", e);
+            let _ = std::fs::remove_dir_all(&path);
+            with_retry(&conn, |conn| conn.execute("DELETE FROM repositories WHERE id = ?1", params![repo_id]))?;
+            return Err(rusqlite::Error::ExecuteReturnedResults);
+        }
+
+        debug!("Репозиторий {} создан из bundle", self.name);
+        Ok(repo_id)
+    }
+
+    /// Возвращает форки репозитория, видимые пользователю, от старых к новым
+    /// 
+    /// # Параметры
+    /// 
+    /// * `repo_id` - ID репозитория-источника
+    /// * `viewer_id` - ID текущего пользователя: его приватные форки тоже видны
+    /// * `limit` - Максимальное количество записей
+    /// * `offset` - Сколько записей пропустить
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<Vec<Repository>>` - Форки
+    pub fn find_forks(
+        repo_id: i64,
+        viewer_id: Option<i64>,
+        limit: i64,
+        offset: i64,
+        conn: Arc<Mutex<Connection>>,
+    ) -> Result<Vec<Repository>> {
+        let conn = conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT r.id, r.name, r.owner_id, r.description, r.is_public, r.created_at, r.forked_from, u.username
+            FROM repositories r
+            JOIN users u ON u.id = r.owner_id
+            WHERE r.forked_from = ?1 AND (r.is_public = 1 OR r.owner_id = ?2)
+            ORDER BY r.created_at, r.id
+            LIMIT ?3 OFFSET ?4"
+        )?;
+
+        let forks = stmt.query_map(params![repo_id, viewer_id, limit, offset], |row| {
+            let created_at: String = row.get(5)?;
+
+            Ok(Repository {
+                id: Some(row.get(0)?),
+                name: row.get(1)?,
+                owner_id: row.get(2)?,
+                owner: row.get(7)?,
+                description: row.get(3)?,
+                is_public: row.get(4)?,
+                created_at: parse_datetime(&created_at),
+                forked_from: row.get(6)?,
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for fork in forks {
+            result.push(fork?);
+        }
+
+        Ok(result)
+    }
+
+    /// Возвращает репозитории, закреплённые в профиле пользователя
+    /// 
+    /// # Параметры
+    /// 
+    /// * `user_id` - ID владельца профиля
+    /// * `viewer_id` - ID того, кто смотрит профиль: приватные репозитории видны только их владельцу
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<Vec<Repository>>` - Репозитории в заданном пользователем порядке
+    pub fn find_pinned(user_id: i64, viewer_id: Option<i64>, conn: Arc<Mutex<Connection>>) -> Result<Vec<Repository>> {
+        let conn = conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT r.id, r.name, r.owner_id, r.description, r.is_public, r.created_at, r.forked_from, u.username
+            FROM profile_pinned_repos p
+            JOIN repositories r ON r.id = p.repository_id
+            JOIN users u ON u.id = r.owner_id
+            WHERE p.user_id = ?1 AND (r.is_public = 1 OR r.owner_id = ?2)
+            ORDER BY p.position"
+        )?;
+
+        let repos = stmt.query_map(params![user_id, viewer_id], |row| {
+            let created_at: String = row.get(5)?;
+
+            Ok(Repository {
+                id: Some(row.get(0)?),
+                name: row.get(1)?,
+                owner_id: row.get(2)?,
+                owner: row.get(7)?,
+                description: row.get(3)?,
+                is_public: row.get(4)?,
+                created_at: parse_datetime(&created_at),
+                forked_from: row.get(6)?,
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for repo in repos {
+            result.push(repo?);
+        }
+
+        Ok(result)
+    }
+
+    /// Заменяет список закреплённых в профиле репозиториев
+    /// 
+    /// # Параметры
+    /// 
+    /// * `user_id` - ID владельца профиля
+    /// * `repo_ids` - ID репозиториев в нужном порядке (должны быть уже проверены)
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<()>` - Результат операции
+    pub fn set_pinned(user_id: i64, repo_ids: &[i64], conn: Arc<Mutex<Connection>>) -> Result<()> {
+        with_retry(&conn, |conn| {
+            let tx = conn.unchecked_transaction()?;
+            tx.execute("DELETE FROM profile_pinned_repos WHERE user_id = ?1", params![user_id])?;
+            for (position, repo_id) in repo_ids.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO profile_pinned_repos (user_id, repository_id, position) VALUES (?1, ?2, ?3)",
+                    params![user_id, repo_id, position as i64],
+                )?;
+            }
+            tx.commit()
+        })
+    }
+
+    /// Возвращает темы репозитория по алфавиту
+    /// 
+    /// # Параметры
+    /// 
+    /// * `repo_id` - ID репозитория
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<Vec<String>>` - Темы
+    pub fn topics(repo_id: i64, conn: Arc<Mutex<Connection>>) -> Result<Vec<String>> {
+        Ok(Self::topics_by_repository(&[repo_id], conn)?.remove(&repo_id).unwrap_or_default())
+    }
+
+    /// Возвращает темы нескольких репозиториев одним запросом
+    /// 
+    /// # Параметры
+    /// 
+    /// * `repo_ids` - ID репозиториев
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<HashMap<i64, Vec<String>>>` - Темы по ID репозитория, по алфавиту;
+    ///   репозиториев без тем в результате нет
+    pub fn topics_by_repository(repo_ids: &[i64], conn: Arc<Mutex<Connection>>) -> Result<HashMap<i64, Vec<String>>> {
+        let mut result: HashMap<i64, Vec<String>> = HashMap::new();
+        if repo_ids.is_empty() {
+            return Ok(result);
+        }
+
+        let conn = conn.lock().unwrap();
+        let placeholders = vec!["?"; repo_ids.len()].join(", ");
+        let mut stmt = conn.prepare(&format!(
+            "SELECT repository_id, topic FROM repo_topics WHERE repository_id IN ({}) ORDER BY topic",
+            placeholders
+        ))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(repo_ids), |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        for row in rows {
+            let (repo_id, topic) = row?;
+            result.entry(repo_id).or_default().push(topic);
+        }
+
+        Ok(result)
+    }
+
+    /// Заменяет набор тем репозитория
+    /// 
+    /// # Параметры
+    /// 
+    /// * `repo_id` - ID репозитория
+    /// * `topics` - Новые темы (должны быть уже нормализованы)
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<()>` - Результат операции
+    pub fn set_topics(repo_id: i64, topics: &[String], conn: Arc<Mutex<Connection>>) -> Result<()> {
+        with_retry(&conn, |conn| {
+            let tx = conn.unchecked_transaction()?;
+            tx.execute("DELETE FROM repo_topics WHERE repository_id = ?1", params![repo_id])?;
+            for topic in topics {
+                tx.execute(
+                    "INSERT INTO repo_topics (repository_id, topic) VALUES (?1, ?2)",
+                    params![repo_id, topic],
+                )?;
+            }
+            tx.commit()
+        })
+    }
+
+    /// Возвращает репозитории для обзора, от новых к старым
+    /// 
+    /// # Параметры
+    /// 
+    /// * `topic` - Только репозитории с этой темой
+    /// * `viewer_id` - ID текущего пользователя: его приватные репозитории тоже видны
+    /// * `limit` - Максимальное количество записей
+    /// * `offset` - Сколько записей пропустить
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<Vec<Repository>>` - Репозитории
+    pub fn explore(
+        topic: Option<&str>,
+        viewer_id: Option<i64>,
+        limit: i64,
+        offset: i64,
+        conn: Arc<Mutex<Connection>>,
+    ) -> Result<Vec<Repository>> {
+        let conn = conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT r.id, r.name, r.owner_id, r.description, r.is_public, r.created_at, r.forked_from, u.username
+            FROM repositories r
+            JOIN users u ON u.id = r.owner_id
+            WHERE (r.is_public = 1 OR r.owner_id = ?2)
+              AND (?1 IS NULL OR EXISTS (
+                  SELECT 1 FROM repo_topics t WHERE t.repository_id = r.id AND t.topic = ?1
+              ))
+            ORDER BY r.created_at DESC, r.id DESC
+            LIMIT ?3 OFFSET ?4"
+        )?;
+
+        let repos = stmt.query_map(params![topic, viewer_id, limit, offset], |row| {
+            let created_at: String = row.get(5)?;
+
+            Ok(Repository {
+                id: Some(row.get(0)?),
+                name: row.get(1)?,
+                owner_id: row.get(2)?,
+                owner: row.get(7)?,
+                description: row.get(3)?,
+                is_public: row.get(4)?,
+                created_at: parse_datetime(&created_at),
+                forked_from: row.get(6)?,
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for repo in repos {
+            result.push(repo?);
+        }
+
+        Ok(result)
+    }
+
+    /// Считает форки репозитория, видимые пользователю
+    /// 
+    /// # Параметры
+    /// 
+    /// * `repo_id` - ID репозитория-источника
+    /// * `viewer_id` - ID текущего пользователя
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<i64>` - Число форков
+    pub fn count_forks(repo_id: i64, viewer_id: Option<i64>, conn: Arc<Mutex<Connection>>) -> Result<i64> {
+        let conn = conn.lock().unwrap();
+
+        conn.query_row(
+            "SELECT COUNT(*) FROM repositories WHERE forked_from = ?1 AND (is_public = 1 OR owner_id = ?2)",
+            params![repo_id, viewer_id],
+            |row| row.get(0),
+        )
+    }
+
+    /// Переносит репозитории из плоской раскладки `{name}.git` в `{owner}/{name}.git`
+    ///
+    /// Раньше имя репозитория было глобальным и каталог лежал прямо в корне.
+    /// Если одному плоскому каталогу соответствует несколько записей, он
+    /// достаётся самой старой из них. Уже перенесённые каталоги пропускаются,
+    /// поэтому повторный запуск ничего не меняет.
+    ///
+    /// # Параметры
+    ///
+    /// * `root` - Корень хранилища репозиториев
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<usize>` - Число перенесённых репозиториев
+    pub fn migrate_storage_layout(root: &Path, conn: Arc<Mutex<Connection>>) -> Result<usize> {
+        let rows: Vec<(String, String)> = {
+            let conn = conn.lock().unwrap();
+            let mut stmt = conn.prepare(
+                "SELECT r.name, u.username FROM repositories r
+                JOIN users u ON u.id = r.owner_id
+                ORDER BY r.id"
+            )?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<Result<_>>()?
+        };
+
+        let mut moved = 0;
+        let mut claimed = HashSet::new();
+        for (name, owner) in rows {
+            // Плоский каталог принадлежит только самой старой записи с этим именем
+            if !claimed.insert(name.clone()) {
+                continue;
+            }
+            let legacy = root.join(format!("{}.git", name));
+            if !is_bare_repo(&legacy) {
+                continue;
+            }
+            let target = match repo_path(root, &full_repo_name(&owner, &name)) {
+                Ok(path) => path,
+                Err(e) => {
+                    error!("Репозиторий {} не перенесён: {}", legacy.display(), e);
+                    continue;
+                }
+            };
+            if target.exists() {
+                continue;
+            }
+            let renamed = target
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|_| std::fs::rename(&legacy, &target));
+            match renamed {
+                Ok(()) => {
+                    info!("Репозиторий {} перенесён в {}", legacy.display(), target.display());
+                    moved += 1;
+                }
+                Err(e) => error!("Не удалось перенести {} в {}: {}", legacy.display(), target.display(), e),
+            }
+        }
+
+        Ok(moved)
+    }
+}
+
+/// Инициализирует пустой bare репозиторий
+///
+/// `--shared` выставляет `core.sharedRepository` и права на каталоги и файлы, которые git создаёт.
+///
+/// # Параметры
+///
+/// * `target` - Путь к новому репозиторию
+///
+/// # Возвращает
+///
+/// * `std::result::Result<(), String>` - Описание ошибки git
+fn init_bare_repository(target: &Path) -> std::result::Result<(), String> {
+    match git_command()
+        .args(["init", "--bare", "--quiet"])
+        .arg(format!("--shared={}", CONFIG.shared_repository))
+        .arg(target)
+        .output()
+    {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+        Err(e) => Err(format!("Не удалось выполнить команду git init: {}", e)),
+    }
+}
+
+/// Копирует ветки, теги и HEAD bare репозитория в новый bare репозиторий
+///
+/// Вместо `git clone --bare` репозиторий создаётся через `git init --shared`,
+/// а объекты загружаются через `git fetch`: так права на каталоги и объекты
+/// соответствуют `core.sharedRepository`, а не копируются жёсткими ссылками
+/// из источника. Удалённый репозиторий `origin` при этом не создаётся.
+///
+/// # Параметры
+///
+/// * `source` - Путь к репозиторию-источнику
+/// * `target` - Путь к новому репозиторию
+///
+/// # Возвращает
+///
+/// * `std::result::Result<(), String>` - Описание ошибки git
+fn copy_repository(source: &Path, target: &Path) -> std::result::Result<(), String> {
+    let run = |command: &mut Command| match command.output() {
+        Ok(output) if output.status.success() => Ok(output.stdout),
+        Ok(output) => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+
+    run(git_command()
+        .args(["init", "--bare", "--quiet"])
+        .arg(format!("--shared={}", CONFIG.shared_repository))
+        .arg(target))?;
+    run(git_command()
+        .arg("--git-dir")
+        .arg(target)
+        .args(["fetch", "--quiet", "--no-tags"])
+        .arg(source)
+        .args(["+refs/heads/*:refs/heads/*", "+refs/tags/*:refs/tags/*"]))?;
+
+    // Ветка по умолчанию - как у источника; у пустого источника HEAD может отсутствовать
+    if let Ok(head) = run(git_command().arg("--git-dir").arg(source).args(["symbolic-ref", "HEAD"])) {
+        let head = String::from_utf8_lossy(&head).trim().to_string();
+        run(git_command().arg("--git-dir").arg(target).args(["symbolic-ref", "HEAD", &head]))?;
+    }
+
+    Ok(())
+}
+
+/// Загружает ветки и теги из bundle в новый bare репозиторий
+///
+/// Как и в `copy_repository`, репозиторий создаётся через `git init --shared`
+/// и `git fetch`, а не `git clone --bare`, чтобы права соответствовали
+/// `core.sharedRepository`.
+///
+/// # Параметры
+///
+/// * `bundle` - Путь к файлу bundle
+/// * `target` - Путь к новому репозиторию
+///
+/// # Возвращает
+///
+/// * `std::result::Result<(), String>` - Описание ошибки git
+fn import_bundle(bundle: &Path, target: &Path) -> std::result::Result<(), String> {
+    let run = |command: &mut Command| match command.output() {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+
+    run(git_command()
+        .args(["init", "--bare", "--quiet"])
+        .arg(format!("--shared={}", CONFIG.shared_repository))
+        .arg(target))?;
+    run(git_command()
+        .arg("--git-dir")
+        .arg(target)
+        .args(["fetch", "--quiet", "--no-tags"])
+        .arg(bundle)
+        .args(["+refs/heads/*:refs/heads/*", "+refs/tags/*:refs/tags/*"]))?;
+
+    // Ветка по умолчанию - та, на которую указывал HEAD при создании bundle
+    if let Some(head) = head_branch(target, bundle).map_err(|e| e.to_string())? {
+        run(git_command().arg("--git-dir").arg(target).args(["symbolic-ref", "HEAD", &head]))?;
+    }
+
+    Ok(())
 }
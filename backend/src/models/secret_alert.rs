@@ -0,0 +1,126 @@
+use rusqlite::{params, Result};
+use std::sync::{Arc, Mutex};
+use serde::Serialize;
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use crate::git::secrets::SecretFinding;
+use crate::models::db::with_retry;
+use crate::timestamps::parse_datetime;
+
+/// Предупреждение о возможном секрете в коммите
+///
+/// Хранится только место находки и имя правила, сам секрет не сохраняется.
+#[derive(Debug, Serialize, Clone)]
+pub struct SecretAlert {
+    /// Идентификатор предупреждения
+    pub id: Option<i64>,
+    /// ID репозитория
+    #[serde(skip_serializing)]
+    pub repository_id: i64,
+    /// Ссылка, push в которую принёс коммит
+    #[serde(rename = "ref")]
+    pub ref_name: String,
+    /// SHA коммита
+    pub commit: String,
+    /// Путь к файлу
+    pub path: String,
+    /// Номер строки
+    pub line: i64,
+    /// Имя сработавшего правила
+    pub rule: String,
+    /// Дата обнаружения
+    #[serde(serialize_with = "crate::timestamps::serialize_rfc3339")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl SecretAlert {
+    /// Создаёт предупреждение по находке сканера
+    ///
+    /// # Параметры
+    ///
+    /// * `repository_id` - ID репозитория
+    /// * `ref_name` - Ссылка, push в которую принёс коммит
+    /// * `finding` - Находка сканера
+    pub fn new(repository_id: i64, ref_name: &str, finding: &SecretFinding) -> Self {
+        SecretAlert {
+            id: None,
+            repository_id,
+            ref_name: ref_name.to_string(),
+            commit: finding.commit.clone(),
+            path: finding.path.clone(),
+            line: finding.line as i64,
+            rule: finding.rule.clone(),
+            created_at: None,
+        }
+    }
+
+    /// Сохраняет предупреждение, если о той же строке того же коммита ещё не предупреждали
+    ///
+    /// Один коммит может прийти в нескольких ветках; повторная находка не дублируется.
+    ///
+    /// # Параметры
+    ///
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<bool>` - true, если предупреждение создано
+    pub fn create_once(&self, conn: Arc<Mutex<Connection>>) -> Result<bool> {
+        let inserted = with_retry(&conn, |conn| {
+            conn.execute(
+                "INSERT OR IGNORE INTO secret_alerts 
+                (repository_id, ref_name, commit_sha, path, line, rule) 
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![self.repository_id, self.ref_name, self.commit, self.path, self.line, self.rule]
+            )
+        })?;
+
+        Ok(inserted > 0)
+    }
+
+    /// Возвращает предупреждения репозитория, от новых к старым
+    ///
+    /// # Параметры
+    ///
+    /// * `repository_id` - ID репозитория
+    /// * `limit` - Максимальное количество записей
+    /// * `offset` - Сколько записей пропустить
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<Vec<SecretAlert>>` - Предупреждения
+    pub fn find_by_repository(repository_id: i64, limit: i64, offset: i64, conn: Arc<Mutex<Connection>>) -> Result<Vec<SecretAlert>> {
+        let conn_guard = conn.lock().unwrap();
+
+        let mut stmt = conn_guard.prepare(
+            "SELECT id, repository_id, ref_name, commit_sha, path, line, rule, created_at 
+             FROM secret_alerts 
+             WHERE repository_id = ?1 
+             ORDER BY created_at DESC, id DESC 
+             LIMIT ?2 OFFSET ?3"
+        )?;
+
+        let alerts = stmt.query_map(params![repository_id, limit, offset], |row| {
+            let created_at_str: String = row.get(7)?;
+
+            Ok(SecretAlert {
+                id: Some(row.get(0)?),
+                repository_id: row.get(1)?,
+                ref_name: row.get(2)?,
+                commit: row.get(3)?,
+                path: row.get(4)?,
+                line: row.get(5)?,
+                rule: row.get(6)?,
+                created_at: parse_datetime(&created_at_str),
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for alert in alerts {
+            result.push(alert?);
+        }
+
+        Ok(result)
+    }
+}
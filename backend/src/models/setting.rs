@@ -0,0 +1,54 @@
+use rusqlite::{params, OptionalExtension, Result};
+use std::sync::{Arc, Mutex};
+use rusqlite::Connection;
+use crate::models::db::with_retry;
+
+/// Настройки сервера, изменяемые во время работы (таблица `server_settings`)
+pub struct Setting;
+
+impl Setting {
+    /// Читает значение настройки
+    ///
+    /// # Параметры
+    ///
+    /// * `key` - Имя настройки
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<Option<String>>` - Значение или None, если настройка не задана
+    pub fn get(key: &str, conn: Arc<Mutex<Connection>>) -> Result<Option<String>> {
+        let conn = conn.lock().unwrap();
+
+        conn.query_row(
+            "SELECT value FROM server_settings WHERE key = ?1",
+            params![key],
+            |row| row.get(0),
+        )
+        .optional()
+    }
+
+    /// Задаёт или удаляет значение настройки
+    ///
+    /// # Параметры
+    ///
+    /// * `key` - Имя настройки
+    /// * `value` - Новое значение; None удаляет настройку
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<()>` - Результат операции
+    pub fn set(key: &str, value: Option<&str>, conn: Arc<Mutex<Connection>>) -> Result<()> {
+        with_retry(&conn, |conn| match value {
+            Some(value) => conn.execute(
+                "INSERT INTO server_settings (key, value) VALUES (?1, ?2)
+                 ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+                params![key, value],
+            ),
+            None => conn.execute("DELETE FROM server_settings WHERE key = ?1", params![key]),
+        })?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+use crate::clock::Clock;
+use crate::error::AppError;
+use crate::models::db::Database;
+use crate::models::repository::Repository;
+use crate::models::user::{LockoutPolicy, LoginOutcome, User};
+
+/// Хранилище, через которое запросы находят пользователей и репозитории
+///
+/// Аутентификация, выбор репозитория по URL и кэш объявлений ссылок работают
+/// только с этим трейтом и не зависят от SQL конкретной базы. Ошибки приходят
+/// как `AppError::Database`. Единственная реализация - `Database` (SQLite);
+/// остальные модели пока принимают соединение SQLite напрямую.
+pub trait Store: Send + Sync {
+    /// Находит пользователя по ID
+    fn find_user_by_id(&self, id: i64) -> Result<Option<User>, AppError>;
+
+    /// Находит пользователя по имени
+    fn find_user_by_username(&self, username: &str) -> Result<Option<User>, AppError>;
+
+    /// Проверяет пароль пользователя и учитывает попытку входа (см. `User::authenticate`)
+    ///
+    /// # Параметры
+    ///
+    /// * `username` - Имя пользователя
+    /// * `password` - Пароль пользователя
+    /// * `ip` - Адрес клиента, если известен
+    /// * `clock` - Часы, по которым отсчитываются блокировка и время входа
+    /// * `lockout` - Настройки блокировки
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<LoginOutcome, AppError>` - Пользователь, отказ или блокировка
+    fn authenticate(
+        &self,
+        username: &str,
+        password: &str,
+        ip: Option<&str>,
+        clock: &dyn Clock,
+        lockout: LockoutPolicy,
+    ) -> Result<LoginOutcome, AppError>;
+
+    /// Находит репозиторий по полному имени `{owner}/{name}`
+    fn find_repository(&self, full_name: &str) -> Result<Option<Repository>, AppError>;
+
+    /// Находит репозитории всех владельцев с этим именем
+    fn find_repositories_by_name(&self, name: &str) -> Result<Vec<Repository>, AppError>;
+
+    /// Счётчик изменений ссылок репозитория или None, если репозитория нет
+    fn ref_generation(&self, full_name: &str) -> Result<Option<i64>, AppError>;
+
+    /// Увеличивает счётчик изменений ссылок репозитория
+    fn bump_ref_generation(&self, full_name: &str) -> Result<(), AppError>;
+}
+
+impl Store for Database {
+    fn find_user_by_id(&self, id: i64) -> Result<Option<User>, AppError> {
+        Ok(User::find_by_id(id, self.get_connection())?)
+    }
+
+    fn find_user_by_username(&self, username: &str) -> Result<Option<User>, AppError> {
+        Ok(User::find_by_username(username, self.get_connection())?)
+    }
+
+    fn authenticate(
+        &self,
+        username: &str,
+        password: &str,
+        ip: Option<&str>,
+        clock: &dyn Clock,
+        lockout: LockoutPolicy,
+    ) -> Result<LoginOutcome, AppError> {
+        Ok(User::authenticate(username, password, ip, clock, lockout, self.get_connection())?)
+    }
+
+    fn find_repository(&self, full_name: &str) -> Result<Option<Repository>, AppError> {
+        Ok(Repository::find_by_full_name(full_name, self.get_connection())?)
+    }
+
+    fn find_repositories_by_name(&self, name: &str) -> Result<Vec<Repository>, AppError> {
+        Ok(Repository::find_all_by_name(name, self.get_connection())?)
+    }
+
+    fn ref_generation(&self, full_name: &str) -> Result<Option<i64>, AppError> {
+        Ok(Repository::ref_generation(full_name, self.get_connection())?)
+    }
+
+    fn bump_ref_generation(&self, full_name: &str) -> Result<(), AppError> {
+        Ok(Repository::bump_ref_generation(full_name, self.get_connection())?)
+    }
+}
@@ -0,0 +1,153 @@
+use rusqlite::{params, Result};
+use std::sync::{Arc, Mutex};
+use serde::{Serialize, Deserialize};
+use rusqlite::Connection;
+use crate::models::db::with_retry;
+
+/// Какие уведомления пользователь получает о репозитории
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SubscriptionLevel {
+    /// Только о том, в чём пользователь участвует (по умолчанию)
+    Participating,
+    /// Обо всех пул-реквестах, комментариях и push'ах в пул-реквесты
+    Watch,
+    /// Никаких уведомлений о репозитории
+    Ignore,
+}
+
+impl SubscriptionLevel {
+    /// Значение для столбца `level`
+    fn to_str(self) -> &'static str {
+        match self {
+            SubscriptionLevel::Participating => "participating",
+            SubscriptionLevel::Watch => "watch",
+            SubscriptionLevel::Ignore => "ignore",
+        }
+    }
+
+    /// Разбирает значение столбца `level`; неизвестное значение - уровень по умолчанию
+    fn from_str(value: &str) -> Self {
+        match value {
+            "watch" => SubscriptionLevel::Watch,
+            "ignore" => SubscriptionLevel::Ignore,
+            _ => SubscriptionLevel::Participating,
+        }
+    }
+}
+
+/// Подписка пользователя на уведомления репозитория
+///
+/// Хранятся только отличия от уровня по умолчанию (`participating`).
+pub struct Subscription;
+
+impl Subscription {
+    /// Возвращает уровень подписки пользователя на репозиторий
+    ///
+    /// # Параметры
+    ///
+    /// * `user_id` - ID пользователя
+    /// * `repository_id` - ID репозитория
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<SubscriptionLevel>` - Уровень подписки
+    pub fn level(user_id: i64, repository_id: i64, conn: Arc<Mutex<Connection>>) -> Result<SubscriptionLevel> {
+        let conn_guard = conn.lock().unwrap();
+
+        let mut stmt = conn_guard.prepare(
+            "SELECT level FROM repo_subscriptions WHERE user_id = ?1 AND repository_id = ?2"
+        )?;
+        let mut rows = stmt.query(params![user_id, repository_id])?;
+
+        match rows.next()? {
+            Some(row) => Ok(SubscriptionLevel::from_str(&row.get::<_, String>(0)?)),
+            None => Ok(SubscriptionLevel::Participating),
+        }
+    }
+
+    /// Устанавливает уровень подписки пользователя на репозиторий
+    ///
+    /// # Параметры
+    ///
+    /// * `user_id` - ID пользователя
+    /// * `repository_id` - ID репозитория
+    /// * `level` - Новый уровень
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<()>` - Результат операции
+    pub fn set(user_id: i64, repository_id: i64, level: SubscriptionLevel, conn: Arc<Mutex<Connection>>) -> Result<()> {
+        with_retry(&conn, |conn| match level {
+            SubscriptionLevel::Participating => conn.execute(
+                "DELETE FROM repo_subscriptions WHERE user_id = ?1 AND repository_id = ?2",
+                params![user_id, repository_id],
+            ),
+            _ => conn.execute(
+                "INSERT INTO repo_subscriptions (user_id, repository_id, level) VALUES (?1, ?2, ?3)
+                ON CONFLICT (user_id, repository_id) DO UPDATE SET level = excluded.level",
+                params![user_id, repository_id, level.to_str()],
+            ),
+        })?;
+
+        Ok(())
+    }
+
+    /// Определяет, кому отправить уведомление о событии в репозитории
+    ///
+    /// Участники события получают уведомление, если не игнорируют репозиторий;
+    /// подписчики (`watch`) - всегда, пока у них есть доступ на чтение. Автор
+    /// события уведомление о нём не получает.
+    ///
+    /// # Параметры
+    ///
+    /// * `repository_id` - ID репозитория
+    /// * `participants` - Пользователи, непосредственно затронутые событием
+    /// * `actor_id` - Автор события
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<Vec<i64>>` - ID получателей без повторов
+    pub fn recipients(
+        repository_id: i64,
+        participants: &[i64],
+        actor_id: i64,
+        conn: Arc<Mutex<Connection>>
+    ) -> Result<Vec<i64>> {
+        let conn_guard = conn.lock().unwrap();
+
+        let mut ignoring = Vec::new();
+        let mut watching = Vec::new();
+        {
+            // Приватный репозиторий читает только владелец, поэтому остальные подписчики пропускаются
+            let mut stmt = conn_guard.prepare(
+                "SELECT s.user_id, s.level FROM repo_subscriptions s
+                JOIN repositories r ON r.id = s.repository_id
+                WHERE s.repository_id = ?1 AND (s.level = 'ignore' OR r.is_public OR r.owner_id = s.user_id)"
+            )?;
+            let rows = stmt.query_map(params![repository_id], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?;
+            for row in rows {
+                let (user_id, level) = row?;
+                match SubscriptionLevel::from_str(&level) {
+                    SubscriptionLevel::Ignore => ignoring.push(user_id),
+                    SubscriptionLevel::Watch => watching.push(user_id),
+                    SubscriptionLevel::Participating => {}
+                }
+            }
+        }
+
+        let mut recipients: Vec<i64> = Vec::new();
+        for user_id in participants.iter().chain(watching.iter()).copied() {
+            if user_id != actor_id && !ignoring.contains(&user_id) && !recipients.contains(&user_id) {
+                recipients.push(user_id);
+            }
+        }
+
+        Ok(recipients)
+    }
+}
@@ -1,8 +1,24 @@
-use rusqlite::{params, Result};
+use rusqlite::{params, params_from_iter, Result};
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use serde::{Serialize, Deserialize};
-use chrono::{DateTime, Utc, NaiveDateTime};
+use chrono::{DateTime, Utc};
 use rusqlite::Connection;
+use crate::models::db::with_retry;
+use std::path::Path;
+use crate::git::{dir_size, repo_path, REPOS_ROOT};
+use crate::models::repository::Repository;
+use crate::avatar::avatar_url;
+use crate::config::CONFIG;
+use crate::clock::Clock;
+use crate::timestamps::{parse_datetime, DB_FORMAT};
+
+/// Столбцы, из которых собирается пользователь в `User::from_row`
+const USER_COLUMNS: &str = "id, username, password, email, created_at, storage_quota_bytes, \
+    last_login_at, last_login_ip, failed_login_count, locked_until";
+
+/// Сколько адресов ищется одним запросом в `User::usernames_by_email`
+const EMAIL_LOOKUP_CHUNK: usize = 500;
 
 /// Модель пользователя системы
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -17,10 +33,88 @@ pub struct User {
     /// Электронная почта пользователя
     pub email: Option<String>,
     /// Дата создания пользователя
+    #[serde(serialize_with = "crate::timestamps::serialize_rfc3339")]
     pub created_at: Option<DateTime<Utc>>,
+    /// Квота на суммарный размер репозиториев в байтах (None - без ограничений)
+    pub storage_quota_bytes: Option<i64>,
+    /// Время последнего успешного входа
+    #[serde(skip_serializing, default)]
+    pub last_login_at: Option<DateTime<Utc>>,
+    /// IP адрес последнего успешного входа
+    #[serde(skip_serializing, default)]
+    pub last_login_ip: Option<String>,
+    /// Число неудачных попыток входа подряд
+    #[serde(skip_serializing, default)]
+    pub failed_login_count: i64,
+    /// До какого момента вход заблокирован после неудачных попыток
+    #[serde(skip_serializing, default)]
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+/// Результат проверки учётных данных
+#[derive(Debug)]
+pub enum LoginOutcome {
+    /// Учётные данные верны
+    Success(User),
+    /// Пользователь не найден или пароль неверен
+    InvalidCredentials,
+    /// Вход заблокирован после неудачных попыток до указанного момента
+    Locked(DateTime<Utc>),
+}
+
+/// Блокировка входа после неудачных попыток подряд
+#[derive(Debug, Clone, Copy)]
+pub struct LockoutPolicy {
+    /// После скольких неудачных попыток подряд вход блокируется (None - никогда)
+    pub threshold: Option<u32>,
+    /// На сколько секунд блокируется вход
+    pub duration_secs: u64,
+}
+
+impl LockoutPolicy {
+    /// Политика из GIT_HTTP_LOGIN_LOCKOUT_THRESHOLD и GIT_HTTP_LOGIN_LOCKOUT_SECS
+    pub fn configured() -> Self {
+        LockoutPolicy {
+            threshold: CONFIG.login_lockout_threshold,
+            duration_secs: CONFIG.login_lockout_secs,
+        }
+    }
+}
+
+/// Пользователь в ответах API
+///
+/// Email отдаётся только самому пользователю; остальным достаточно `avatar_url`,
+/// который считается на сервере.
+#[derive(Debug, Serialize, Clone)]
+pub struct UserView {
+    #[serde(flatten)]
+    pub user: User,
+    /// Адрес аватара
+    pub avatar_url: String,
 }
 
 impl User {
+    /// Готовит пользователя к отдаче клиенту
+    /// 
+    /// # Параметры
+    /// 
+    /// * `include_email` - Оставить email (только для самого пользователя)
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `UserView` - Пользователь с адресом аватара
+    pub fn to_view(&self, include_email: bool) -> UserView {
+        let mut user = self.clone();
+        if !include_email {
+            user.email = None;
+        }
+
+        UserView {
+            avatar_url: avatar_url(self.email.as_deref(), &self.username),
+            user,
+        }
+    }
+
     /// Создаёт нового пользователя в базе данных
     /// 
     /// # Параметры
@@ -31,14 +125,13 @@ impl User {
     /// 
     /// * `Result<i64>` - ID созданного пользователя
     pub fn create(&self, conn: Arc<Mutex<Connection>>) -> Result<i64> {
-        let conn = conn.lock().unwrap();
-        
-        conn.execute(
-            "INSERT INTO users (username, password, email) VALUES (?1, ?2, ?3)",
-            params![self.username, self.password, self.email],
-        )?;
-        
-        Ok(conn.last_insert_rowid())
+        with_retry(&conn, |conn| {
+            conn.execute(
+                "INSERT INTO users (username, password, email, storage_quota_bytes) VALUES (?1, ?2, ?3, ?4)",
+                params![self.username, self.password, self.email, self.storage_quota_bytes],
+            )?;
+            Ok(conn.last_insert_rowid())
+        })
     }
 
     /// Находит пользователя по имени пользователя
@@ -54,64 +147,371 @@ impl User {
     pub fn find_by_username(username: &str, conn: Arc<Mutex<Connection>>) -> Result<Option<User>> {
         let conn = conn.lock().map_err(|_| rusqlite::Error::InvalidQuery)?;        
 
-        let mut stmt = conn.prepare("SELECT id, username, password, email, created_at FROM users WHERE username = ?1")?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM users WHERE username = ?1", USER_COLUMNS))?;
         let mut rows = stmt.query(params![username])?;
         
         if let Some(row) = rows.next()? {
-            // Безопасное получение даты создания (с обработкой возможных ошибок формата)
-            let created_at_str: Option<String> = row.get(4).ok();
-            let created_at = if let Some(datetime_str) = created_at_str {
-                // Пробуем разные форматы даты
-                if let Ok(dt) = DateTime::parse_from_rfc3339(&datetime_str) {
-                    Some(dt.with_timezone(&Utc))
-                } else {
-                    // Если формат не RFC3339, возможно это формат SQLite (YYYY-MM-DD HH:MM:SS)
-                    let naive = NaiveDateTime::parse_from_str(&datetime_str, "%Y-%m-%d %H:%M:%S")
-                        .or_else(|_| NaiveDateTime::parse_from_str(&datetime_str, "%Y-%m-%dT%H:%M:%S"));
-                    
-                    if let Ok(ndt) = naive {
-                        Some(DateTime::<Utc>::from_naive_utc_and_offset(ndt, Utc))
-                    } else {
-                        // Если не можем разобрать дату, вернем None
-                        None
-                    }
-                }
-            } else {
-                None
-            };
-            
-            Ok(Some(User {
-                id: Some(row.get(0)?),
-                username: row.get(1)?,
-                password: row.get(2)?,
-                email: row.get(3)?,
-                created_at,
-            }))
+            Ok(Some(Self::from_row(row)?))
         } else {
             Ok(None)
         }
     }
 
-    /// Проверяет учетные данные пользователя
+    /// Находит пользователя по email без учёта регистра
+    ///
+    /// # Параметры
+    ///
+    /// * `email` - Адрес email
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<Option<User>>` - Найденный пользователь или None
+    pub fn find_by_email(email: &str, conn: Arc<Mutex<Connection>>) -> Result<Option<User>> {
+        let conn = conn.lock().map_err(|_| rusqlite::Error::InvalidQuery)?;
+
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM users WHERE lower(email) = lower(?1)", USER_COLUMNS))?;
+        let mut rows = stmt.query(params![email])?;
+
+        if let Some(row) = rows.next()? {
+            Ok(Some(Self::from_row(row)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Находит пользователя по ID
+    /// 
+    /// # Параметры
+    /// 
+    /// * `id` - ID пользователя
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<Option<User>>` - Найденный пользователь или None
+    pub fn find_by_id(id: i64, conn: Arc<Mutex<Connection>>) -> Result<Option<User>> {
+        let conn = conn.lock().map_err(|_| rusqlite::Error::InvalidQuery)?;
+
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM users WHERE id = ?1", USER_COLUMNS))?;
+        let mut rows = stmt.query(params![id])?;
+
+        if let Some(row) = rows.next()? {
+            Ok(Some(Self::from_row(row)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Собирает пользователя из строки выборки со столбцами `USER_COLUMNS`
+    fn from_row(row: &rusqlite::Row) -> Result<User> {
+        Ok(User {
+            id: Some(row.get(0)?),
+            username: row.get(1)?,
+            password: row.get(2)?,
+            email: row.get(3)?,
+            created_at: row.get::<_, String>(4).ok().as_deref().and_then(parse_datetime),
+            storage_quota_bytes: row.get(5)?,
+            last_login_at: row.get::<_, Option<String>>(6)?.as_deref().and_then(parse_datetime),
+            last_login_ip: row.get(7)?,
+            failed_login_count: row.get(8)?,
+            locked_until: row.get::<_, Option<String>>(9)?.as_deref().and_then(parse_datetime),
+        })
+    }
+
+    /// Возвращает всех пользователей постранично, в порядке регистрации
+    /// 
+    /// # Параметры
+    /// 
+    /// * `limit` - Максимальное количество записей
+    /// * `offset` - Сколько записей пропустить
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<Vec<User>>` - Пользователи
+    pub fn find_all(limit: i64, offset: i64, conn: Arc<Mutex<Connection>>) -> Result<Vec<User>> {
+        let conn = conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM users ORDER BY id LIMIT ?1 OFFSET ?2", USER_COLUMNS))?;
+        let users = stmt.query_map(params![limit, offset], Self::from_row)?;
+        users.collect()
+    }
+
+    /// Находит имена пользователей по email без учёта регистра
+    /// 
+    /// # Параметры
+    /// 
+    /// * `emails` - Адреса email
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<HashMap<String, String>>` - Email в нижнем регистре и имя пользователя;
+    ///   незарегистрированных адресов в словаре нет
+    pub fn usernames_by_email(emails: &[String], conn: Arc<Mutex<Connection>>) -> Result<HashMap<String, String>> {
+        if emails.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let conn = conn.lock().unwrap();
+
+        // Порциями, чтобы не упереться в ограничение SQLite на число параметров
+        let mut result = HashMap::new();
+        for chunk in emails.chunks(EMAIL_LOOKUP_CHUNK) {
+            let placeholders = vec!["?"; chunk.len()].join(", ");
+            let mut stmt = conn.prepare(&format!(
+                "SELECT lower(email), username FROM users WHERE lower(email) IN ({})",
+                placeholders
+            ))?;
+            let lowered = chunk.iter().map(|email| email.to_lowercase());
+            let rows = stmt.query_map(params_from_iter(lowered), |row| Ok((row.get(0)?, row.get(1)?)))?;
+            for row in rows {
+                let (email, username) = row?;
+                result.insert(email, username);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Считает, сколько места на диске занимают все репозитории пользователя
+    /// 
+    /// # Параметры
+    /// 
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<u64>` - Суммарный размер в байтах
+    pub fn storage_usage(&self, conn: Arc<Mutex<Connection>>) -> Result<u64> {
+        let owner_id = match self.id {
+            Some(id) => id,
+            None => return Ok(0),
+        };
+
+        let repos = Repository::find_by_owner(owner_id, conn)?;
+        Ok(repos
+            .iter()
+            .filter_map(|repo| repo_path(Path::new(REPOS_ROOT), &repo.full_name()).ok())
+            .map(|path| dir_size(&path))
+            .sum())
+    }
+
+    /// Проверяет, исчерпал ли пользователь квоту на хранение
+    /// 
+    /// # Параметры
+    /// 
+    /// * `conn` - Соединение с базой данных
+    /// 
+    /// # Возвращает
+    /// 
+    /// * `Result<bool>` - true, если использование достигло квоты
+    pub fn is_over_quota(&self, conn: Arc<Mutex<Connection>>) -> Result<bool> {
+        match self.storage_quota_bytes {
+            Some(quota) => Ok(self.storage_usage(conn)? >= quota.max(0) as u64),
+            None => Ok(false),
+        }
+    }
+
+    /// Проверяет учетные данные пользователя и учитывает попытку входа
+    /// 
+    /// Неверный пароль увеличивает счётчик неудачных попыток, а после
+    /// `lockout.threshold` попыток подряд вход блокируется на
+    /// `lockout.duration_secs` секунд, даже с верным паролем. Успешный вход
+    /// сбрасывает счётчик и запоминает время и адрес входа.
     /// 
     /// # Параметры
     /// 
     /// * `username` - Имя пользователя
     /// * `password` - Пароль пользователя
+    /// * `ip` - Адрес клиента, если известен
+    /// * `clock` - Часы, по которым отсчитываются блокировка и время входа
+    /// * `lockout` - Настройки блокировки, на сервере `LockoutPolicy::configured()`
     /// * `conn` - Соединение с базой данных
     /// 
     /// # Возвращает
     /// 
-    /// * `Result<Option<User>>` - Пользователь, если учетные данные верны
-    pub fn authenticate(username: &str, password: &str, conn: Arc<Mutex<Connection>>) -> Result<Option<User>> {
-        let user = Self::find_by_username(username, conn)?;
-        
-        if let Some(user) = user {
-            if user.password == password {
-                return Ok(Some(user));
+    /// * `Result<LoginOutcome>` - Пользователь, отказ или блокировка
+    pub fn authenticate(
+        username: &str,
+        password: &str,
+        ip: Option<&str>,
+        clock: &dyn Clock,
+        lockout: LockoutPolicy,
+        conn: Arc<Mutex<Connection>>,
+    ) -> Result<LoginOutcome> {
+        let user = match Self::find_by_username(username, conn.clone())? {
+            Some(user) => user,
+            None => return Ok(LoginOutcome::InvalidCredentials),
+        };
+
+        let now = clock.now();
+        if let Some(locked_until) = user.locked_until.filter(|until| *until > now) {
+            return Ok(LoginOutcome::Locked(locked_until));
+        }
+
+        if user.password != password {
+            let threshold = lockout.threshold.map(i64::from);
+            let failures = user.failed_login_count + 1;
+            if threshold.is_some_and(|threshold| failures >= threshold) {
+                // Счётчик сбрасывается, чтобы после блокировки снова было N попыток
+                let locked_until = now + chrono::Duration::seconds(lockout.duration_secs as i64);
+                with_retry(&conn, |conn| conn.execute(
+                    "UPDATE users SET failed_login_count = 0, locked_until = ?1 WHERE id = ?2",
+                    params![locked_until.format(DB_FORMAT).to_string(), user.id],
+                ))?;
+            } else {
+                with_retry(&conn, |conn| conn.execute(
+                    "UPDATE users SET failed_login_count = failed_login_count + 1 WHERE id = ?1",
+                    params![user.id],
+                ))?;
             }
+            return Ok(LoginOutcome::InvalidCredentials);
         }
-        
-        Ok(None)
+
+        // Basic-аутентификация проверяется на каждом git запросе, поэтому время входа
+        // обновляется не чаще раза в минуту с одного адреса
+        let login_at = now.format(DB_FORMAT).to_string();
+        let refresh_before = (now - chrono::Duration::minutes(1)).format(DB_FORMAT).to_string();
+        with_retry(&conn, |conn| conn.execute(
+            "UPDATE users SET last_login_at = ?3, last_login_ip = ?1,
+                failed_login_count = 0, locked_until = NULL
+             WHERE id = ?2 AND (failed_login_count > 0 OR locked_until IS NOT NULL
+                OR last_login_at IS NULL OR last_login_at < ?4
+                OR last_login_ip IS NOT ?1)",
+            params![ip, user.id, login_at, refresh_before],
+        ))?;
+
+        Ok(LoginOutcome::Success(user))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use crate::clock::MockClock;
+    use crate::test_support::{create_user, test_db, PASSWORD};
+
+    const LOCKOUT: LockoutPolicy = LockoutPolicy { threshold: Some(3), duration_secs: 900 };
+
+    fn reload(user: &User, db: &crate::models::db::Database) -> User {
+        User::find_by_username(&user.username, db.get_connection()).unwrap().unwrap()
+    }
+
+    #[test]
+    fn lockout_expires_after_the_configured_time() {
+        let db = test_db();
+        let user = create_user(&db, "locked");
+        let start = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let clock = MockClock::new(start);
+        let login = |password: &str| {
+            User::authenticate(&user.username, password, Some("10.0.0.1"), &clock, LOCKOUT, db.get_connection()).unwrap()
+        };
+
+        assert!(matches!(login("wrong"), LoginOutcome::InvalidCredentials));
+        assert!(matches!(login("wrong"), LoginOutcome::InvalidCredentials));
+        assert_eq!(reload(&user, &db).failed_login_count, 2);
+        assert!(matches!(login("wrong"), LoginOutcome::InvalidCredentials));
+
+        // Блокировка действует и для верного пароля
+        let locked_until = start + Duration::seconds(900);
+        assert!(matches!(login(PASSWORD), LoginOutcome::Locked(until) if until == locked_until));
+        clock.advance(Duration::seconds(899));
+        assert!(matches!(login(PASSWORD), LoginOutcome::Locked(until) if until == locked_until));
+
+        clock.advance(Duration::seconds(1));
+        assert!(matches!(login(PASSWORD), LoginOutcome::Success(_)));
+        let user = reload(&user, &db);
+        assert_eq!(user.failed_login_count, 0);
+        assert_eq!(user.locked_until, None);
+    }
+
+    #[test]
+    fn successful_login_resets_the_failure_count() {
+        let db = test_db();
+        let user = create_user(&db, "reset");
+        let clock = MockClock::new(Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap());
+        let login = |password: &str| {
+            User::authenticate(&user.username, password, None, &clock, LOCKOUT, db.get_connection()).unwrap()
+        };
+
+        for _ in 0..2 {
+            assert!(matches!(login("wrong"), LoginOutcome::InvalidCredentials));
+        }
+        assert!(matches!(login(PASSWORD), LoginOutcome::Success(_)));
+        // Счётчик начался заново, поэтому две ошибки ещё не блокируют
+        for _ in 0..2 {
+            assert!(matches!(login("wrong"), LoginOutcome::InvalidCredentials));
+        }
+        assert!(matches!(login(PASSWORD), LoginOutcome::Success(_)));
+    }
+
+    #[test]
+    fn no_lockout_without_a_threshold() {
+        let db = test_db();
+        let user = create_user(&db, "open");
+        let clock = MockClock::new(Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap());
+        let policy = LockoutPolicy { threshold: None, duration_secs: 900 };
+
+        for _ in 0..10 {
+            let outcome = User::authenticate(&user.username, "wrong", None, &clock, policy, db.get_connection()).unwrap();
+            assert!(matches!(outcome, LoginOutcome::InvalidCredentials));
+        }
+        let outcome = User::authenticate(&user.username, PASSWORD, None, &clock, policy, db.get_connection()).unwrap();
+        assert!(matches!(outcome, LoginOutcome::Success(_)));
+    }
+
+    #[test]
+    fn last_login_is_written_at_most_once_a_minute_per_address() {
+        let db = test_db();
+        let user = create_user(&db, "seen");
+        let start = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let clock = MockClock::new(start);
+        let login = |ip: &str| {
+            let outcome = User::authenticate(&user.username, PASSWORD, Some(ip), &clock, LOCKOUT, db.get_connection()).unwrap();
+            assert!(matches!(outcome, LoginOutcome::Success(_)));
+            let user = reload(&user, &db);
+            (user.last_login_at, user.last_login_ip)
+        };
+
+        assert_eq!(login("10.0.0.1"), (Some(start), Some("10.0.0.1".to_string())));
+
+        clock.advance(Duration::seconds(30));
+        assert_eq!(login("10.0.0.1"), (Some(start), Some("10.0.0.1".to_string())));
+
+        // Другой адрес записывается сразу
+        assert_eq!(login("10.0.0.2"), (Some(start + Duration::seconds(30)), Some("10.0.0.2".to_string())));
+
+        clock.advance(Duration::seconds(61));
+        assert_eq!(login("10.0.0.2"), (Some(start + Duration::seconds(91)), Some("10.0.0.2".to_string())));
     }
-} 
+
+    #[test]
+    fn attempts_are_allowed_again_once_the_lockout_window_has_passed() {
+        let db = test_db();
+        let user = create_user(&db, "window");
+        let start = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let clock = MockClock::new(start);
+        let login = |password: &str| {
+            User::authenticate(&user.username, password, None, &clock, LOCKOUT, db.get_connection()).unwrap()
+        };
+
+        for _ in 0..3 {
+            assert!(matches!(login("wrong"), LoginOutcome::InvalidCredentials));
+        }
+        assert!(matches!(login("wrong"), LoginOutcome::Locked(_)));
+
+        // После окна неверный пароль снова проверяется, а не отклоняется блокировкой,
+        // и до новой блокировки снова есть `threshold` попыток
+        clock.advance(Duration::seconds(901));
+        for _ in 0..2 {
+            assert!(matches!(login("wrong"), LoginOutcome::InvalidCredentials));
+        }
+        assert_eq!(reload(&user, &db).failed_login_count, 2);
+        assert!(matches!(login("wrong"), LoginOutcome::InvalidCredentials));
+        let locked_until = start + Duration::seconds(901 + 900);
+        assert!(matches!(login(PASSWORD), LoginOutcome::Locked(until) if until == locked_until));
+    }
+}
@@ -0,0 +1,407 @@
+use rusqlite::{params, Result};
+use std::sync::{Arc, Mutex};
+use serde::Serialize;
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use crate::models::db::with_retry;
+use crate::timestamps::parse_datetime;
+
+/// Столбцы, из которых собирается webhook в `Webhook::from_row`
+const WEBHOOK_COLUMNS: &str = "id, repository_id, url, events, active, created_at, secret";
+/// Столбцы, из которых собирается доставка в `WebhookDelivery::from_row`
+const DELIVERY_COLUMNS: &str = "id, hook_id, event, payload, response_status, response_body, duration_ms, created_at, guid";
+/// Столбцы, из которых собирается webhook сервера в `ServerWebhook::from_row`
+const SERVER_WEBHOOK_COLUMNS: &str = "id, url, events, active, created_at, secret";
+
+/// Webhook репозитория: URL, на который отправляются события
+#[derive(Debug, Serialize, Clone)]
+pub struct Webhook {
+    /// Идентификатор webhook
+    pub id: Option<i64>,
+    /// ID репозитория
+    #[serde(skip_serializing)]
+    pub repository_id: i64,
+    /// URL получателя
+    pub url: String,
+    /// События, на которые подписан webhook
+    pub events: Vec<String>,
+    /// Отправлять ли события
+    pub active: bool,
+    /// Дата создания
+    #[serde(serialize_with = "crate::timestamps::serialize_rfc3339")]
+    pub created_at: Option<DateTime<Utc>>,
+    /// Секрет для HMAC подписи доставок; наружу не отдаётся
+    #[serde(skip_serializing)]
+    pub secret: Option<String>,
+}
+
+/// Одна попытка доставки события
+#[derive(Debug, Serialize, Clone)]
+pub struct WebhookDelivery {
+    /// Идентификатор доставки
+    pub id: Option<i64>,
+    /// ID webhook
+    pub hook_id: i64,
+    /// Имя события
+    pub event: String,
+    /// Отправленное JSON тело
+    pub payload: String,
+    /// HTTP статус ответа; None - ответа не было
+    pub response_status: Option<i64>,
+    /// Тело ответа (обрезанное) или описание ошибки соединения
+    pub response_body: Option<String>,
+    /// Длительность попытки в миллисекундах
+    pub duration_ms: i64,
+    /// Дата попытки
+    #[serde(serialize_with = "crate::timestamps::serialize_rfc3339")]
+    pub created_at: Option<DateTime<Utc>>,
+    /// Идентификатор события (заголовок `X-Delivery-Id`), общий для всех попыток его доставки
+    pub guid: Option<String>,
+}
+
+impl Webhook {
+    /// Подписан ли webhook на событие
+    pub fn subscribed_to(&self, event: &str) -> bool {
+        self.events.iter().any(|e| e == event)
+    }
+
+    /// Создаёт webhook в базе данных
+    ///
+    /// # Параметры
+    ///
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<i64>` - ID созданного webhook
+    pub fn create(&self, conn: Arc<Mutex<Connection>>) -> Result<i64> {
+        with_retry(&conn, |conn| {
+            conn.execute(
+                "INSERT INTO webhooks (repository_id, url, events, active, secret) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![self.repository_id, self.url, self.events.join(","), self.active, self.secret],
+            )?;
+            Ok(conn.last_insert_rowid())
+        })
+    }
+
+    /// Находит webhook репозитория по ID
+    ///
+    /// # Параметры
+    ///
+    /// * `repository_id` - ID репозитория
+    /// * `id` - ID webhook
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<Option<Webhook>>` - Webhook или None, если его нет в этом репозитории
+    pub fn find_by_id(repository_id: i64, id: i64, conn: Arc<Mutex<Connection>>) -> Result<Option<Webhook>> {
+        let conn_guard = conn.lock().unwrap();
+
+        let mut stmt = conn_guard.prepare(&format!(
+            "SELECT {} FROM webhooks WHERE id = ?1 AND repository_id = ?2",
+            WEBHOOK_COLUMNS
+        ))?;
+        let mut rows = stmt.query(params![id, repository_id])?;
+
+        match rows.next()? {
+            Some(row) => Ok(Some(Self::from_row(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Возвращает все webhook'и репозитория
+    ///
+    /// # Параметры
+    ///
+    /// * `repository_id` - ID репозитория
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<Vec<Webhook>>` - Список webhook'ов
+    pub fn find_by_repository(repository_id: i64, conn: Arc<Mutex<Connection>>) -> Result<Vec<Webhook>> {
+        let conn_guard = conn.lock().unwrap();
+
+        let mut stmt = conn_guard.prepare(&format!(
+            "SELECT {} FROM webhooks WHERE repository_id = ?1 ORDER BY id",
+            WEBHOOK_COLUMNS
+        ))?;
+        let hooks = stmt.query_map(params![repository_id], Self::from_row)?;
+
+        let mut result = Vec::new();
+        for hook in hooks {
+            result.push(hook?);
+        }
+
+        Ok(result)
+    }
+
+    /// Удаляет webhook вместе с журналом его доставок
+    ///
+    /// # Параметры
+    ///
+    /// * `id` - ID webhook
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<()>` - Результат операции
+    pub fn delete(id: i64, conn: Arc<Mutex<Connection>>) -> Result<()> {
+        with_retry(&conn, |conn| {
+            conn.execute("DELETE FROM webhook_deliveries WHERE hook_id = ?1", params![id])?;
+            conn.execute("DELETE FROM webhooks WHERE id = ?1", params![id])
+        })?;
+
+        Ok(())
+    }
+
+    /// Собирает webhook из строки выборки со столбцами `WEBHOOK_COLUMNS`
+    fn from_row(row: &rusqlite::Row) -> Result<Webhook> {
+        let events: String = row.get(3)?;
+        let created_at_str: String = row.get(5)?;
+
+        Ok(Webhook {
+            id: Some(row.get(0)?),
+            repository_id: row.get(1)?,
+            url: row.get(2)?,
+            events: events.split(',').filter(|e| !e.is_empty()).map(|e| e.to_string()).collect(),
+            active: row.get(4)?,
+            created_at: parse_datetime(&created_at_str),
+            secret: row.get(6)?,
+        })
+    }
+}
+
+/// Webhook сервера: получает события о репозиториях (создание, удаление)
+///
+/// Не привязан к репозиторию, потому что при этих событиях репозитория ещё
+/// или уже нет. Управляется администратором.
+#[derive(Debug, Serialize, Clone)]
+pub struct ServerWebhook {
+    /// Идентификатор webhook
+    pub id: Option<i64>,
+    /// URL получателя
+    pub url: String,
+    /// События, на которые подписан webhook
+    pub events: Vec<String>,
+    /// Отправлять ли события
+    pub active: bool,
+    /// Дата создания
+    #[serde(serialize_with = "crate::timestamps::serialize_rfc3339")]
+    pub created_at: Option<DateTime<Utc>>,
+    /// Секрет для HMAC подписи доставок; наружу не отдаётся
+    #[serde(skip_serializing)]
+    pub secret: Option<String>,
+}
+
+impl ServerWebhook {
+    /// Подписан ли webhook на событие
+    pub fn subscribed_to(&self, event: &str) -> bool {
+        self.events.iter().any(|e| e == event)
+    }
+
+    /// Создаёт webhook сервера в базе данных
+    ///
+    /// # Параметры
+    ///
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<i64>` - ID созданного webhook
+    pub fn create(&self, conn: Arc<Mutex<Connection>>) -> Result<i64> {
+        with_retry(&conn, |conn| {
+            conn.execute(
+                "INSERT INTO server_webhooks (url, events, active, secret) VALUES (?1, ?2, ?3, ?4)",
+                params![self.url, self.events.join(","), self.active, self.secret],
+            )?;
+            Ok(conn.last_insert_rowid())
+        })
+    }
+
+    /// Находит webhook сервера по ID
+    ///
+    /// # Параметры
+    ///
+    /// * `id` - ID webhook
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<Option<ServerWebhook>>` - Webhook или None, если его нет
+    pub fn find_by_id(id: i64, conn: Arc<Mutex<Connection>>) -> Result<Option<ServerWebhook>> {
+        let conn_guard = conn.lock().unwrap();
+
+        let mut stmt = conn_guard.prepare(&format!(
+            "SELECT {} FROM server_webhooks WHERE id = ?1",
+            SERVER_WEBHOOK_COLUMNS
+        ))?;
+        let mut rows = stmt.query(params![id])?;
+
+        match rows.next()? {
+            Some(row) => Ok(Some(Self::from_row(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Возвращает все webhook'и сервера
+    ///
+    /// # Параметры
+    ///
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<Vec<ServerWebhook>>` - Список webhook'ов
+    pub fn find_all(conn: Arc<Mutex<Connection>>) -> Result<Vec<ServerWebhook>> {
+        let conn_guard = conn.lock().unwrap();
+
+        let mut stmt = conn_guard.prepare(&format!(
+            "SELECT {} FROM server_webhooks ORDER BY id",
+            SERVER_WEBHOOK_COLUMNS
+        ))?;
+        let hooks = stmt.query_map([], Self::from_row)?;
+
+        let mut result = Vec::new();
+        for hook in hooks {
+            result.push(hook?);
+        }
+
+        Ok(result)
+    }
+
+    /// Удаляет webhook сервера
+    ///
+    /// # Параметры
+    ///
+    /// * `id` - ID webhook
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<bool>` - true, если webhook был удалён
+    pub fn delete(id: i64, conn: Arc<Mutex<Connection>>) -> Result<bool> {
+        let deleted = with_retry(&conn, |conn| conn.execute("DELETE FROM server_webhooks WHERE id = ?1", params![id]))?;
+
+        Ok(deleted > 0)
+    }
+
+    /// Собирает webhook из строки выборки со столбцами `SERVER_WEBHOOK_COLUMNS`
+    fn from_row(row: &rusqlite::Row) -> Result<ServerWebhook> {
+        let events: String = row.get(2)?;
+        let created_at_str: String = row.get(4)?;
+
+        Ok(ServerWebhook {
+            id: Some(row.get(0)?),
+            url: row.get(1)?,
+            events: events.split(',').filter(|e| !e.is_empty()).map(|e| e.to_string()).collect(),
+            active: row.get(3)?,
+            created_at: parse_datetime(&created_at_str),
+            secret: row.get(5)?,
+        })
+    }
+}
+
+impl WebhookDelivery {
+    /// Записывает попытку доставки
+    ///
+    /// # Параметры
+    ///
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<i64>` - ID записи
+    pub fn create(&self, conn: Arc<Mutex<Connection>>) -> Result<i64> {
+        with_retry(&conn, |conn| {
+            conn.execute(
+                "INSERT INTO webhook_deliveries
+                (hook_id, event, payload, response_status, response_body, duration_ms, guid)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    self.hook_id,
+                    self.event,
+                    self.payload,
+                    self.response_status,
+                    self.response_body,
+                    self.duration_ms,
+                    self.guid
+                ]
+            )?;
+            Ok(conn.last_insert_rowid())
+        })
+    }
+
+    /// Находит доставку webhook по ID
+    ///
+    /// # Параметры
+    ///
+    /// * `hook_id` - ID webhook
+    /// * `id` - ID доставки
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<Option<WebhookDelivery>>` - Доставка или None, если она не относится к webhook
+    pub fn find_by_id(hook_id: i64, id: i64, conn: Arc<Mutex<Connection>>) -> Result<Option<WebhookDelivery>> {
+        let conn_guard = conn.lock().unwrap();
+
+        let mut stmt = conn_guard.prepare(&format!(
+            "SELECT {} FROM webhook_deliveries WHERE id = ?1 AND hook_id = ?2",
+            DELIVERY_COLUMNS
+        ))?;
+        let mut rows = stmt.query(params![id, hook_id])?;
+
+        match rows.next()? {
+            Some(row) => Ok(Some(Self::from_row(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Возвращает доставки webhook, от новых к старым
+    ///
+    /// # Параметры
+    ///
+    /// * `hook_id` - ID webhook
+    /// * `limit` - Максимальное количество записей
+    /// * `offset` - Сколько записей пропустить
+    /// * `conn` - Соединение с базой данных
+    ///
+    /// # Возвращает
+    ///
+    /// * `Result<Vec<WebhookDelivery>>` - Доставки
+    pub fn find_by_hook(hook_id: i64, limit: i64, offset: i64, conn: Arc<Mutex<Connection>>) -> Result<Vec<WebhookDelivery>> {
+        let conn_guard = conn.lock().unwrap();
+
+        let mut stmt = conn_guard.prepare(&format!(
+            "SELECT {} FROM webhook_deliveries WHERE hook_id = ?1 ORDER BY id DESC LIMIT ?2 OFFSET ?3",
+            DELIVERY_COLUMNS
+        ))?;
+        let deliveries = stmt.query_map(params![hook_id, limit, offset], Self::from_row)?;
+
+        let mut result = Vec::new();
+        for delivery in deliveries {
+            result.push(delivery?);
+        }
+
+        Ok(result)
+    }
+
+    /// Собирает доставку из строки выборки со столбцами `DELIVERY_COLUMNS`
+    fn from_row(row: &rusqlite::Row) -> Result<WebhookDelivery> {
+        let created_at_str: String = row.get(7)?;
+
+        Ok(WebhookDelivery {
+            id: Some(row.get(0)?),
+            hook_id: row.get(1)?,
+            event: row.get(2)?,
+            payload: row.get(3)?,
+            response_status: row.get(4)?,
+            response_body: row.get(5)?,
+            duration_ms: row.get(6)?,
+            created_at: parse_datetime(&created_at_str),
+            guid: row.get(8)?,
+        })
+    }
+}
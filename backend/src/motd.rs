@@ -0,0 +1,159 @@
+use std::io::Cursor;
+use std::sync::RwLock;
+use lazy_static::lazy_static;
+use log::error;
+use crate::git::pktline::{read_pkt, write_pkt, Packet};
+use crate::models::db::Database;
+use crate::models::setting::Setting;
+
+/// Ключ сообщения в таблице `server_settings`
+const SETTING_KEY: &str = "motd";
+/// Максимальное число строк сообщения
+pub const MAX_LINES: usize = 10;
+/// Максимальная длина сообщения в символах
+pub const MAX_CHARS: usize = 1000;
+/// Номер side-band канала для сообщений о ходе работы (`remote: ...`)
+const SIDEBAND_PROGRESS: u8 = 2;
+
+lazy_static! {
+    /// Текущее сообщение; читается на каждый fetch, поэтому хранится в памяти
+    static ref MOTD: RwLock<Option<String>> = RwLock::new(None);
+}
+
+/// Загружает сообщение из базы данных при запуске сервера
+///
+/// # Параметры
+///
+/// * `db` - База данных
+pub fn load(db: &Database) {
+    match Setting::get(SETTING_KEY, db.get_connection()) {
+        Ok(message) => *MOTD.write().unwrap_or_else(|e| e.into_inner()) = message,
+        Err(e) => error!("Failed to load message of the day: {}", e),
+    }
+}
+
+/// Текущее сообщение или None, если оно не задано
+pub fn current() -> Option<String> {
+    MOTD.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Сохраняет сообщение и сразу начинает его показывать
+///
+/// # Параметры
+///
+/// * `message` - Проверенное `normalize` сообщение; None убирает его
+/// * `db` - База данных
+///
+/// # Возвращает
+///
+/// * `rusqlite::Result<()>` - Результат сохранения
+pub fn set(message: Option<String>, db: &Database) -> rusqlite::Result<()> {
+    Setting::set(SETTING_KEY, message.as_deref(), db.get_connection())?;
+    *MOTD.write().unwrap_or_else(|e| e.into_inner()) = message;
+    Ok(())
+}
+
+/// Приводит сообщение к виду, в котором оно выводится git клиенту
+///
+/// Переводы строк `\r\n` заменяются на `\n`, пробелы в конце строк и пустые
+/// строки по краям убираются. Управляющие символы запрещены: они попали бы
+/// прямо в терминал клиента.
+///
+/// # Параметры
+///
+/// * `message` - Сообщение от администратора
+///
+/// # Возвращает
+///
+/// * `Result<Option<String>, String>` - Сообщение (None, если оно пустое) или описание ошибки
+pub fn normalize(message: &str) -> Result<Option<String>, String> {
+    let message = message.replace("\r\n", "\n");
+    if message.chars().any(|c| c.is_control() && c != '\n') {
+        return Err("Message of the day must not contain control characters".to_string());
+    }
+
+    let lines: Vec<&str> = message.trim_matches('\n').lines().map(str::trim_end).collect();
+    let message = lines.join("\n");
+    if message.trim().is_empty() {
+        return Ok(None);
+    }
+    if lines.len() > MAX_LINES || message.chars().count() > MAX_CHARS {
+        return Err(format!(
+            "Message of the day must be at most {} lines and {} characters",
+            MAX_LINES, MAX_CHARS
+        ));
+    }
+
+    Ok(Some(message))
+}
+
+/// Сообщение в виде side-band пакетов канала 2, по одному на строку
+fn progress_packets(message: &str) -> Option<Vec<u8>> {
+    let mut packets = Vec::new();
+    for line in message.lines() {
+        let mut payload = vec![SIDEBAND_PROGRESS];
+        payload.extend_from_slice(format!("{}\n", line).as_bytes());
+        if let Err(e) = write_pkt(&mut packets, &payload) {
+            error!("Failed to encode message of the day: {}", e);
+            return None;
+        }
+    }
+    Some(packets)
+}
+
+/// Вставляет сообщение в ответ upload-pack перед первым side-band пакетом
+///
+/// `info/refs` для этого не подходит: из объявления ссылок git ничего не
+/// выводит. Side-band данные начинаются вместе с packfile (после `NAK`/`ACK`
+/// в protocol v0, после `packfile` в v2), и сообщения канала 2 git печатает
+/// как `remote: ...`. Ответы без packfile (согласование, `ls-refs`) и клиенты
+/// без side-band остаются без изменений.
+///
+/// # Параметры
+///
+/// * `output` - Ответ git-upload-pack
+///
+/// # Возвращает
+///
+/// * `Option<Vec<u8>>` - Ответ с сообщением или None, если вставлять некуда
+pub fn insert_into_upload_pack(output: &[u8]) -> Option<Vec<u8>> {
+    let message = current()?;
+
+    let mut reader = Cursor::new(output);
+    let offset = loop {
+        let start = reader.position() as usize;
+        match read_pkt(&mut reader) {
+            Ok(Some(Packet::Data(payload))) if matches!(payload.first(), Some(1) | Some(2)) => break start,
+            Ok(Some(_)) => continue,
+            // Конец ответа или сырой pack без side-band
+            Ok(None) | Err(_) => return None,
+        }
+    };
+
+    let packets = progress_packets(&message)?;
+    let mut response = Vec::with_capacity(output.len() + packets.len());
+    response.extend_from_slice(&output[..offset]);
+    response.extend_from_slice(&packets);
+    response.extend_from_slice(&output[offset..]);
+    Some(response)
+}
+
+/// Добавляет сообщение в начало ответа receive-pack, если клиент просил side-band
+///
+/// С side-band весь ответ receive-pack мультиплексирован, поэтому пакеты канала 2
+/// можно поставить первыми.
+///
+/// # Параметры
+///
+/// * `output` - Ответ git-receive-pack
+///
+/// # Возвращает
+///
+/// * `Option<Vec<u8>>` - Ответ с сообщением или None, если сообщение не задано
+pub fn prepend_to_receive_pack(output: &[u8]) -> Option<Vec<u8>> {
+    let message = current()?;
+
+    let mut response = progress_packets(&message)?;
+    response.extend_from_slice(output);
+    Some(response)
+}
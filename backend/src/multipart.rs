@@ -0,0 +1,104 @@
+/// Часть формы
+pub struct Part<'a> {
+    /// Имя поля (`name` в `Content-Disposition`)
+    pub name: String,
+    /// Имя загруженного файла, если часть - файл
+    pub filename: Option<String>,
+    /// Содержимое части
+    pub data: &'a [u8],
+}
+
+impl Part<'_> {
+    /// Содержимое текстового поля
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(self.data).to_string()
+    }
+}
+
+/// Извлекает границу частей из заголовка `Content-Type: multipart/form-data; boundary=...`
+///
+/// # Параметры
+///
+/// * `content_type` - Значение заголовка `Content-Type`
+///
+/// # Возвращает
+///
+/// * `Option<String>` - Граница или None, если тело не `multipart/form-data`
+pub fn boundary(content_type: &str) -> Option<String> {
+    let mut params = content_type.split(';');
+    if !params.next()?.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+
+    params
+        .filter_map(|param| param.trim().split_once('='))
+        .find(|(key, _)| key.eq_ignore_ascii_case("boundary"))
+        .map(|(_, value)| value.trim_matches('"').to_string())
+        .filter(|boundary| !boundary.is_empty())
+}
+
+/// Разбирает тело `multipart/form-data` на части
+///
+/// Тело уже целиком прочитано в память: его размер ограничивает `web::PayloadConfig`.
+///
+/// # Параметры
+///
+/// * `body` - Тело запроса
+/// * `boundary` - Граница из `Content-Type`
+///
+/// # Возвращает
+///
+/// * `Result<Vec<Part>, String>` - Части формы или описание ошибки
+pub fn parse<'a>(body: &'a [u8], boundary: &str) -> Result<Vec<Part<'a>>, String> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let separator = format!("\r\n--{}", boundary).into_bytes();
+
+    let start = find(body, &delimiter).ok_or("Multipart body has no parts")?;
+    let mut rest = &body[start + delimiter.len()..];
+    let mut parts = Vec::new();
+
+    loop {
+        // После границы идёт либо `--` (конец формы), либо перевод строки и заголовки части
+        if rest.starts_with(b"--") {
+            return Ok(parts);
+        }
+        rest = rest.strip_prefix(b"\r\n").ok_or("Malformed multipart boundary")?;
+
+        let headers_end = find(rest, b"\r\n\r\n").ok_or("Malformed multipart part headers")?;
+        let headers = String::from_utf8_lossy(&rest[..headers_end]);
+        rest = &rest[headers_end + 4..];
+
+        let data_end = find(rest, &separator).ok_or("Multipart body is truncated")?;
+        let (name, filename) = content_disposition(&headers).ok_or("Multipart part has no field name")?;
+        parts.push(Part { name, filename, data: &rest[..data_end] });
+        rest = &rest[data_end + separator.len()..];
+    }
+}
+
+/// Имя поля и имя файла из заголовка `Content-Disposition: form-data; name="..."; filename="..."`
+fn content_disposition(headers: &str) -> Option<(String, Option<String>)> {
+    let value = headers.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim().eq_ignore_ascii_case("content-disposition").then_some(value)
+    })?;
+
+    let mut name = None;
+    let mut filename = None;
+    for param in value.split(';').skip(1) {
+        if let Some((key, value)) = param.trim().split_once('=') {
+            let value = value.trim().trim_matches('"').to_string();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "name" => name = Some(value),
+                "filename" => filename = Some(value),
+                _ => {}
+            }
+        }
+    }
+
+    Some((name?, filename))
+}
+
+/// Позиция первого вхождения `needle` в `haystack`
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
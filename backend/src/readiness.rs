@@ -0,0 +1,49 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header;
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpResponse};
+use crate::handlers::api::ApiResponse;
+
+/// Через сколько секунд клиенту стоит повторить запрос, пока сервер запускается
+pub const RETRY_AFTER_SECS: u64 = 5;
+
+/// Путь проверки состояния, доступный до готовности сервера
+pub const HEALTH_PATH: &str = "/health";
+
+/// Завершены ли задачи запуска (проверка конфигурации, каталог репозиториев, миграции)
+static READY: AtomicBool = AtomicBool::new(false);
+
+/// Готов ли сервер обслуживать запросы
+pub fn is_ready() -> bool {
+    READY.load(Ordering::Acquire)
+}
+
+/// Отмечает, что задачи запуска завершены
+pub fn mark_ready() {
+    READY.store(true, Ordering::Release);
+}
+
+/// Ответ 503 с `Retry-After` для запросов, пришедших до готовности сервера
+pub fn starting_response() -> HttpResponse {
+    HttpResponse::ServiceUnavailable()
+        .insert_header((header::RETRY_AFTER, RETRY_AFTER_SECS.to_string()))
+        .json(ApiResponse::<()> {
+            success: false,
+            message: Some("Server is starting, retry later".to_string()),
+            data: None,
+        })
+}
+
+/// Middleware: до готовности сервера отвечает 503 на все запросы, кроме `/health`
+pub async fn gate(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    if is_ready() || req.path() == HEALTH_PATH {
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    }
+
+    Ok(req.into_response(starting_response()).map_into_right_body())
+}
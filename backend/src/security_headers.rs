@@ -0,0 +1,59 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::Error;
+use crate::config::CONFIG;
+
+/// Значение `Referrer-Policy`: другим сайтам уходит только origin, и только по HTTPS
+const REFERRER_POLICY: &str = "strict-origin-when-cross-origin";
+
+/// Префикс Content-Type ответов git протокола (`application/x-git-upload-pack-result` и т. п.)
+const GIT_CONTENT_TYPE_PREFIX: &str = "application/x-git-";
+
+/// Middleware: добавляет заголовки безопасности к ответам для браузера
+///
+/// `X-Content-Type-Options`, `X-Frame-Options` и `Referrer-Policy` получают все ответы,
+/// кроме ответов git протокола, `Content-Security-Policy` - только HTML. Заголовки,
+/// уже выставленные обработчиком, не перезаписываются.
+pub async fn apply(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let mut res = next.call(req).await?;
+
+    let content_type = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if content_type.starts_with(GIT_CONTENT_TYPE_PREFIX) {
+        return Ok(res);
+    }
+
+    let headers = res.headers_mut();
+    set_default(headers, header::X_CONTENT_TYPE_OPTIONS, "nosniff");
+    set_default(headers, header::REFERRER_POLICY, REFERRER_POLICY);
+    if let Some(frame_options) = &CONFIG.frame_options {
+        set_default(headers, header::X_FRAME_OPTIONS, frame_options);
+    }
+    if content_type.starts_with("text/html") {
+        if let Some(policy) = &CONFIG.content_security_policy {
+            set_default(headers, header::CONTENT_SECURITY_POLICY, policy);
+        }
+    }
+
+    Ok(res)
+}
+
+/// Выставляет заголовок, если обработчик не выставил его сам
+fn set_default(headers: &mut HeaderMap, name: HeaderName, value: &str) {
+    if headers.contains_key(&name) {
+        return;
+    }
+    // Значения из конфигурации проверены при запуске, см. `Config::validate`
+    if let Ok(value) = HeaderValue::from_str(value) {
+        headers.insert(name, value);
+    }
+}
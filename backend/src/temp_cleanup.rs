@@ -0,0 +1,48 @@
+use std::time::Duration;
+use log::{debug, error, info};
+use crate::config::CONFIG;
+use crate::git::remove_stale_temp_dirs;
+use crate::jobs;
+use crate::models::db::Database;
+
+/// Тип фоновой задачи удаления брошенных временных каталогов
+pub const TEMP_CLEANUP_JOB: &str = "temp_cleanup";
+
+/// Удаляет временные каталоги старше GIT_HTTP_TEMP_MAX_AGE_SECS и пишет в лог, сколько удалено
+pub fn sweep() {
+    let removed = remove_stale_temp_dirs(Duration::from_secs(CONFIG.temp_max_age_secs));
+    if removed > 0 {
+        info!("Removed {} stale temporary directories", removed);
+    } else {
+        debug!("No stale temporary directories found");
+    }
+}
+
+/// Ставит очистку в очередь, если она ещё не запланирована
+///
+/// # Параметры
+///
+/// * `delay_secs` - Через сколько секунд выполнить очистку
+/// * `db` - База данных
+pub fn schedule(delay_secs: u64, db: &Database) {
+    if let Err(e) = jobs::schedule(TEMP_CLEANUP_JOB, serde_json::json!({}), delay_secs, db) {
+        error!("Failed to schedule temporary directory cleanup: {}", e);
+    }
+}
+
+/// Выполняет задачу очистки (`TEMP_CLEANUP_JOB`) и ставит следующую через
+/// GIT_HTTP_TEMP_CLEANUP_INTERVAL_SECS
+///
+/// # Параметры
+///
+/// * `db` - База данных
+///
+/// # Возвращает
+///
+/// * `Result<(), String>` - Очистка не возвращает ошибок: каталоги, которые не
+///   удалось удалить, только попадают в лог
+pub fn run_cleanup_job(db: &Database) -> Result<(), String> {
+    sweep();
+    schedule(CONFIG.temp_cleanup_interval_secs, db);
+    Ok(())
+}
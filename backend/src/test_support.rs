@@ -0,0 +1,198 @@
+//! Общие помощники для тестов
+//!
+//! Каталог репозиториев (`REPOS_ROOT`) задан относительным путём, поэтому все
+//! тесты работают во временном рабочем каталоге, который создаётся один раз на
+//! процесс. База данных у каждого теста своя, а имена пользователей уникальны:
+//! кэш проверенных учётных данных общий для процесса.
+
+use actix_web::{web, App, HttpServer};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use std::io::Write;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::OnceLock;
+
+use crate::git::{git_command, repo_path, REPOS_ROOT};
+use crate::models::db::Database;
+use crate::models::repository::Repository;
+use crate::models::user::User;
+
+/// Пароль всех пользователей, созданных `create_user`
+pub const PASSWORD: &str = "secret";
+
+/// Временный рабочий каталог тестов
+///
+/// При первом вызове создаёт каталог и делает его текущим.
+pub fn work_dir() -> &'static Path {
+    static DIR: OnceLock<PathBuf> = OnceLock::new();
+    DIR.get_or_init(|| {
+        let dir = std::env::temp_dir().join(format!("git-server-tests-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create test directory");
+        std::env::set_current_dir(&dir).expect("enter test directory");
+        dir
+    })
+}
+
+/// Уникальный суффикс для имён пользователей и репозиториев
+pub fn unique(prefix: &str) -> String {
+    format!("{}{}", prefix, &uuid::Uuid::new_v4().simple().to_string()[..12])
+}
+
+/// Новая база данных со всеми таблицами
+pub fn test_db() -> web::Data<Database> {
+    let path = work_dir().join(format!("{}.db", unique("test")));
+    let db = Database::open(&path).expect("open test database");
+    db.migrate().expect("migrate test database");
+    web::Data::new(db)
+}
+
+/// Создаёт пользователя с уникальным именем и паролем `PASSWORD`
+pub fn create_user(db: &Database, prefix: &str) -> User {
+    let username = unique(prefix);
+    let user = User {
+        id: None,
+        username: username.clone(),
+        password: PASSWORD.to_string(),
+        email: None,
+        created_at: None,
+        storage_quota_bytes: None,
+        last_login_at: None,
+        last_login_ip: None,
+        failed_login_count: 0,
+        locked_until: None,
+    };
+    user.create(db.get_connection()).expect("create test user");
+    User::find_by_username(&username, db.get_connection())
+        .expect("load test user")
+        .expect("test user exists")
+}
+
+/// Создаёт пустой bare репозиторий пользователя
+pub fn create_repo(db: &Database, owner: &User, name: &str, is_public: bool) -> Repository {
+    let mut repo = Repository {
+        id: None,
+        name: name.to_string(),
+        owner_id: owner.id.unwrap(),
+        owner: owner.username.clone(),
+        description: None,
+        is_public,
+        created_at: None,
+        forked_from: None,
+    };
+    repo.id = Some(repo.create(db.get_connection()).expect("create test repository"));
+    repo
+}
+
+/// Заголовок Basic-аутентификации пользователя
+pub fn basic_auth(user: &User) -> (&'static str, String) {
+    let credentials = BASE64.encode(format!("{}:{}", user.username, PASSWORD));
+    ("Authorization", format!("Basic {}", credentials))
+}
+
+/// Запускает git в каталоге репозитория и возвращает его stdout без пробелов по краям
+fn git(repo_path: &Path, args: &[&str], input: Option<&[u8]>) -> String {
+    let mut command = git_command();
+    command
+        .arg("-C")
+        .arg(repo_path)
+        .args(args)
+        .env("GIT_AUTHOR_NAME", "Test")
+        .env("GIT_AUTHOR_EMAIL", "test@example.com")
+        .env("GIT_COMMITTER_NAME", "Test")
+        .env("GIT_COMMITTER_EMAIL", "test@example.com")
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = command.spawn().expect("run git");
+    if let Some(input) = input {
+        child.stdin.take().unwrap().write_all(input).expect("write git stdin");
+    }
+    drop(child.stdin.take());
+    let output = child.wait_with_output().expect("wait for git");
+    assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// Запускает git в bare репозитории
+///
+/// # Возвращает
+///
+/// * `String` - stdout без пробелов по краям
+pub fn repo_git(repo: &Repository, args: &[&str]) -> String {
+    let path = repo_path(Path::new(REPOS_ROOT), &repo.full_name()).expect("repository path");
+    git(&path, args, None)
+}
+
+/// Создаёт коммит с файлами в корне репозитория на ветке `main` и делает её `HEAD`
+///
+/// Коммит продолжает ветку, если она уже есть; дерево содержит только переданные файлы.
+///
+/// # Возвращает
+///
+/// * `String` - SHA коммита
+pub fn commit_files(repo: &Repository, files: &[(&str, &[u8])]) -> String {
+    let path = repo_path(Path::new(REPOS_ROOT), &repo.full_name()).expect("repository path");
+    let mut tree = String::new();
+    for (name, content) in files {
+        let blob = git(&path, &["hash-object", "-w", "--stdin"], Some(content));
+        tree.push_str(&format!("100644 blob {}\t{}\n", blob, name));
+    }
+    let tree = git(&path, &["mktree"], Some(tree.as_bytes()));
+    let parent = git(&path, &["for-each-ref", "--format=%(objectname)", "refs/heads/main"], None);
+    let mut args = vec!["commit-tree", tree.as_str(), "-m", "test commit"];
+    if !parent.is_empty() {
+        args.extend(["-p", parent.as_str()]);
+    }
+    let commit = git(&path, &args, None);
+    git(&path, &["update-ref", "refs/heads/main", &commit], None);
+    git(&path, &["symbolic-ref", "HEAD", "refs/heads/main"], None);
+    commit
+}
+
+/// Запускает git клиента в рабочем каталоге `dir`
+///
+/// # Возвращает
+///
+/// * `String` - stdout без пробелов по краям
+pub fn client_git(dir: &Path, args: &[&str]) -> String {
+    git(dir, args, None)
+}
+
+/// Запускает в отдельном потоке HTTP сервер с git маршрутами для настоящего git клиента
+///
+/// Сервер работает до конца тестового процесса.
+///
+/// # Возвращает
+///
+/// * `String` - Адрес сервера `127.0.0.1:{port}`
+pub fn serve(db: &web::Data<Database>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind test server");
+    let addr = listener.local_addr().expect("test server address").to_string();
+    let db = db.clone();
+    std::thread::spawn(move || {
+        actix_web::rt::System::new().block_on(async move {
+            HttpServer::new(move || {
+                crate::models::db::mark_async_worker();
+                App::new()
+                    .app_data(db.clone())
+                    .configure(|cfg| crate::git_routes(cfg, "/git/{owner}/{repo_name}"))
+                    .configure(|cfg| crate::git_routes(cfg, "/git/{repo_name}"))
+            })
+            .workers(2)
+            .disable_signals()
+            .listen(listener)
+            .expect("listen test server")
+            .run()
+            .await
+        })
+    });
+    addr
+}
+
+/// URL репозитория на тестовом сервере с учётными данными пользователя
+pub fn remote_url(addr: &str, user: &User, repo: &Repository) -> String {
+    format!("http://{}:{}@{}/git/{}", user.username, PASSWORD, addr, repo.full_name())
+}
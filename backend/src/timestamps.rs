@@ -0,0 +1,105 @@
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+use serde::Serializer;
+
+/// Формат, в котором SQLite записывает даты (`CURRENT_TIMESTAMP`, `datetime('now')`)
+///
+/// Все даты в базе данных хранятся в нём и в UTC: только тогда их можно сравнивать
+/// с `datetime('now', ...)` в запросах.
+pub const DB_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Разбирает дату из базы данных
+///
+/// Кроме формата SQLite понимает RFC3339 и `YYYY-MM-DDTHH:MM:SS`, которые могли
+/// остаться в базах, созданных старыми версиями.
+///
+/// # Параметры
+///
+/// * `value` - Значение столбца
+///
+/// # Возвращает
+///
+/// * `Option<DateTime<Utc>>` - Дата или None, если формат не распознан
+pub fn parse_datetime(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(dt.with_timezone(&Utc));
+    }
+
+    NaiveDateTime::parse_from_str(value, DB_FORMAT)
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S"))
+        .ok()
+        .map(|ndt| DateTime::<Utc>::from_naive_utc_and_offset(ndt, Utc))
+}
+
+/// Дата в формате API: RFC3339 в UTC с точностью до секунды (`2024-05-01T12:30:00Z`)
+pub fn to_rfc3339(dt: &DateTime<Utc>) -> String {
+    dt.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Сериализует необязательную дату в формате API (`#[serde(serialize_with = ...)]`)
+pub fn serialize_rfc3339<S: Serializer>(dt: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error> {
+    match dt {
+        Some(dt) => serializer.serialize_str(&to_rfc3339(dt)),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use rusqlite::params;
+    use crate::models::user::User;
+    use crate::test_support::{create_user, test_db};
+
+    #[test]
+    fn legacy_and_rfc3339_values_parse_to_the_same_instant() {
+        let expected = Utc.with_ymd_and_hms(2024, 5, 1, 12, 30, 0).unwrap();
+        for value in [
+            "2024-05-01 12:30:00",
+            "2024-05-01T12:30:00",
+            "2024-05-01T12:30:00Z",
+            "2024-05-01T12:30:00+00:00",
+            "2024-05-01T15:30:00+03:00",
+        ] {
+            assert_eq!(parse_datetime(value), Some(expected), "{}", value);
+        }
+    }
+
+    #[test]
+    fn formats_in_utc_to_the_second() {
+        let dt = parse_datetime("2024-05-01T15:30:00.750+03:00").unwrap();
+        assert_eq!(to_rfc3339(&dt), "2024-05-01T12:30:00Z");
+        assert_eq!(to_rfc3339(&parse_datetime("2024-05-01 12:30:00").unwrap()), "2024-05-01T12:30:00Z");
+    }
+
+    #[test]
+    fn rejects_unknown_formats() {
+        for value in ["", "yesterday", "2024-05-01", "01.05.2024 12:30:00", "2024-13-01 00:00:00"] {
+            assert_eq!(parse_datetime(value), None, "{}", value);
+        }
+    }
+
+    #[test]
+    fn stored_values_serialize_identically() {
+        let db = test_db();
+        let mut serialized = Vec::new();
+        for stored in ["2024-05-01 12:30:00", "2024-05-01T12:30:00Z", "2024-05-01T14:30:00+02:00"] {
+            let user = create_user(&db, "clock");
+            db.get_connection().lock().unwrap()
+                .execute("UPDATE users SET created_at = ?1 WHERE id = ?2", params![stored, user.id])
+                .unwrap();
+            let user = User::find_by_username(&user.username, db.get_connection()).unwrap().unwrap();
+            let json = serde_json::to_value(&user).unwrap();
+            serialized.push(json["created_at"].clone());
+        }
+
+        assert!(serialized.iter().all(|value| value == "2024-05-01T12:30:00Z"), "{:?}", serialized);
+    }
+
+    #[test]
+    fn missing_dates_serialize_as_null() {
+        let mut serializer = serde_json::Serializer::new(Vec::new());
+        serialize_rfc3339(&None, &mut serializer).unwrap();
+        assert_eq!(serializer.into_inner(), b"null");
+    }
+}
@@ -0,0 +1,114 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Cursor;
+use log::{error, info};
+use crate::config::CONFIG;
+use crate::git::pktline::{read_pkt, Packet};
+
+/// Направление передачи данных при трассировке
+#[derive(Debug, Clone, Copy)]
+pub enum Direction {
+    /// Тело запроса клиента
+    Request,
+    /// Тело ответа сервера
+    Response,
+}
+
+impl Direction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Request => "request",
+            Direction::Response => "response",
+        }
+    }
+}
+
+/// Записывает тело запроса или ответа git протокола, если включён GIT_HTTP_TRACE_PACKETS
+///
+/// Тела могут содержать данные репозитория, поэтому трассировка выключена по умолчанию.
+/// Если задан GIT_HTTP_TRACE_DIR, каждое тело пишется в отдельный файл, иначе в лог.
+///
+/// # Параметры
+///
+/// * `request_id` - Идентификатор запроса, общий для запроса и ответа
+/// * `repo_name` - Полное имя репозитория `{owner}/{name}`
+/// * `service` - Имя сервиса (upload-pack или receive-pack)
+/// * `direction` - Запрос или ответ
+/// * `body` - Сырые байты тела
+pub fn trace_packets(request_id: &str, repo_name: &str, service: &str, direction: Direction, body: &[u8]) {
+    if !CONFIG.trace_packets {
+        return;
+    }
+
+    let dump = format!(
+        "# {} {} {} ({} bytes)\n## pkt-lines\n{}## hex\n{}",
+        service,
+        repo_name,
+        direction.as_str(),
+        body.len(),
+        decode_pkt_lines(body),
+        hex_dump(body),
+    );
+
+    match &CONFIG.trace_dir {
+        Some(dir) => {
+            let file_name = format!("{}-{}-{}.trace", request_id, service, direction.as_str());
+            let result = fs::create_dir_all(dir).and_then(|_| fs::write(dir.join(&file_name), dump));
+            if let Err(e) = result {
+                error!("Failed to write packet trace {}: {}", file_name, e);
+            }
+        }
+        None => info!(target: "git_packets", "[{}]\n{}", request_id, dump),
+    }
+}
+
+/// Разбирает последовательность pkt-line для удобного чтения
+///
+/// Разбор останавливается на первой некорректной длине; остаток (например,
+/// бинарный pack-файл) выводится только в hex дампе.
+fn decode_pkt_lines(body: &[u8]) -> String {
+    let mut out = String::new();
+    let mut reader = Cursor::new(body);
+    let mut pos = 0;
+
+    while let Ok(Some(packet)) = read_pkt(&mut reader) {
+        match packet {
+            Packet::Flush => out.push_str("0000 (flush)\n"),
+            Packet::Delim => out.push_str("0001 (delim)\n"),
+            Packet::ResponseEnd => out.push_str("0002 (response-end)\n"),
+            Packet::Data(payload) => {
+                let payload = String::from_utf8_lossy(&payload);
+                let _ = writeln!(out, "{:04x} {}", payload.len() + 4, payload.trim_end_matches('\n').escape_debug());
+            }
+        }
+        pos = reader.position() as usize;
+    }
+
+    if pos < body.len() {
+        let _ = writeln!(out, "... {} bytes of non pkt-line data", body.len() - pos);
+    }
+
+    out
+}
+
+/// Формирует hex дамп по 16 байт в строке
+fn hex_dump(body: &[u8]) -> String {
+    let mut out = String::new();
+
+    for (i, chunk) in body.chunks(16).enumerate() {
+        let _ = write!(out, "{:08x}  ", i * 16);
+        for byte in chunk {
+            let _ = write!(out, "{:02x} ", byte);
+        }
+        for _ in chunk.len()..16 {
+            out.push_str("   ");
+        }
+        out.push(' ');
+        for &byte in chunk {
+            out.push(if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' });
+        }
+        out.push('\n');
+    }
+
+    out
+}
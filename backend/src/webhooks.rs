@@ -0,0 +1,411 @@
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use crate::jobs;
+use crate::models::db::Database;
+use crate::models::repository::Repository;
+use crate::models::user::User;
+use crate::models::webhook::{ServerWebhook, Webhook, WebhookDelivery};
+
+/// События, на которые можно подписать webhook
+///
+/// `create` и `delete` - создание и удаление веток и тегов; `push` приходит
+/// для любых изменений ссылок, в том числе вместе с ними.
+pub const SUPPORTED_EVENTS: &[&str] = &["push", "create", "delete"];
+
+/// События, на которые можно подписать webhook сервера
+pub const SERVER_EVENTS: &[&str] = &["repository_created", "repository_deleted"];
+
+/// Тип фоновой задачи доставки события одному webhook
+pub const DELIVERY_JOB: &str = "webhook_delivery";
+/// Тип фоновой задачи доставки события одному webhook сервера
+pub const SERVER_DELIVERY_JOB: &str = "server_webhook_delivery";
+
+/// Сколько байт ответа получателя сохранять в журнале доставок
+const MAX_STORED_RESPONSE_BYTES: usize = 16 * 1024;
+/// Таймаут соединения, записи и чтения при доставке
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Параметры задачи доставки
+#[derive(Serialize, Deserialize)]
+struct DeliveryJob {
+    repository_id: i64,
+    hook_id: i64,
+    event: String,
+    payload: String,
+    /// Идентификатор события; в задачах, поставленных до его появления, отсутствует
+    #[serde(default)]
+    guid: Option<String>,
+}
+
+/// Параметры задачи доставки webhook'у сервера
+#[derive(Serialize, Deserialize)]
+struct ServerDeliveryJob {
+    hook_id: i64,
+    event: String,
+    payload: String,
+    guid: String,
+}
+
+/// Результат одной попытки доставки
+pub struct DeliveryResult {
+    /// HTTP статус ответа; None - ответа не было
+    pub status: Option<u16>,
+    /// Тело ответа (обрезанное) или описание ошибки
+    pub body: String,
+    /// Длительность попытки
+    pub duration: Duration,
+}
+
+/// Разбирает URL получателя на хост, порт и путь
+///
+/// Поддерживаются только `http://` адреса: в сборке нет TLS клиента.
+///
+/// # Параметры
+///
+/// * `url` - URL получателя
+///
+/// # Возвращает
+///
+/// * `Result<(String, u16, String), String>` - Хост, порт и путь или описание ошибки
+pub fn parse_url(url: &str) -> Result<(String, u16, String), String> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| "Only http:// webhook URLs are supported".to_string())?;
+    let (authority, path) = match rest.find('/') {
+        Some(pos) => (&rest[..pos], &rest[pos..]),
+        None => (rest, "/"),
+    };
+
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().map_err(|_| format!("Invalid port in {}", url))?),
+        None => (authority, 80),
+    };
+
+    let valid = !host.is_empty()
+        && !authority.contains('@')
+        && !path.chars().any(|c| c.is_control() || c.is_whitespace());
+    if !valid {
+        return Err(format!("Invalid webhook URL: {}", url));
+    }
+
+    Ok((host.to_string(), port, path.to_string()))
+}
+
+/// Подпись доставки: HMAC-SHA256 от `<timestamp>.<payload>` в hex
+///
+/// Метка времени входит в подписанные данные, поэтому перехваченную доставку
+/// нельзя переслать позже с новой меткой.
+///
+/// # Параметры
+///
+/// * `secret` - Секрет webhook
+/// * `timestamp` - Метка времени доставки (заголовок `X-Hub-Timestamp`), Unix секунды
+/// * `payload` - JSON тело
+///
+/// # Возвращает
+///
+/// * `String` - Значение заголовка `X-Hub-Signature-256`: `sha256=<hex>`
+pub fn sign(secret: &str, timestamp: i64, payload: &str) -> String {
+    // HMAC принимает ключ любой длины, ошибки здесь не бывает
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(payload.as_bytes());
+
+    let digest = mac.finalize().into_bytes();
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("sha256={}", hex)
+}
+
+/// Отправляет событие получателю одним POST запросом
+///
+/// Каждая попытка получает свою метку времени (`X-Hub-Timestamp`) и, если у webhook
+/// есть секрет, подпись (`X-Hub-Signature-256`, см. `sign`).
+///
+/// # Параметры
+///
+/// * `url` - URL получателя
+/// * `event` - Имя события (заголовок `X-Git-Event`)
+/// * `delivery_id` - Идентификатор события, общий для повторов (заголовки `X-Delivery-Id` и `X-Git-Delivery`)
+/// * `payload` - JSON тело
+/// * `secret` - Секрет webhook
+///
+/// # Возвращает
+///
+/// * `DeliveryResult` - Статус и тело ответа или описание ошибки
+pub fn deliver(url: &str, event: &str, delivery_id: &str, payload: &str, secret: Option<&str>) -> DeliveryResult {
+    let started = Instant::now();
+    let timestamp = Utc::now().timestamp();
+    let mut headers = format!(
+        "X-Git-Event: {}\r\nX-Git-Delivery: {}\r\nX-Delivery-Id: {}\r\nX-Hub-Timestamp: {}\r\n",
+        event, delivery_id, delivery_id, timestamp
+    );
+    if let Some(secret) = secret {
+        headers.push_str(&format!("X-Hub-Signature-256: {}\r\n", sign(secret, timestamp, payload)));
+    }
+    let result = post(url, &headers, payload);
+
+    let (status, body) = match result {
+        Ok((status, body)) => (Some(status), body),
+        Err(e) => (None, e),
+    };
+
+    DeliveryResult { status, body, duration: started.elapsed() }
+}
+
+/// Выполняет POST с дополнительными заголовками и возвращает статус и тело ответа
+fn post(url: &str, headers: &str, payload: &str) -> Result<(u16, String), String> {
+    let (host, port, path) = parse_url(url)?;
+
+    let addr = (host.as_str(), port)
+        .to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve {}: {}", host, e))?
+        .next()
+        .ok_or_else(|| format!("No address for {}", host))?;
+    let mut stream = TcpStream::connect_timeout(&addr, DELIVERY_TIMEOUT)
+        .map_err(|e| format!("Failed to connect to {}: {}", addr, e))?;
+    stream.set_read_timeout(Some(DELIVERY_TIMEOUT)).map_err(|e| e.to_string())?;
+    stream.set_write_timeout(Some(DELIVERY_TIMEOUT)).map_err(|e| e.to_string())?;
+
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}:{}\r\nUser-Agent: Git-HTTP-Server-Webhook\r\n\
+         Content-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n",
+        path, host, port, payload.len(), headers
+    );
+    stream
+        .write_all(request.as_bytes())
+        .and_then(|_| stream.write_all(payload.as_bytes()))
+        .map_err(|e| format!("Failed to send request: {}", e))?;
+
+    // Заголовки плюс сохраняемая часть тела; остальное не читаем
+    let mut response = Vec::new();
+    stream
+        .take((MAX_STORED_RESPONSE_BYTES + 8 * 1024) as u64)
+        .read_to_end(&mut response)
+        .map_err(|e| format!("Failed to read response: {}", e))?;
+
+    let text = String::from_utf8_lossy(&response);
+    let status = text
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| "Invalid HTTP response".to_string())?;
+    let body = text.split_once("\r\n\r\n").map(|(_, body)| body).unwrap_or_default();
+
+    Ok((status, truncate(body, MAX_STORED_RESPONSE_BYTES)))
+}
+
+/// Обрезает строку до `max` байт по границе символа
+fn truncate(text: &str, max: usize) -> String {
+    if text.len() <= max {
+        return text.to_string();
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text[..end].to_string()
+}
+
+/// Доставляет событие и записывает попытку в журнал доставок
+///
+/// # Параметры
+///
+/// * `hook` - Webhook получателя
+/// * `event` - Имя события
+/// * `guid` - Идентификатор события
+/// * `payload` - JSON тело
+/// * `db` - База данных
+///
+/// # Возвращает
+///
+/// * `Option<WebhookDelivery>` - Записанная доставка; None, если запись не удалась
+pub fn deliver_and_record(hook: &Webhook, event: &str, guid: &str, payload: &str, db: &Database) -> Option<WebhookDelivery> {
+    let result = deliver(&hook.url, event, guid, payload, hook.secret.as_deref());
+    debug!("Webhook {} delivery {}: {:?}", hook.id.unwrap_or_default(), guid, result.status);
+
+    let mut delivery = WebhookDelivery {
+        id: None,
+        hook_id: hook.id.unwrap_or_default(),
+        event: event.to_string(),
+        payload: payload.to_string(),
+        response_status: result.status.map(i64::from),
+        response_body: Some(result.body),
+        duration_ms: result.duration.as_millis() as i64,
+        created_at: None,
+        guid: Some(guid.to_string()),
+    };
+
+    match delivery.create(db.get_connection()) {
+        Ok(id) => {
+            delivery.id = Some(id);
+            Some(delivery)
+        }
+        Err(e) => {
+            error!("Failed to record webhook delivery: {}", e);
+            None
+        }
+    }
+}
+
+/// Ставит в очередь доставку события всем активным webhook'ам репозитория, подписанным на него
+///
+/// Доставка идёт в фоне и не задерживает обработчик, вызвавший событие; неудачные
+/// доставки повторяются с нарастающей задержкой (см. `jobs`).
+///
+/// # Параметры
+///
+/// * `repository_id` - ID репозитория
+/// * `event` - Имя события
+/// * `payload` - JSON тело
+/// * `db` - База данных
+pub fn fire(repository_id: i64, event: &str, payload: serde_json::Value, db: &Database) {
+    let hooks = match Webhook::find_by_repository(repository_id, db.get_connection()) {
+        Ok(hooks) => hooks,
+        Err(e) => {
+            error!("Failed to load webhooks: {}", e);
+            return;
+        }
+    };
+
+    let payload = payload.to_string();
+    for hook in hooks.iter().filter(|hook| hook.active && hook.subscribed_to(event)) {
+        let job = DeliveryJob {
+            repository_id,
+            hook_id: hook.id.unwrap_or_default(),
+            event: event.to_string(),
+            payload: payload.clone(),
+            guid: Some(uuid::Uuid::new_v4().to_string()),
+        };
+        let job = serde_json::to_value(job).unwrap_or_default();
+        if let Err(e) = jobs::enqueue(DELIVERY_JOB, job, db) {
+            error!("Failed to enqueue webhook delivery: {}", e);
+        }
+    }
+}
+
+/// Выполняет задачу доставки (`DELIVERY_JOB`)
+///
+/// Если webhook с тех пор удалён или выключен, задача считается выполненной.
+/// Повторы задачи отправляются с тем же `X-Delivery-Id`.
+///
+/// # Параметры
+///
+/// * `job` - Параметры задачи
+/// * `db` - База данных
+///
+/// # Возвращает
+///
+/// * `Result<(), String>` - Ошибка, если получатель не ответил кодом 2xx
+pub fn run_delivery_job(job: &serde_json::Value, db: &Database) -> Result<(), String> {
+    let job: DeliveryJob = serde_json::from_value(job.clone())
+        .map_err(|e| format!("Invalid delivery job: {}", e))?;
+
+    let hook = match Webhook::find_by_id(job.repository_id, job.hook_id, db.get_connection()) {
+        Ok(Some(hook)) if hook.active => hook,
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(format!("Failed to load webhook: {}", e)),
+    };
+
+    let guid = job.guid.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let delivery = deliver_and_record(&hook, &job.event, &guid, &job.payload, db)
+        .ok_or_else(|| "Failed to record delivery".to_string())?;
+    match delivery.response_status {
+        Some(status) if (200..300).contains(&status) => Ok(()),
+        Some(status) => Err(format!("Receiver responded with HTTP {}", status)),
+        None => Err(delivery.response_body.unwrap_or_default()),
+    }
+}
+
+/// Ставит в очередь доставку события о репозитории webhook'ам сервера
+///
+/// Тело события: `{ action, repository, sender }`, где `action` - `created` или
+/// `deleted`. Для удаления передаётся запись репозитория, прочитанная до удаления.
+///
+/// # Параметры
+///
+/// * `event` - `repository_created` или `repository_deleted`
+/// * `repo` - Репозиторий
+/// * `sender` - Пользователь, создавший или удаливший репозиторий
+/// * `db` - База данных
+pub fn fire_repository_event(event: &str, repo: &Repository, sender: &User, db: &Database) {
+    let action = event.strip_prefix("repository_").unwrap_or(event);
+    fire_server(event, serde_json::json!({
+        "action": action,
+        "repository": repo,
+        "sender": {
+            "id": sender.id,
+            "username": sender.username,
+        },
+    }), db);
+}
+
+/// Ставит в очередь доставку события всем активным webhook'ам сервера, подписанным на него
+///
+/// # Параметры
+///
+/// * `event` - Имя события из `SERVER_EVENTS`
+/// * `payload` - JSON тело
+/// * `db` - База данных
+pub fn fire_server(event: &str, payload: serde_json::Value, db: &Database) {
+    let hooks = match ServerWebhook::find_all(db.get_connection()) {
+        Ok(hooks) => hooks,
+        Err(e) => {
+            error!("Failed to load server webhooks: {}", e);
+            return;
+        }
+    };
+
+    let payload = payload.to_string();
+    for hook in hooks.iter().filter(|hook| hook.active && hook.subscribed_to(event)) {
+        let job = ServerDeliveryJob {
+            hook_id: hook.id.unwrap_or_default(),
+            event: event.to_string(),
+            payload: payload.clone(),
+            guid: uuid::Uuid::new_v4().to_string(),
+        };
+        let job = serde_json::to_value(job).unwrap_or_default();
+        if let Err(e) = jobs::enqueue(SERVER_DELIVERY_JOB, job, db) {
+            error!("Failed to enqueue server webhook delivery: {}", e);
+        }
+    }
+}
+
+/// Выполняет задачу доставки webhook'у сервера (`SERVER_DELIVERY_JOB`)
+///
+/// Журнал доставок ведётся только для webhook'ов репозиториев; неудачные
+/// доставки сервера видны в очереди задач (`/api/admin/jobs`) и в логе.
+///
+/// # Параметры
+///
+/// * `job` - Параметры задачи
+/// * `db` - База данных
+///
+/// # Возвращает
+///
+/// * `Result<(), String>` - Ошибка, если получатель не ответил кодом 2xx
+pub fn run_server_delivery_job(job: &serde_json::Value, db: &Database) -> Result<(), String> {
+    let job: ServerDeliveryJob = serde_json::from_value(job.clone())
+        .map_err(|e| format!("Invalid server delivery job: {}", e))?;
+
+    let hook = match ServerWebhook::find_by_id(job.hook_id, db.get_connection()) {
+        Ok(Some(hook)) if hook.active => hook,
+        Ok(_) => return Ok(()),
+        Err(e) => return Err(format!("Failed to load server webhook: {}", e)),
+    };
+
+    let result = deliver(&hook.url, &job.event, &job.guid, &job.payload, hook.secret.as_deref());
+    debug!("Server webhook {} delivery {}: {:?}", job.hook_id, job.guid, result.status);
+    match result.status {
+        Some(status) if (200..300).contains(&status) => Ok(()),
+        Some(status) => Err(format!("Receiver responded with HTTP {}", status)),
+        None => Err(result.body),
+    }
+}
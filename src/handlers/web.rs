@@ -116,12 +116,25 @@ pub async fn profile(
         }
     };
     
+    let current_user = check_auth(&req, &db);
+    
+    // Закреплённые репозитории в заданном пользователем порядке; чужие приватные скрыты
+    let viewer_id = current_user.as_ref().and_then(|user| user.id);
+    let pinned_repos = match Repository::find_pinned(target_user.id.unwrap(), viewer_id, db.get_connection()) {
+        Ok(repos) => repos,
+        Err(e) => {
+            error!("Failed to fetch pinned repositories: {}", e);
+            vec![]
+        }
+    };
+    
     let mut ctx = Context::new();
     ctx.insert("profile_user", &target_user);
     ctx.insert("repos", &repos);
+    ctx.insert("pinned_repos", &pinned_repos);
     
     // Проверяем, авторизован ли текущий пользователь
-    if let Some(current_user) = check_auth(&req, &db) {
+    if let Some(current_user) = current_user {
         ctx.insert("user", &current_user);
         ctx.insert("is_own_profile", &(current_user.username == target_user.username));
     } else {